@@ -0,0 +1,135 @@
+//! Acknowledge and Resolve buttons in chat sinks
+//!
+//! Incoming webhooks can't receive button callbacks, so the buttons are
+//! links to the bridge's `/api/alerts/<action>` endpoint. Each link carries
+//! an HMAC-SHA256 of the action and alert key under `chat_actions.secret`,
+//! so only links the bridge itself handed out are honored. The endpoint
+//! acknowledges or resolves the alert through the PagerDuty client like the
+//! `ack` and `resolve` commands do.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Where responders reach the bridge, and the key its links are signed with
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ChatActionsConfig {
+    /// Base URL the bridge's HTTP server is reachable at from responders'
+    /// browsers, e.g. `https://near-alerts.example.com`
+    pub public_url: String,
+    /// Key the links are signed with; anyone holding it can resolve alerts
+    pub secret: String,
+}
+
+/// What a chat button does to the alert
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatAction {
+    Acknowledge,
+    Resolve,
+}
+
+impl ChatAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Acknowledge => "acknowledge",
+            Self::Resolve => "resolve",
+        }
+    }
+}
+
+impl ChatActionsConfig {
+    /// Link that performs `action` on the alert with `alert_key`
+    pub fn link(&self, action: ChatAction, alert_key: &str) -> String {
+        format!(
+            "{}/api/alerts/{}?key={}&sig={}",
+            self.public_url.trim_end_matches('/'),
+            action.as_str(),
+            encode_query_value(alert_key),
+            self.signature(action, alert_key)
+        )
+    }
+
+    /// Whether `signature` was issued by [`Self::link`] for this action and key
+    pub fn verify(&self, action: ChatAction, alert_key: &str, signature: &str) -> bool {
+        let expected = self.signature(action, alert_key);
+        // Compare in constant time so the signature can't be guessed byte by byte
+        expected.len() == signature.len()
+            && expected
+                .bytes()
+                .zip(signature.bytes())
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+
+    fn signature(&self, action: ChatAction, alert_key: &str) -> String {
+        let message = format!("{}\n{}", action.as_str(), alert_key);
+        format!(
+            "{:x}",
+            hmac_sha256(self.secret.as_bytes(), message.as_bytes())
+        )
+    }
+}
+
+/// HMAC (RFC 2104) over SHA-256
+pub(crate) fn hmac_sha256(key: &[u8], message: &[u8]) -> impl std::fmt::LowerHex + AsRef<[u8]> {
+    const BLOCK_SIZE: usize = 64;
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let pad = |byte: u8| block.map(|b| b ^ byte);
+    let inner = Sha256::new()
+        .chain_update(pad(0x36))
+        .chain_update(message)
+        .finalize();
+    Sha256::new()
+        .chain_update(pad(0x5c))
+        .chain_update(inner)
+        .finalize()
+}
+
+/// Percent-encode everything but unreserved characters
+pub(crate) fn encode_query_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_action_links() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            format!(
+                "{:x}",
+                hmac_sha256(b"Jefe", b"what do ya want for nothing?")
+            ),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+
+        let config = ChatActionsConfig {
+            public_url: "https://alerts.example.com/".to_string(),
+            secret: "s3cret".to_string(),
+        };
+        let link = config.link(ChatAction::Resolve, "venear pause/abc");
+        assert!(link.starts_with(
+            "https://alerts.example.com/api/alerts/resolve?key=venear%20pause%2Fabc&sig="
+        ));
+        let signature = link.rsplit_once("sig=").unwrap().1;
+        assert!(config.verify(ChatAction::Resolve, "venear pause/abc", signature));
+        assert!(!config.verify(ChatAction::Acknowledge, "venear pause/abc", signature));
+        assert!(!config.verify(ChatAction::Resolve, "other", signature));
+        assert!(!config.verify(ChatAction::Resolve, "venear pause/abc", "00"));
+    }
+}
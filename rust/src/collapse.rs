@@ -0,0 +1,148 @@
+//! Collapsing bursts of alerts that only differ in their dedup key
+//!
+//! Per-transaction dedup keys give every event its own incident, which
+//! during a spam attack means hundreds of identical pages. With
+//! `collapse_duplicate_summaries`, a summary seen again within the window
+//! reuses the first alert's dedup key, so PagerDuty folds the burst into one
+//! incident, and carries a count of how often it was seen.
+
+#[cfg(feature = "monitor")]
+use std::{collections::HashMap, sync::Mutex};
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "monitor")]
+use sha2::{Digest, Sha256};
+
+/// Settings of `collapse_duplicate_summaries`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct DuplicateSummaryConfig {
+    /// Seconds after the first alert that identical summaries join it (default: 300)
+    #[serde(default = "default_collapse_window")]
+    pub window_secs: u64,
+}
+
+fn default_collapse_window() -> u64 {
+    300
+}
+
+#[cfg(feature = "monitor")]
+struct Burst {
+    started: std::time::Instant,
+    window: std::time::Duration,
+    dedup_key: String,
+    count: u64,
+}
+
+/// Open bursts by subscription and summary
+#[cfg(feature = "monitor")]
+#[derive(Default)]
+pub(crate) struct SummaryBursts {
+    bursts: Mutex<HashMap<(String, String), Burst>>,
+}
+
+#[cfg(feature = "monitor")]
+impl SummaryBursts {
+    /// The dedup key and summary to alert with: unchanged for the first
+    /// alert of a burst, else the burst's key and the summary with its count
+    pub fn collapse(
+        &self,
+        subscription: &str,
+        config: &DuplicateSummaryConfig,
+        summary: String,
+        dedup_key: Option<String>,
+    ) -> (String, Option<String>) {
+        let mut bursts = self.bursts.lock().unwrap();
+        bursts.retain(|_, burst| burst.started.elapsed() < burst.window);
+        let key = (subscription.to_string(), summary.clone());
+        if let Some(burst) = bursts.get_mut(&key) {
+            burst.count += 1;
+            log::info!(
+                "Collapsing alert {:?} of '{}' into {}, seen {} times",
+                dedup_key,
+                subscription,
+                burst.dedup_key,
+                burst.count
+            );
+            return (
+                format!("{} (seen {} times)", summary, burst.count),
+                Some(burst.dedup_key.clone()),
+            );
+        }
+        // Alerts without a dedup key need one for later duplicates to join
+        let dedup_key = dedup_key.unwrap_or_else(|| {
+            let digest = Sha256::digest(format!("{}\n{}", subscription, summary).as_bytes());
+            format!(
+                "{}-burst-{}",
+                crate::slug(subscription),
+                &format!("{:x}", digest)[..16]
+            )
+        });
+        bursts.insert(
+            key,
+            Burst {
+                started: std::time::Instant::now(),
+                window: std::time::Duration::from_secs(config.window_secs),
+                dedup_key: dedup_key.clone(),
+                count: 1,
+            },
+        );
+        (summary, Some(dedup_key))
+    }
+}
+
+#[cfg(all(test, feature = "monitor"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identical_summaries_collapse() {
+        let bursts = SummaryBursts::default();
+        let config = DuplicateSummaryConfig { window_secs: 300 };
+        let spam = "Transfer to drainer.near";
+        assert_eq!(
+            bursts.collapse("Spam", &config, spam.to_string(), Some("tx-1".to_string())),
+            (spam.to_string(), Some("tx-1".to_string()))
+        );
+        assert_eq!(
+            bursts.collapse("Spam", &config, spam.to_string(), Some("tx-2".to_string())),
+            (
+                "Transfer to drainer.near (seen 2 times)".to_string(),
+                Some("tx-1".to_string())
+            )
+        );
+        // Other summaries and subscriptions have their own bursts
+        assert_eq!(
+            bursts
+                .collapse(
+                    "Spam",
+                    &config,
+                    "Other".to_string(),
+                    Some("tx-3".to_string())
+                )
+                .1
+                .as_deref(),
+            Some("tx-3")
+        );
+        assert_eq!(
+            bursts.collapse("Other", &config, spam.to_string(), None).0,
+            spam
+        );
+
+        // A closed window starts a new burst
+        let closed = DuplicateSummaryConfig { window_secs: 0 };
+        assert_eq!(
+            bursts
+                .collapse("Short", &closed, spam.to_string(), Some("tx-4".to_string()))
+                .1
+                .as_deref(),
+            Some("tx-4")
+        );
+        assert_eq!(
+            bursts
+                .collapse("Short", &closed, spam.to_string(), Some("tx-5".to_string()))
+                .1
+                .as_deref(),
+            Some("tx-5")
+        );
+    }
+}
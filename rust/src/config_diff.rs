@@ -0,0 +1,253 @@
+//! What changed between two configurations
+//!
+//! Subscriptions are matched by name, so renaming one shows up as a removal
+//! plus an addition. Secrets are never printed, only reported as changed.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::PagerDutyAlertConfig;
+
+/// Settings whose values must not appear in logs or change events
+const SECRET_FIELDS: &[&str] = &[
+    "pagerduty_routing_key",
+    "pagerduty_api_token",
+    "meta_routing_key",
+    "admin_token",
+    "pagerduty_webhook_secret",
+];
+
+/// Secrets inside nested settings such as `latency_slo` and `sinks`
+const NESTED_SECRET_FIELDS: &[&str] = &[
+    "routing_key",
+    "api_key",
+    "token",
+    "webhook_url",
+    "integration_key",
+    "access_token",
+    "auth_token",
+    "password",
+    "redis_url",
+    "auth",
+    "headers",
+    "secret",
+    "secret_access_key",
+];
+
+/// One setting that differs
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+/// A subscription present in both configurations with different settings
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SubscriptionChange {
+    pub name: String,
+    pub fields: Vec<FieldChange>,
+}
+
+/// Differences between an old and a new configuration
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ConfigDiff {
+    /// Names of subscriptions only in the new configuration
+    pub added: Vec<String>,
+    /// Names of subscriptions only in the old configuration
+    pub removed: Vec<String>,
+    pub modified: Vec<SubscriptionChange>,
+    /// Global settings that changed
+    pub settings: Vec<FieldChange>,
+}
+
+impl ConfigDiff {
+    pub fn between(old: &PagerDutyAlertConfig, new: &PagerDutyAlertConfig) -> Self {
+        let old = to_object(old);
+        let new = to_object(new);
+        let old_subs = subscriptions_by_name(&old);
+        let new_subs = subscriptions_by_name(&new);
+
+        let mut diff = Self {
+            added: new_subs
+                .keys()
+                .filter(|name| !old_subs.contains_key(*name))
+                .cloned()
+                .collect(),
+            removed: old_subs
+                .keys()
+                .filter(|name| !new_subs.contains_key(*name))
+                .cloned()
+                .collect(),
+            ..Self::default()
+        };
+        for (name, old_sub) in &old_subs {
+            if let Some(new_sub) = new_subs.get(name) {
+                let fields = field_changes(old_sub, new_sub);
+                if !fields.is_empty() {
+                    diff.modified.push(SubscriptionChange {
+                        name: name.clone(),
+                        fields,
+                    });
+                }
+            }
+        }
+
+        let mut old_settings = old;
+        let mut new_settings = new;
+        old_settings.remove("subscriptions");
+        new_settings.remove("subscriptions");
+        diff.settings = field_changes(&old_settings, &new_settings);
+        for change in &mut diff.settings {
+            if SECRET_FIELDS.contains(&change.field.as_str()) {
+                change.old = Value::String("<redacted>".to_string());
+                change.new = Value::String("<redacted>".to_string());
+            }
+            for value in [&mut change.old, &mut change.new] {
+                redact_nested(value);
+            }
+        }
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.modified.is_empty()
+            && self.settings.is_empty()
+    }
+
+    /// One-line description, e.g. for a PagerDuty change event summary
+    pub fn summary(&self) -> String {
+        if self.is_empty() {
+            return "Configuration unchanged".to_string();
+        }
+        format!(
+            "Configuration changed: {} subscription(s) added, {} removed, {} modified, {} setting(s) changed",
+            self.added.len(),
+            self.removed.len(),
+            self.modified.len(),
+            self.settings.len()
+        )
+    }
+}
+
+impl fmt::Display for ConfigDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.summary())?;
+        for name in &self.added {
+            writeln!(f, "  + subscription '{}'", name)?;
+        }
+        for name in &self.removed {
+            writeln!(f, "  - subscription '{}'", name)?;
+        }
+        for change in &self.modified {
+            writeln!(f, "  ~ subscription '{}'", change.name)?;
+            for field in &change.fields {
+                writeln!(f, "      {}: {} -> {}", field.field, field.old, field.new)?;
+            }
+        }
+        for field in &self.settings {
+            writeln!(f, "  ~ {}: {} -> {}", field.field, field.old, field.new)?;
+        }
+        Ok(())
+    }
+}
+
+/// Hide secrets inside nested settings such as `latency_slo.routing_key`
+/// or `sinks[].api_key`
+fn redact_nested(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (field, value) in map.iter_mut() {
+                if NESTED_SECRET_FIELDS.contains(&field.as_str()) && !value.is_null() {
+                    *value = Value::String("<redacted>".to_string());
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact_nested),
+        _ => {}
+    }
+}
+
+fn to_object(config: &PagerDutyAlertConfig) -> serde_json::Map<String, Value> {
+    match serde_json::to_value(config) {
+        Ok(Value::Object(map)) => map,
+        _ => unreachable!("configuration serializes to an object"),
+    }
+}
+
+fn subscriptions_by_name(
+    config: &serde_json::Map<String, Value>,
+) -> BTreeMap<String, serde_json::Map<String, Value>> {
+    let Some(Value::Array(subscriptions)) = config.get("subscriptions") else {
+        return BTreeMap::new();
+    };
+    subscriptions
+        .iter()
+        .filter_map(|sub| match sub {
+            Value::Object(map) => {
+                let name = map.get("name")?.as_str()?.to_string();
+                Some((name, map.clone()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn field_changes(
+    old: &serde_json::Map<String, Value>,
+    new: &serde_json::Map<String, Value>,
+) -> Vec<FieldChange> {
+    let mut fields: Vec<&String> = old.keys().chain(new.keys()).collect();
+    fields.sort_unstable();
+    fields.dedup();
+    fields
+        .into_iter()
+        .filter_map(|field| {
+            let old = old.get(field).cloned().unwrap_or(Value::Null);
+            let new = new.get(field).cloned().unwrap_or(Value::Null);
+            (old != new).then(|| FieldChange {
+                field: field.clone(),
+                old,
+                new,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_reports_subscription_and_setting_changes() {
+        let old = crate::venear_pause_config("old-key", "venear.near");
+        let mut new = crate::venear_pause_config("new-key", "venear.near");
+        assert!(ConfigDiff::between(&old, &old).is_empty());
+
+        new.subscriptions[0].severity = "error".to_string();
+        new.subscriptions.push(
+            crate::method_call_config("k", "dao.near", None)
+                .subscriptions
+                .remove(0),
+        );
+        new.drain_timeout_secs = 10;
+
+        let diff = ConfigDiff::between(&old, &new);
+        assert_eq!(diff.added, ["Contract Call: dao.near"]);
+        assert!(diff.removed.is_empty());
+        assert_eq!(diff.modified.len(), 1);
+        assert_eq!(diff.modified[0].fields[0].field, "severity");
+        let fields: Vec<&str> = diff.settings.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(fields, ["drain_timeout_secs", "pagerduty_routing_key"]);
+
+        let report = diff.to_string();
+        assert!(report.contains("+ subscription 'Contract Call: dao.near'"));
+        assert!(report.contains("severity: \"critical\" -> \"error\""));
+        assert!(!report.contains("new-key"));
+    }
+}
@@ -0,0 +1,1182 @@
+//! Concurrent delivery of rendered alerts to PagerDuty
+//!
+//! Matched alerts are buffered in a bounded [`AlertQueue`] that releases the
+//! most severe first and whose behavior when full is configurable, then sent
+//! in parallel up to a configured limit.
+//! Alerts that share a dedup key are still delivered in the order they were
+//! matched, so a re-trigger can never overtake the trigger it updates. With
+//! `coalesce`, a re-trigger still waiting in the queue is folded into the
+//! queued alert instead, which takes its summary and the higher of the two
+//! severities, so a block that re-triggers one key many times costs one
+//! request.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU32, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{oneshot, Notify, OwnedSemaphorePermit, Semaphore};
+
+use crate::{
+    AlertEvent, AlertQueueConfig, AlertStore, IncidentUpdate, MonitorStats, OverflowPolicy,
+    PagerDutyClient, PagerDutyRestClient,
+};
+#[cfg(feature = "sqlite")]
+use crate::{EventOutcome, EventStore};
+
+/// A fully rendered alert waiting to be sent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct PendingAlert {
+    /// Content-hash ID of the matched event, claimed in the alert store
+    pub event_id: String,
+    pub subscription: String,
+    pub severity: String,
+    pub summary: String,
+    pub source: String,
+    pub dedup_key: Option<String>,
+    pub custom_details: serde_json::Value,
+    pub explorer_link: Option<(String, String)>,
+    #[serde(default)]
+    pub runbook_url: Option<String>,
+    pub priority: Option<String>,
+    #[serde(default)]
+    pub escalation_policy: Option<String>,
+    #[serde(default)]
+    pub response_play: Option<String>,
+    pub alert_ttl_secs: Option<u64>,
+    /// Events folded into this alert by coalescing, delivered along with it
+    #[serde(default)]
+    pub coalesced_event_ids: Vec<String>,
+    /// Block time of a live event, for measuring delivery latency
+    #[serde(default)]
+    pub block_timestamp_ms: Option<f64>,
+    /// Add this event as a note to the open incident for its dedup key
+    /// instead of re-triggering, falling back to a trigger if there is none
+    #[serde(default)]
+    pub follow_up: bool,
+}
+
+/// What happened to an alert pushed onto a full queue
+#[derive(Debug)]
+pub(crate) enum PushOutcome {
+    Queued,
+    /// Folded into a queued alert with the same dedup key
+    Coalesced,
+    /// An alert was discarded: the oldest queued one or the one being pushed
+    Dropped(Box<PendingAlert>),
+    Spilled,
+}
+
+struct QueueInner {
+    items: VecDeque<Queued>,
+    /// Alerts written to disk, oldest first; they rejoin `items` as room frees up
+    spilled: VecDeque<Spilled>,
+    next_spill_seq: u64,
+}
+
+/// An alert in memory and the rank it leaves the queue at: its severity's,
+/// or that of a later alert with the same dedup key if that is higher
+struct Queued {
+    rank: u8,
+    alert: PendingAlert,
+}
+
+/// An alert on disk, with its dedup key so later ones for it wait their turn
+struct Spilled {
+    path: PathBuf,
+    dedup_key: Option<String>,
+}
+
+/// Delivery order of a PagerDuty severity; higher is sent first
+pub(crate) fn severity_rank(severity: &str) -> u8 {
+    match severity {
+        "critical" => 3,
+        "error" => 2,
+        "warning" => 1,
+        _ => 0,
+    }
+}
+
+/// Insert behind every alert of the same or higher rank, keeping FIFO order
+/// within a rank. Alerts that share a dedup key leave in the order they were
+/// matched, so earlier ones for the key that rank lower are raised to the new
+/// alert's rank and move up with it.
+fn insert_by_severity(items: &mut VecDeque<Queued>, alert: PendingAlert) {
+    let rank = severity_rank(&alert.severity);
+    let key = alert.dedup_key.clone();
+    raise_key(items, key.as_ref(), rank, Some(alert));
+}
+
+/// Move the queued alerts for `key` that rank below `rank` up to it, in the
+/// order they were matched, followed by `alert`
+fn raise_key(
+    items: &mut VecDeque<Queued>,
+    key: Option<&String>,
+    rank: u8,
+    alert: Option<PendingAlert>,
+) {
+    let mut group = Vec::new();
+    if let Some(key) = key {
+        // Ranks never rise along one key's alerts, so these are its last ones
+        let mut i = 0;
+        while i < items.len() {
+            if items[i].rank < rank && items[i].alert.dedup_key.as_ref() == Some(key) {
+                group.push(items.remove(i).expect("index is in bounds").alert);
+            } else {
+                i += 1;
+            }
+        }
+    }
+    group.extend(alert);
+    let index = items.partition_point(|q| q.rank >= rank);
+    for (offset, alert) in group.into_iter().enumerate() {
+        items.insert(index + offset, Queued { rank, alert });
+    }
+}
+
+/// Bounded queue of alerts waiting for delivery, most severe first
+///
+/// Alerts of the same severity leave in the order they arrived, and so do
+/// alerts with the same dedup key, whatever their severities. When the
+/// queue is full, the overflow policy applies to the lowest severity queued,
+/// so a burst of info alerts can never push out a critical one.
+pub(crate) struct AlertQueue {
+    inner: Mutex<QueueInner>,
+    capacity: usize,
+    policy: OverflowPolicy,
+    coalesce: bool,
+    spill_dir: PathBuf,
+    not_empty: Notify,
+    not_full: Notify,
+}
+
+impl AlertQueue {
+    pub fn new(config: &AlertQueueConfig) -> Result<Self, anyhow::Error> {
+        let spill_dir = config
+            .spill_dir
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| std::env::temp_dir().join("near-pagerduty-alerts-spill"));
+
+        // Pick up alerts spilled by a previous run so they are not lost
+        let mut spilled = Vec::new();
+        if config.overflow == OverflowPolicy::SpillToDisk {
+            std::fs::create_dir_all(&spill_dir)?;
+            for entry in std::fs::read_dir(&spill_dir)? {
+                let path = entry?.path();
+                if path.extension().is_some_and(|ext| ext == "json") {
+                    let dedup_key = std::fs::read(&path)
+                        .ok()
+                        .and_then(|json| serde_json::from_slice::<PendingAlert>(&json).ok())
+                        .and_then(|alert| alert.dedup_key);
+                    spilled.push(Spilled { path, dedup_key });
+                }
+            }
+            spilled.sort_by(|a, b| a.path.cmp(&b.path));
+            if !spilled.is_empty() {
+                log::warn!(
+                    "Recovered {} spilled alert(s) from {}",
+                    spilled.len(),
+                    spill_dir.display()
+                );
+            }
+        }
+        let next_spill_seq = spilled
+            .last()
+            .and_then(|s| s.path.file_stem()?.to_str()?.parse::<u64>().ok())
+            .map_or(0, |seq| seq + 1);
+
+        Ok(Self {
+            inner: Mutex::new(QueueInner {
+                items: VecDeque::new(),
+                spilled: spilled.into(),
+                next_spill_seq,
+            }),
+            capacity: config.capacity.max(1),
+            policy: config.overflow,
+            coalesce: config.coalesce,
+            spill_dir,
+            not_empty: Notify::new(),
+            not_full: Notify::new(),
+        })
+    }
+
+    /// Alerts waiting in memory and on disk
+    pub fn len(&self) -> usize {
+        let inner = self.inner.lock().unwrap();
+        inner.items.len() + inner.spilled.len()
+    }
+
+    /// Queue an alert, applying the overflow policy if the queue is full
+    pub async fn push(&self, alert: PendingAlert) -> PushOutcome {
+        loop {
+            let not_full = self.not_full.notified();
+            {
+                let mut inner = self.inner.lock().unwrap();
+                if let Some(index) = self.coalesce_target(&inner.items, &alert) {
+                    // The folded alert goes out with the newest summary at
+                    // the highest severity of the two
+                    let queued = &mut inner.items[index];
+                    let rank = severity_rank(&alert.severity).max(queued.rank);
+                    if rank > queued.rank {
+                        queued.alert.severity = alert.severity;
+                    }
+                    queued.alert.summary = alert.summary;
+                    queued.alert.coalesced_event_ids.push(alert.event_id);
+                    queued
+                        .alert
+                        .coalesced_event_ids
+                        .extend(alert.coalesced_event_ids);
+                    if rank > queued.rank {
+                        let key = queued.alert.dedup_key.clone();
+                        raise_key(&mut inner.items, key.as_ref(), rank, None);
+                    }
+                    return PushOutcome::Coalesced;
+                }
+                let rank = severity_rank(&alert.severity);
+                let lowest = inner.items.back().map(|q| q.rank);
+                // Alerts that don't outrank anything in memory wait behind
+                // spilled ones, as do those whose dedup key has any spilled
+                let behind_spill = !inner.spilled.is_empty()
+                    && (lowest.is_none_or(|low| rank <= low)
+                        || alert.dedup_key.as_ref().is_some_and(|key| {
+                            inner
+                                .spilled
+                                .iter()
+                                .any(|s| s.dedup_key.as_ref() == Some(key))
+                        }));
+                if inner.items.len() < self.capacity && !behind_spill {
+                    insert_by_severity(&mut inner.items, alert);
+                    self.not_empty.notify_one();
+                    return PushOutcome::Queued;
+                }
+
+                // The newest alert of the lowest severity, possibly the incoming one
+                let outranks_lowest = lowest.is_some_and(|low| rank > low) && !behind_spill;
+                match self.policy {
+                    OverflowPolicy::Block => {}
+                    OverflowPolicy::DropNewest if outranks_lowest => {
+                        let newest = inner.items.pop_back().expect("queue is not empty").alert;
+                        insert_by_severity(&mut inner.items, alert);
+                        self.not_empty.notify_one();
+                        return PushOutcome::Dropped(Box::new(newest));
+                    }
+                    OverflowPolicy::DropNewest => return PushOutcome::Dropped(Box::new(alert)),
+                    OverflowPolicy::DropOldest => {
+                        // Oldest alert of the lowest severity, unless the incoming one is lower
+                        let Some(low) = lowest.filter(|&low| rank >= low) else {
+                            return PushOutcome::Dropped(Box::new(alert));
+                        };
+                        let index = inner
+                            .items
+                            .iter()
+                            .position(|q| q.rank == low)
+                            .expect("lowest severity is queued");
+                        let oldest = inner.items.remove(index).expect("index is in bounds").alert;
+                        insert_by_severity(&mut inner.items, alert);
+                        self.not_empty.notify_one();
+                        return PushOutcome::Dropped(Box::new(oldest));
+                    }
+                    OverflowPolicy::SpillToDisk => {
+                        // A spilled alert can't make room for a later one with its dedup key
+                        let same_key = inner.items.back().is_some_and(|q| {
+                            q.alert.dedup_key.is_some() && q.alert.dedup_key == alert.dedup_key
+                        });
+                        let victim = if outranks_lowest && !same_key {
+                            let newest = inner.items.pop_back().expect("queue is not empty").alert;
+                            insert_by_severity(&mut inner.items, alert);
+                            newest
+                        } else {
+                            alert
+                        };
+                        let path = self
+                            .spill_dir
+                            .join(format!("{:020}.json", inner.next_spill_seq));
+                        let written = serde_json::to_vec(&victim)
+                            .map_err(anyhow::Error::from)
+                            .and_then(|json| Ok(std::fs::write(&path, json)?));
+                        if let Err(e) = written {
+                            log::error!("Failed to spill alert to {}: {:?}", path.display(), e);
+                            return PushOutcome::Dropped(Box::new(victim));
+                        }
+                        inner.next_spill_seq += 1;
+                        inner.spilled.push_back(Spilled {
+                            path,
+                            dedup_key: victim.dedup_key,
+                        });
+                        self.not_empty.notify_one();
+                        return PushOutcome::Spilled;
+                    }
+                }
+            }
+            not_full.await;
+        }
+    }
+
+    /// Index of a queued alert `alert` can be folded into: PagerDuty would deduplicate it anyway
+    fn coalesce_target(&self, items: &VecDeque<Queued>, alert: &PendingAlert) -> Option<usize> {
+        // A follow-up's note would be lost in the alert it was folded into
+        let key = alert
+            .dedup_key
+            .as_ref()
+            .filter(|_| self.coalesce && !alert.follow_up)?;
+        items.iter().position(|queued| {
+            queued.alert.dedup_key.as_ref() == Some(key) && !queued.alert.follow_up
+        })
+    }
+
+    /// Wait for the next alert
+    pub async fn pop(&self) -> PendingAlert {
+        loop {
+            let not_empty = self.not_empty.notified();
+            if let Some(alert) = self.try_pop() {
+                self.not_full.notify_one();
+                return alert;
+            }
+            not_empty.await;
+        }
+    }
+
+    fn try_pop(&self) -> Option<PendingAlert> {
+        let mut inner = self.inner.lock().unwrap();
+        // Move spilled alerts back into memory as room frees up
+        while inner.items.len() < self.capacity {
+            let Some(Spilled { path, .. }) = inner.spilled.pop_front() else {
+                break;
+            };
+            match std::fs::read(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|json| Ok(serde_json::from_slice::<PendingAlert>(&json)?))
+            {
+                Ok(alert) => insert_by_severity(&mut inner.items, alert),
+                Err(e) => log::error!("Lost spilled alert {}: {:?}", path.display(), e),
+            }
+            let _ = std::fs::remove_file(&path);
+        }
+        inner.items.pop_front().map(|q| q.alert)
+    }
+
+    /// Discard the oldest spilled alerts while they are `max_age` old
+    /// or more than `max_files` are on disk, returning those still readable
+    fn prune_spilled(
+        &self,
+        max_age: Option<Duration>,
+        max_files: Option<usize>,
+    ) -> Vec<PendingAlert> {
+        let mut inner = self.inner.lock().unwrap();
+        let now = SystemTime::now();
+        let mut pruned = Vec::new();
+        while let Some(Spilled { path, .. }) = inner.spilled.front() {
+            let over_count = max_files.is_some_and(|max| inner.spilled.len() > max);
+            let too_old = max_age.is_some_and(|max| {
+                path.metadata()
+                    .and_then(|m| m.modified())
+                    .is_ok_and(|spilled_at| {
+                        now.duration_since(spilled_at).unwrap_or_default() >= max
+                    })
+            });
+            if !over_count && !too_old {
+                break;
+            }
+            let Some(Spilled { path, .. }) = inner.spilled.pop_front() else {
+                break;
+            };
+            match std::fs::read(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|json| Ok(serde_json::from_slice::<PendingAlert>(&json)?))
+            {
+                Ok(alert) => pruned.push(alert),
+                Err(e) => log::error!("Lost spilled alert {}: {:?}", path.display(), e),
+            }
+            let _ = std::fs::remove_file(&path);
+        }
+        pruned
+    }
+}
+
+pub(crate) struct Dispatcher {
+    pd_client: Arc<PagerDutyClient>,
+    meta_client: Arc<PagerDutyClient>,
+    rest_client: Option<Arc<PagerDutyRestClient>>,
+    alerts: Arc<AlertStore>,
+    stats: Arc<MonitorStats>,
+    queue: AlertQueue,
+    permits: Arc<Semaphore>,
+    /// Alerts being sent; `run` holds a permit while it waits for the next
+    /// alert, so this can't be derived from the permits
+    in_flight: Arc<AtomicUsize>,
+    /// Dedup key -> signal that fires when the latest alert for that key is done
+    last_by_key: Mutex<HashMap<String, oneshot::Receiver<()>>>,
+    /// When the last queue overflow meta-alert was sent
+    last_overflow_alert: Mutex<Option<Instant>>,
+    /// Failed deliveries in a row that page the meta routing key; 0 never pages
+    failed_delivery_threshold: u32,
+    failed_deliveries: Arc<AtomicU32>,
+    /// Where the outcome of each alert's events is recorded
+    #[cfg(feature = "sqlite")]
+    event_store: Option<Arc<EventStore>>,
+}
+
+impl Dispatcher {
+    /// Minimum time between queue overflow meta-alerts
+    const OVERFLOW_ALERT_INTERVAL: Duration = Duration::from_secs(300);
+
+    pub fn new(
+        pd_client: Arc<PagerDutyClient>,
+        meta_client: Arc<PagerDutyClient>,
+        rest_client: Option<Arc<PagerDutyRestClient>>,
+        alerts: Arc<AlertStore>,
+        stats: Arc<MonitorStats>,
+        queue: AlertQueue,
+        max_concurrent: usize,
+    ) -> Self {
+        Self {
+            pd_client,
+            meta_client,
+            rest_client,
+            alerts,
+            stats,
+            queue,
+            permits: Arc::new(Semaphore::new(max_concurrent.max(1))),
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            last_by_key: Mutex::new(HashMap::new()),
+            last_overflow_alert: Mutex::new(None),
+            failed_delivery_threshold: 0,
+            failed_deliveries: Arc::new(AtomicU32::new(0)),
+            #[cfg(feature = "sqlite")]
+            event_store: None,
+        }
+    }
+
+    /// Page the meta routing key after `threshold` failed deliveries in a row
+    pub fn with_failed_delivery_alert(mut self, threshold: u32) -> Self {
+        self.failed_delivery_threshold = threshold;
+        self
+    }
+
+    /// Record delivery outcomes in `event_store`
+    #[cfg(feature = "sqlite")]
+    pub fn with_event_store(mut self, event_store: Option<Arc<EventStore>>) -> Self {
+        self.event_store = event_store;
+        self
+    }
+
+    pub fn queue_len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Queue an alert for delivery
+    pub async fn enqueue(&self, alert: PendingAlert) {
+        match self.queue.push(alert).await {
+            PushOutcome::Queued => {}
+            PushOutcome::Coalesced => self.stats.record_coalesced(),
+            PushOutcome::Spilled => self.stats.record_spilled(),
+            PushOutcome::Dropped(dropped) => {
+                self.alerts.release_event(&dropped.event_id);
+                for event_id in &dropped.coalesced_event_ids {
+                    self.alerts.release_event(event_id);
+                }
+                #[cfg(feature = "sqlite")]
+                record_outcome(
+                    self.event_store.as_deref(),
+                    std::iter::once(&dropped.event_id).chain(&dropped.coalesced_event_ids),
+                    EventOutcome::Dropped,
+                );
+                self.stats.record_dropped();
+                log::error!(
+                    "Alert queue full ({:?}), dropped alert for '{}': {}",
+                    self.queue.policy,
+                    dropped.subscription,
+                    dropped.summary
+                );
+                self.report_overflow();
+            }
+        }
+    }
+
+    /// Apply the spill directory's retention, counting what it discards as
+    /// dropped; returns the number of alerts discarded
+    pub fn prune_spilled(&self, max_age: Option<Duration>, max_files: Option<usize>) -> usize {
+        let pruned = self.queue.prune_spilled(max_age, max_files);
+        for dropped in &pruned {
+            self.alerts.release_event(&dropped.event_id);
+            for event_id in &dropped.coalesced_event_ids {
+                self.alerts.release_event(event_id);
+            }
+            #[cfg(feature = "sqlite")]
+            record_outcome(
+                self.event_store.as_deref(),
+                std::iter::once(&dropped.event_id).chain(&dropped.coalesced_event_ids),
+                EventOutcome::Dropped,
+            );
+            self.stats.record_dropped();
+            log::error!(
+                "Spilled alert for '{}' exceeded the spill retention and was dropped: {}",
+                dropped.subscription,
+                dropped.summary
+            );
+        }
+        if !pruned.is_empty() {
+            self.report_overflow();
+        }
+        pruned.len()
+    }
+
+    /// Page the meta routing key about dropped alerts, at most once per interval
+    fn report_overflow(&self) {
+        {
+            let mut last = self.last_overflow_alert.lock().unwrap();
+            if last.is_some_and(|at| at.elapsed() < Self::OVERFLOW_ALERT_INTERVAL) {
+                return;
+            }
+            *last = Some(Instant::now());
+        }
+
+        let meta_client = Arc::clone(&self.meta_client);
+        let dropped = self.stats.snapshot(Vec::new(), 0).errors.dropped_alerts;
+        let policy = self.queue.policy;
+        tokio::spawn(async move {
+            let summary = format!(
+                "NEAR PagerDuty Monitor alert queue overflowed: {} alert(s) dropped so far ({:?})",
+                dropped, policy
+            );
+            if let Err(e) = meta_client
+                .trigger(
+                    &summary,
+                    "near-pagerduty-monitor",
+                    "error",
+                    Some("near-pagerduty-monitor-queue-overflow".to_string()),
+                    Some(serde_json::json!({ "dropped_alerts": dropped })),
+                    &[],
+                )
+                .await
+            {
+                log::error!("Failed to send queue overflow alert: {:?}", e);
+            }
+        });
+    }
+
+    /// Deliver queued alerts until the task is aborted
+    ///
+    /// An alert only leaves the queue once a send slot is free, so aborting
+    /// this loop never loses an alert.
+    pub async fn run(&self) {
+        loop {
+            let permit = Arc::clone(&self.permits)
+                .acquire_owned()
+                .await
+                .expect("dispatch semaphore is never closed");
+            let alert = self.queue.pop().await;
+            self.dispatch(permit, alert);
+        }
+    }
+
+    /// Alerts currently being sent
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    /// Deliver everything queued and wait for in-flight sends, giving up after
+    /// `timeout`; returns the number of alerts left undelivered
+    pub async fn drain(&self, timeout: Duration) -> usize {
+        let deadline = tokio::time::Instant::now() + timeout;
+        let progress = async {
+            let mut interval = tokio::time::interval(Duration::from_millis(100));
+            for tick in 0u64.. {
+                interval.tick().await;
+                let (queued, in_flight) = (self.queue.len(), self.in_flight());
+                if queued == 0 && in_flight == 0 {
+                    return;
+                }
+                if tick % 50 == 0 {
+                    log::info!("Draining: {} queued, {} in flight", queued, in_flight);
+                }
+            }
+        };
+        tokio::select! {
+            _ = self.run() => {}
+            _ = progress => {}
+            _ = tokio::time::sleep_until(deadline) => {}
+        }
+        self.queue.len() + self.in_flight()
+    }
+
+    /// Start sending an alert in the slot held by `permit`
+    fn dispatch(&self, permit: OwnedSemaphorePermit, alert: PendingAlert) {
+        let (done_tx, done_rx) = oneshot::channel();
+        let previous = alert.dedup_key.as_ref().and_then(|key| {
+            let mut last_by_key = self.last_by_key.lock().unwrap();
+            // Forget keys whose last alert has finished
+            last_by_key
+                .retain(|_, rx| matches!(rx.try_recv(), Err(oneshot::error::TryRecvError::Empty)));
+            last_by_key.insert(key.clone(), done_rx)
+        });
+
+        let in_flight = InFlight::start(&self.in_flight);
+        let pd_client = Arc::clone(&self.pd_client);
+        let rest_client = self.rest_client.clone();
+        let alerts = Arc::clone(&self.alerts);
+        let stats = Arc::clone(&self.stats);
+        let meta_client = Arc::clone(&self.meta_client);
+        let (failed_deliveries, threshold) = (
+            Arc::clone(&self.failed_deliveries),
+            self.failed_delivery_threshold,
+        );
+        #[cfg(feature = "sqlite")]
+        let (event_store, event_ids): (_, Vec<String>) = (
+            self.event_store.clone(),
+            std::iter::once(&alert.event_id)
+                .chain(&alert.coalesced_event_ids)
+                .cloned()
+                .collect(),
+        );
+        tokio::spawn(async move {
+            if let Some(previous) = previous {
+                // Resolves when the earlier alert finishes, whether or not it succeeded
+                let _ = previous.await;
+            }
+            let result = send(&pd_client, rest_client, &alerts, &stats, alert).await;
+            #[cfg(feature = "sqlite")]
+            record_outcome(
+                event_store.as_deref(),
+                &event_ids,
+                if result.is_ok() {
+                    EventOutcome::Delivered
+                } else {
+                    EventOutcome::Failed
+                },
+            );
+            match &result {
+                Ok(()) => {
+                    let failed_before = failed_deliveries.swap(0, Ordering::Relaxed);
+                    if threshold > 0 && failed_before >= threshold {
+                        log::info!("PagerDuty deliveries are succeeding again");
+                        if let Err(e) = meta_client.resolve(FAILED_DELIVERIES_DEDUP_KEY).await {
+                            log::error!("Failed to resolve delivery failure alert: {:?}", e);
+                        }
+                    }
+                }
+                Err(e) => {
+                    log::error!("Error processing action: {:?}", e);
+                    let failed_in_a_row = failed_deliveries.fetch_add(1, Ordering::Relaxed) + 1;
+                    if failed_in_a_row == threshold {
+                        report_failed_deliveries(&meta_client, failed_in_a_row, e).await;
+                    }
+                }
+            }
+            drop(in_flight);
+            drop(permit);
+            drop(done_tx);
+        });
+    }
+}
+
+/// Counts an alert as in flight until dropped, even if sending it panics
+struct InFlight(Arc<AtomicUsize>);
+
+impl InFlight {
+    fn start(count: &Arc<AtomicUsize>) -> Self {
+        count.fetch_add(1, Ordering::Relaxed);
+        Self(Arc::clone(count))
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+const FAILED_DELIVERIES_DEDUP_KEY: &str = "near-pagerduty-monitor-delivery-failures";
+
+/// Page the meta routing key about alerts that keep failing to reach PagerDuty
+///
+/// If the meta routing key is the main one and PagerDuty itself is
+/// unreachable, this fails too; the error is still logged.
+async fn report_failed_deliveries(
+    meta_client: &PagerDutyClient,
+    failed_in_a_row: u32,
+    error: &anyhow::Error,
+) {
+    let summary = format!(
+        "NEAR PagerDuty Monitor failed to deliver {} alerts in a row",
+        failed_in_a_row
+    );
+    log::error!("{}", summary);
+    let details = serde_json::json!({
+        "failed_deliveries": failed_in_a_row,
+        "last_error": format!("{:#}", error),
+    });
+    if let Err(e) = meta_client
+        .trigger(
+            &summary,
+            "near-pagerduty-monitor",
+            "critical",
+            Some(FAILED_DELIVERIES_DEDUP_KEY.to_string()),
+            Some(details),
+            &[],
+        )
+        .await
+    {
+        log::error!("Failed to send delivery failure alert: {:?}", e);
+    }
+}
+
+/// Record the outcome of an alert's events in the event store, if any
+#[cfg(feature = "sqlite")]
+fn record_outcome<'a>(
+    event_store: Option<&EventStore>,
+    event_ids: impl IntoIterator<Item = &'a String>,
+    outcome: EventOutcome,
+) {
+    let Some(store) = event_store else {
+        return;
+    };
+    for event_id in event_ids {
+        if let Err(e) = store.set_outcome(event_id, outcome) {
+            log::warn!(
+                "Could not record the outcome of event {}: {:#}",
+                event_id,
+                e
+            );
+        }
+    }
+}
+
+async fn send(
+    pd_client: &PagerDutyClient,
+    rest_client: Option<Arc<PagerDutyRestClient>>,
+    alerts: &AlertStore,
+    stats: &MonitorStats,
+    alert: PendingAlert,
+) -> Result<(), anyhow::Error> {
+    if let (true, Some(rest_client), Some(dedup_key)) =
+        (alert.follow_up, &rest_client, &alert.dedup_key)
+    {
+        match rest_client
+            .add_note_for(dedup_key, &follow_up_note(&alert))
+            .await
+        {
+            Ok(true) => {
+                stats.record_delivery(&alert.subscription, Some(dedup_key), true);
+                std::iter::once(&alert.event_id)
+                    .chain(&alert.coalesced_event_ids)
+                    .for_each(|id| alerts.mark_event_sent(id));
+                return Ok(());
+            }
+            Ok(false) => log::info!(
+                "No open incident for {}; triggering instead of adding a note",
+                dedup_key
+            ),
+            Err(e) => log::warn!(
+                "Failed to add a note for {}, triggering instead: {:#}",
+                dedup_key,
+                e
+            ),
+        }
+    }
+
+    let result = pd_client
+        .trigger(
+            &alert.summary,
+            &alert.source,
+            &alert.severity,
+            alert.dedup_key.clone(),
+            Some(alert.custom_details),
+            &alert
+                .explorer_link
+                .iter()
+                .map(|(h, t)| (h.as_str(), t.as_str()))
+                .chain(alert.runbook_url.as_deref().map(|url| (url, "Runbook")))
+                .collect::<Vec<_>>(),
+        )
+        .await;
+    stats.record_delivery(
+        &alert.subscription,
+        alert.dedup_key.as_deref(),
+        result.is_ok(),
+    );
+    let event_ids = std::iter::once(&alert.event_id).chain(&alert.coalesced_event_ids);
+    let response = match result {
+        Ok(response) => response,
+        Err(e) => {
+            event_ids.for_each(|id| alerts.release_event(id));
+            return Err(e);
+        }
+    };
+    event_ids.for_each(|id| alerts.mark_event_sent(id));
+    if let Some(block_ms) = alert.block_timestamp_ms {
+        let latency_ms = chrono::Utc::now().timestamp_millis() as f64 - block_ms;
+        stats.record_delivery_latency((latency_ms / 1000.0).max(0.0));
+    }
+
+    // Without a dedup template, track the alert under the key PagerDuty
+    // assigned so it can still be acknowledged and resolved
+    let tracked_key = match (&alert.dedup_key, &response.dedup_key) {
+        (Some(key), _) => Some(key.clone()),
+        (None, Some(assigned)) => {
+            alerts.record_match(
+                assigned,
+                &alert.subscription,
+                &alert.severity,
+                &alert.summary,
+                alert.alert_ttl_secs,
+            );
+            Some(assigned.clone())
+        }
+        (None, None) => None,
+    };
+    if let Some(key) = &tracked_key {
+        if let Err(e) = alerts.apply(key, AlertEvent::Delivered) {
+            log::warn!("{}", e);
+        }
+    }
+
+    // The Events API can't touch the incident, so priority, escalation policy
+    // and response plays go through the REST API once PagerDuty has created it
+    let update = IncidentUpdate {
+        priority: alert.priority,
+        escalation_policy: alert.escalation_policy,
+        response_play: alert.response_play,
+    };
+    if let (false, Some(rest_client), Some(dedup_key)) =
+        (update.is_empty(), rest_client, tracked_key)
+    {
+        tokio::spawn(async move {
+            if let Err(e) = rest_client.apply_update(&dedup_key, &update).await {
+                log::error!("Failed to update the incident for {}: {:#}", dedup_key, e);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// PagerDuty notes are limited to 25,000 characters
+const NOTE_MAX_CHARS: usize = 25_000;
+
+/// Incident note text for a follow-up event: when it happened, its summary,
+/// explorer link and details
+fn follow_up_note(alert: &PendingAlert) -> String {
+    let time = alert
+        .block_timestamp_ms
+        .and_then(|ms| chrono::DateTime::<chrono::Utc>::from_timestamp_millis(ms as i64))
+        .unwrap_or_else(chrono::Utc::now);
+    let mut note = format!(
+        "[{}] {}",
+        time.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        alert.summary
+    );
+    if let Some((href, _)) = &alert.explorer_link {
+        note.push_str(&format!("\n{}", href));
+    }
+    note.push_str(&format!("\n\n{}", alert.custom_details));
+    match note.char_indices().nth(NOTE_MAX_CHARS) {
+        Some((end, _)) => note[..end].to_string(),
+        None => note,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending(summary: &str) -> PendingAlert {
+        with_severity(summary, "info")
+    }
+
+    fn with_severity(summary: &str, severity: &str) -> PendingAlert {
+        PendingAlert {
+            event_id: summary.to_string(),
+            subscription: "sub".to_string(),
+            severity: severity.to_string(),
+            summary: summary.to_string(),
+            source: "near:venear.near".to_string(),
+            dedup_key: None,
+            custom_details: serde_json::json!({}),
+            explorer_link: None,
+            runbook_url: None,
+            priority: None,
+            escalation_policy: None,
+            response_play: None,
+            alert_ttl_secs: None,
+            coalesced_event_ids: Vec::new(),
+            block_timestamp_ms: None,
+            follow_up: false,
+        }
+    }
+
+    fn queue(overflow: OverflowPolicy, spill_dir: Option<String>) -> AlertQueue {
+        AlertQueue::new(&AlertQueueConfig {
+            capacity: 2,
+            overflow,
+            spill_dir,
+            coalesce: false,
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_overflow_policies() {
+        let oldest = queue(OverflowPolicy::DropOldest, None);
+        for summary in ["a", "b", "c"] {
+            if let PushOutcome::Dropped(alert) = oldest.push(pending(summary)).await {
+                assert_eq!(alert.summary, "a");
+            }
+        }
+        assert_eq!(oldest.pop().await.summary, "b");
+
+        let newest = queue(OverflowPolicy::DropNewest, None);
+        newest.push(pending("a")).await;
+        newest.push(pending("b")).await;
+        assert!(
+            matches!(newest.push(pending("c")).await, PushOutcome::Dropped(a) if a.summary == "c")
+        );
+
+        let dir = std::env::temp_dir().join(format!("alert-spill-{}", std::process::id()));
+        let spill = queue(OverflowPolicy::SpillToDisk, Some(dir.display().to_string()));
+        for summary in ["a", "b", "c", "d"] {
+            spill.push(pending(summary)).await;
+        }
+        assert_eq!(spill.len(), 4);
+        let order: Vec<String> = [(); 4]
+            .iter()
+            .map(|_| spill.try_pop().unwrap().summary)
+            .collect();
+        assert_eq!(order, ["a", "b", "c", "d"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_spill_retention() {
+        let dir =
+            std::env::temp_dir().join(format!("alert-spill-retention-{}", std::process::id()));
+        let spill = queue(OverflowPolicy::SpillToDisk, Some(dir.display().to_string()));
+        for summary in ["a", "b", "c", "d", "e"] {
+            spill.push(pending(summary)).await;
+        }
+        // Two in memory, three on disk; only the oldest spilled one goes
+        let pruned = spill.prune_spilled(Some(Duration::from_secs(3600)), Some(2));
+        assert_eq!(
+            pruned
+                .iter()
+                .map(|a| a.summary.as_str())
+                .collect::<Vec<_>>(),
+            ["c"]
+        );
+        assert!(spill
+            .prune_spilled(Some(Duration::from_secs(3600)), None)
+            .is_empty());
+        assert_eq!(spill.prune_spilled(Some(Duration::ZERO), None).len(), 2);
+        assert_eq!(spill.len(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_follow_up_note() {
+        let alert = PendingAlert {
+            explorer_link: Some((
+                "https://nearblocks.io/txns/abc".to_string(),
+                "View Transaction".to_string(),
+            )),
+            custom_details: serde_json::json!({"method_name": "act_proposal"}),
+            block_timestamp_ms: Some(1_705_321_800_000.0),
+            ..pending("Vote on proposal 7")
+        };
+        assert_eq!(
+            follow_up_note(&alert),
+            "[2024-01-15T12:30:00Z] Vote on proposal 7\nhttps://nearblocks.io/txns/abc\n\n{\"method_name\":\"act_proposal\"}"
+        );
+        let huge = PendingAlert {
+            custom_details: serde_json::json!({"args": "é".repeat(NOTE_MAX_CHARS)}),
+            ..pending("big")
+        };
+        assert_eq!(follow_up_note(&huge).chars().count(), NOTE_MAX_CHARS);
+    }
+
+    #[tokio::test]
+    async fn test_coalescing_folds_retriggers_into_queued_alert() {
+        let queue = AlertQueue::new(&AlertQueueConfig {
+            capacity: 10,
+            overflow: OverflowPolicy::Block,
+            spill_dir: None,
+            coalesce: true,
+        })
+        .unwrap();
+        let keyed = |summary: &str, key: &str| PendingAlert {
+            dedup_key: Some(key.to_string()),
+            ..pending(summary)
+        };
+        assert!(matches!(
+            queue.push(keyed("vote 1", "proposal-1")).await,
+            PushOutcome::Queued
+        ));
+        assert!(matches!(
+            queue.push(keyed("vote 2", "proposal-2")).await,
+            PushOutcome::Queued
+        ));
+        assert!(matches!(
+            queue.push(keyed("vote 3", "proposal-1")).await,
+            PushOutcome::Coalesced
+        ));
+        assert!(matches!(
+            queue.push(pending("unkeyed")).await,
+            PushOutcome::Queued
+        ));
+        // A more severe re-trigger raises the queued alert ahead of the rest
+        let escalated = PendingAlert {
+            dedup_key: Some("proposal-2".to_string()),
+            ..with_severity("vote 4", "critical")
+        };
+        assert!(matches!(
+            queue.push(escalated).await,
+            PushOutcome::Coalesced
+        ));
+        let calmer = PendingAlert {
+            dedup_key: Some("proposal-2".to_string()),
+            ..with_severity("vote 5", "info")
+        };
+        assert!(matches!(queue.push(calmer).await, PushOutcome::Coalesced));
+
+        assert_eq!(queue.len(), 3);
+        let first = queue.pop().await;
+        assert_eq!(first.summary, "vote 5");
+        assert_eq!(first.severity, "critical");
+        assert_eq!(first.coalesced_event_ids, ["vote 4", "vote 5"]);
+        let second = queue.pop().await;
+        assert_eq!(second.summary, "vote 3");
+        assert_eq!(second.severity, "info");
+        assert_eq!(second.coalesced_event_ids, ["vote 3"]);
+    }
+
+    #[cfg(feature = "http-api")]
+    #[tokio::test]
+    async fn test_failed_deliveries_page_the_meta_key() {
+        // An Events API that rejects the main routing key while `down` is set
+        // and records what the meta routing key was sent
+        type Received = Arc<Mutex<Vec<(String, String)>>>;
+        let down = Arc::new(std::sync::atomic::AtomicBool::new(true));
+        let received: Received = Arc::default();
+        let app = axum::Router::new()
+            .route(
+                "/v2/enqueue",
+                axum::routing::post(
+                    |axum::extract::State((down, received)): axum::extract::State<(Arc<std::sync::atomic::AtomicBool>, Received)>,
+                     axum::Json(event): axum::Json<serde_json::Value>| async move {
+                        let key = event["dedup_key"].as_str().unwrap_or("assigned").to_string();
+                        if event["routing_key"] == "meta" {
+                            received.lock().unwrap().push((event["event_action"].as_str().unwrap().to_string(), key.clone()));
+                        } else if down.load(Ordering::Relaxed) {
+                            let body = serde_json::json!({"status": "invalid event", "message": "down", "errors": []});
+                            return (axum::http::StatusCode::BAD_REQUEST, axum::Json(body));
+                        }
+                        let body = serde_json::json!({"status": "success", "message": "Event processed", "dedup_key": key});
+                        (axum::http::StatusCode::ACCEPTED, axum::Json(body))
+                    },
+                ),
+            )
+            .with_state((Arc::clone(&down), Arc::clone(&received)));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let client = |key: &str| {
+            Arc::new(PagerDutyClient::new(key.to_string()).with_events_api_url(url.clone()))
+        };
+        let dispatcher = Dispatcher::new(
+            client("main"),
+            client("meta"),
+            None,
+            Arc::new(AlertStore::new()),
+            Arc::new(MonitorStats::new(&[])),
+            queue(OverflowPolicy::Block, None),
+            1,
+        )
+        .with_failed_delivery_alert(3);
+        let deliver = |summary: &'static str| async {
+            dispatcher.enqueue(pending(summary)).await;
+            assert_eq!(dispatcher.drain(Duration::from_secs(5)).await, 0);
+        };
+
+        for summary in ["a", "b", "c", "d"] {
+            deliver(summary).await;
+        }
+        let paged = (
+            "trigger".to_string(),
+            FAILED_DELIVERIES_DEDUP_KEY.to_string(),
+        );
+        assert_eq!(*received.lock().unwrap(), std::slice::from_ref(&paged));
+
+        down.store(false, Ordering::Relaxed);
+        deliver("e").await;
+        deliver("f").await;
+        let resolved = (
+            "resolve".to_string(),
+            FAILED_DELIVERIES_DEDUP_KEY.to_string(),
+        );
+        assert_eq!(*received.lock().unwrap(), [paged, resolved]);
+    }
+
+    #[tokio::test]
+    async fn test_severe_alerts_jump_the_queue() {
+        let queue = queue(OverflowPolicy::DropOldest, None);
+        queue.push(with_severity("vote 1", "info")).await;
+        queue.push(with_severity("vote 2", "info")).await;
+        // Full of info alerts: the critical one displaces the oldest of them
+        match queue.push(with_severity("paused", "critical")).await {
+            PushOutcome::Dropped(alert) => assert_eq!(alert.summary, "vote 1"),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(queue.pop().await.summary, "paused");
+        queue.push(with_severity("owner changed", "warning")).await;
+        assert_eq!(queue.pop().await.summary, "owner changed");
+        assert_eq!(queue.pop().await.summary, "vote 2");
+    }
+
+    #[tokio::test]
+    async fn test_same_dedup_key_keeps_its_order() {
+        let queue = AlertQueue::new(&AlertQueueConfig {
+            capacity: 10,
+            overflow: OverflowPolicy::Block,
+            spill_dir: None,
+            coalesce: false,
+        })
+        .unwrap();
+        let keyed = |summary: &str, severity: &str, key: &str| PendingAlert {
+            dedup_key: Some(key.to_string()),
+            ..with_severity(summary, severity)
+        };
+        queue
+            .push(keyed("proposal 1 opened", "info", "proposal-1"))
+            .await;
+        queue.push(with_severity("vote", "warning")).await;
+        queue.push(with_severity("other vote", "error")).await;
+        // The escalation pulls the earlier trigger for its key along with it
+        queue
+            .push(keyed("proposal 1 escalated", "critical", "proposal-1"))
+            .await;
+        queue
+            .push(keyed("proposal 1 note", "info", "proposal-1"))
+            .await;
+        let mut order = Vec::new();
+        while let Some(alert) = queue.try_pop() {
+            order.push(alert.summary);
+        }
+        assert_eq!(
+            order,
+            [
+                "proposal 1 opened",
+                "proposal 1 escalated",
+                "other vote",
+                "vote",
+                "proposal 1 note"
+            ]
+        );
+    }
+}
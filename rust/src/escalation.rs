@@ -0,0 +1,75 @@
+//! Raising the severity of alerts that keep firing
+//!
+//! With `severity_escalation`, the first occurrence of a dedup key alerts at
+//! the first step's severity. Each time the key fires again within
+//! `window_secs` of its previous occurrence, it is re-triggered one step
+//! higher, up to the last. A quiet window starts over at the first step. The
+//! alert store keeps the current step with the alert's lifecycle record.
+
+use serde::{Deserialize, Serialize};
+
+/// Settings of `severity_escalation`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SeverityEscalationConfig {
+    /// Seconds within which a repeat escalates (default: 600)
+    #[serde(default = "default_escalation_window")]
+    pub window_secs: u64,
+    /// Severities from the first occurrence on (default: warning, error, critical)
+    #[serde(default = "default_escalation_steps")]
+    pub steps: Vec<String>,
+}
+
+fn default_escalation_window() -> u64 {
+    600
+}
+
+fn default_escalation_steps() -> Vec<String> {
+    vec![
+        "warning".to_string(),
+        "error".to_string(),
+        "critical".to_string(),
+    ]
+}
+
+impl SeverityEscalationConfig {
+    /// Severity after `repeats` occurrences in a row, staying at the last step
+    pub fn severity(&self, repeats: u32) -> Option<&str> {
+        let step = (repeats as usize).min(self.steps.len().checked_sub(1)?);
+        Some(&self.steps[step])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AlertStore;
+
+    #[test]
+    fn test_severity_escalates_on_repeats() {
+        let config: SeverityEscalationConfig = serde_yaml::from_str("window_secs: 60").unwrap();
+        let severities: Vec<_> = (0..4)
+            .map(|repeats| config.severity(repeats).unwrap())
+            .collect();
+        assert_eq!(severities, ["warning", "error", "critical", "critical"]);
+        let empty = SeverityEscalationConfig {
+            window_secs: 60,
+            steps: Vec::new(),
+        };
+        assert_eq!(empty.severity(0), None);
+
+        let store = AlertStore::new();
+        let now = std::time::Instant::now();
+        // The first occurrence has no record to escalate
+        assert_eq!(store.escalate("k", 60, now), 0);
+        store.record_match("k", "sub", "warning", "flapping", None);
+        assert_eq!(store.escalate("k", 60, now), 1);
+        store.record_match("k", "sub", "error", "flapping", None);
+        assert_eq!(store.escalate("k", 60, now), 2);
+        // A repeat after a quiet window starts over
+        assert_eq!(
+            store.escalate("k", 60, now + std::time::Duration::from_secs(300)),
+            0
+        );
+        assert_eq!(store.get("k").unwrap().escalation_level, 0);
+    }
+}
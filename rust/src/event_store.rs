@@ -0,0 +1,409 @@
+//! Matched events kept in SQLite
+//!
+//! The state file only tracks alerts; this store keeps every match with the
+//! action that caused it and what became of its alert, so operators can
+//! answer "what fired last Tuesday" or "why didn't this page" from the
+//! `query` command without PagerDuty access, and run the recorded actions
+//! through the subscriptions again with `reprocess`. Compaction deletes
+//! events past the retention period, then the oldest ones while the database
+//! is over its size limit, and vacuums the file if anything was deleted.
+
+use std::{fmt, path::Path, str::FromStr, sync::Mutex};
+
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+/// A stored match
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StoredEvent {
+    pub time: DateTime<Utc>,
+    pub event_id: String,
+    pub subscription: String,
+    pub network: String,
+    pub account_id: String,
+    pub method_name: Option<String>,
+    pub predecessor_id: Option<String>,
+    pub block_height: u64,
+    pub tx_hash: Option<String>,
+    pub severity: String,
+    pub summary: String,
+    pub dedup_key: Option<String>,
+    /// The neardata action, as JSON
+    pub action: serde_json::Value,
+    /// The whole neardata action as received, with its block and receipt
+    /// (unset for events recorded before it was kept)
+    pub raw: Option<serde_json::Value>,
+    pub outcome: EventOutcome,
+}
+
+/// What became of a match's alert
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventOutcome {
+    /// Waiting in the alert queue
+    Queued,
+    /// Accepted by PagerDuty
+    Delivered,
+    /// PagerDuty rejected it or could not be reached; a replay may retry it
+    Failed,
+    /// Dropped by a full alert queue or the spill retention
+    Dropped,
+    /// Not sent because of a maintenance window
+    Suppressed,
+    /// Sent as a change event because of a maintenance window
+    ChangeEvent,
+    /// Withheld because the subscription is in `shadow` mode
+    Shadowed,
+}
+
+impl EventOutcome {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Queued => "queued",
+            Self::Delivered => "delivered",
+            Self::Failed => "failed",
+            Self::Dropped => "dropped",
+            Self::Suppressed => "suppressed",
+            Self::ChangeEvent => "change_event",
+            Self::Shadowed => "shadowed",
+        }
+    }
+}
+
+impl fmt::Display for EventOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for EventOutcome {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [
+            Self::Queued,
+            Self::Delivered,
+            Self::Failed,
+            Self::Dropped,
+            Self::Suppressed,
+            Self::ChangeEvent,
+            Self::Shadowed,
+        ]
+        .into_iter()
+        .find(|outcome| outcome.as_str() == s)
+        .ok_or_else(|| anyhow::anyhow!("unknown event outcome '{}'", s))
+    }
+}
+
+/// Filters of [`EventStore::query`]; unset fields match everything
+#[derive(Debug, Clone, Default)]
+pub struct EventQuery {
+    pub subscription: Option<String>,
+    pub account_id: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub outcome: Option<EventOutcome>,
+    /// Newest events returned (default: all)
+    pub limit: Option<usize>,
+}
+
+pub struct EventStore {
+    connection: Mutex<Connection>,
+    retention: Duration,
+    max_size_bytes: Option<u64>,
+}
+
+impl EventStore {
+    /// Share of the events deleted at a time while over the size limit
+    const SIZE_PRUNE_FRACTION: u64 = 10;
+
+    /// Open or create the store at `path`, keeping events for `retention_days`
+    /// and the database within `max_size_bytes`
+    pub fn open(
+        path: impl AsRef<Path>,
+        retention_days: u64,
+        max_size_bytes: Option<u64>,
+    ) -> Result<Self, anyhow::Error> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             CREATE TABLE IF NOT EXISTS events (
+                 time_ms INTEGER NOT NULL,
+                 event_id TEXT NOT NULL,
+                 subscription TEXT NOT NULL,
+                 network TEXT NOT NULL,
+                 account_id TEXT NOT NULL,
+                 method_name TEXT,
+                 predecessor_id TEXT,
+                 block_height INTEGER NOT NULL,
+                 tx_hash TEXT,
+                 severity TEXT NOT NULL,
+                 summary TEXT NOT NULL,
+                 dedup_key TEXT,
+                 action TEXT NOT NULL,
+                 raw TEXT,
+                 outcome TEXT NOT NULL DEFAULT 'queued'
+             );
+             CREATE INDEX IF NOT EXISTS events_time ON events (time_ms);
+             CREATE INDEX IF NOT EXISTS events_subscription ON events (subscription, time_ms);
+             CREATE INDEX IF NOT EXISTS events_account ON events (account_id, time_ms);",
+        )?;
+        // Databases created before the raw action and outcome were kept
+        let columns: Vec<String> = connection
+            .prepare("SELECT name FROM pragma_table_info('events')")?
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        if !columns.iter().any(|c| c == "raw") {
+            connection.execute_batch(
+                "ALTER TABLE events ADD COLUMN raw TEXT;
+                 ALTER TABLE events ADD COLUMN outcome TEXT NOT NULL DEFAULT 'queued';",
+            )?;
+        }
+        connection
+            .execute_batch("CREATE INDEX IF NOT EXISTS events_event_id ON events (event_id);")?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+            retention: Duration::days(retention_days as i64),
+            max_size_bytes,
+        })
+    }
+
+    pub fn record(&self, event: &StoredEvent) -> Result<(), anyhow::Error> {
+        let connection = self.connection.lock().unwrap();
+        connection.execute(
+            "INSERT INTO events (time_ms, event_id, subscription, network, account_id, method_name,
+                 predecessor_id, block_height, tx_hash, severity, summary, dedup_key, action, raw, outcome)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+            params![
+                event.time.timestamp_millis(),
+                event.event_id,
+                event.subscription,
+                event.network,
+                event.account_id,
+                event.method_name,
+                event.predecessor_id,
+                event.block_height as i64,
+                event.tx_hash,
+                event.severity,
+                event.summary,
+                event.dedup_key,
+                event.action.to_string(),
+                event.raw.as_ref().map(|raw| raw.to_string()),
+                event.outcome.as_str(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Record what became of the alert for `event_id`
+    pub fn set_outcome(&self, event_id: &str, outcome: EventOutcome) -> Result<(), anyhow::Error> {
+        self.connection.lock().unwrap().execute(
+            "UPDATE events SET outcome = ?2 WHERE event_id = ?1",
+            params![event_id, outcome.as_str()],
+        )?;
+        Ok(())
+    }
+
+    /// Apply retention and the size limit as of `now`, vacuuming if events
+    /// were deleted; returns the number deleted
+    pub fn compact(&self, now: DateTime<Utc>) -> Result<usize, anyhow::Error> {
+        let connection = self.connection.lock().unwrap();
+        let mut deleted = connection.execute(
+            "DELETE FROM events WHERE time_ms < ?1",
+            params![(now - self.retention).timestamp_millis()],
+        )?;
+        if let Some(max_size) = self.max_size_bytes {
+            loop {
+                let used: i64 = connection.query_row(
+                    "SELECT (page_count - freelist_count) * page_size
+                     FROM pragma_page_count(), pragma_freelist_count(), pragma_page_size()",
+                    [],
+                    |row| row.get(0),
+                )?;
+                let count: i64 =
+                    connection.query_row("SELECT COUNT(*) FROM events", [], |row| row.get(0))?;
+                if used as u64 <= max_size || count == 0 {
+                    break;
+                }
+                deleted += connection.execute(
+                    "DELETE FROM events WHERE rowid IN (SELECT rowid FROM events ORDER BY time_ms LIMIT ?1)",
+                    params![(count as u64 / Self::SIZE_PRUNE_FRACTION).max(1) as i64],
+                )?;
+            }
+        }
+        if deleted > 0 {
+            connection.execute_batch("VACUUM")?;
+            log::info!(
+                "Deleted {} event(s) from the event store and vacuumed it",
+                deleted
+            );
+        }
+        Ok(deleted)
+    }
+
+    /// Events matching every filter, newest first
+    pub fn query(&self, query: &EventQuery) -> Result<Vec<StoredEvent>, anyhow::Error> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare(
+            "SELECT time_ms, event_id, subscription, network, account_id, method_name, predecessor_id,
+                 block_height, tx_hash, severity, summary, dedup_key, action, raw, outcome
+             FROM events
+             WHERE (?1 IS NULL OR subscription = ?1)
+               AND (?2 IS NULL OR account_id = ?2)
+               AND (?3 IS NULL OR time_ms >= ?3)
+               AND (?4 IS NULL OR time_ms < ?4)
+               AND (?5 IS NULL OR outcome = ?5)
+             ORDER BY time_ms DESC
+             LIMIT ?6",
+        )?;
+        let rows = statement.query_map(
+            params![
+                query.subscription,
+                query.account_id,
+                query.since.map(|t| t.timestamp_millis()),
+                query.until.map(|t| t.timestamp_millis()),
+                query.outcome.map(EventOutcome::as_str),
+                query.limit.map_or(-1, |limit| limit as i64),
+            ],
+            |row| {
+                let action: String = row.get(12)?;
+                let raw: Option<String> = row.get(13)?;
+                let outcome: String = row.get(14)?;
+                Ok(StoredEvent {
+                    time: DateTime::from_timestamp_millis(row.get(0)?).unwrap_or_default(),
+                    event_id: row.get(1)?,
+                    subscription: row.get(2)?,
+                    network: row.get(3)?,
+                    account_id: row.get(4)?,
+                    method_name: row.get(5)?,
+                    predecessor_id: row.get(6)?,
+                    block_height: row.get::<_, i64>(7)? as u64,
+                    tx_hash: row.get(8)?,
+                    severity: row.get(9)?,
+                    summary: row.get(10)?,
+                    dedup_key: row.get(11)?,
+                    action: serde_json::from_str(&action)
+                        .unwrap_or(serde_json::Value::String(action)),
+                    raw: raw.and_then(|raw| serde_json::from_str(&raw).ok()),
+                    outcome: outcome.parse().unwrap_or(EventOutcome::Queued),
+                })
+            },
+        )?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_and_retention() {
+        let store = EventStore::open(":memory:", 30, None).unwrap();
+        let event = |days_ago: i64, subscription: &str, account_id: &str| StoredEvent {
+            time: "2026-05-31T12:00:00Z".parse::<DateTime<Utc>>().unwrap()
+                - Duration::days(days_ago),
+            event_id: format!("{}-{}", subscription, days_ago),
+            subscription: subscription.to_string(),
+            network: "mainnet".to_string(),
+            account_id: account_id.to_string(),
+            method_name: Some("pause".to_string()),
+            predecessor_id: None,
+            block_height: 100 - days_ago as u64,
+            tx_hash: None,
+            severity: "critical".to_string(),
+            summary: "paused".to_string(),
+            dedup_key: None,
+            action: serde_json::json!({"FunctionCall": {"method_name": "pause"}}),
+            raw: None,
+            outcome: EventOutcome::Queued,
+        };
+        for e in [
+            event(40, "DAO", "dao.near"),
+            event(3, "DAO", "dao.near"),
+            event(2, "Pause", "venear.near"),
+            event(0, "DAO", "dao.near"),
+        ] {
+            store.record(&e).unwrap();
+        }
+        assert_eq!(store.compact(event(0, "DAO", "dao.near").time).unwrap(), 1);
+
+        let all = store.query(&EventQuery::default()).unwrap();
+        assert_eq!(
+            all.iter().map(|e| e.event_id.as_str()).collect::<Vec<_>>(),
+            ["DAO-0", "Pause-2", "DAO-3"]
+        );
+        assert_eq!(all[0], event(0, "DAO", "dao.near"));
+
+        let last_week = store
+            .query(&EventQuery {
+                subscription: Some("DAO".to_string()),
+                since: Some("2026-05-27T00:00:00Z".parse().unwrap()),
+                until: Some("2026-05-30T00:00:00Z".parse().unwrap()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(last_week.len(), 1);
+        assert_eq!(last_week[0].event_id, "DAO-3");
+
+        let by_account = store
+            .query(&EventQuery {
+                account_id: Some("venear.near".to_string()),
+                limit: Some(5),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(by_account.len(), 1);
+
+        store.set_outcome("Pause-2", EventOutcome::Failed).unwrap();
+        let failed = store
+            .query(&EventQuery {
+                outcome: Some(EventOutcome::Failed),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(
+            failed
+                .iter()
+                .map(|e| e.event_id.as_str())
+                .collect::<Vec<_>>(),
+            ["Pause-2"]
+        );
+        assert_eq!(
+            "change_event".parse::<EventOutcome>().unwrap(),
+            EventOutcome::ChangeEvent
+        );
+
+        // Over the size limit, the oldest events go first
+        let small = EventStore::open(":memory:", 30, Some(0)).unwrap();
+        small.record(&event(1, "DAO", "dao.near")).unwrap();
+        assert_eq!(small.compact(event(0, "DAO", "dao.near").time).unwrap(), 1);
+        assert!(small.query(&EventQuery::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_upgrades_older_databases() {
+        let path = std::env::temp_dir().join(format!("event-store-{}.sqlite", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        Connection::open(&path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE events (time_ms INTEGER NOT NULL, event_id TEXT NOT NULL, subscription TEXT NOT NULL,
+                     network TEXT NOT NULL, account_id TEXT NOT NULL, method_name TEXT, predecessor_id TEXT,
+                     block_height INTEGER NOT NULL, tx_hash TEXT, severity TEXT NOT NULL, summary TEXT NOT NULL,
+                     dedup_key TEXT, action TEXT NOT NULL);
+                 INSERT INTO events VALUES (0, 'old', 'DAO', 'mainnet', 'dao.near', NULL, NULL, 1, NULL,
+                     'info', 'vote', NULL, '\"Other\"');",
+            )
+            .unwrap();
+        let store = EventStore::open(&path, 30, None).unwrap();
+        let events = store.query(&EventQuery::default()).unwrap();
+        assert_eq!(
+            (events[0].raw.as_ref(), events[0].outcome),
+            (None, EventOutcome::Queued)
+        );
+        drop(store);
+        let _ = std::fs::remove_file(&path);
+    }
+}
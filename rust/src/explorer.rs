@@ -0,0 +1,116 @@
+//! Block explorer links in alerts
+//!
+//! Each network's links come from an [`ExplorerProvider`]. The built-in ones
+//! are [`NearBlocks`], for `explorer_url`, and [`ExplorerLinks`], URL
+//! templates for any other explorer such as a localnet one. Library users
+//! can register their own per network with
+//! `NearPagerDutyMonitor::with_explorer`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::NeardataAction;
+
+/// Builds explorer URLs for one network
+pub trait ExplorerProvider: Send + Sync {
+    fn transaction_url(&self, tx_hash: &str) -> String;
+    fn account_url(&self, account_id: &str) -> String;
+
+    /// Link and label for an action: its transaction, else the account it
+    /// was on
+    fn action_link(&self, action: &NeardataAction) -> (String, String) {
+        match &action.tx_hash {
+            Some(tx_hash) => (
+                self.transaction_url(tx_hash),
+                "View Transaction".to_string(),
+            ),
+            None => (
+                self.account_url(&action.account_id),
+                "View Contract".to_string(),
+            ),
+        }
+    }
+}
+
+/// nearblocks.io and explorers with its URL scheme
+pub struct NearBlocks {
+    base_url: String,
+}
+
+impl NearBlocks {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+impl ExplorerProvider for NearBlocks {
+    fn transaction_url(&self, tx_hash: &str) -> String {
+        format!("{}/txns/{}", self.base_url, tx_hash)
+    }
+
+    fn account_url(&self, account_id: &str) -> String {
+        format!("{}/address/{}", self.base_url, account_id)
+    }
+}
+
+/// Explorer URL templates, used instead of the nearblocks scheme on `explorer_url`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ExplorerLinks {
+    /// e.g. `http://localhost:3000/transactions/{tx_hash}`
+    pub transaction_url: String,
+    /// e.g. `http://localhost:3000/accounts/{account_id}`
+    pub account_url: String,
+}
+
+impl ExplorerProvider for ExplorerLinks {
+    fn transaction_url(&self, tx_hash: &str) -> String {
+        self.transaction_url.replace("{tx_hash}", tx_hash)
+    }
+
+    fn account_url(&self, account_id: &str) -> String {
+        self.account_url.replace("{account_id}", account_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explorer_providers() {
+        let mut action: NeardataAction = serde_json::from_value(serde_json::json!({
+            "blockHeight": 1,
+            "txHash": "abc",
+            "accountId": "venear.near",
+            "status": "SUCCESS",
+            "action": "Other",
+        }))
+        .unwrap();
+        let nearblocks = NearBlocks::new("https://nearblocks.io/");
+        assert_eq!(
+            nearblocks.action_link(&action),
+            (
+                "https://nearblocks.io/txns/abc".to_string(),
+                "View Transaction".to_string()
+            )
+        );
+
+        let localnet = ExplorerLinks {
+            transaction_url: "http://localhost:3000/transactions/{tx_hash}".to_string(),
+            account_url: "http://localhost:3000/accounts/{account_id}".to_string(),
+        };
+        assert_eq!(
+            localnet.action_link(&action).0,
+            "http://localhost:3000/transactions/abc"
+        );
+        action.tx_hash = None;
+        assert_eq!(
+            localnet.action_link(&action),
+            (
+                "http://localhost:3000/accounts/venear.near".to_string(),
+                "View Contract".to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,427 @@
+//! Client-side conditions on matched actions
+//!
+//! neardata only filters by account and status. A subscription's
+//! `post_filter` narrows matches further with conditions on the action's
+//! fields, including the decoded JSON arguments of function calls. With the
+//! `cel` feature, conditions and severity rules can also be CEL expressions
+//! over the same fields, bound to `event`.
+
+use std::fmt;
+#[cfg(feature = "cel")]
+use std::sync::Arc;
+
+use base64::Engine;
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::{ActionType, MetaTransaction, NeardataAction, StorageEvent};
+
+/// One entry of a `post_filter`; every entry must hold
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum PostFilter {
+    /// `{cel: "event.args.amount != '0'"}`
+    Cel {
+        cel: CelExpression,
+    },
+    Field(FieldCondition),
+}
+
+impl PostFilter {
+    pub fn matches(&self, fields: &Value) -> bool {
+        match self {
+            Self::Field(condition) => condition.matches(fields),
+            // A failing expression, e.g. on a missing field, does not match
+            Self::Cel { cel } => cel.evaluate(fields).unwrap_or_else(|e| {
+                log::debug!("CEL filter `{}` did not evaluate: {:#}", cel, e);
+                false
+            }),
+        }
+    }
+
+    pub fn is_cel(&self) -> bool {
+        matches!(self, Self::Cel { .. })
+    }
+}
+
+impl<'de> Deserialize<'de> for PostFilter {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Cel {
+            cel: CelExpression,
+        }
+
+        // Decide on the shape first so field condition errors stay specific
+        let value = Value::deserialize(deserializer)?;
+        if value.get("cel").is_some() {
+            let Cel { cel } = Cel::deserialize(value).map_err(serde::de::Error::custom)?;
+            return Ok(Self::Cel { cel });
+        }
+        FieldCondition::deserialize(value)
+            .map(Self::Field)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Severity applied when a CEL expression holds; the first matching rule wins
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SeverityRule {
+    pub when: CelExpression,
+    pub severity: String,
+}
+
+/// A CEL expression over `event`, compiled when the configuration is loaded
+#[derive(Clone)]
+pub struct CelExpression {
+    source: String,
+    #[cfg(feature = "cel")]
+    program: Arc<cel_interpreter::Program>,
+}
+
+impl CelExpression {
+    pub fn new(source: &str) -> Result<Self, anyhow::Error> {
+        Ok(Self {
+            source: source.to_string(),
+            #[cfg(feature = "cel")]
+            program: Arc::new(
+                cel_interpreter::Program::compile(source)
+                    .map_err(|e| anyhow::anyhow!("invalid CEL `{}`: {}", source, e))?,
+            ),
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Evaluate with `fields` bound to `event`; the result must be a boolean
+    #[cfg(feature = "cel")]
+    pub fn evaluate(&self, fields: &Value) -> Result<bool, anyhow::Error> {
+        let mut context = cel_interpreter::Context::default();
+        context
+            .add_variable("event", fields)
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        match self.program.execute(&context) {
+            Ok(cel_interpreter::Value::Bool(result)) => Ok(result),
+            Ok(other) => anyhow::bail!("expected a boolean, got {:?}", other),
+            Err(e) => anyhow::bail!("{}", e),
+        }
+    }
+
+    #[cfg(not(feature = "cel"))]
+    pub fn evaluate(&self, _fields: &Value) -> Result<bool, anyhow::Error> {
+        anyhow::bail!("CEL expressions require the `cel` feature")
+    }
+}
+
+impl fmt::Debug for CelExpression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("CelExpression").field(&self.source).finish()
+    }
+}
+
+impl fmt::Display for CelExpression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl<'de> Deserialize<'de> for CelExpression {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        Self::new(&source).map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for CelExpression {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.source)
+    }
+}
+
+/// A test applied to one field of an action
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FieldCondition {
+    /// Dotted path such as `predecessor_id` or `args.receiver_id`
+    pub field: String,
+    #[serde(flatten)]
+    pub op: ConditionOp,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConditionOp {
+    /// The field equals this value
+    Equals(Value),
+    /// The field equals one of these values
+    In(Vec<Value>),
+    /// The field is a string matching this regular expression
+    Regex(
+        #[serde(
+            deserialize_with = "deserialize_regex",
+            serialize_with = "serialize_regex"
+        )]
+        Regex,
+    ),
+    /// Numeric comparisons; amounts such as yoctoNEAR are compared as u128,
+    /// whether written as numbers or strings
+    Gt(#[serde(with = "u128_string")] u128),
+    Gte(#[serde(with = "u128_string")] u128),
+    Lt(#[serde(with = "u128_string")] u128),
+    Lte(#[serde(with = "u128_string")] u128),
+    /// Whether the field is present and not null
+    Exists(bool),
+}
+
+impl FieldCondition {
+    pub fn matches(&self, fields: &Value) -> bool {
+        let value = lookup(fields, &self.field);
+        match &self.op {
+            ConditionOp::Exists(expected) => value.is_some() == *expected,
+            ConditionOp::Equals(expected) => value.is_some_and(|v| loosely_equal(v, expected)),
+            ConditionOp::In(options) => {
+                value.is_some_and(|v| options.iter().any(|o| loosely_equal(v, o)))
+            }
+            ConditionOp::Regex(regex) => value
+                .and_then(Value::as_str)
+                .is_some_and(|s| regex.is_match(s)),
+            ConditionOp::Gt(limit) => value.and_then(as_u128).is_some_and(|n| n > *limit),
+            ConditionOp::Gte(limit) => value.and_then(as_u128).is_some_and(|n| n >= *limit),
+            ConditionOp::Lt(limit) => value.and_then(as_u128).is_some_and(|n| n < *limit),
+            ConditionOp::Lte(limit) => value.and_then(as_u128).is_some_and(|n| n <= *limit),
+        }
+    }
+}
+
+/// The fields conditions can refer to. Function call arguments appear under
+/// `args` when they are base64-encoded JSON, meta transactions under `meta_tx`,
+/// and NEP-297 events, when the source carries logs, under `events`.
+pub fn action_fields(action: &NeardataAction) -> Value {
+    let mut fields = serde_json::json!({
+        "account_id": action.account_id,
+        "predecessor_id": action.predecessor_id,
+        "signer_id": action.signer_id,
+        "status": action.status,
+        "block_height": action.block_height,
+        "tx_hash": action.tx_hash,
+        "receipt_id": action.receipt_id,
+        "action_kind": action.action.kind(),
+    });
+    if !action.events.is_empty() {
+        fields["events"] = Value::from(action.events.clone());
+    }
+    match &action.action {
+        ActionType::FunctionCall(fc) => {
+            fields["method_name"] = Value::from(fc.method_name.as_str());
+            fields["deposit"] = Value::from(fc.deposit.clone());
+            fields["args"] = fc
+                .args
+                .as_deref()
+                .and_then(|args| base64::engine::general_purpose::STANDARD.decode(args).ok())
+                .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+                .unwrap_or(Value::Null);
+            if let Some(storage) = StorageEvent::from_call(
+                &fc.method_name,
+                &fields["args"],
+                fc.deposit.as_deref(),
+                action.predecessor_id.as_deref(),
+            ) {
+                fields["storage"] = serde_json::to_value(storage).unwrap_or_default();
+            }
+        }
+        ActionType::Transfer(transfer) => {
+            fields["deposit"] = Value::from(transfer.deposit.as_str());
+        }
+        ActionType::Delegate(delegate) => {
+            let meta_tx = MetaTransaction::from_action(delegate, action.signer_id.as_deref());
+            fields["meta_tx"] = serde_json::to_value(meta_tx).unwrap_or_default();
+        }
+        _ => {}
+    }
+    fields
+}
+
+/// Match an account ID against a pattern where `*` stands for any run of
+/// characters, e.g. `*.bridge.near`
+pub(crate) fn glob_matches(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // No `*` at all
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+pub(crate) fn lookup<'a>(fields: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(fields, |value, key| match value {
+            Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+            _ => value.get(key),
+        })
+        .filter(|value| !value.is_null())
+}
+
+/// Equality that treats `"5"` and `5` alike, since amounts are usually strings
+fn loosely_equal(value: &Value, expected: &Value) -> bool {
+    value == expected || matches!((as_u128(value), as_u128(expected)), (Some(a), Some(b)) if a == b)
+}
+
+fn as_u128(value: &Value) -> Option<u128> {
+    match value {
+        Value::String(s) => s.parse().ok(),
+        Value::Number(n) => n.as_u64().map(u128::from),
+        _ => None,
+    }
+}
+
+fn deserialize_regex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+    let pattern = String::deserialize(deserializer)?;
+    Regex::new(&pattern).map_err(serde::de::Error::custom)
+}
+
+fn serialize_regex<S: Serializer>(regex: &Regex, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(regex.as_str())
+}
+
+mod u128_string {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NumberOrString {
+        Number(u64),
+        String(String),
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u128, D::Error> {
+        match NumberOrString::deserialize(deserializer)? {
+            NumberOrString::Number(n) => Ok(n.into()),
+            NumberOrString::String(s) => s.parse().map_err(serde::de::Error::custom),
+        }
+    }
+
+    pub fn serialize<S: Serializer>(value: &u128, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conditions() {
+        let action: NeardataAction = serde_json::from_value(serde_json::json!({
+            "blockHeight": 1,
+            "accountId": "usdt.tether-token.near",
+            "predecessorId": "treasury.sputnik-dao.near",
+            "status": "SUCCESS",
+            "action": {"FunctionCall": {
+                "method_name": "ft_transfer",
+                // {"receiver_id":"bob.near","amount":"5000000000000"}
+                "args": "eyJyZWNlaXZlcl9pZCI6ImJvYi5uZWFyIiwiYW1vdW50IjoiNTAwMDAwMDAwMDAwMCJ9",
+                "deposit": "1"
+            }}
+        }))
+        .unwrap();
+        let fields = action_fields(&action);
+        let conditions: Vec<FieldCondition> = serde_yaml::from_str(
+            r#"
+- field: args.amount
+  gte: "1000000000000"
+- field: args.amount
+  lt: "100000000000000000000000000000000"
+- field: predecessor_id
+  regex: "\\.sputnik-dao\\.near$"
+- field: args.receiver_id
+  in: ["alice.near", "bob.near"]
+- field: deposit
+  equals: 1
+- field: args.memo
+  exists: false
+"#,
+        )
+        .unwrap();
+        for condition in &conditions {
+            assert!(condition.matches(&fields), "{:?}", condition);
+        }
+
+        let failing: Vec<FieldCondition> = serde_yaml::from_str(
+            r#"
+- field: args.amount
+  gt: "5000000000000"
+- field: method_name
+  in: [storage_deposit]
+- field: args.receiver_id
+  exists: false
+- field: signer_id
+  regex: "."
+"#,
+        )
+        .unwrap();
+        for condition in &failing {
+            assert!(!condition.matches(&fields), "{:?}", condition);
+        }
+
+        assert!(serde_yaml::from_str::<FieldCondition>("{field: a, regex: \"(\"}").is_err());
+        assert!(serde_yaml::from_str::<FieldCondition>("{field: a, gt: \"-1\"}").is_err());
+    }
+
+    #[cfg(feature = "cel")]
+    #[test]
+    fn test_cel_filters_and_severity_rules() {
+        let fields = serde_json::json!({
+            "method_name": "ft_transfer",
+            "block_height": 100,
+            "args": {"receiver_id": "bob.near", "amount": "5000"},
+        });
+        let filters: Vec<PostFilter> = serde_yaml::from_str(
+            r#"
+- cel: "event.method_name == 'ft_transfer' && event.args.receiver_id.endsWith('.near')"
+- cel: "int(event.args.amount) > 1000 && event.block_height > 50"
+- field: args.amount
+  equals: 5000
+"#,
+        )
+        .unwrap();
+        assert!(filters[0].is_cel() && !filters[2].is_cel());
+        assert!(filters.iter().all(|f| f.matches(&fields)));
+
+        // Missing fields and non-boolean results do not match
+        let missing: PostFilter = serde_yaml::from_str("cel: \"event.args.memo == 'x'\"").unwrap();
+        assert!(!missing.matches(&fields));
+        let number: PostFilter = serde_yaml::from_str("cel: \"event.block_height\"").unwrap();
+        assert!(!number.matches(&fields));
+
+        let rules: Vec<SeverityRule> = serde_yaml::from_str(
+            r#"
+- when: "int(event.args.amount) > 1000000"
+  severity: critical
+- when: "int(event.args.amount) > 1000"
+  severity: error
+"#,
+        )
+        .unwrap();
+        let severity = rules
+            .iter()
+            .find(|r| r.when.evaluate(&fields).unwrap())
+            .map(|r| r.severity.as_str());
+        assert_eq!(severity, Some("error"));
+
+        assert!(serde_yaml::from_str::<PostFilter>("cel: \"event.(\"").is_err());
+        assert!(serde_yaml::from_str::<PostFilter>("{cel: \"true\", field: a}").is_err());
+    }
+}
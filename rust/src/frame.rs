@@ -0,0 +1,319 @@
+//! Incremental parsing of neardata WebSocket frames
+//!
+//! A busy block can arrive as one very large frame. Rather than materializing
+//! every action in it, the frame is walked with serde seeds. Each action is
+//! first read as a borrowed [`ActionHeader`] that points into the frame, and
+//! only actions the subscriptions want are decoded into owned
+//! [`NeardataAction`]s.
+
+use std::{borrow::Cow, collections::HashMap, fmt};
+
+use serde::de::{
+    DeserializeSeed, Deserializer, Error as _, IgnoredAny, MapAccess, SeqAccess, Visitor,
+};
+use serde::Deserialize;
+use serde_json::value::RawValue;
+
+use crate::NeardataAction;
+
+/// The fields needed to decide whether an action is interesting, borrowed from the frame
+#[derive(Debug)]
+pub(crate) struct ActionHeader<'a> {
+    pub account_id: Cow<'a, str>,
+    pub receipt_id: Option<Cow<'a, str>>,
+    /// Action kind such as `FunctionCall` or `CreateAccount`
+    pub kind: Cow<'a, str>,
+    /// Method name for function calls
+    pub method_name: Option<Cow<'a, str>>,
+}
+
+impl<'a> ActionHeader<'a> {
+    fn parse(raw: &'a RawValue) -> Result<Self, serde_json::Error> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Fields<'a> {
+            #[serde(borrow)]
+            account_id: Cow<'a, str>,
+            #[serde(default, borrow)]
+            receipt_id: Option<Cow<'a, str>>,
+            #[serde(borrow)]
+            action: &'a RawValue,
+        }
+        #[derive(Deserialize)]
+        struct FunctionCallOnly<'a> {
+            #[serde(rename = "FunctionCall", borrow)]
+            function_call: Option<MethodOnly<'a>>,
+        }
+        #[derive(Deserialize)]
+        struct MethodOnly<'a> {
+            #[serde(borrow)]
+            method_name: Cow<'a, str>,
+        }
+
+        let fields: Fields<'a> = serde_json::from_str(raw.get())?;
+        // Non-function-call actions may be plain strings such as "CreateAccount"
+        let method_name = serde_json::from_str::<FunctionCallOnly<'a>>(fields.action.get())
+            .ok()
+            .and_then(|f| f.function_call)
+            .map(|m| m.method_name);
+        let kind = serde_json::from_str::<Cow<'a, str>>(fields.action.get())
+            .or_else(|_| {
+                serde_json::from_str::<HashMap<Cow<'a, str>, IgnoredAny>>(fields.action.get())
+                    .map(|kinds| kinds.into_keys().next().unwrap_or_default())
+            })
+            .unwrap_or_default();
+        Ok(Self {
+            account_id: fields.account_id,
+            receipt_id: fields.receipt_id,
+            kind,
+            method_name,
+        })
+    }
+}
+
+/// What was kept from one frame
+#[derive(Debug, Default)]
+pub(crate) struct NeardataFrame {
+    /// Actions accepted by the filter, in stream order
+    pub actions: Vec<NeardataAction>,
+    /// Messages in the frame; a frame is one message or a batch of them
+    pub messages: usize,
+    /// Actions decoded, whether kept or not
+    pub decoded_actions: usize,
+    /// Notes from status and keep-alive messages
+    pub notes: Vec<String>,
+    /// Actions seen per receipt, kept or not, to number them
+    receipt_actions: HashMap<String, u32>,
+}
+
+impl NeardataFrame {
+    /// Parse a frame, keeping the actions `keep` accepts; an empty frame is a keep-alive
+    pub fn parse(
+        text: &str,
+        keep: impl Fn(&ActionHeader) -> bool,
+    ) -> Result<Self, serde_json::Error> {
+        let mut frame = Self::default();
+        if text.trim().is_empty() {
+            return Ok(frame);
+        }
+        let mut de = serde_json::Deserializer::from_str(text);
+        FrameSeed {
+            frame: &mut frame,
+            keep: &keep,
+        }
+        .deserialize(&mut de)?;
+        de.end()?;
+        Ok(frame)
+    }
+}
+
+/// A single message object or an array of them
+struct FrameSeed<'a, F> {
+    frame: &'a mut NeardataFrame,
+    keep: &'a F,
+}
+
+impl<'de, F: Fn(&ActionHeader) -> bool> DeserializeSeed<'de> for FrameSeed<'_, F> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, F: Fn(&ActionHeader) -> bool> Visitor<'de> for FrameSeed<'_, F> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a neardata message or an array of messages")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<(), A::Error> {
+        visit_message(map, self.frame, self.keep)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while seq
+            .next_element_seed(MessageSeed {
+                frame: &mut *self.frame,
+                keep: self.keep,
+            })?
+            .is_some()
+        {}
+        Ok(())
+    }
+}
+
+/// One message inside a batch
+struct MessageSeed<'a, F> {
+    frame: &'a mut NeardataFrame,
+    keep: &'a F,
+}
+
+impl<'de, F: Fn(&ActionHeader) -> bool> DeserializeSeed<'de> for MessageSeed<'_, F> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, F: Fn(&ActionHeader) -> bool> Visitor<'de> for MessageSeed<'_, F> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a neardata message")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<(), A::Error> {
+        visit_message(map, self.frame, self.keep)
+    }
+}
+
+fn visit_message<'de, A, F>(mut map: A, frame: &mut NeardataFrame, keep: &F) -> Result<(), A::Error>
+where
+    A: MapAccess<'de>,
+    F: Fn(&ActionHeader) -> bool,
+{
+    frame.messages += 1;
+    while let Some(key) = map.next_key::<Cow<'de, str>>()? {
+        match key.as_ref() {
+            "actions" => map.next_value_seed(ActionsSeed {
+                frame: &mut *frame,
+                keep,
+            })?,
+            "note" => {
+                if let Some(note) = map.next_value::<Option<String>>()? {
+                    frame.notes.push(note);
+                }
+            }
+            _ => {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The `actions` array, filtered element by element
+struct ActionsSeed<'a, F> {
+    frame: &'a mut NeardataFrame,
+    keep: &'a F,
+}
+
+impl<'de, F: Fn(&ActionHeader) -> bool> DeserializeSeed<'de> for ActionsSeed<'_, F> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, F: Fn(&ActionHeader) -> bool> Visitor<'de> for ActionsSeed<'_, F> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an array of actions")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(raw) = seq.next_element::<&'de RawValue>()? {
+            self.frame.decoded_actions += 1;
+            let header = ActionHeader::parse(raw).map_err(A::Error::custom)?;
+            let action_index = match &header.receipt_id {
+                Some(receipt_id) => {
+                    let count = self
+                        .frame
+                        .receipt_actions
+                        .entry(receipt_id.to_string())
+                        .or_default();
+                    *count += 1;
+                    *count - 1
+                }
+                None => 0,
+            };
+            if (self.keep)(&header) {
+                let mut action: NeardataAction =
+                    serde_json::from_str(raw.get()).map_err(A::Error::custom)?;
+                action.action_index = action_index;
+                self.frame.actions.push(action);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn action(account_id: &str) -> String {
+        format!(
+            r#"{{"blockHeight":1,"accountId":"{}","status":"SUCCESS","action":"Other"}}"#,
+            account_id
+        )
+    }
+
+    #[test]
+    fn test_frame_shapes_and_filtering() {
+        let keep = |a: &ActionHeader| a.account_id == "venear.near";
+        let single = format!(
+            r#"{{"secret":"tmp","actions":[{},{}]}}"#,
+            action("venear.near"),
+            action("other.near")
+        );
+        let frame = NeardataFrame::parse(&single, keep).unwrap();
+        assert_eq!((frame.messages, frame.decoded_actions), (1, 2));
+        assert_eq!(frame.actions.len(), 1);
+
+        let batch = format!("[{},{}]", single, single);
+        let frame = NeardataFrame::parse(&batch, keep).unwrap();
+        assert_eq!((frame.messages, frame.actions.len()), (2, 2));
+
+        let keep_alive = NeardataFrame::parse(r#"{"note":"no new actions"}"#, keep).unwrap();
+        assert!(keep_alive.actions.is_empty());
+        assert_eq!(keep_alive.notes, ["no new actions"]);
+        assert_eq!(NeardataFrame::parse("  ", keep).unwrap().messages, 0);
+        assert!(NeardataFrame::parse("not json", keep).is_err());
+    }
+
+    #[test]
+    fn test_actions_are_numbered_within_receipts() {
+        let action = |account_id: &str, receipt_id: &str| {
+            format!(
+                r#"{{"blockHeight":1,"receiptId":"{}","accountId":"{}","status":"SUCCESS","action":"Other"}}"#,
+                receipt_id, account_id
+            )
+        };
+        let text = format!(
+            r#"{{"actions":[{},{},{},{}]}}"#,
+            action("other.near", "r1"),
+            action("venear.near", "r1"),
+            action("venear.near", "r2"),
+            action("venear.near", "r1")
+        );
+        let frame =
+            NeardataFrame::parse(&text, |a: &ActionHeader| a.account_id == "venear.near").unwrap();
+        let indexes: Vec<u32> = frame.actions.iter().map(|a| a.action_index).collect();
+        // Skipped actions still count
+        assert_eq!(indexes, [1, 0, 2]);
+    }
+
+    #[test]
+    fn test_action_header_borrows_from_frame() {
+        let raw: &RawValue = serde_json::from_str(
+            r#"{"blockHeight":1,"accountId":"venear.near","status":"SUCCESS","action":{"FunctionCall":{"method_name":"pause","args":"e30="}}}"#,
+        )
+        .unwrap();
+        let header = ActionHeader::parse(raw).unwrap();
+        assert!(matches!(header.account_id, Cow::Borrowed("venear.near")));
+        assert!(matches!(header.method_name, Some(Cow::Borrowed("pause"))));
+        assert_eq!(header.kind, "FunctionCall");
+
+        let other = action("venear.near");
+        let raw: &RawValue = serde_json::from_str(&other).unwrap();
+        let header = ActionHeader::parse(raw).unwrap();
+        assert!(header.method_name.is_none());
+        assert_eq!(header.kind, "Other");
+    }
+}
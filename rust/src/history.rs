@@ -0,0 +1,282 @@
+//! Client for the neardata HTTP block API
+//!
+//! The WebSocket stream only delivers actions from the moment a connection is
+//! made. Past blocks are fetched here, from `{history_url}/v0/block/{height}`,
+//! and flattened into the same [`NeardataAction`]s the stream produces so they
+//! can be matched and alerted on in the usual way.
+
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+use futures_util::{Stream, StreamExt};
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+
+use crate::{ActionType, NeardataAction};
+
+/// Blocks fetched concurrently when reading a range
+const CONCURRENT_REQUESTS: usize = 8;
+
+/// neardata HTTP API client for one network
+pub struct NeardataHistoryClient {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl NeardataHistoryClient {
+    /// Create a client for a neardata API base URL such as `https://mainnet.neardata.xyz`
+    pub fn new(base_url: impl Into<String>) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .expect("Failed to build HTTP client");
+        Self {
+            client,
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Height of the latest final block
+    pub async fn last_final_block_height(&self) -> Result<u64, anyhow::Error> {
+        let block = self
+            .get(&format!("{}/v0/last_block/final", self.base_url))
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("neardata returned no final block"))?;
+        Ok(block.block.header.height)
+    }
+
+    /// Every action executed in a block, or `None` if the height was skipped
+    pub async fn block_actions(
+        &self,
+        height: u64,
+    ) -> Result<Option<Vec<NeardataAction>>, anyhow::Error> {
+        let block = self
+            .get(&format!("{}/v0/block/{}", self.base_url, height))
+            .await?;
+        Ok(block.map(|b| b.into_actions()))
+    }
+
+    /// The actions of each block in a range, in block order, fetched a few
+    /// blocks ahead so only those are held in memory. Skipped heights yield
+    /// no actions.
+    pub fn blocks_in_range(
+        &self,
+        heights: RangeInclusive<u64>,
+    ) -> impl Stream<Item = Result<Vec<NeardataAction>, anyhow::Error>> + '_ {
+        futures_util::stream::iter(heights)
+            .map(move |height| async move { Ok(self.block_actions(height).await?.unwrap_or_default()) })
+            .buffered(CONCURRENT_REQUESTS)
+    }
+
+    /// Every action executed in a range of blocks, in block order
+    pub async fn actions_in_range(
+        &self,
+        heights: RangeInclusive<u64>,
+    ) -> Result<Vec<NeardataAction>, anyhow::Error> {
+        let mut blocks = std::pin::pin!(self.blocks_in_range(heights));
+        let mut actions = Vec::new();
+        while let Some(block) = blocks.next().await {
+            actions.extend(block?);
+        }
+        Ok(actions)
+    }
+
+    async fn get(&self, url: &str) -> Result<Option<Block>, anyhow::Error> {
+        let response = self.client.get(url).send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("neardata API error: {} - {}", status, body);
+        }
+        // Skipped heights are returned as `null`
+        Ok(response.json().await?)
+    }
+}
+
+/// The parts of a neardata block that actions are built from; NEAR Lake
+/// stores blocks in the same format
+#[derive(Debug, Deserialize)]
+pub(crate) struct Block {
+    block: BlockView,
+    #[serde(default)]
+    shards: Vec<Shard>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BlockView {
+    header: BlockHeader,
+}
+
+#[derive(Debug, Deserialize)]
+struct BlockHeader {
+    height: u64,
+    hash: String,
+    /// Nanoseconds since the Unix epoch
+    timestamp: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Shard {
+    #[serde(default)]
+    receipt_execution_outcomes: Vec<ExecutedReceipt>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExecutedReceipt {
+    receipt: Receipt,
+    execution_outcome: ExecutionOutcome,
+    #[serde(default)]
+    tx_hash: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Receipt {
+    predecessor_id: String,
+    receiver_id: String,
+    receipt_id: String,
+    receipt: ReceiptBody,
+}
+
+/// Only action receipts carry actions; data receipts are skipped
+#[derive(Debug, Deserialize)]
+struct ReceiptBody {
+    #[serde(rename = "Action")]
+    action: Option<ActionReceipt>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ActionReceipt {
+    signer_id: String,
+    actions: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExecutionOutcome {
+    outcome: Outcome,
+}
+
+#[derive(Debug, Deserialize)]
+struct Outcome {
+    status: OutcomeStatus,
+    #[serde(default)]
+    logs: Vec<String>,
+}
+
+/// `{"SuccessValue": ..}`, `{"SuccessReceiptId": ..}` or `{"Failure": ..}`
+#[derive(Debug, Deserialize)]
+struct OutcomeStatus {
+    #[serde(rename = "Failure")]
+    failure: Option<IgnoredAny>,
+}
+
+impl Block {
+    pub(crate) fn into_actions(self) -> Vec<NeardataAction> {
+        let header = self.block.header;
+        let mut actions = Vec::new();
+        for executed in self
+            .shards
+            .into_iter()
+            .flat_map(|s| s.receipt_execution_outcomes)
+        {
+            let Some(receipt) = executed.receipt.receipt.action else {
+                continue;
+            };
+            let outcome = executed.execution_outcome.outcome;
+            let status = if outcome.status.failure.is_some() {
+                "FAILURE"
+            } else {
+                "SUCCESS"
+            };
+            let events = nep297_events(&outcome.logs);
+            for (action_index, action) in receipt.actions.into_iter().enumerate() {
+                actions.push(NeardataAction {
+                    block_height: header.height,
+                    block_hash: Some(header.hash.clone()),
+                    block_timestamp_ms: Some(header.timestamp as f64 / 1e6),
+                    tx_hash: executed.tx_hash.clone(),
+                    receipt_id: Some(executed.receipt.receipt_id.clone()),
+                    signer_id: Some(receipt.signer_id.clone()),
+                    account_id: executed.receipt.receiver_id.clone(),
+                    predecessor_id: Some(executed.receipt.predecessor_id.clone()),
+                    status: status.to_string(),
+                    // Unit actions such as "CreateAccount" are plain strings in blocks
+                    action: serde_json::from_value(action).unwrap_or(ActionType::Other),
+                    action_index: action_index as u32,
+                    events: events.clone(),
+                });
+            }
+        }
+        actions
+    }
+}
+
+/// The NEP-297 events among a receipt's logs, `EVENT_JSON:` followed by
+/// an object with `standard`, `version` and `event`
+fn nep297_events(logs: &[String]) -> Vec<serde_json::Value> {
+    logs.iter()
+        .filter_map(|log| log.strip_prefix("EVENT_JSON:"))
+        .filter_map(|json| serde_json::from_str::<serde_json::Value>(json.trim()).ok())
+        .filter(|event| event["standard"].is_string() && event["event"].is_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_flattens_into_actions() {
+        let block: Option<Block> = serde_json::from_str(
+            r#"{
+                "block": {"header": {"height": 100, "hash": "abc", "timestamp": 1700000000000000000}},
+                "shards": [{
+                    "shard_id": 0,
+                    "receipt_execution_outcomes": [
+                        {
+                            "receipt": {
+                                "predecessor_id": "alice.near",
+                                "receiver_id": "venear.near",
+                                "receipt_id": "r1",
+                                "receipt": {"Action": {"signer_id": "alice.near", "actions": [
+                                    {"FunctionCall": {"method_name": "pause", "args": "e30=", "gas": 1, "deposit": "0"}},
+                                    "CreateAccount"
+                                ]}}
+                            },
+                            "execution_outcome": {"outcome": {"status": {"Failure": {"ActionError": {}}}, "logs": [
+                                "paused",
+                                "EVENT_JSON:{\"standard\":\"venear\",\"version\":\"1.0.0\",\"event\":\"pause\"}"
+                            ]}},
+                            "tx_hash": "tx1"
+                        },
+                        {
+                            "receipt": {
+                                "predecessor_id": "system",
+                                "receiver_id": "bob.near",
+                                "receipt_id": "r2",
+                                "receipt": {"Data": {"data_id": "d1", "data": null}}
+                            },
+                            "execution_outcome": {"outcome": {"status": {"SuccessValue": ""}}}
+                        }
+                    ]
+                }]
+            }"#,
+        )
+        .unwrap();
+        let actions = block.unwrap().into_actions();
+        assert_eq!(actions.len(), 2);
+        assert_eq!(actions[0].account_id, "venear.near");
+        assert_eq!(actions[0].status, "FAILURE");
+        assert_eq!(actions[0].block_timestamp_ms, Some(1_700_000_000_000.0));
+        assert!(
+            matches!(&actions[0].action, ActionType::FunctionCall(fc) if fc.method_name == "pause")
+        );
+        assert!(matches!(actions[1].action, ActionType::Other));
+        assert_eq!(
+            actions[1].events,
+            [serde_json::json!({"standard": "venear", "version": "1.0.0", "event": "pause"})]
+        );
+
+        let skipped: Option<Block> = serde_json::from_str("null").unwrap();
+        assert!(skipped.is_none());
+    }
+}
@@ -0,0 +1,337 @@
+//! HTTP endpoints served alongside the monitor
+
+use std::{collections::BTreeMap, convert::Infallible, sync::Arc, time::Duration};
+
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, IntoResponse, Response,
+    },
+    routing::{get, post},
+    Json, Router,
+};
+use futures_util::Stream;
+use tokio::sync::broadcast::error::RecvError;
+
+use serde::Deserialize;
+
+use crate::{
+    pagerduty_webhook, BulkResolve, ChatAction, NearPagerDutyMonitor, RecentEvent, StatusSnapshot,
+    SubscriptionMute,
+};
+
+const DASHBOARD_HTML: &str = include_str!("dashboard.html");
+
+/// Build the HTTP router: `/health` always, plus the dashboard, alert stream,
+/// admin API, chat action links and PagerDuty webhook when enabled
+pub fn router(monitor: Arc<NearPagerDutyMonitor>) -> Router {
+    let mut app = Router::new().route("/health", get(health));
+
+    if monitor.config().dashboard {
+        app = app
+            .route("/dashboard", get(dashboard))
+            .route("/api/status", get(status))
+            .route("/debug/recent", get(recent));
+    }
+    if monitor.config().alert_stream {
+        app = app.route("/stream", get(stream));
+    }
+    if monitor.config().admin_api {
+        let admin = Router::new()
+            .route("/api/resolve-all", post(resolve_all))
+            .route("/subscriptions", get(subscriptions))
+            .route("/subscriptions/:name/mute", post(mute))
+            .route("/subscriptions/:name/unmute", post(unmute))
+            .route_layer(middleware::from_fn_with_state(
+                monitor.clone(),
+                require_admin_token,
+            ));
+        app = app.merge(admin);
+    }
+    if monitor.config().chat_actions.is_some() {
+        app = app.route("/api/alerts/:action", get(chat_action));
+    }
+    if monitor.config().pagerduty_webhook_secret.is_some() {
+        app = app.route("/webhooks/pagerduty", post(pagerduty_webhook));
+    }
+
+    app.with_state(monitor)
+}
+
+/// Health check endpoint
+async fn health() -> &'static str {
+    "OK"
+}
+
+async fn dashboard() -> Html<&'static str> {
+    Html(DASHBOARD_HTML)
+}
+
+async fn status(State(monitor): State<Arc<NearPagerDutyMonitor>>) -> Json<StatusSnapshot> {
+    Json(monitor.status())
+}
+
+/// Restricts `/debug/recent` and `/api/resolve-all` to one subscription
+#[derive(Deserialize)]
+struct SubscriptionQuery {
+    subscription: Option<String>,
+}
+
+/// The full actions behind recent matches, optionally for one `?subscription=`
+async fn recent(
+    State(monitor): State<Arc<NearPagerDutyMonitor>>,
+    Query(query): Query<SubscriptionQuery>,
+) -> Json<BTreeMap<String, Vec<RecentEvent>>> {
+    Json(monitor.recent_events(query.subscription.as_deref()))
+}
+
+/// Resolve every open alert, optionally only for one `?subscription=`
+async fn resolve_all(
+    State(monitor): State<Arc<NearPagerDutyMonitor>>,
+    Query(query): Query<SubscriptionQuery>,
+) -> Json<BulkResolve> {
+    Json(monitor.resolve_all(query.subscription.as_deref()).await)
+}
+
+/// Reject admin requests without `Authorization: Bearer <admin_token>`, and
+/// every admin request if no token is configured
+async fn require_admin_token(
+    State(monitor): State<Arc<NearPagerDutyMonitor>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let presented = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    let authorized = monitor
+        .config()
+        .admin_token
+        .as_ref()
+        .is_some_and(|token| constant_time_eq(presented.as_bytes(), token.as_bytes()));
+    if !authorized {
+        log::warn!(
+            "Rejected an unauthenticated {} {}",
+            request.method(),
+            request.uri().path()
+        );
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    next.run(request).await
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Every subscription and when its mute ends
+async fn subscriptions(
+    State(monitor): State<Arc<NearPagerDutyMonitor>>,
+) -> Json<Vec<SubscriptionMute>> {
+    Json(monitor.subscription_mutes())
+}
+
+/// How long `/subscriptions/<name>/mute` silences the subscription
+#[derive(Deserialize)]
+struct MuteQuery {
+    /// e.g. `90s`, `30m`, `2h` or `1d`
+    duration: String,
+}
+
+/// Mute a subscription for `?duration=`
+async fn mute(
+    State(monitor): State<Arc<NearPagerDutyMonitor>>,
+    Path(name): Path<String>,
+    Query(query): Query<MuteQuery>,
+) -> Result<Json<SubscriptionMute>, (StatusCode, String)> {
+    let duration = parse_duration(&query.duration).ok_or_else(|| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!(
+                "'{}' is not a duration of up to 365d such as 30m or 2h",
+                query.duration
+            ),
+        )
+    })?;
+    monitor
+        .mute_subscription(&name, duration)
+        .map_err(|e| (StatusCode::NOT_FOUND, format!("{:#}", e)))?;
+    Ok(Json(subscription_mute(&monitor, &name)))
+}
+
+/// Lift a subscription's mute
+async fn unmute(
+    State(monitor): State<Arc<NearPagerDutyMonitor>>,
+    Path(name): Path<String>,
+) -> Result<Json<SubscriptionMute>, (StatusCode, String)> {
+    if !monitor.unmute_subscription(&name) {
+        return Err((StatusCode::NOT_FOUND, format!("'{}' is not muted", name)));
+    }
+    Ok(Json(subscription_mute(&monitor, &name)))
+}
+
+fn subscription_mute(monitor: &NearPagerDutyMonitor, name: &str) -> SubscriptionMute {
+    monitor
+        .subscription_mutes()
+        .into_iter()
+        .find(|s| s.name == name)
+        .expect("mutes are only changed for configured subscriptions")
+}
+
+/// Longest mute `/subscriptions/<name>/mute` accepts
+const MAX_MUTE: Duration = Duration::from_secs(365 * 86_400);
+
+/// Parse a whole number of seconds, minutes, hours or days, e.g. `2h`, of up
+/// to [`MAX_MUTE`]
+fn parse_duration(value: &str) -> Option<Duration> {
+    let unit = value.chars().last()?;
+    let count: u64 = value[..value.len() - unit.len_utf8()].parse().ok()?;
+    let secs = match unit {
+        's' => count,
+        'm' => count.checked_mul(60)?,
+        'h' => count.checked_mul(3600)?,
+        'd' => count.checked_mul(86_400)?,
+        _ => return None,
+    };
+    (secs > 0 && secs <= MAX_MUTE.as_secs()).then(|| Duration::from_secs(secs))
+}
+
+/// The alert and signature of a chat action link
+#[derive(Deserialize)]
+struct ChatActionQuery {
+    key: String,
+    sig: String,
+}
+
+/// Acknowledge or resolve an alert from a link in a chat message. Links are
+/// opened in a browser, so this is a GET answered with a line of text.
+async fn chat_action(
+    State(monitor): State<Arc<NearPagerDutyMonitor>>,
+    Path(action): Path<ChatAction>,
+    Query(query): Query<ChatActionQuery>,
+) -> (StatusCode, String) {
+    let Some(config) = &monitor.config().chat_actions else {
+        return (
+            StatusCode::NOT_FOUND,
+            "Chat actions are disabled".to_string(),
+        );
+    };
+    if !config.verify(action, &query.key, &query.sig) {
+        log::warn!(
+            "Rejected a chat {} link for {} with a bad signature",
+            action.as_str(),
+            query.key
+        );
+        return (StatusCode::FORBIDDEN, "This link is not valid".to_string());
+    }
+    let result = match action {
+        ChatAction::Acknowledge => monitor.acknowledge_alert(&query.key).await,
+        ChatAction::Resolve => monitor.resolve_alert(&query.key).await,
+    };
+    match result {
+        Ok(state) => {
+            log::info!(
+                "Alert {} is {:?} after a chat {} link",
+                query.key,
+                state,
+                action.as_str()
+            );
+            (
+                StatusCode::OK,
+                format!("Alert {} is now {:?}", query.key, state).to_lowercase(),
+            )
+        }
+        Err(e) => {
+            log::warn!("Chat {} of {} failed: {:#}", action.as_str(), query.key, e);
+            (
+                StatusCode::BAD_GATEWAY,
+                format!("Could not {} alert {}: {:#}", action.as_str(), query.key, e),
+            )
+        }
+    }
+}
+
+/// Apply an acknowledgement or resolution made in PagerDuty. Anything but a
+/// bad signature or body is answered with 200, since PagerDuty retries
+/// other errors and an unknown or already closed alert won't change.
+async fn pagerduty_webhook(
+    State(monitor): State<Arc<NearPagerDutyMonitor>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    let Some(secret) = &monitor.config().pagerduty_webhook_secret else {
+        return StatusCode::NOT_FOUND;
+    };
+    let signature = headers
+        .get("x-pagerduty-signature")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    if !pagerduty_webhook::verify_signature(secret, &body, signature) {
+        log::warn!("Rejected a PagerDuty webhook with a bad signature");
+        return StatusCode::UNAUTHORIZED;
+    }
+    let change = match pagerduty_webhook::parse(&body) {
+        Ok(Some(change)) => change,
+        Ok(None) => return StatusCode::OK,
+        Err(e) => {
+            log::warn!("Could not parse a PagerDuty webhook: {}", e);
+            return StatusCode::BAD_REQUEST;
+        }
+    };
+    match monitor.record_pagerduty_change(&change.dedup_key, change.event) {
+        Ok(state) => log::info!(
+            "Alert {} is {:?} after a PagerDuty webhook",
+            change.dedup_key,
+            state
+        ),
+        Err(e) => log::debug!("Ignored a PagerDuty webhook: {}", e),
+    }
+    StatusCode::OK
+}
+
+/// Server-Sent Events stream with one `alert` event per rendered alert
+async fn stream(
+    State(monitor): State<Arc<NearPagerDutyMonitor>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let rx = monitor.subscribe_alerts();
+    let events = futures_util::stream::unfold(rx, |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(alert) => match Event::default().event("alert").json_data(&alert) {
+                    Ok(event) => return Some((Ok(event), rx)),
+                    Err(e) => log::warn!("Failed to encode alert for /stream: {:?}", e),
+                },
+                Err(RecvError::Lagged(skipped)) => {
+                    log::warn!("/stream listener fell behind, skipped {} alert(s)", skipped);
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90s"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration("30m"), Some(Duration::from_secs(1800)));
+        assert_eq!(parse_duration("2h"), Some(Duration::from_secs(7200)));
+        assert_eq!(parse_duration("1d"), Some(Duration::from_secs(86_400)));
+        assert_eq!(parse_duration("365d"), Some(MAX_MUTE));
+        for invalid in ["", "h", "2", "0h", "-1h", "2w", "1.5h", "366d"] {
+            assert_eq!(parse_duration(invalid), None, "{}", invalid);
+        }
+        // Would overflow the mute's end time
+        assert_eq!(parse_duration("100000000d"), None);
+    }
+}
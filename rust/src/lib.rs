@@ -7,20 +7,150 @@
 //!
 //! The system connects to neardata's WebSocket API (wss://actions.near.stream/ws)
 //! and filters for specific contract calls, optionally filtering by method name.
+//!
+//! # Features
+//!
+//! Everything but `scripting` and `cel` is enabled by default. Library users
+//! can opt out with `default-features = false` and pick what they need:
+//!
+//! - `client`: [`PagerDutyClient`] and [`PagerDutyRestClient`] (reqwest). On
+//!   wasm32 only [`PagerDutyClient`] is built, on reqwest's fetch backend
+//! - `monitor`: [`NearPagerDutyMonitor`], the neardata stream (tokio-tungstenite)
+//!   and [`NeardataHistoryClient`]
+//! - `http-api`: the [`http`] router with the dashboard and alert stream (axum)
+//! - `yaml-config`: [`PagerDutyAlertConfig::from_yaml`]
+//! - `cli`: the `near-pagerduty-monitor` binary
+//! - `scripting`: Rhai `script`s on subscriptions
+//! - `cel`: CEL expressions in post-filters and severity rules
+//!
+//! Configuration types, neardata types and the rendering helpers are always available.
 
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::borrow::Cow;
+#[cfg(feature = "redis")]
+use std::collections::VecDeque;
+use std::collections::{BTreeMap, HashMap};
+#[cfg(feature = "client")]
+use std::path::PathBuf;
+#[cfg(feature = "monitor")]
+use std::{
+    collections::{BTreeSet, HashSet},
+    ops::RangeInclusive,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
 
+#[cfg(feature = "client")]
 use chrono::Utc;
-use futures_util::{SinkExt, StreamExt};
+#[cfg(feature = "monitor")]
+use futures_util::{FutureExt, SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+#[cfg(feature = "monitor")]
 use tokio_tungstenite::{connect_async, tungstenite::Message};
+use unicode_segmentation::UnicodeSegmentation;
+
+mod chat_actions;
+mod collapse;
+mod config_diff;
+mod config_summary;
+#[cfg(feature = "monitor")]
+mod dispatch;
+mod escalation;
+#[cfg(feature = "sqlite")]
+mod event_store;
+mod explorer;
+mod filter;
+#[cfg(feature = "monitor")]
+mod frame;
+#[cfg(feature = "monitor")]
+mod history;
+#[cfg(feature = "http-api")]
+pub mod http;
+#[cfg(feature = "monitor")]
+mod lake;
+mod lint;
+#[cfg(feature = "monitor")]
+mod market;
+mod meta_tx;
+#[cfg(feature = "http-api")]
+mod pagerduty_webhook;
+#[cfg(feature = "monitor")]
+pub mod preflight;
+#[cfg(feature = "monitor")]
+mod replay;
+#[cfg(feature = "monitor")]
+mod report;
+#[cfg(all(feature = "client", not(target_arch = "wasm32")))]
+mod rest;
+#[cfg(feature = "monitor")]
+mod rpc;
+#[cfg(feature = "scripting")]
+mod script;
+#[cfg(feature = "redis")]
+mod shared;
+mod sink;
+#[cfg(feature = "monitor")]
+mod slo;
+mod state;
+mod stats;
+mod storage;
+#[cfg(feature = "monitor")]
+mod supervisor;
+#[cfg(feature = "monitor")]
+mod systemd;
+pub mod template;
+#[cfg(feature = "monitor")]
+mod token;
+
+pub use chat_actions::{ChatAction, ChatActionsConfig};
+pub use collapse::DuplicateSummaryConfig;
+pub use config_diff::{ConfigDiff, FieldChange, SubscriptionChange};
+#[cfg(feature = "monitor")]
+use dispatch::{AlertQueue, Dispatcher, PendingAlert};
+pub use escalation::SeverityEscalationConfig;
+#[cfg(feature = "sqlite")]
+pub use event_store::{EventOutcome, EventQuery, EventStore, StoredEvent};
+pub use explorer::{ExplorerLinks, ExplorerProvider, NearBlocks};
+pub use filter::{
+    action_fields, CelExpression, ConditionOp, FieldCondition, PostFilter, SeverityRule,
+};
+#[cfg(feature = "monitor")]
+use frame::{ActionHeader, NeardataFrame};
+#[cfg(feature = "monitor")]
+pub use history::NeardataHistoryClient;
+#[cfg(feature = "monitor")]
+use lake::LakeClient;
+pub use lint::{LintFinding, LintReport, LintRule, Linter};
+pub use meta_tx::MetaTransaction;
+#[cfg(all(feature = "client", not(target_arch = "wasm32")))]
+pub use rest::{IncidentReference, IncidentUpdate, PagerDutyRestClient};
+pub use sink::{
+    CsvExportConfig, DiscordConfig, GoogleChatConfig, IncidentIoConfig, MatrixConfig, MqttConfig,
+    PubSubConfig, RedisStreamConfig, SinkConfig, SinkKind, SquadcastConfig, TwilioConfig,
+    VictorOpsConfig, WebhookAuth, WebhookConfig, ZendutyConfig,
+};
+#[cfg(feature = "monitor")]
+use sink::{CsvPartitions, ExportRow, SinkEvent, SinkEventKind, Sinks};
+pub use state::{
+    AlertEvent, AlertRecord, AlertState, AlertStore, InvalidTransition, PENDING_TIMEOUT,
+};
+pub use stats::{
+    ErrorCounters, MatchedEvent, MonitorStats, NetworkStatus, RecentEvent, StatusSnapshot,
+    SubscriptionStatus,
+};
+pub use storage::StorageEvent;
+#[cfg(feature = "monitor")]
+use supervisor::Supervisor;
 
 // =============================================================================
 // Configuration Types
 // =============================================================================
 
 /// Configuration for the PagerDuty alerting system
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PagerDutyAlertConfig {
     /// PagerDuty integration/routing key (can be omitted from YAML to use env var)
     #[serde(rename = "pagerduty_routing_key", default = "default_routing_key")]
@@ -30,615 +160,7181 @@ pub struct PagerDutyAlertConfig {
     /// Reconnection delay in seconds (default: 5)
     #[serde(default = "default_reconnect_delay")]
     pub reconnect_delay_secs: u64,
+    /// Maximum alert summary length in characters (default and upper bound: 1024)
+    #[serde(default = "default_summary_max_length")]
+    pub summary_max_length: usize,
+    /// Directory for full custom_details of events too large for PagerDuty
+    /// (default: `near-pagerduty-alerts` under the system temp directory)
+    #[serde(default)]
+    pub oversized_details_dir: Option<String>,
+    /// Secret of a PagerDuty webhook subscription posting to
+    /// `/webhooks/pagerduty`, so acknowledgements and resolutions made in
+    /// PagerDuty update the alert state
+    #[serde(default)]
+    pub pagerduty_webhook_secret: Option<String>,
+    /// PagerDuty REST API token, needed for incident-level features such as
+    /// `priority` (can be omitted from YAML to use PAGERDUTY_API_TOKEN)
+    #[serde(default)]
+    pub pagerduty_api_token: Option<String>,
+    /// Email of the PagerDuty user that REST API changes are made as
+    /// (can be omitted from YAML to use PAGERDUTY_FROM_EMAIL)
+    #[serde(default)]
+    pub pagerduty_from_email: Option<String>,
+    /// Service region of the PagerDuty account, `us` or `eu` (default: `us`)
+    #[serde(default)]
+    pub region: PagerDutyRegion,
+    /// Base URL of the Events API, e.g. a proxy, instead of the region's
+    #[serde(default)]
+    pub events_api_url: Option<String>,
+    /// JSON file that alert lifecycle state is persisted to (in-memory only if unset)
+    #[serde(default)]
+    pub state_file: Option<String>,
+    /// SQLite database every match is recorded in, for the `query` command
+    /// (requires the `sqlite` feature)
+    #[serde(default)]
+    pub event_store: Option<EventStoreConfig>,
+    /// How long the state file, spilled alerts and event store keep data
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    /// Send a daily roll-up of alerts by subscription and severity
+    #[serde(default)]
+    pub daily_report: Option<DailyReportConfig>,
+    /// Serve the live status dashboard at `/dashboard` (default: false)
+    #[serde(default)]
+    pub dashboard: bool,
+    /// Serve matched alerts as Server-Sent Events at `/stream` (default: false)
+    #[serde(default)]
+    pub alert_stream: bool,
+    /// Serve administrative operations such as `POST /api/resolve-all` and
+    /// muting subscriptions (default: false)
+    #[serde(default)]
+    pub admin_api: bool,
+    /// Bearer token the admin API requires in an `Authorization` header;
+    /// required when `admin_api` is enabled
+    #[serde(default)]
+    pub admin_token: Option<String>,
+    /// Add signed Acknowledge and Resolve links to Google Chat and Matrix
+    /// alerts, served at `/api/alerts/<action>`
+    #[serde(default)]
+    pub chat_actions: Option<ChatActionsConfig>,
+    /// Additional or overridden networks that subscriptions can select by name
+    #[serde(default)]
+    pub networks: HashMap<String, NetworkConfig>,
+    /// Deployment environment (e.g. prod, staging) added to every alert's source and custom_details
+    #[serde(default)]
+    pub environment: Option<String>,
+    /// Extra key/value tags added to every alert's custom_details
+    #[serde(default)]
+    pub tags: BTreeMap<String, String>,
+    /// Buffering between matching and delivery
+    #[serde(default)]
+    pub alert_queue: AlertQueueConfig,
+    /// How often a panicking monitor task may restart before it pages
+    #[serde(default)]
+    pub task_restarts: TaskRestartConfig,
+    /// When the monitor pages the meta routing key about its own failures
+    #[serde(default)]
+    pub self_alerts: SelfAlertConfig,
+    /// Routing key for alerts about the bridge itself (default: the main routing key)
+    #[serde(default)]
+    pub meta_routing_key: Option<String>,
+    /// Maximum number of alerts sent to PagerDuty at the same time (default: 8)
+    #[serde(default = "default_max_concurrent_dispatches")]
+    pub max_concurrent_dispatches: usize,
+    /// `client_url` shown on PagerDuty alerts, e.g. this bridge's dashboard (default: https://nearblocks.io)
+    #[serde(default)]
+    pub client_url: Option<String>,
+    /// Consecutive failed reconnects before the monitor exits (default: retry forever)
+    #[serde(default)]
+    pub max_reconnect_attempts: Option<u32>,
+    /// Exit immediately when neardata rejects the connection with a 4xx (default: false)
+    #[serde(default)]
+    pub exit_on_fatal_errors: bool,
+    /// Seconds to spend delivering queued alerts on shutdown (default: 25)
+    #[serde(default = "default_drain_timeout")]
+    pub drain_timeout_secs: u64,
+    /// Check connectivity and credentials before starting (default: true)
+    #[serde(default = "default_preflight")]
+    pub preflight: bool,
+    /// Validate the routing key during preflight by sending a change event (default: false)
+    #[serde(default)]
+    pub preflight_change_event: bool,
+    /// Page the meta routing key when received events trail the chain by more than this many seconds
+    #[serde(default)]
+    pub max_event_lag_secs: Option<u64>,
+    /// How far ahead of the local clock a block timestamp may be and still
+    /// count as just produced; further ahead, it is left out of lag and
+    /// latency measurements (default: 30)
+    #[serde(default = "default_max_clock_skew")]
+    pub max_clock_skew_secs: u64,
+    /// Ping a neardata stream that has sent nothing for this many seconds,
+    /// and reconnect if the pong doesn't arrive within as long again
+    /// (default: 60; 0 disables)
+    #[serde(default = "default_stream_idle_timeout")]
+    pub stream_idle_timeout_secs: u64,
+    /// Page the meta routing key once a network's stream has gone idle this
+    /// many connections in a row (default: 3)
+    #[serde(default = "default_max_idle_reconnects")]
+    pub max_idle_reconnects: u32,
+    /// Verify against each network's `history_url` that no matching event was missed
+    #[serde(default)]
+    pub cross_check: Option<CrossCheckConfig>,
+    /// What to do when subscriptions can render the same dedup key (default: warn)
+    #[serde(default)]
+    pub dedup_key_collisions: DedupCollisionPolicy,
+    /// Named templates that subscriptions reference as `"@name"`
+    #[serde(default)]
+    pub templates: BTreeMap<String, String>,
+    /// Page when alerts take too long to reach PagerDuty
+    #[serde(default)]
+    pub latency_slo: Option<LatencySloConfig>,
+    /// Token price conditions to page on, checked against `price_feed`
+    #[serde(default)]
+    pub market_alerts: Vec<MarketAlert>,
+    /// Where `market_alerts` read prices from (default: Intear's price API)
+    #[serde(default)]
+    pub price_feed: PriceFeedConfig,
+    /// Base URL that relative `runbook_url`s are joined to; subscriptions
+    /// without one link to `<base>/<subscription slug>`
+    #[serde(default)]
+    pub runbook_base_url: Option<String>,
+    /// Matched events kept per subscription for `/debug/recent` (default: 20, 0 disables)
+    #[serde(default = "default_recent_events_per_subscription")]
+    pub recent_events_per_subscription: usize,
+    /// Accounts subscriptions may watch; entries may use `*` patterns
+    /// (default: any account)
+    #[serde(default)]
+    pub allowed_accounts: Vec<String>,
+    /// Allow subscriptions whose `account_id` is a pattern or empty, which can
+    /// match far more actions than intended (default: false)
+    #[serde(default)]
+    pub allow_broad_filters: bool,
+    /// Estimate each subscription's daily matches from recent blocks before going live
+    #[serde(default)]
+    pub event_budget: Option<EventBudgetConfig>,
+    /// Alert once per action even when several subscriptions match it, from
+    /// the most severe of them (default: false)
+    #[serde(default)]
+    pub dedup_across_subscriptions: bool,
+    /// Add events for an alert that is already open to its incident's
+    /// timeline as notes instead of re-triggering; needs `pagerduty_api_token`
+    /// (default: false)
+    #[serde(default)]
+    pub follow_up_notes: bool,
+    /// Also resolve alerts in PagerDuty when their `alert_ttl_secs` expires
+    /// (default: false, only forgotten locally)
+    #[serde(default)]
+    pub resolve_expired_alerts: bool,
+    /// Escalation policy whose first responder is named on `/stream` alerts;
+    /// needs `pagerduty_api_token`
+    #[serde(default)]
+    pub on_call_escalation_policy: Option<String>,
+    /// Hold back alerts while the service is in a PagerDuty maintenance
+    /// window; needs `pagerduty_api_token`
+    #[serde(default)]
+    pub maintenance_windows: Option<MaintenanceWindowsConfig>,
+    /// Other on-call and chat tools that alerts are mirrored to
+    #[serde(default)]
+    pub sinks: Vec<SinkConfig>,
+    /// Redis that replicas claim events in, so only one of them alerts
+    /// (requires the `redis` feature)
+    #[serde(default)]
+    pub shared_state: Option<SharedStateConfig>,
 }
 
-fn default_reconnect_delay() -> u64 {
-    5
+impl PagerDutyAlertConfig {
+    /// Parse a YAML configuration file's contents
+    #[cfg(feature = "yaml-config")]
+    pub fn from_yaml(yaml: &str) -> Result<Self, anyhow::Error> {
+        Ok(serde_yaml::from_str(yaml)?)
+    }
+
+    /// Runbook link for an alert from `subscription` (with method overrides applied)
+    pub fn runbook_url(
+        &self,
+        subscription: &EventSubscription,
+        method_name: Option<&str>,
+    ) -> Option<String> {
+        let url = match (&subscription.runbook_url, &self.runbook_base_url) {
+            (Some(url), _) => url.replace("{method_name}", method_name.unwrap_or("unknown")),
+            (None, Some(_)) => slug(&subscription.name),
+            (None, None) => return None,
+        };
+        if url.contains("://") {
+            return Some(url);
+        }
+        let base = self.runbook_base_url.as_ref()?;
+        Some(format!(
+            "{}/{}",
+            base.trim_end_matches('/'),
+            url.trim_start_matches('/')
+        ))
+    }
+
+    /// Replace `"@name"` template references in subscriptions with the named
+    /// entry from `templates`; `"@@..."` stands for a literal leading `@`
+    pub fn resolve_templates(&mut self) -> Result<(), anyhow::Error> {
+        let templates = &self.templates;
+        let resolve =
+            |template: &mut Option<String>, subscription: &str| -> Result<(), anyhow::Error> {
+                let Some(text) = template.as_deref() else {
+                    return Ok(());
+                };
+                if let Some(literal) = text.strip_prefix("@@") {
+                    *template = Some(format!("@{}", literal));
+                } else if let Some(name) = text.strip_prefix('@') {
+                    let resolved = templates.get(name).ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "Subscription '{}' references unknown template '@{}'",
+                            subscription,
+                            name
+                        )
+                    })?;
+                    *template = Some(resolved.clone());
+                }
+                Ok(())
+            };
+        for sub in &mut self.subscriptions {
+            resolve(&mut sub.summary_template, &sub.name)?;
+            resolve(&mut sub.dedup_key_template, &sub.name)?;
+            for overrides in sub.method_overrides.values_mut() {
+                resolve(&mut overrides.summary_template, &sub.name)?;
+                resolve(&mut overrides.dedup_key_template, &sub.name)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Pairs of subscription names whose alerts can share a dedup key and so
+    /// group into one incident
+    ///
+    /// Templates are compared as written (a missing template is `{tx_hash}`).
+    /// `{account_id}` and `{method_name}` tell subscriptions apart when their
+    /// account or method filters differ.
+    pub fn dedup_key_collisions(&self) -> Vec<(String, String)> {
+        let mut collisions = Vec::new();
+        for (i, a) in self.subscriptions.iter().enumerate() {
+            for b in &self.subscriptions[i + 1..] {
+                let template = a.key_template().unwrap_or("{tx_hash}");
+                if template != b.key_template().unwrap_or("{tx_hash}") {
+                    continue;
+                }
+                let accounts_differ = a.account_id != b.account_id;
+                let methods_differ = matches!(
+                    (&a.method_name, &b.method_name),
+                    (Some(x), Some(y)) if x != y
+                );
+                if (accounts_differ && template.contains("{account_id}"))
+                    || (methods_differ && template.contains("{method_name}"))
+                {
+                    continue;
+                }
+                collisions.push((a.name.clone(), b.name.clone()));
+            }
+        }
+        collisions
+    }
+
+    /// Check every subscription's `account_id` against `allowed_accounts` and
+    /// `allow_broad_filters`, so a typo can't subscribe to the whole chain
+    pub fn check_account_scope(&self) -> Result<(), anyhow::Error> {
+        for sub in &self.subscriptions {
+            if !self.allow_broad_filters && (sub.account_id.is_empty() || sub.is_account_pattern())
+            {
+                anyhow::bail!(
+                    "Subscription '{}' watches '{}', which can match any number of accounts; set allow_broad_filters: true if that is intended",
+                    sub.name,
+                    sub.account_id
+                );
+            }
+            let allowed = self.allowed_accounts.is_empty()
+                || self.allowed_accounts.iter().any(|allowed| {
+                    *allowed == sub.account_id || filter::glob_matches(allowed, &sub.account_id)
+                });
+            if !allowed {
+                anyhow::bail!(
+                    "Subscription '{}' watches '{}', which is not in allowed_accounts",
+                    sub.name,
+                    sub.account_id
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Subscriptions ordered so each comes after everything in its
+    /// `depends_on`, otherwise in config order. Unknown dependencies and
+    /// cycles are errors, as are dependencies between networks that run
+    /// both ways, since each network's stream would wait for the other.
+    pub fn startup_order(&self) -> Result<Vec<&EventSubscription>, anyhow::Error> {
+        for sub in &self.subscriptions {
+            if let Some(missing) = sub
+                .depends_on
+                .iter()
+                .find(|d| !self.subscriptions.iter().any(|s| s.name == **d))
+            {
+                anyhow::bail!(
+                    "Subscription '{}' depends on unknown subscription '{}'",
+                    sub.name,
+                    missing
+                );
+            }
+        }
+        let edges: Vec<(&str, &str)> = self
+            .subscriptions
+            .iter()
+            .flat_map(|sub| {
+                sub.depends_on
+                    .iter()
+                    .map(move |d| (d.as_str(), sub.name.as_str()))
+            })
+            .collect();
+        let names: Vec<&str> = self.subscriptions.iter().map(|s| s.name.as_str()).collect();
+        let order = topological_order(&names, &edges).map_err(|cycle| {
+            anyhow::anyhow!(
+                "Subscriptions {} depend on each other in a cycle",
+                quoted_list(&cycle)
+            )
+        })?;
+
+        let network_of = |name: &str| {
+            self.subscriptions
+                .iter()
+                .find(|s| s.name == name)
+                .map_or("", |s| s.network.as_str())
+        };
+        let mut networks: Vec<&str> = Vec::new();
+        for sub in &self.subscriptions {
+            if !networks.contains(&sub.network.as_str()) {
+                networks.push(&sub.network);
+            }
+        }
+        let network_edges: Vec<(&str, &str)> = edges
+            .iter()
+            .map(|(from, to)| (network_of(from), network_of(to)))
+            .filter(|(from, to)| from != to)
+            .collect();
+        topological_order(&networks, &network_edges).map_err(|cycle| {
+            anyhow::anyhow!(
+                "Subscriptions on networks {} depend on each other; dependencies across networks must all point one way",
+                quoted_list(&cycle)
+            )
+        })?;
+
+        Ok(order
+            .into_iter()
+            .filter_map(|name| self.subscriptions.iter().find(|s| s.name == name))
+            .collect())
+    }
+
+    /// Look up a network by name, preferring the `networks` map over the built-ins
+    pub fn network(&self, name: &str) -> Option<NetworkConfig> {
+        self.networks
+            .get(name)
+            .cloned()
+            .or_else(|| NetworkConfig::builtin(name))
+    }
+
+    /// `events_api_url` if set, else the Events API of the account's region
+    pub fn events_api_url(&self) -> &str {
+        self.events_api_url
+            .as_deref()
+            .unwrap_or(self.region.events_api_url())
+    }
 }
 
-fn default_routing_key() -> String {
-    String::new()
+/// Order `nodes` so every `(from, to)` edge points forward, keeping the given
+/// order where edges allow; on a cycle, the nodes that could not be placed
+fn topological_order<'a>(
+    nodes: &[&'a str],
+    edges: &[(&'a str, &'a str)],
+) -> Result<Vec<&'a str>, Vec<&'a str>> {
+    let mut remaining: Vec<&str> = nodes.to_vec();
+    let mut order = Vec::with_capacity(remaining.len());
+    while !remaining.is_empty() {
+        let ready = remaining
+            .iter()
+            .position(|node| {
+                !edges
+                    .iter()
+                    .any(|(from, to)| to == node && remaining.contains(from))
+            })
+            .ok_or_else(|| remaining.clone())?;
+        order.push(remaining.remove(ready));
+    }
+    Ok(order)
 }
 
-/// A single event subscription that triggers PagerDuty alerts
-#[derive(Debug, Clone, Deserialize)]
-pub struct EventSubscription {
-    /// Human-readable name for this subscription
-    pub name: String,
-    /// The contract account ID to monitor
-    pub account_id: String,
-    /// Optional method name filter - if set, only alerts for this method
-    #[serde(default)]
-    pub method_name: Option<String>,
-    /// PagerDuty severity: critical, error, warning, info
-    #[serde(default = "default_severity")]
-    pub severity: String,
-    /// Summary template (can include placeholders like {account_id}, {method_name}, {predecessor_id})
-    #[serde(default)]
-    pub summary_template: Option<String>,
-    /// Optional dedup key template
-    #[serde(default)]
-    pub dedup_key_template: Option<String>,
+fn quoted_list(names: &[&str]) -> String {
+    names
+        .iter()
+        .map(|n| format!("'{}'", n))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
-fn default_severity() -> String {
-    "warning".to_string()
+/// Periodic comparison of delivered events against the neardata block archive
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CrossCheckConfig {
+    /// Seconds between checks (default: 300)
+    #[serde(default = "default_cross_check_interval")]
+    pub interval_secs: u64,
+    /// Most recent blocks examined per check (default: 600)
+    #[serde(default = "default_cross_check_blocks")]
+    pub blocks: u64,
+    /// Blocks behind the final block to stop at, giving the stream time to deliver (default: 30)
+    #[serde(default = "default_cross_check_settle_blocks")]
+    pub settle_blocks: u64,
 }
 
-// =============================================================================
-// Neardata Types
-// =============================================================================
+fn default_cross_check_interval() -> u64 {
+    300
+}
 
-/// Message received from neardata WebSocket
-#[derive(Debug, Deserialize)]
-struct NeardataMessage {
-    #[allow(dead_code)]
-    secret: String,
-    actions: Vec<NeardataAction>,
-    #[allow(dead_code)]
-    note: Option<String>,
+fn default_cross_check_blocks() -> u64 {
+    600
 }
 
-/// A single action from neardata
+fn default_cross_check_settle_blocks() -> u64 {
+    30
+}
+
+/// Startup estimate of how many events each subscription will alert on
 #[derive(Debug, Clone, Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct NeardataAction {
-    pub block_height: u64,
+pub struct EventBudgetConfig {
+    /// Most events per day a subscription is expected to match
+    pub max_events_per_day: u64,
+    /// Recent blocks the daily rate is extrapolated from (default: 3600)
+    #[serde(default = "default_event_budget_sample_blocks")]
+    pub sample_blocks: u64,
+    /// Refuse to start instead of warning when a subscription is over budget (default: false)
     #[serde(default)]
-    pub block_hash: Option<String>,
+    pub refuse: bool,
+}
+
+fn default_event_budget_sample_blocks() -> u64 {
+    3600
+}
+
+/// Objective for the time from an event's block to PagerDuty accepting its alert
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LatencySloConfig {
+    /// Highest acceptable 95th percentile latency, in seconds
+    pub p95_secs: f64,
+    /// Seconds of deliveries the percentile covers (default: 300)
+    #[serde(default = "default_latency_slo_window")]
+    pub window_secs: u64,
+    /// Seconds the percentile must stay above the objective before paging (default: 600)
+    #[serde(default = "default_latency_slo_sustained")]
+    pub sustained_secs: u64,
+    /// Routing key for latency pages (default: `meta_routing_key`)
     #[serde(default)]
-    pub block_timestamp_ms: Option<f64>,
+    pub routing_key: Option<String>,
+}
+
+fn default_latency_slo_window() -> u64 {
+    300
+}
+
+fn default_latency_slo_sustained() -> u64 {
+    600
+}
+
+/// A page on a token's price rather than on an on-chain action
+///
+/// Triggers when the price goes `above` or `below` a threshold, or moves by
+/// `change_pct` percent or more within `window_secs`, and resolves once none
+/// of those hold.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MarketAlert {
+    pub name: String,
+    /// Token contract as listed by the price feed, e.g. `wrap.near`
+    pub token_id: String,
     #[serde(default)]
-    pub tx_hash: Option<String>,
+    pub above: Option<f64>,
     #[serde(default)]
-    pub receipt_id: Option<String>,
+    pub below: Option<f64>,
     #[serde(default)]
-    pub signer_id: Option<String>,
-    pub account_id: String,
+    pub change_pct: Option<f64>,
+    /// Span `change_pct` is measured over (default: 3600)
+    #[serde(default = "default_market_window")]
+    pub window_secs: u64,
+    #[serde(default = "default_severity")]
+    pub severity: String,
+    /// Placeholders: {name}, {token_id}, {price}, {change_pct}, {reason}
     #[serde(default)]
-    pub predecessor_id: Option<String>,
-    pub status: String,
-    pub action: ActionType,
+    pub summary_template: Option<String>,
 }
 
-/// The type of action
+fn default_market_window() -> u64 {
+    3600
+}
+
+/// HTTP source of token prices for `market_alerts`
 #[derive(Debug, Clone, Deserialize, Serialize)]
-pub enum ActionType {
-    FunctionCall(FunctionCallAction),
-    Transfer(TransferAction),
-    DeployContract(DeployContractAction),
-    AddKey(AddKeyAction),
-    DeleteKey(DeleteKeyAction),
-    CreateAccount(CreateAccountAction),
-    DeleteAccount(DeleteAccountAction),
-    Stake(StakeAction),
-    #[serde(other)]
-    Other,
+pub struct PriceFeedConfig {
+    /// Returns `{token_id: price}`, where a price is a number, a numeric string
+    /// or an object with a `price` field (default: `https://prices.intear.tech/prices`)
+    #[serde(default = "default_price_feed_url")]
+    pub url: String,
+    /// Seconds between polls (default: 60)
+    #[serde(default = "default_price_feed_interval")]
+    pub interval_secs: u64,
+}
+
+impl Default for PriceFeedConfig {
+    fn default() -> Self {
+        Self {
+            url: default_price_feed_url(),
+            interval_secs: default_price_feed_interval(),
+        }
+    }
+}
+
+fn default_price_feed_url() -> String {
+    "https://prices.intear.tech/prices".to_string()
+}
+
+fn default_price_feed_interval() -> u64 {
+    60
+}
+
+/// Handling of subscriptions that can render identical dedup keys
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DedupCollisionPolicy {
+    /// Log a warning at startup
+    #[default]
+    Warn,
+    /// Prefix the colliding subscriptions' dedup keys with their name
+    Namespace,
+}
+
+/// Where a PagerDuty account is hosted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PagerDutyRegion {
+    #[default]
+    Us,
+    Eu,
+}
+
+impl PagerDutyRegion {
+    /// Base URL of the Events API v2
+    pub fn events_api_url(self) -> &'static str {
+        match self {
+            Self::Us => "https://events.pagerduty.com",
+            Self::Eu => "https://events.eu.pagerduty.com",
+        }
+    }
+
+    /// Base URL of the REST API
+    pub fn rest_api_url(self) -> &'static str {
+        match self {
+            Self::Us => "https://api.pagerduty.com",
+            Self::Eu => "https://api.eu.pagerduty.com",
+        }
+    }
 }
 
+/// Redis shared by replicas of the monitor
 #[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct FunctionCallAction {
-    pub method_name: String,
+pub struct SharedStateConfig {
+    /// e.g. `redis://:password@redis.local:6379/0`
+    pub redis_url: String,
+    /// Prefix of every key the monitor writes (default: `near-pagerduty`)
+    #[serde(default = "default_shared_key_prefix")]
+    pub key_prefix: String,
+    /// Seconds an event stays claimed by the replica that alerted on it (default: 86400)
+    #[serde(default = "default_claim_ttl")]
+    pub claim_ttl_secs: u64,
+    /// Name of this replica (default: `$HOSTNAME` and the process ID)
     #[serde(default)]
-    pub args: Option<String>,
+    pub instance_id: Option<String>,
+    /// Elect one replica to alert, holding a lease of this many seconds that
+    /// it renews at a third of that; the others stand by (default: every
+    /// replica alerts, coordinating per event)
     #[serde(default)]
-    pub deposit: Option<String>,
+    pub leader_lease_secs: Option<u64>,
+    /// Split subscriptions between the live replicas, so each matches only
+    /// its share (default: false)
     #[serde(default)]
-    pub gas: Option<u64>,
+    pub shard_subscriptions: bool,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct TransferAction {
-    pub deposit: String,
+fn default_shared_key_prefix() -> String {
+    "near-pagerduty".to_string()
 }
 
+fn default_claim_ttl() -> u64 {
+    86_400
+}
+
+/// Where matched events are kept and for how long
 #[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct DeployContractAction {
+pub struct EventStoreConfig {
+    /// Path of the SQLite database, created if missing
+    pub path: String,
+    /// Days events are kept (default: 90)
+    #[serde(default = "default_event_retention")]
+    pub retention_days: u64,
+    /// Size the database is kept under by deleting the oldest events (default: unlimited)
     #[serde(default)]
-    pub code: Option<String>,
+    pub max_size_mb: Option<u64>,
 }
 
+fn default_event_retention() -> u64 {
+    90
+}
+
+impl EventStoreConfig {
+    pub fn max_size_bytes(&self) -> Option<u64> {
+        self.max_size_mb.map(|mb| mb * 1024 * 1024)
+    }
+}
+
+/// When and where the daily alert report goes
 #[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct AddKeyAction {
-    pub public_key: String,
+pub struct DailyReportConfig {
+    /// Local time of day the report is sent, as `HH:MM` (default: 09:00)
+    #[serde(default = "default_report_time")]
+    pub at: String,
+    /// IANA time zone of `at` (default: UTC)
     #[serde(default)]
-    pub access_key: Option<serde_json::Value>,
+    pub timezone: Option<String>,
+    /// Record the report as a PagerDuty change event (default: true)
+    #[serde(default = "default_report_change_event")]
+    pub change_event: bool,
+    /// Also post the report as `{"text": ...}` to this chat webhook
+    #[serde(default)]
+    pub webhook_url: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct DeleteKeyAction {
-    pub public_key: String,
+impl DailyReportConfig {
+    /// `at` and `timezone`, parsed
+    pub fn schedule(&self) -> Result<(chrono::NaiveTime, chrono_tz::Tz), anyhow::Error> {
+        let at = chrono::NaiveTime::parse_from_str(&self.at, "%H:%M")
+            .map_err(|_| anyhow::anyhow!("daily_report.at '{}' is not an HH:MM time", self.at))?;
+        let timezone = match &self.timezone {
+            Some(name) => template::parse_timezone(name)?,
+            None => chrono_tz::Tz::UTC,
+        };
+        Ok((at, timezone))
+    }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct CreateAccountAction {}
+fn default_report_time() -> String {
+    "09:00".to_string()
+}
+
+fn default_report_change_event() -> bool {
+    true
+}
 
+/// How long data the monitor writes to disk is kept
 #[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct DeleteAccountAction {
+pub struct RetentionConfig {
+    /// Days resolved and expired alerts stay in the state file (default: 30)
+    #[serde(default = "default_closed_alert_days")]
+    pub closed_alert_days: u64,
+    /// Hours a spilled alert may wait on disk before it is dropped (default: until delivered)
     #[serde(default)]
-    pub beneficiary_id: Option<String>,
+    pub spill_max_age_hours: Option<u64>,
+    /// Spilled alerts kept on disk, dropping the oldest beyond it (default: unlimited)
+    #[serde(default)]
+    pub spill_max_files: Option<usize>,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            closed_alert_days: default_closed_alert_days(),
+            spill_max_age_hours: None,
+            spill_max_files: None,
+        }
+    }
+}
+
+fn default_closed_alert_days() -> u64 {
+    30
 }
 
+/// Following the PagerDuty maintenance windows of the routing key's service
 #[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct StakeAction {
-    pub stake: String,
-    pub public_key: String,
+pub struct MaintenanceWindowsConfig {
+    /// ID of the PagerDuty service, e.g. `PSERVICE1`
+    pub service_id: String,
+    /// What happens to alerts during a window (default: change_event)
+    #[serde(default)]
+    pub during: MaintenanceAction,
+    /// Seconds between checks for ongoing windows (default: 60)
+    #[serde(default = "default_maintenance_refresh")]
+    pub refresh_secs: u64,
 }
 
-// =============================================================================
-// PagerDuty Client
-// =============================================================================
+fn default_maintenance_refresh() -> u64 {
+    60
+}
 
-/// PagerDuty Events API v2 client
-pub struct PagerDutyClient {
-    client: reqwest::Client,
-    routing_key: String,
+/// How an alert matched during a maintenance window is handled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaintenanceAction {
+    /// Send it as a change event, which shows on the service timeline without paging
+    #[default]
+    ChangeEvent,
+    /// Drop it, logging the summary
+    Suppress,
 }
 
-#[derive(Debug, Serialize)]
-struct PagerDutyEvent {
-    routing_key: String,
-    event_action: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    dedup_key: Option<String>,
-    payload: PagerDutyPayload,
+/// Size of the internal alert queue and what to do when it fills up
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AlertQueueConfig {
+    /// Alerts held in memory before the overflow policy applies (default: 1000)
+    #[serde(default = "default_queue_capacity")]
+    pub capacity: usize,
+    #[serde(default)]
+    pub overflow: OverflowPolicy,
+    /// Directory for `spill_to_disk` (default: `near-pagerduty-alerts-spill` under the system temp directory)
+    #[serde(default)]
+    pub spill_dir: Option<String>,
+    /// Fold alerts into a queued alert with the same dedup key instead of sending each (default: false)
+    #[serde(default)]
+    pub coalesce: bool,
+}
+
+impl Default for AlertQueueConfig {
+    fn default() -> Self {
+        Self {
+            capacity: default_queue_capacity(),
+            overflow: OverflowPolicy::default(),
+            spill_dir: None,
+            coalesce: false,
+        }
+    }
+}
+
+fn default_queue_capacity() -> usize {
+    1000
+}
+
+/// Restart budget for monitor tasks that panic
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TaskRestartConfig {
+    /// Restarts allowed within the window before the meta routing key is paged (default: 5)
+    #[serde(default = "default_max_restarts")]
+    pub max_restarts: u32,
+    /// Seconds restarts are counted over; a task that stays up this long is healthy again (default: 300)
+    #[serde(default = "default_restart_window")]
+    pub window_secs: u64,
+}
+
+impl Default for TaskRestartConfig {
+    fn default() -> Self {
+        Self {
+            max_restarts: default_max_restarts(),
+            window_secs: default_restart_window(),
+        }
+    }
+}
+
+fn default_max_restarts() -> u32 {
+    5
+}
+
+fn default_restart_window() -> u64 {
+    300
+}
+
+/// Failure streaks that mean the monitor can no longer see or report events
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SelfAlertConfig {
+    /// Failed reconnects to a network in a row before paging; 0 disables (default: 5)
+    #[serde(default = "default_failed_reconnects")]
+    pub failed_reconnects: u32,
+    /// Failed PagerDuty deliveries in a row before paging; 0 disables (default: 10)
+    #[serde(default = "default_failed_deliveries")]
+    pub failed_deliveries: u32,
+    /// Seconds a network may wait for the networks its subscriptions depend
+    /// on before paging; 0 disables (default: 300)
+    #[serde(default = "default_upstream_wait_secs")]
+    pub upstream_wait_secs: u64,
+}
+
+impl Default for SelfAlertConfig {
+    fn default() -> Self {
+        Self {
+            failed_reconnects: default_failed_reconnects(),
+            failed_deliveries: default_failed_deliveries(),
+            upstream_wait_secs: default_upstream_wait_secs(),
+        }
+    }
+}
+
+fn default_failed_reconnects() -> u32 {
+    5
+}
+
+fn default_failed_deliveries() -> u32 {
+    10
+}
+
+fn default_upstream_wait_secs() -> u64 {
+    300
+}
+
+/// Behavior when the alert queue is full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Stop reading events until there is room (back-pressure)
+    #[default]
+    Block,
+    /// Discard the oldest queued alert
+    DropOldest,
+    /// Discard the alert being queued
+    DropNewest,
+    /// Write overflowing alerts to disk and deliver them once there is room
+    SpillToDisk,
+}
+
+/// Endpoints for one NEAR network
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct NetworkConfig {
+    /// neardata actions WebSocket URL
+    pub ws_url: String,
+    /// Block explorer base URL used for alert links
+    pub explorer_url: String,
+    /// neardata HTTP API base URL used to fetch past blocks
+    #[serde(default)]
+    pub history_url: Option<String>,
+    /// NEAR JSON-RPC URL used to look up token metadata
+    #[serde(default)]
+    pub rpc_url: Option<String>,
+    /// Link templates for explorers whose URLs differ from nearblocks'
+    #[serde(default)]
+    pub explorer_links: Option<ExplorerLinks>,
+    /// Read blocks from a NEAR Lake bucket instead of the `ws_url` stream
+    #[serde(default)]
+    pub lake: Option<LakeConfig>,
+}
+
+impl NetworkConfig {
+    /// Built-in `mainnet` and `testnet` definitions
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "mainnet" => Some(Self {
+                ws_url: "wss://actions.near.stream/ws".to_string(),
+                explorer_url: "https://nearblocks.io".to_string(),
+                history_url: Some("https://mainnet.neardata.xyz".to_string()),
+                rpc_url: Some("https://rpc.mainnet.near.org".to_string()),
+                explorer_links: None,
+                lake: None,
+            }),
+            "testnet" => Some(Self {
+                ws_url: "wss://actions-testnet.near.stream/ws".to_string(),
+                explorer_url: "https://testnet.nearblocks.io".to_string(),
+                history_url: Some("https://testnet.neardata.xyz".to_string()),
+                rpc_url: Some("https://rpc.testnet.near.org".to_string()),
+                explorer_links: None,
+                lake: None,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The provider of the network's explorer links
+    pub fn explorer(&self) -> std::sync::Arc<dyn ExplorerProvider> {
+        match &self.explorer_links {
+            Some(links) => std::sync::Arc::new(links.clone()),
+            None => std::sync::Arc::new(NearBlocks::new(&self.explorer_url)),
+        }
+    }
+}
+
+/// A NEAR Lake S3 bucket, which holds every block as `block.json` and one
+/// `shard_<id>.json` per shard under the zero-padded height
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LakeConfig {
+    /// Bucket name, e.g. `near-lake-data-mainnet`
+    pub bucket: String,
+    /// AWS region of the bucket (default: `eu-central-1`)
+    #[serde(default = "default_lake_region")]
+    pub region: String,
+    /// S3-compatible endpoint the bucket is addressed under by path
+    /// (default: the region's AWS S3 endpoint)
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    /// Access key ID (default: `AWS_ACCESS_KEY_ID`); the public buckets are requester pays
+    #[serde(default)]
+    pub access_key_id: Option<String>,
+    /// Secret access key (default: `AWS_SECRET_ACCESS_KEY`)
+    #[serde(default)]
+    pub secret_access_key: Option<String>,
+    /// First block to read (default: the latest final block from `rpc_url`)
+    #[serde(default)]
+    pub start_block: Option<u64>,
+    /// How often to look for new blocks once caught up (default: 1000)
+    #[serde(default = "default_lake_poll_interval")]
+    pub poll_interval_ms: u64,
+}
+
+impl LakeConfig {
+    /// Base URL objects are fetched under, ending in the bucket name
+    pub fn bucket_url(&self) -> String {
+        let endpoint = self
+            .endpoint
+            .clone()
+            .unwrap_or_else(|| format!("https://s3.{}.amazonaws.com", self.region));
+        format!("{}/{}", endpoint.trim_end_matches('/'), self.bucket)
+    }
+}
+
+fn default_lake_region() -> String {
+    "eu-central-1".to_string()
+}
+
+fn default_lake_poll_interval() -> u64 {
+    1000
+}
+
+fn default_reconnect_delay() -> u64 {
+    5
+}
+
+fn default_max_clock_skew() -> u64 {
+    30
+}
+
+fn default_stream_idle_timeout() -> u64 {
+    60
+}
+
+fn default_max_idle_reconnects() -> u32 {
+    3
+}
+
+fn default_drain_timeout() -> u64 {
+    25
+}
+
+fn default_preflight() -> bool {
+    true
+}
+
+fn default_recent_events_per_subscription() -> usize {
+    20
+}
+
+fn default_max_concurrent_dispatches() -> usize {
+    8
+}
+
+fn default_summary_max_length() -> usize {
+    // PagerDutyClient::MAX_SUMMARY_LENGTH, which needs the `client` feature
+    1024
+}
+
+fn default_routing_key() -> String {
+    String::new()
+}
+
+/// A single event subscription that triggers PagerDuty alerts
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EventSubscription {
+    /// Human-readable name for this subscription
+    pub name: String,
+    /// The contract account ID to monitor; `*` matches any run of characters,
+    /// e.g. `*.bridge.near` for every sub-account
+    pub account_id: String,
+    /// Only match these action kinds, e.g. `CreateAccount` or `DeployContract` (default: any)
+    #[serde(default)]
+    pub action_kinds: Vec<String>,
+    /// Optional method name filter - if set, only alerts for this method
+    #[serde(default)]
+    pub method_name: Option<String>,
+    /// PagerDuty severity: critical, error, warning, info
+    #[serde(default = "default_severity")]
+    pub severity: String,
+    /// Summary template (can include placeholders like {account_id}, {method_name}, {predecessor_id})
+    #[serde(default)]
+    pub summary_template: Option<String>,
+    /// Optional dedup key template
+    #[serde(default)]
+    pub dedup_key_template: Option<String>,
+    /// Dedup key shared by events about the same thing, e.g.
+    /// `proposal-{args.proposal_id}`, so they update one alert; events it
+    /// can't be rendered for fall back to `dedup_key_template`
+    #[serde(default)]
+    pub correlation_key_template: Option<String>,
+    /// JSON object sent as the alert's custom_details instead of the whole
+    /// event; strings may use any action field, e.g. `{args.proposal_id}`
+    #[serde(default)]
+    pub custom_details_template: Option<serde_json::Value>,
+    /// Incident priority (e.g. P1-P5) applied via the REST API after triggering
+    #[serde(default)]
+    pub priority: Option<String>,
+    /// ID of an escalation policy the incident is reassigned to via the REST API
+    #[serde(default)]
+    pub escalation_policy: Option<String>,
+    /// ID of a response play run on the incident via the REST API
+    #[serde(default)]
+    pub response_play: Option<String>,
+    /// Who is responsible for this subscription's alerts, copied into custom_details
+    #[serde(default)]
+    pub owner: Option<String>,
+    /// Team the alerts belong to, copied into custom_details
+    #[serde(default)]
+    pub team: Option<String>,
+    /// Service the alerts concern, copied into custom_details
+    #[serde(default)]
+    pub service: Option<String>,
+    /// Remediation docs linked from the alert; absolute, or a path under
+    /// `runbook_base_url`, and may include `{method_name}`
+    #[serde(default)]
+    pub runbook_url: Option<String>,
+    /// Stop tracking an open alert locally after this many seconds without a new match
+    #[serde(default)]
+    pub alert_ttl_secs: Option<u64>,
+    /// Network to monitor: `mainnet`, `testnet`, or a name from `networks` (default: mainnet)
+    #[serde(default = "default_network")]
+    pub network: String,
+    /// Overrides the global `max_reconnect_attempts`; the lowest limit on a network wins
+    #[serde(default)]
+    pub max_reconnect_attempts: Option<u32>,
+    /// On startup, alert on matching actions from this many recent blocks
+    #[serde(default)]
+    pub backfill_blocks: Option<u64>,
+    /// Severity and templates for specific methods, keyed by method name
+    #[serde(default)]
+    pub method_overrides: BTreeMap<String, MethodOverride>,
+    /// Further conditions on the action's fields, all of which must hold
+    #[serde(default)]
+    pub post_filter: Vec<PostFilter>,
+    /// CEL rules that override `severity`; the first that holds wins
+    /// (requires the `cel` feature)
+    #[serde(default)]
+    pub severity_rules: Vec<SeverityRule>,
+    /// IANA time zone for `datetime` placeholders without one (default: UTC)
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// Rhai source defining `should_alert(event)` and/or `render(event)`
+    /// (requires the `scripting` feature)
+    #[serde(default)]
+    pub script: Option<String>,
+    /// Names of the `sinks` this subscription's alerts are mirrored to (default: all)
+    #[serde(default)]
+    pub sinks: Option<Vec<String>>,
+    /// Log how each match passed the filters and was rendered, and add it to
+    /// custom_details as `match_trace` (default: false)
+    #[serde(default)]
+    pub match_trace: bool,
+    /// Fold alerts with the same summary as a recent one into its incident,
+    /// whatever their dedup key, e.g. per-transaction keys during spam
+    #[serde(default)]
+    pub collapse_duplicate_summaries: Option<DuplicateSummaryConfig>,
+    /// Re-trigger a dedup key that keeps firing at rising severities,
+    /// e.g. warning, then error, then critical; replaces `severity`
+    #[serde(default)]
+    pub severity_escalation: Option<SeverityEscalationConfig>,
+    /// Names of subscriptions that must be running before this one alerts,
+    /// such as an upstream health check whose pages explain this one's
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// Run the subscription without delivering its alerts to PagerDuty or
+    /// the sinks, to judge a new filter's noise before it can page anyone;
+    /// matches still show in the stats, logs and event store (default: false)
+    #[serde(default)]
+    pub shadow: bool,
+    /// Seconds after it first ran a `shadow` subscription starts delivering,
+    /// counted across restarts when there is a `state_file` (default: stays
+    /// shadowed until the flag is removed)
+    #[serde(default)]
+    pub shadow_burn_in_secs: Option<u64>,
+}
+
+impl EventSubscription {
+    /// The template alerts are usually keyed by: the correlation key, else the dedup key
+    fn key_template(&self) -> Option<&str> {
+        self.correlation_key_template
+            .as_deref()
+            .or(self.dedup_key_template.as_deref())
+    }
+
+    /// Whether `account_id` is a pattern rather than one account
+    pub fn is_account_pattern(&self) -> bool {
+        self.account_id.contains('*')
+    }
+
+    /// Whether alerts are withheld at `now` when the subscription first ran
+    /// in shadow at `shadow_started`
+    pub fn is_shadowed(
+        &self,
+        shadow_started: chrono::DateTime<chrono::Utc>,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> bool {
+        let running_secs = u64::try_from((now - shadow_started).num_seconds()).unwrap_or(0);
+        self.shadow
+            && self
+                .shadow_burn_in_secs
+                .is_none_or(|secs| running_secs < secs)
+    }
+
+    pub fn matches_account(&self, account_id: &str) -> bool {
+        filter::glob_matches(&self.account_id, account_id)
+    }
+
+    /// Whether the action kind and method pass this subscription's filters
+    pub fn matches_kind(&self, action_kind: &str, method_name: Option<&str>) -> bool {
+        if !self.action_kinds.is_empty() && !self.action_kinds.iter().any(|k| k == action_kind) {
+            return false;
+        }
+        // If method_name filter is set, only match FunctionCall with that method
+        match &self.method_name {
+            Some(required_method) => method_name == Some(required_method.as_str()),
+            None => true,
+        }
+    }
+
+    /// This subscription with the overrides for `method_name` applied
+    pub fn for_method(&self, method_name: Option<&str>) -> Cow<'_, Self> {
+        let Some(overrides) = method_name.and_then(|m| self.method_overrides.get(m)) else {
+            return Cow::Borrowed(self);
+        };
+        let mut subscription = self.clone();
+        if let Some(severity) = &overrides.severity {
+            subscription.severity = severity.clone();
+        }
+        if overrides.summary_template.is_some() {
+            subscription.summary_template = overrides.summary_template.clone();
+        }
+        if overrides.dedup_key_template.is_some() {
+            subscription.dedup_key_template = overrides.dedup_key_template.clone();
+        }
+        if overrides.priority.is_some() {
+            subscription.priority = overrides.priority.clone();
+        }
+        if overrides.escalation_policy.is_some() {
+            subscription.escalation_policy = overrides.escalation_policy.clone();
+        }
+        if overrides.response_play.is_some() {
+            subscription.response_play = overrides.response_play.clone();
+        }
+        if overrides.runbook_url.is_some() {
+            subscription.runbook_url = overrides.runbook_url.clone();
+        }
+        Cow::Owned(subscription)
+    }
+}
+
+/// Settings that replace a subscription's own for one method
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct MethodOverride {
+    #[serde(default)]
+    pub severity: Option<String>,
+    #[serde(default)]
+    pub summary_template: Option<String>,
+    #[serde(default)]
+    pub dedup_key_template: Option<String>,
+    #[serde(default)]
+    pub priority: Option<String>,
+    #[serde(default)]
+    pub escalation_policy: Option<String>,
+    #[serde(default)]
+    pub response_play: Option<String>,
+    #[serde(default)]
+    pub runbook_url: Option<String>,
+}
+
+fn default_severity() -> String {
+    "warning".to_string()
+}
+
+fn default_network() -> String {
+    "mainnet".to_string()
+}
+
+// =============================================================================
+// Neardata Types
+// =============================================================================
+
+/// A single action from neardata
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NeardataAction {
+    pub block_height: u64,
+    #[serde(default)]
+    pub block_hash: Option<String>,
+    #[serde(default)]
+    pub block_timestamp_ms: Option<f64>,
+    #[serde(default)]
+    pub tx_hash: Option<String>,
+    #[serde(default)]
+    pub receipt_id: Option<String>,
+    #[serde(default)]
+    pub signer_id: Option<String>,
+    pub account_id: String,
+    #[serde(default)]
+    pub predecessor_id: Option<String>,
+    pub status: String,
+    pub action: ActionType,
+    /// Position of the action within its receipt. neardata does not send
+    /// it; it is counted while reading frames and blocks, and kept when
+    /// the action is recorded.
+    #[serde(default)]
+    pub action_index: u32,
+    /// NEP-297 events logged by the action's receipt. The neardata stream
+    /// carries no logs; they are decoded from blocks, such as NEAR Lake's.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<serde_json::Value>,
+}
+
+/// The type of action
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum ActionType {
+    FunctionCall(FunctionCallAction),
+    Transfer(TransferAction),
+    DeployContract(DeployContractAction),
+    AddKey(AddKeyAction),
+    DeleteKey(DeleteKeyAction),
+    CreateAccount(CreateAccountAction),
+    DeleteAccount(DeleteAccountAction),
+    Stake(StakeAction),
+    Delegate(DelegateAction),
+    #[serde(other)]
+    Other,
+}
+
+impl ActionType {
+    /// The action's name as neardata spells it, e.g. `FunctionCall`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ActionType::FunctionCall(_) => "FunctionCall",
+            ActionType::Transfer(_) => "Transfer",
+            ActionType::DeployContract(_) => "DeployContract",
+            ActionType::AddKey(_) => "AddKey",
+            ActionType::DeleteKey(_) => "DeleteKey",
+            ActionType::CreateAccount(_) => "CreateAccount",
+            ActionType::DeleteAccount(_) => "DeleteAccount",
+            ActionType::Stake(_) => "Stake",
+            ActionType::Delegate(_) => "Delegate",
+            ActionType::Other => "Other",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FunctionCallAction {
+    pub method_name: String,
+    #[serde(default)]
+    pub args: Option<String>,
+    #[serde(default)]
+    pub deposit: Option<String>,
+    #[serde(default)]
+    pub gas: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TransferAction {
+    pub deposit: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DeployContractAction {
+    #[serde(default)]
+    pub code: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AddKeyAction {
+    pub public_key: String,
+    #[serde(default)]
+    pub access_key: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DeleteKeyAction {
+    pub public_key: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CreateAccountAction {}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DeleteAccountAction {
+    #[serde(default)]
+    pub beneficiary_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StakeAction {
+    pub stake: String,
+    pub public_key: String,
+}
+
+/// A meta transaction submitted by a relayer (see [`MetaTransaction`])
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DelegateAction {
+    pub delegate_action: DelegatedActions,
+    #[serde(default)]
+    pub signature: Option<String>,
+}
+
+/// The actions a user signed for a relayer to submit
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DelegatedActions {
+    pub sender_id: String,
+    pub receiver_id: String,
+    #[serde(deserialize_with = "deserialize_delegated_actions")]
+    pub actions: Vec<ActionType>,
+    #[serde(default)]
+    pub nonce: Option<u64>,
+    #[serde(default)]
+    pub max_block_height: Option<u64>,
+    #[serde(default)]
+    pub public_key: Option<String>,
+}
+
+/// Read delegated actions, keeping action kinds added to the protocol later as `Other`
+fn deserialize_delegated_actions<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<ActionType>, D::Error> {
+    let actions: Vec<serde_json::Value> = Deserialize::deserialize(deserializer)?;
+    Ok(actions
+        .into_iter()
+        .map(|action| serde_json::from_value(action).unwrap_or(ActionType::Other))
+        .collect())
+}
+
+// =============================================================================
+// PagerDuty Client
+// =============================================================================
+
+#[cfg(feature = "client")]
+/// PagerDuty Events API v2 client
+pub struct PagerDutyClient {
+    client: reqwest::Client,
+    routing_key: String,
+    summary_max_length: usize,
+    /// Where full custom_details of oversized events go (default: the system
+    /// temp directory, looked up only when needed since wasm32 has none)
+    oversized_details_dir: Option<PathBuf>,
+    client_name: String,
+    client_url: String,
+    /// Events API base URL, without `/v2/...`
+    events_api_url: String,
+}
+
+#[cfg(feature = "client")]
+#[derive(Debug, Serialize)]
+struct PagerDutyEvent {
+    routing_key: String,
+    event_action: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dedup_key: Option<String>,
+    payload: PagerDutyPayload,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    links: Option<Vec<PagerDutyLink>>,
+    client: String,
+    client_url: String,
+}
+
+#[cfg(feature = "client")]
+#[derive(Debug, Serialize)]
+struct PagerDutyPayload {
+    summary: String,
+    source: String,
+    severity: String,
+    timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    custom_details: Option<serde_json::Value>,
+}
+
+#[cfg(feature = "client")]
+#[derive(Debug, Serialize)]
+struct PagerDutyLink {
+    href: String,
+    text: String,
+}
+
+/// Outcome reported by the Events API
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum EventStatus {
+    #[serde(rename = "success")]
+    Success,
+    #[serde(rename = "invalid event")]
+    InvalidEvent,
+    #[serde(other)]
+    Unknown,
+}
+
+impl std::fmt::Display for EventStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            EventStatus::Success => "success",
+            EventStatus::InvalidEvent => "invalid event",
+            EventStatus::Unknown => "unknown",
+        })
+    }
+}
+
+/// Events API response
+#[derive(Debug, Clone, Deserialize)]
+pub struct PagerDutyResponse {
+    pub status: EventStatus,
+    pub message: String,
+    /// Dedup key of the alert; assigned by PagerDuty when the event had none
+    #[serde(default)]
+    pub dedup_key: Option<String>,
+    /// Validation errors for rejected events
+    #[serde(default)]
+    pub errors: Vec<String>,
+}
+
+impl PagerDutyResponse {
+    pub fn is_success(&self) -> bool {
+        self.status == EventStatus::Success
+    }
+}
+
+#[cfg(feature = "client")]
+impl PagerDutyClient {
+    /// PagerDuty's hard limit on `payload.summary`
+    pub const MAX_SUMMARY_LENGTH: usize = 1024;
+
+    /// PagerDuty rejects events larger than 512 KB
+    pub const MAX_EVENT_SIZE: usize = 512 * 1024;
+
+    /// String values in custom_details are cut to this many bytes when an event is oversized
+    const TRIMMED_STRING_LENGTH: usize = 1024;
+
+    pub fn new(routing_key: String) -> Self {
+        Self::with_http_client(routing_key, reqwest::Client::new())
+    }
+
+    /// Send through `client`, sharing its connection pool with other clients
+    pub fn with_http_client(routing_key: String, client: reqwest::Client) -> Self {
+        Self {
+            client,
+            routing_key,
+            summary_max_length: Self::MAX_SUMMARY_LENGTH,
+            oversized_details_dir: None,
+            client_name: format!(
+                "NEAR Blockchain Monitor v{} ({})",
+                env!("CARGO_PKG_VERSION"),
+                hostname()
+            ),
+            client_url: "https://nearblocks.io".to_string(),
+            events_api_url: PagerDutyRegion::Us.events_api_url().to_string(),
+        }
+    }
+
+    /// Send to another Events API, such as the EU region's or a proxy
+    pub fn with_events_api_url(mut self, events_api_url: impl Into<String>) -> Self {
+        self.events_api_url = events_api_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    fn events_url(&self) -> String {
+        format!("{}/v2/enqueue", self.events_api_url)
+    }
+
+    /// Set the `client_url` PagerDuty links the alert's client name to
+    pub fn with_client_url(mut self, client_url: impl Into<String>) -> Self {
+        self.client_url = client_url.into();
+        self
+    }
+
+    /// Set where full custom_details are written when an event exceeds PagerDuty's size limit
+    pub fn with_oversized_details_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.oversized_details_dir = Some(dir.into());
+        self
+    }
+
+    /// Set the summary budget in characters (clamped to PagerDuty's 1024 limit)
+    pub fn with_summary_max_length(mut self, max_length: usize) -> Self {
+        self.summary_max_length = max_length.clamp(1, Self::MAX_SUMMARY_LENGTH);
+        self
+    }
+
+    /// Trigger a PagerDuty alert
+    pub async fn trigger(
+        &self,
+        summary: &str,
+        source: &str,
+        severity: &str,
+        dedup_key: Option<String>,
+        custom_details: Option<serde_json::Value>,
+        links: &[(&str, &str)],
+    ) -> Result<PagerDutyResponse, anyhow::Error> {
+        let links = (!links.is_empty()).then(|| {
+            links
+                .iter()
+                .map(|(href, text)| PagerDutyLink {
+                    href: href.to_string(),
+                    text: text.to_string(),
+                })
+                .collect()
+        });
+
+        let (summary, overflow) = truncate_summary(summary, self.summary_max_length);
+        let custom_details = match overflow {
+            Some(overflow) => {
+                let mut details = custom_details.unwrap_or_else(|| serde_json::json!({}));
+                if let Some(map) = details.as_object_mut() {
+                    map.insert("summary_overflow".to_string(), overflow.into());
+                }
+                Some(details)
+            }
+            None => custom_details,
+        };
+
+        let mut event = PagerDutyEvent {
+            routing_key: self.routing_key.clone(),
+            event_action: "trigger".to_string(),
+            dedup_key,
+            payload: PagerDutyPayload {
+                summary,
+                source: source.to_string(),
+                severity: severity.to_string(),
+                timestamp: Utc::now().to_rfc3339(),
+                custom_details,
+            },
+            links,
+            client: self.client_name.clone(),
+            client_url: self.client_url.clone(),
+        };
+        self.enforce_size_limit(&mut event).await?;
+
+        let result = self.send_event(&self.events_url(), &event).await?;
+        log::info!(
+            "PagerDuty alert triggered: status={}, message={}, dedup_key={:?}",
+            result.status,
+            result.message,
+            result.dedup_key
+        );
+        Ok(result)
+    }
+
+    /// Keep the event under PagerDuty's size limit: first trim long strings in
+    /// custom_details, then fall back to storing the full details on disk and
+    /// sending only a reference to them.
+    async fn enforce_size_limit(&self, event: &mut PagerDutyEvent) -> Result<(), anyhow::Error> {
+        let original_size = serde_json::to_vec(event)?.len();
+        if original_size <= Self::MAX_EVENT_SIZE {
+            return Ok(());
+        }
+        let Some(details) = event.payload.custom_details.take() else {
+            anyhow::bail!(
+                "PagerDuty event is {} bytes and has no custom_details to trim",
+                original_size
+            );
+        };
+
+        let mut trimmed = details.clone();
+        trim_long_strings(&mut trimmed, Self::TRIMMED_STRING_LENGTH);
+        event.payload.custom_details = Some(trimmed);
+        let trimmed_size = serde_json::to_vec(event)?.len();
+        if trimmed_size <= Self::MAX_EVENT_SIZE {
+            log::warn!(
+                "PagerDuty event trimmed from {} to {} bytes",
+                original_size,
+                trimmed_size
+            );
+            return Ok(());
+        }
+
+        let path = self
+            .store_oversized_details(&details, event.dedup_key.as_deref())
+            .await?;
+        let mut reference = serde_json::Map::new();
+        if let Some(map) = details.as_object() {
+            for (key, value) in map {
+                if !value.is_object() && !value.is_array() {
+                    reference.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        trim_long_strings_in_map(&mut reference, Self::TRIMMED_STRING_LENGTH);
+        reference.insert("details_truncated".to_string(), true.into());
+        reference.insert("original_size_bytes".to_string(), original_size.into());
+        reference.insert(
+            "full_details_path".to_string(),
+            path.display().to_string().into(),
+        );
+        event.payload.custom_details = Some(serde_json::Value::Object(reference));
+        log::warn!(
+            "PagerDuty event was {} bytes; full custom_details stored at {}",
+            original_size,
+            path.display()
+        );
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn store_oversized_details(
+        &self,
+        details: &serde_json::Value,
+        dedup_key: Option<&str>,
+    ) -> Result<PathBuf, anyhow::Error> {
+        let dir = self
+            .oversized_details_dir
+            .clone()
+            .unwrap_or_else(|| std::env::temp_dir().join("near-pagerduty-alerts"));
+        tokio::fs::create_dir_all(&dir).await?;
+        let key: String = dedup_key
+            .unwrap_or("event")
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .take(128)
+            .collect();
+        let path = dir.join(format!("{}-{}.json", Utc::now().timestamp_millis(), key));
+        tokio::fs::write(&path, serde_json::to_vec_pretty(details)?).await?;
+        Ok(path)
+    }
+
+    /// There is no filesystem in the browser or edge workers, so oversized
+    /// events that can't be trimmed are rejected
+    #[cfg(target_arch = "wasm32")]
+    async fn store_oversized_details(
+        &self,
+        _details: &serde_json::Value,
+        _dedup_key: Option<&str>,
+    ) -> Result<PathBuf, anyhow::Error> {
+        anyhow::bail!("oversized custom_details cannot be stored on wasm32")
+    }
+
+    /// Acknowledge an existing alert
+    pub async fn acknowledge(&self, dedup_key: &str) -> Result<PagerDutyResponse, anyhow::Error> {
+        let event = serde_json::json!({
+            "routing_key": self.routing_key,
+            "event_action": "acknowledge",
+            "dedup_key": dedup_key,
+        });
+
+        let result = self.send_event(&self.events_url(), &event).await?;
+        log::info!(
+            "PagerDuty alert acknowledged: status={}, message={}",
+            result.status,
+            result.message
+        );
+        Ok(result)
+    }
+
+    /// Send a change event, which shows on the service timeline without paging anyone
+    pub async fn send_change(
+        &self,
+        summary: &str,
+        source: &str,
+        custom_details: Option<serde_json::Value>,
+    ) -> Result<PagerDutyResponse, anyhow::Error> {
+        let mut event = serde_json::json!({
+            "routing_key": self.routing_key,
+            "payload": {
+                "summary": summary,
+                "source": source,
+                "timestamp": Utc::now().to_rfc3339(),
+            },
+        });
+        if let Some(details) = custom_details {
+            event["payload"]["custom_details"] = details;
+        }
+        self.send_event(
+            &format!("{}/v2/change/enqueue", self.events_api_url),
+            &event,
+        )
+        .await
+    }
+
+    /// Post an event, failing unless PagerDuty accepted it
+    async fn send_event(
+        &self,
+        url: &str,
+        event: &impl Serialize,
+    ) -> Result<PagerDutyResponse, anyhow::Error> {
+        let response = self.client.post(url).json(event).send().await?;
+        let http_status = response.status();
+        let body = response.text().await?;
+
+        match serde_json::from_str::<PagerDutyResponse>(&body) {
+            Ok(result) if http_status.is_success() && result.is_success() => Ok(result),
+            Ok(result) => anyhow::bail!(
+                "PagerDuty rejected event ({}): {}: {} {:?}",
+                http_status,
+                result.status,
+                result.message,
+                result.errors
+            ),
+            Err(_) => anyhow::bail!("PagerDuty returned {}: {}", http_status, body),
+        }
+    }
+
+    /// Resolve an existing alert
+    pub async fn resolve(&self, dedup_key: &str) -> Result<PagerDutyResponse, anyhow::Error> {
+        let event = serde_json::json!({
+            "routing_key": self.routing_key,
+            "event_action": "resolve",
+            "dedup_key": dedup_key,
+        });
+
+        let result = self.send_event(&self.events_url(), &event).await?;
+        log::info!(
+            "PagerDuty alert resolved: status={}, message={}",
+            result.status,
+            result.message
+        );
+        Ok(result)
+    }
+
+    /// Acknowledge many alerts, [`BATCH_CONCURRENCY`] at a time
+    pub async fn acknowledge_many(&self, dedup_keys: &[String]) -> BatchOutcome {
+        let sends = dedup_keys
+            .iter()
+            .map(|key| async move { (key, self.acknowledge(key).await) });
+        Self::send_many(sends.collect()).await
+    }
+
+    /// Resolve many alerts, [`BATCH_CONCURRENCY`] at a time
+    pub async fn resolve_many(&self, dedup_keys: &[String]) -> BatchOutcome {
+        let sends = dedup_keys
+            .iter()
+            .map(|key| async move { (key, self.resolve(key).await) });
+        Self::send_many(sends.collect()).await
+    }
+
+    /// Run the sends a batch at a time, keeping their order
+    async fn send_many<'a, F>(sends: Vec<F>) -> BatchOutcome
+    where
+        F: std::future::Future<Output = (&'a String, Result<PagerDutyResponse, anyhow::Error>)>,
+    {
+        use futures_util::StreamExt;
+
+        let results: Vec<_> = futures_util::stream::iter(sends)
+            .buffered(BATCH_CONCURRENCY)
+            .collect()
+            .await;
+        let mut outcome = BatchOutcome::default();
+        for (key, result) in results {
+            match result {
+                Ok(_) => outcome.succeeded.push(key.clone()),
+                Err(e) => {
+                    log::error!("Failed to update {}: {:?}", key, e);
+                    outcome.failed.push((key.clone(), format!("{:#}", e)));
+                }
+            }
+        }
+        outcome
+    }
+}
+
+/// Events in flight at once in [`PagerDutyClient::resolve_many`] and
+/// [`PagerDutyClient::acknowledge_many`]
+#[cfg(feature = "client")]
+pub const BATCH_CONCURRENCY: usize = 8;
+
+/// Outcome of acknowledging or resolving many alerts at once
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct BatchOutcome {
+    /// Dedup keys PagerDuty accepted, in the order given
+    pub succeeded: Vec<String>,
+    /// Dedup keys PagerDuty did not accept, with the error
+    pub failed: Vec<(String, String)>,
+}
+
+#[cfg(feature = "client")]
+/// Best-effort hostname of this machine, for identifying which bridge sent an alert
+fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/proc/sys/kernel/hostname").ok())
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "unknown host".to_string())
+}
+
+/// Stable ID for a subscription's match on an action: a SHA-256 over the
+/// block height, receipt ID, action payload and, past the first action of a
+/// receipt, its index, so identical actions in one receipt stay apart
+pub fn event_id(action: &NeardataAction, subscription: &EventSubscription) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(action.block_height.to_le_bytes());
+    hasher.update(action.receipt_id.as_deref().unwrap_or_default());
+    hasher.update(serde_json::to_vec(&action.action).unwrap_or_default());
+    // Left out for the first action so IDs in existing state files still match
+    if action.action_index > 0 {
+        hasher.update(action.action_index.to_le_bytes());
+    }
+    hasher.update(subscription.name.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// Lowercase a name, keeping ASCII letters and digits and joining the rest
+/// with single dashes: `"veNEAR: Contract Paused"` becomes `venear-contract-paused`
+pub fn slug(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Truncate a summary to at most `max_chars` characters without splitting a
+/// grapheme cluster. When truncation happens the result ends with an ellipsis
+/// and the dropped tail is returned so it can be preserved elsewhere.
+pub fn truncate_summary(summary: &str, max_chars: usize) -> (String, Option<String>) {
+    const ELLIPSIS: char = '…';
+
+    if summary.chars().count() <= max_chars {
+        return (summary.to_string(), None);
+    }
+
+    let budget = max_chars.saturating_sub(1);
+    let mut used = 0;
+    let mut cut = 0;
+    for (idx, grapheme) in summary.grapheme_indices(true) {
+        let len = grapheme.chars().count();
+        if used + len > budget {
+            break;
+        }
+        used += len;
+        cut = idx + grapheme.len();
+    }
+
+    let mut truncated = summary[..cut].trim_end().to_string();
+    truncated.push(ELLIPSIS);
+    (truncated, Some(summary[cut..].to_string()))
+}
+
+/// Recursively cut string values longer than `max_bytes`, noting how much was dropped
+pub fn trim_long_strings(value: &mut serde_json::Value, max_bytes: usize) {
+    match value {
+        serde_json::Value::String(s) if s.len() > max_bytes => {
+            let mut cut = max_bytes;
+            while !s.is_char_boundary(cut) {
+                cut -= 1;
+            }
+            let dropped = s.len() - cut;
+            s.truncate(cut);
+            s.push_str(&format!("… [{} bytes trimmed]", dropped));
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                trim_long_strings(item, max_bytes);
+            }
+        }
+        serde_json::Value::Object(map) => trim_long_strings_in_map(map, max_bytes),
+        _ => {}
+    }
+}
+
+fn trim_long_strings_in_map(
+    map: &mut serde_json::Map<String, serde_json::Value>,
+    max_bytes: usize,
+) {
+    for item in map.values_mut() {
+        trim_long_strings(item, max_bytes);
+    }
+}
+
+/// Outcome of resolving many alerts at once
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BulkResolve {
+    /// Dedup keys resolved in PagerDuty and locally
+    pub resolved: Vec<String>,
+    /// Dedup keys PagerDuty did not accept a resolve for, with the error
+    pub failed: Vec<(String, String)>,
+}
+
+/// A subscription and its mute, as listed by `GET /subscriptions`
+#[cfg(feature = "monitor")]
+#[derive(Debug, Clone, Serialize)]
+pub struct SubscriptionMute {
+    pub name: String,
+    pub network: String,
+    pub severity: String,
+    /// End of the mute; `None` when the subscription alerts
+    pub muted_until: Option<chrono::DateTime<Utc>>,
+}
+
+/// Resolve every open alert in `store`, or only those of one subscription,
+/// e.g. to clean up after a bad filter paged for events that didn't matter
+#[cfg(feature = "client")]
+pub async fn resolve_open_alerts(
+    client: &PagerDutyClient,
+    store: &AlertStore,
+    subscription: Option<&str>,
+) -> BulkResolve {
+    let dedup_keys: Vec<String> = store
+        .open_alerts()
+        .into_iter()
+        .filter(|record| subscription.is_none_or(|name| name == record.subscription))
+        .map(|record| record.dedup_key)
+        .collect();
+    let batch = client.resolve_many(&dedup_keys).await;
+    for dedup_key in &batch.succeeded {
+        if let Err(e) = store.apply(dedup_key, AlertEvent::Resolve) {
+            log::warn!("{}", e);
+        }
+    }
+    BulkResolve {
+        resolved: batch.succeeded,
+        failed: batch.failed,
+    }
+}
+
+// =============================================================================
+// Event Monitor
+// =============================================================================
+
+/// An alert as rendered for PagerDuty, published to `/stream` listeners
+#[derive(Debug, Clone, Serialize)]
+pub struct RenderedAlert {
+    pub timestamp: String,
+    pub subscription: String,
+    pub severity: String,
+    pub summary: String,
+    pub source: String,
+    pub dedup_key: Option<String>,
+    pub explorer_link: Option<String>,
+    pub custom_details: serde_json::Value,
+    /// Who `on_call_escalation_policy` pages first, for chat mirrors to mention
     #[serde(skip_serializing_if = "Option::is_none")]
-    links: Option<Vec<PagerDutyLink>>,
-    client: String,
-    client_url: String,
+    pub on_call: Option<String>,
+}
+
+/// A past action matched by [`NearPagerDutyMonitor::backfill_subscription`]
+#[cfg(feature = "monitor")]
+#[derive(Debug, Clone, Serialize)]
+pub struct BackfillMatch {
+    pub subscription: String,
+    pub block_height: u64,
+    pub block_timestamp: Option<chrono::DateTime<Utc>>,
+    pub tx_hash: Option<String>,
+    pub severity: String,
+    pub summary: String,
+    pub dedup_key: Option<String>,
+    pub explorer_link: Option<String>,
+}
+
+/// What [`NearPagerDutyMonitor::reprocess`] went through
+#[cfg(feature = "sqlite")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReprocessOutcome {
+    /// Distinct recorded actions run through the subscriptions
+    pub actions: usize,
+    /// Subscription matches among them
+    pub matched: usize,
+    /// Events recorded without their action, by older versions
+    pub skipped: usize,
+}
+
+/// One action of a transaction, as seen by
+/// [`NearPagerDutyMonitor::explain_transaction`]
+#[cfg(feature = "monitor")]
+#[derive(Debug, Clone, Serialize)]
+pub struct ExplainedAction {
+    pub block_height: u64,
+    pub receipt_id: Option<String>,
+    pub account_id: String,
+    pub predecessor_id: Option<String>,
+    pub action_kind: String,
+    pub method_name: Option<String>,
+    pub status: String,
+    /// Every subscription on the transaction's network
+    pub verdicts: Vec<SubscriptionVerdict>,
+}
+
+#[cfg(feature = "monitor")]
+#[derive(Debug, Clone, Serialize)]
+pub struct SubscriptionVerdict {
+    pub subscription: String,
+    #[serde(flatten)]
+    pub verdict: MatchVerdict,
+}
+
+/// Whether a subscription alerts on an action, or the first check it fails
+#[cfg(feature = "monitor")]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "verdict", rename_all = "snake_case")]
+pub enum MatchVerdict {
+    /// The alert that would be sent
+    Matched {
+        severity: String,
+        summary: String,
+        dedup_key: Option<String>,
+    },
+    /// `account_id` doesn't cover the action's receiver
+    OtherAccount,
+    /// The action failed; only successful actions alert
+    Unsuccessful,
+    /// `action_kinds` or `method_name` exclude the action
+    KindOrMethod,
+    /// This `post_filter` entry doesn't hold
+    PostFilter { condition: String },
+    /// The `should_alert` script returned false
+    Script,
+    /// `dedup_across_subscriptions` picked a more severe subscription
+    Deduplicated { alerted_by: String },
+}
+
+#[cfg(feature = "monitor")]
+/// Main event monitoring service
+pub struct NearPagerDutyMonitor {
+    config: PagerDutyAlertConfig,
+    /// Networks with at least one subscription, resolved from the config,
+    /// in subscription startup order
+    networks: Vec<(String, NetworkConfig)>,
+    /// Networks whose streams must connect before a network's starts, for
+    /// subscriptions that depend on subscriptions elsewhere
+    upstream_networks: HashMap<String, BTreeSet<String>>,
+    /// Subscription names in startup order; shutdown goes the other way
+    subscription_order: Vec<String>,
+    /// Subscriptions allowed to alert: their network is connected and
+    /// everything they depend on is running
+    running_subscriptions: Mutex<HashSet<String>>,
+    /// Explorer link providers by network name
+    explorers: HashMap<String, Arc<dyn ExplorerProvider>>,
+    pd_client: Arc<PagerDutyClient>,
+    dispatcher: Arc<Dispatcher>,
+    alerts: Arc<AlertStore>,
+    stats: Arc<MonitorStats>,
+    alert_tx: tokio::sync::broadcast::Sender<RenderedAlert>,
+    /// Whether systemd has been told the monitor is ready
+    ready_notified: AtomicBool,
+    /// Client for alerts about the monitor itself
+    meta_client: Arc<PagerDutyClient>,
+    /// Client for delivery latency SLO pages
+    slo_client: Arc<PagerDutyClient>,
+    /// Networks with an open event lag alert
+    lagging_networks: Mutex<HashSet<String>>,
+    /// Connections in a row that went idle, by network
+    idle_streams: Mutex<HashMap<String, u32>>,
+    /// Restarts long-running tasks that panic
+    supervisor: Arc<Supervisor>,
+    #[cfg(feature = "sqlite")]
+    event_store: Option<Arc<EventStore>>,
+    /// Matches since the last daily report
+    report_tally: Mutex<report::ReportTally>,
+    /// Symbols and decimals of tokens in `token_amount` summaries
+    token_metadata: token::TokenMetadataCache,
+    /// Recent summaries of `collapse_duplicate_summaries` subscriptions
+    summary_bursts: collapse::SummaryBursts,
+    /// Stream actions already processed, to skip ones resent on reconnect
+    seen_actions: replay::SeenActions,
+    rest_client: Option<Arc<PagerDutyRestClient>>,
+    /// Last known first responder of `on_call_escalation_policy`
+    on_call: Mutex<Option<String>>,
+    /// Whether the service is in a PagerDuty maintenance window
+    in_maintenance: AtomicBool,
+    /// Whether the last block timestamp was beyond `max_clock_skew_secs`
+    clock_skewed: AtomicBool,
+    sinks: Sinks,
+    /// Event claims shared with other replicas
+    #[cfg(feature = "redis")]
+    shared_state: Option<shared::SharedState>,
+    /// Whether this replica alerts; only ever false under leader election
+    #[cfg(feature = "redis")]
+    leading: AtomicBool,
+    /// Recent matches while standing by, replayed on taking over as leader
+    #[cfg(feature = "redis")]
+    standby_matches: Mutex<VecDeque<StandbyMatch>>,
+    /// Live replicas subscriptions are sharded between; `None` owns them all
+    #[cfg(feature = "redis")]
+    shard_members: Mutex<Option<Vec<String>>>,
+    /// Subscriptions whose dedup keys are prefixed with their name
+    namespaced_subscriptions: HashSet<String>,
+    /// Compiled scripts by subscription name
+    #[cfg(feature = "scripting")]
+    scripts: HashMap<String, script::SubscriptionScript>,
+}
+
+/// A match held by a standby replica in case the leader missed it
+#[cfg(feature = "redis")]
+struct StandbyMatch {
+    seen: std::time::Instant,
+    action: NeardataAction,
+    subscription: EventSubscription,
+    network: NetworkConfig,
+}
+
+/// What a subscription's `render` script replaced
+#[cfg(feature = "monitor")]
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct ScriptOutput {
+    #[serde(default)]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub severity: Option<String>,
+    #[serde(default)]
+    pub dedup_key: Option<String>,
+}
+
+#[cfg(feature = "monitor")]
+impl NearPagerDutyMonitor {
+    const EXPIRY_SWEEP_INTERVAL: Duration = Duration::from_secs(30);
+    /// How often changes to the alert state are written to the state file
+    const STATE_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+    const LATENCY_SLO_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+    const ON_CALL_REFRESH_INTERVAL: Duration = Duration::from_secs(300);
+    const COMPACTION_INTERVAL: Duration = Duration::from_secs(3600);
+    /// Heights listed per NEAR Lake request; a listing is read in full before the next
+    const LAKE_BLOCKS_PER_LISTING: usize = 100;
+    /// How often a network waiting for its upstream networks checks on them
+    const UPSTREAM_POLL_INTERVAL: Duration = Duration::from_millis(500);
+    /// Replicas heartbeat this often and drop out of the shard after three misses
+    #[cfg(feature = "redis")]
+    const SHARD_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+    /// Idle PagerDuty connections are kept this long for the next alert
+    const HTTP_POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+    /// Alerts buffered per `/stream` listener before slow listeners skip ahead
+    const ALERT_STREAM_CAPACITY: usize = 256;
+
+    pub fn new(mut config: PagerDutyAlertConfig) -> Result<Self, anyhow::Error> {
+        config.resolve_templates()?;
+        config.check_account_scope()?;
+        for sub in &config.subscriptions {
+            let in_subscription =
+                |e: anyhow::Error| anyhow::anyhow!("Subscription '{}': {}", sub.name, e);
+            if let Some(tz) = &sub.timezone {
+                template::parse_timezone(tz).map_err(in_subscription)?;
+            }
+            let summaries = std::iter::once(&sub.summary_template)
+                .chain(sub.method_overrides.values().map(|o| &o.summary_template))
+                .flatten();
+            for summary in summaries {
+                template::validate(summary).map_err(in_subscription)?;
+            }
+            if sub
+                .custom_details_template
+                .as_ref()
+                .is_some_and(|t| !t.is_object())
+            {
+                return Err(in_subscription(anyhow::anyhow!(
+                    "custom_details_template must be a JSON object"
+                )));
+            }
+            let runbooks = std::iter::once(&sub.runbook_url)
+                .chain(sub.method_overrides.values().map(|o| &o.runbook_url))
+                .flatten();
+            for runbook in runbooks {
+                if !runbook.contains("://") && config.runbook_base_url.is_none() {
+                    return Err(in_subscription(anyhow::anyhow!(
+                        "runbook_url '{}' is relative, but no runbook_base_url is set",
+                        runbook
+                    )));
+                }
+            }
+        }
+        // Networks start in the order their first subscriptions do
+        let startup_order = config.startup_order()?;
+        let mut upstream_networks: HashMap<String, BTreeSet<String>> = HashMap::new();
+        for sub in &startup_order {
+            for dependency in config
+                .subscriptions
+                .iter()
+                .filter(|s| sub.depends_on.contains(&s.name))
+            {
+                if dependency.network != sub.network {
+                    upstream_networks
+                        .entry(sub.network.clone())
+                        .or_default()
+                        .insert(dependency.network.clone());
+                }
+            }
+        }
+        let subscription_order: Vec<String> =
+            startup_order.iter().map(|s| s.name.clone()).collect();
+        let mut networks: Vec<(String, NetworkConfig)> = Vec::new();
+        for sub in startup_order {
+            if networks.iter().any(|(name, _)| *name == sub.network) {
+                continue;
+            }
+            let network = config.network(&sub.network).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Subscription '{}' uses unknown network '{}'; define it under `networks`",
+                    sub.name,
+                    sub.network
+                )
+            })?;
+            networks.push((sub.network.clone(), network));
+        }
+
+        // One keep-alive pool for every Events API client, so concurrent sends
+        // reuse connections (multiplexed over HTTP/2 where PagerDuty offers it)
+        let http_client = reqwest::Client::builder()
+            .pool_max_idle_per_host(config.max_concurrent_dispatches.max(1))
+            .pool_idle_timeout(Self::HTTP_POOL_IDLE_TIMEOUT)
+            .tcp_keepalive(Self::HTTP_POOL_IDLE_TIMEOUT)
+            .http2_adaptive_window(true)
+            .build()?;
+        let mut pd_client =
+            PagerDutyClient::with_http_client(config.routing_key.clone(), http_client.clone())
+                .with_events_api_url(config.events_api_url())
+                .with_summary_max_length(config.summary_max_length);
+        if let Some(dir) = &config.oversized_details_dir {
+            pd_client = pd_client.with_oversized_details_dir(dir);
+        }
+        if let Some(client_url) = &config.client_url {
+            pd_client = pd_client.with_client_url(client_url);
+        }
+        let pd_client = Arc::new(pd_client);
+
+        let rest_client = config.pagerduty_api_token.as_ref().map(|token| {
+            Arc::new(
+                PagerDutyRestClient::new(token.clone(), config.pagerduty_from_email.clone())
+                    .with_api_url(config.region.rest_api_url()),
+            )
+        });
+        if rest_client.is_none() {
+            if config.maintenance_windows.is_some() {
+                log::warn!("maintenance_windows is set but no pagerduty_api_token is configured; windows will be ignored");
+            }
+            if config.on_call_escalation_policy.is_some() {
+                log::warn!("on_call_escalation_policy is set but no pagerduty_api_token is configured; alerts won't name the on-call");
+            }
+            if config.follow_up_notes {
+                log::warn!("follow_up_notes is set but no pagerduty_api_token is configured; events will re-trigger");
+            }
+            let updates_incident = |s: &EventSubscription| {
+                s.priority.is_some() || s.escalation_policy.is_some() || s.response_play.is_some()
+            };
+            for sub in config.subscriptions.iter().filter(|s| updates_incident(s)) {
+                log::warn!(
+                    "Subscription '{}' changes its incidents (priority, escalation_policy or response_play) but no pagerduty_api_token is configured; the changes will be skipped",
+                    sub.name
+                );
+            }
+        }
+
+        let alerts = match &config.state_file {
+            Some(path) => AlertStore::open(path)?,
+            None => AlertStore::new(),
+        };
+        // Burn-in counts from a shadow subscription's first run, not this start
+        let shadows: Vec<&str> = config
+            .subscriptions
+            .iter()
+            .filter(|s| s.shadow)
+            .map(|s| s.name.as_str())
+            .collect();
+        alerts.track_shadow_subscriptions(&shadows, Utc::now());
+
+        let mut namespaced_subscriptions = HashSet::new();
+        for (a, b) in config.dedup_key_collisions() {
+            match config.dedup_key_collisions {
+                DedupCollisionPolicy::Warn => log::warn!(
+                    "Subscriptions '{}' and '{}' can render the same dedup key, so their alerts may group into one incident",
+                    a,
+                    b
+                ),
+                DedupCollisionPolicy::Namespace => {
+                    log::info!(
+                        "Subscriptions '{}' and '{}' can render the same dedup key; prefixing their keys with the subscription name",
+                        a,
+                        b
+                    );
+                    namespaced_subscriptions.insert(a);
+                    namespaced_subscriptions.insert(b);
+                }
+            }
+        }
+
+        #[cfg(feature = "scripting")]
+        let scripts = config
+            .subscriptions
+            .iter()
+            .filter_map(|sub| sub.script.as_ref().map(|source| (sub, source)))
+            .map(|(sub, source)| {
+                let script = script::SubscriptionScript::compile(source).map_err(|e| {
+                    anyhow::anyhow!("Script for subscription '{}': {}", sub.name, e)
+                })?;
+                Ok((sub.name.clone(), script))
+            })
+            .collect::<Result<HashMap<_, _>, anyhow::Error>>()?;
+        #[cfg(not(feature = "scripting"))]
+        if let Some(sub) = config.subscriptions.iter().find(|s| s.script.is_some()) {
+            anyhow::bail!(
+                "Subscription '{}' has a script, but this build lacks the `scripting` feature",
+                sub.name
+            );
+        }
+
+        #[cfg(not(feature = "cel"))]
+        if let Some(sub) = config
+            .subscriptions
+            .iter()
+            .find(|s| !s.severity_rules.is_empty() || s.post_filter.iter().any(PostFilter::is_cel))
+        {
+            anyhow::bail!(
+                "Subscription '{}' uses CEL expressions, but this build lacks the `cel` feature",
+                sub.name
+            );
+        }
+
+        let alerts = Arc::new(alerts);
+        let mut stats = MonitorStats::new(&config.subscriptions)
+            .with_recent_events(config.recent_events_per_subscription);
+        if let Some(slo) = &config.latency_slo {
+            stats = stats.with_latency_window(slo.window_secs);
+        }
+        let stats = Arc::new(stats);
+        let (alert_tx, _) = tokio::sync::broadcast::channel(Self::ALERT_STREAM_CAPACITY);
+        let meta_client = match &config.meta_routing_key {
+            Some(key) => Arc::new(
+                PagerDutyClient::with_http_client(key.clone(), http_client.clone())
+                    .with_events_api_url(config.events_api_url()),
+            ),
+            None => Arc::clone(&pd_client),
+        };
+        let slo_client = match config
+            .latency_slo
+            .as_ref()
+            .and_then(|slo| slo.routing_key.as_ref())
+        {
+            Some(key) => Arc::new(
+                PagerDutyClient::with_http_client(key.clone(), http_client.clone())
+                    .with_events_api_url(config.events_api_url()),
+            ),
+            None => Arc::clone(&meta_client),
+        };
+        let sinks = Sinks::new(
+            &config.sinks,
+            &config.subscriptions,
+            config.chat_actions.as_ref(),
+            &http_client,
+        )?;
+        #[cfg(feature = "redis")]
+        let shared_state = config
+            .shared_state
+            .as_ref()
+            .map(shared::SharedState::new)
+            .transpose()?;
+        #[cfg(feature = "redis")]
+        if let Some(shared) = &shared_state {
+            log::info!(
+                "Claiming events in Redis as replica '{}'",
+                shared.instance_id()
+            );
+        }
+        #[cfg(feature = "redis")]
+        let elects_leader = config
+            .shared_state
+            .as_ref()
+            .is_some_and(|s| s.leader_lease_secs.is_some());
+        if let Some(shared) = &config.shared_state {
+            if shared.leader_lease_secs.is_some() && shared.shard_subscriptions {
+                anyhow::bail!("shared_state can elect a leader or shard subscriptions, not both");
+            }
+        }
+        if let Some(report) = &config.daily_report {
+            report.schedule()?;
+        }
+        #[cfg(feature = "sqlite")]
+        let event_store = config
+            .event_store
+            .as_ref()
+            .map(|store| {
+                EventStore::open(&store.path, store.retention_days, store.max_size_bytes())
+                    .map(Arc::new)
+            })
+            .transpose()?;
+        #[cfg(not(feature = "sqlite"))]
+        if config.event_store.is_some() {
+            anyhow::bail!("event_store is set, but this build lacks the `sqlite` feature");
+        }
+        #[cfg(not(feature = "redis"))]
+        if config.shared_state.is_some() {
+            anyhow::bail!("shared_state is set, but this build lacks the `redis` feature");
+        }
+        if config.admin_api && config.admin_token.is_none() {
+            anyhow::bail!("admin_api is enabled without an admin_token");
+        }
+        let supervisor = Arc::new(Supervisor::new(
+            config.task_restarts.clone(),
+            Arc::clone(&meta_client),
+            Arc::clone(&stats),
+        ));
+        let dispatcher = Dispatcher::new(
+            Arc::clone(&pd_client),
+            Arc::clone(&meta_client),
+            rest_client.clone(),
+            Arc::clone(&alerts),
+            Arc::clone(&stats),
+            AlertQueue::new(&config.alert_queue)?,
+            config.max_concurrent_dispatches,
+        );
+        let dispatcher =
+            dispatcher.with_failed_delivery_alert(config.self_alerts.failed_deliveries);
+        #[cfg(feature = "sqlite")]
+        let dispatcher = dispatcher.with_event_store(event_store.clone());
+        let dispatcher = Arc::new(dispatcher);
+
+        Ok(Self {
+            config,
+            explorers: networks
+                .iter()
+                .map(|(name, network)| (name.clone(), network.explorer()))
+                .collect(),
+            networks,
+            upstream_networks,
+            subscription_order,
+            running_subscriptions: Mutex::new(HashSet::new()),
+            pd_client,
+            dispatcher,
+            alerts,
+            stats,
+            alert_tx,
+            ready_notified: AtomicBool::new(false),
+            meta_client,
+            slo_client,
+            lagging_networks: Mutex::new(HashSet::new()),
+            idle_streams: Mutex::new(HashMap::new()),
+            supervisor,
+            #[cfg(feature = "sqlite")]
+            event_store,
+            report_tally: Mutex::new(report::ReportTally::new(Utc::now())),
+            token_metadata: token::TokenMetadataCache::new(http_client.clone()),
+            summary_bursts: collapse::SummaryBursts::default(),
+            seen_actions: replay::SeenActions::default(),
+            rest_client,
+            on_call: Mutex::new(None),
+            in_maintenance: AtomicBool::new(false),
+            clock_skewed: AtomicBool::new(false),
+            sinks,
+            #[cfg(feature = "redis")]
+            leading: AtomicBool::new(!elects_leader),
+            #[cfg(feature = "redis")]
+            standby_matches: Mutex::new(VecDeque::new()),
+            #[cfg(feature = "redis")]
+            shard_members: Mutex::new(None),
+            #[cfg(feature = "redis")]
+            shared_state,
+            namespaced_subscriptions,
+            #[cfg(feature = "scripting")]
+            scripts,
+        })
+    }
+
+    /// Build a network's explorer links with `provider` instead of its config
+    pub fn with_explorer(
+        mut self,
+        network: &str,
+        provider: impl ExplorerProvider + 'static,
+    ) -> Self {
+        self.explorers
+            .insert(network.to_string(), Arc::new(provider));
+        self
+    }
+
+    /// Lifecycle state of every alert this monitor has sent
+    pub fn alerts(&self) -> &Arc<AlertStore> {
+        &self.alerts
+    }
+
+    pub fn config(&self) -> &PagerDutyAlertConfig {
+        &self.config
+    }
+
+    /// Current connection status, recent matches, open alerts and error counters
+    pub fn status(&self) -> StatusSnapshot {
+        self.stats
+            .snapshot(self.alerts.open_alerts(), self.dispatcher.queue_len())
+    }
+
+    /// The latest matched events by subscription, newest first
+    pub fn recent_events(&self, subscription: Option<&str>) -> BTreeMap<String, Vec<RecentEvent>> {
+        self.stats.recent_events(subscription)
+    }
+
+    /// Receive every alert as it is rendered, before it is sent to PagerDuty
+    pub fn subscribe_alerts(&self) -> tokio::sync::broadcast::Receiver<RenderedAlert> {
+        self.alert_tx.subscribe()
+    }
+
+    /// Acknowledge an alert in PagerDuty and record it locally
+    pub async fn acknowledge_alert(&self, dedup_key: &str) -> Result<AlertState, anyhow::Error> {
+        self.pd_client.acknowledge(dedup_key).await?;
+        let state = self.alerts.apply(dedup_key, AlertEvent::Acknowledge)?;
+        self.publish_lifecycle(dedup_key, SinkEventKind::Acknowledge);
+        Ok(state)
+    }
+
+    /// Resolve an alert in PagerDuty and record it locally
+    pub async fn resolve_alert(&self, dedup_key: &str) -> Result<AlertState, anyhow::Error> {
+        self.pd_client.resolve(dedup_key).await?;
+        let state = self.alerts.apply(dedup_key, AlertEvent::Resolve)?;
+        self.publish_lifecycle(dedup_key, SinkEventKind::Resolve);
+        Ok(state)
+    }
+
+    /// Subscriptions with when their mute ends, if they are muted
+    pub fn subscription_mutes(&self) -> Vec<SubscriptionMute> {
+        let now = Utc::now();
+        self.config
+            .subscriptions
+            .iter()
+            .map(|s| SubscriptionMute {
+                name: s.name.clone(),
+                network: s.network.clone(),
+                severity: s.severity.clone(),
+                muted_until: self.alerts.muted_until(&s.name, now),
+            })
+            .collect()
+    }
+
+    /// Silence a subscription for `duration`; its matches are recorded but
+    /// not alerted on until the mute ends or is lifted
+    pub fn mute_subscription(
+        &self,
+        name: &str,
+        duration: Duration,
+    ) -> Result<chrono::DateTime<Utc>, anyhow::Error> {
+        if !self.config.subscriptions.iter().any(|s| s.name == name) {
+            anyhow::bail!("No subscription named '{}'", name);
+        }
+        let until = chrono::Duration::from_std(duration)
+            .ok()
+            .and_then(|duration| Utc::now().checked_add_signed(duration))
+            .ok_or_else(|| {
+                anyhow::anyhow!("A mute of {:?} ends too far in the future", duration)
+            })?;
+        self.alerts.mute(name, until);
+        log::warn!("Muted '{}' until {}", name, until.to_rfc3339());
+        Ok(until)
+    }
+
+    /// Lift a subscription's mute, returning whether it was muted
+    pub fn unmute_subscription(&self, name: &str) -> bool {
+        let unmuted = self.alerts.unmute(name);
+        if unmuted {
+            log::warn!("Unmuted '{}'", name);
+        }
+        unmuted
+    }
+
+    /// Record an acknowledgement or resolution made in PagerDuty itself, as
+    /// reported by its webhook; sinks only hear of it if the state changed
+    pub fn record_pagerduty_change(
+        &self,
+        dedup_key: &str,
+        event: AlertEvent,
+    ) -> Result<AlertState, InvalidTransition> {
+        let previous = self.alerts.get(dedup_key).map(|r| r.state);
+        let state = self.alerts.apply(dedup_key, event)?;
+        if previous != Some(state) {
+            match event {
+                AlertEvent::Acknowledge => {
+                    self.publish_lifecycle(dedup_key, SinkEventKind::Acknowledge)
+                }
+                AlertEvent::Resolve => self.publish_lifecycle(dedup_key, SinkEventKind::Resolve),
+                _ => {}
+            }
+        }
+        Ok(state)
+    }
+
+    /// Resolve every open alert, or only those of one subscription
+    pub async fn resolve_all(&self, subscription: Option<&str>) -> BulkResolve {
+        let outcome = resolve_open_alerts(&self.pd_client, &self.alerts, subscription).await;
+        log::warn!(
+            "Bulk resolve for {}: {} resolved, {} failed",
+            subscription.unwrap_or("all subscriptions"),
+            outcome.resolved.len(),
+            outcome.failed.len()
+        );
+        for dedup_key in &outcome.resolved {
+            self.publish_lifecycle(dedup_key, SinkEventKind::Resolve);
+        }
+        outcome
+    }
+
+    /// Mirror an event to the sinks of its subscription
+    fn publish_to_sinks(&self, event: SinkEvent) {
+        let only = self
+            .config
+            .subscriptions
+            .iter()
+            .find(|s| s.name == event.subscription)
+            .and_then(|s| s.sinks.as_deref());
+        self.sinks.publish(event, only);
+    }
+
+    /// Tell sinks a tracked alert was acknowledged or resolved
+    fn publish_lifecycle(&self, dedup_key: &str, kind: SinkEventKind) {
+        if self.sinks.is_empty() {
+            return;
+        }
+        if let Some(record) = self.alerts.get(dedup_key) {
+            self.publish_to_sinks(SinkEvent::lifecycle(kind, &record));
+        }
+    }
+
+    /// Deliver alerts still queued after [`start`](Self::start) was stopped or
+    /// [`replay`](Self::replay) returned, waiting at most `timeout`; returns
+    /// the number left undelivered
+    pub async fn drain(&self, timeout: Duration) -> usize {
+        systemd::notify("STOPPING=1\nSTATUS=Draining alert queue");
+        self.stop_subscriptions();
+        self.stats.set_draining();
+        log::info!(
+            "Draining {} queued alert(s), waiting up to {:?}",
+            self.dispatcher.queue_len(),
+            timeout
+        );
+        let remaining = self.dispatcher.drain(timeout).await;
+        self.alerts.flush();
+        if remaining == 0 {
+            log::info!("Alert queue drained");
+        } else {
+            log::error!("Drain timed out with {} alert(s) undelivered", remaining);
+        }
+        remaining
+    }
+
+    /// Start monitoring - connects to neardata and processes actions
+    pub async fn start(&self) -> Result<(), anyhow::Error> {
+        self.check_event_budget().await?;
+
+        let alerts = Arc::clone(&self.alerts);
+        let pd_client = self
+            .config
+            .resolve_expired_alerts
+            .then(|| Arc::clone(&self.pd_client));
+        let sweeper = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Self::EXPIRY_SWEEP_INTERVAL);
+            loop {
+                interval.tick().await;
+                for dedup_key in alerts.fail_undelivered(std::time::Instant::now()) {
+                    log::warn!(
+                        "Alert {} was not accepted by PagerDuty within {:?}",
+                        dedup_key,
+                        PENDING_TIMEOUT
+                    );
+                }
+                let expired = alerts.expire_stale(std::time::Instant::now());
+                for dedup_key in &expired {
+                    log::info!("Alert {} expired", dedup_key);
+                }
+                if let (Some(pd_client), false) = (&pd_client, expired.is_empty()) {
+                    let batch = pd_client.resolve_many(&expired).await;
+                    log::info!(
+                        "Resolved {} expired alert(s) in PagerDuty, {} failed",
+                        batch.succeeded.len(),
+                        batch.failed.len()
+                    );
+                }
+            }
+        });
+        let _sweeper = AbortOnDrop(sweeper);
+
+        let alerts = Arc::clone(&self.alerts);
+        let _state_writer = AbortOnDrop(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Self::STATE_FLUSH_INTERVAL);
+            loop {
+                interval.tick().await;
+                let alerts = Arc::clone(&alerts);
+                // Keep file writes off the async workers
+                let _ = tokio::task::spawn_blocking(move || alerts.flush()).await;
+            }
+        }));
+
+        let dispatcher = Arc::clone(&self.dispatcher);
+        let supervisor = Arc::clone(&self.supervisor);
+        let _dispatcher = AbortOnDrop(tokio::spawn(async move {
+            supervisor
+                .run("dispatcher", || {
+                    let dispatcher = Arc::clone(&dispatcher);
+                    async move { dispatcher.run().await }
+                })
+                .await
+        }));
+
+        // A wedged or disconnected monitor stops petting the watchdog so systemd restarts it
+        let _watchdog = systemd::watchdog_interval().map(|period| {
+            let stats = Arc::clone(&self.stats);
+            AbortOnDrop(tokio::spawn(async move {
+                let mut interval = tokio::time::interval(period);
+                loop {
+                    interval.tick().await;
+                    if stats.all_connected() {
+                        systemd::notify("WATCHDOG=1");
+                    }
+                }
+            }))
+        });
+
+        // Runs until one network gives up, which stops the others too; a
+        // panic only restarts the task it happened in
+        let supervisor = &self.supervisor;
+        let loops = self.networks.iter().map(|(name, network)| async move {
+            let (stream_task, cross_check_task) = (format!("stream-{}", name), format!("cross-check-{}", name));
+            tokio::select! {
+                result = supervisor.run(&stream_task, move || self.run_network(name, network)) => result,
+                // Never finishes
+                _ = supervisor.run(&cross_check_task, move || self.cross_check(name, network)) => Ok(()),
+            }
+        });
+        tokio::select! {
+            // With only market alerts there are no networks to run
+            result = futures_util::future::try_join_all(loops), if !self.networks.is_empty() => { result?; }
+            // Never finish
+            _ = supervisor.run("latency-slo", || self.watch_latency_slo()) => {}
+            _ = supervisor.run("markets", || self.watch_markets()) => {}
+            _ = supervisor.run("on-call", || self.watch_on_call()) => {}
+            _ = supervisor.run("maintenance-windows", || self.watch_maintenance_windows()) => {}
+            _ = supervisor.run("leadership", || self.watch_leadership()) => {}
+            _ = supervisor.run("shards", || self.watch_shards()) => {}
+            _ = supervisor.run("compaction", || self.watch_retention()) => {}
+            _ = supervisor.run("daily-report", || self.watch_daily_report()) => {}
+        }
+        Ok(())
+    }
+
+    /// Keep or compete for the leader lease, replaying matches held while
+    /// standing by when this replica takes over
+    #[cfg(feature = "redis")]
+    async fn watch_leadership(&self) {
+        let lease = self
+            .config
+            .shared_state
+            .as_ref()
+            .and_then(|s| s.leader_lease_secs);
+        let (Some(shared), Some(lease)) = (&self.shared_state, lease) else {
+            return std::future::pending().await;
+        };
+        let lease = Duration::from_secs(lease.max(1));
+        let mut interval = tokio::time::interval(lease / 3);
+        loop {
+            interval.tick().await;
+            let leading = shared.hold_leadership(lease).await;
+            let was_leading = self.leading.swap(leading, Ordering::Relaxed);
+            if leading && !was_leading {
+                let held: Vec<StandbyMatch> =
+                    self.standby_matches.lock().unwrap().drain(..).collect();
+                log::warn!(
+                    "Replica '{}' is now the leader; replaying {} match(es) seen while standing by",
+                    shared.instance_id(),
+                    held.len()
+                );
+                for held in held.iter().filter(|m| m.seen.elapsed() < 2 * lease) {
+                    if let Err(e) = self
+                        .process_action(&held.action, &held.subscription, &held.network, false)
+                        .await
+                    {
+                        log::error!(
+                            "Failed to replay a match for '{}': {:#}",
+                            held.subscription.name,
+                            e
+                        );
+                    }
+                }
+            } else if !leading && was_leading {
+                log::warn!(
+                    "Replica '{}' lost the leader lease and is standing by",
+                    shared.instance_id()
+                );
+            }
+        }
+    }
+
+    #[cfg(not(feature = "redis"))]
+    async fn watch_leadership(&self) {
+        std::future::pending().await
+    }
+
+    /// Apply `retention` to the state file, spilled alerts and event store
+    /// every `COMPACTION_INTERVAL`, starting at startup
+    async fn watch_retention(&self) {
+        let retention = &self.config.retention;
+        let mut interval = tokio::time::interval(Self::COMPACTION_INTERVAL);
+        loop {
+            interval.tick().await;
+            let now = Utc::now();
+            let pruned = self
+                .alerts
+                .prune_closed(now - chrono::Duration::days(retention.closed_alert_days as i64));
+            if pruned > 0 {
+                log::info!("Removed {} closed alert(s) from the alert state", pruned);
+            }
+            self.dispatcher.prune_spilled(
+                retention
+                    .spill_max_age_hours
+                    .map(|hours| Duration::from_secs(hours * 3600)),
+                retention.spill_max_files,
+            );
+            #[cfg(feature = "sqlite")]
+            if let Some(store) = &self.event_store {
+                if let Err(e) = store.compact(now) {
+                    log::warn!("Could not compact the event store: {:#}", e);
+                }
+            }
+        }
+    }
+
+    /// Send the daily report at `daily_report.at`
+    async fn watch_daily_report(&self) {
+        let Some(config) = &self.config.daily_report else {
+            return std::future::pending().await;
+        };
+        // Checked in new()
+        let Ok((at, timezone)) = config.schedule() else {
+            return std::future::pending().await;
+        };
+        let http_client = reqwest::Client::new();
+        let mut last_errors = self.stats.snapshot(Vec::new(), 0).errors;
+        loop {
+            let now = Utc::now();
+            let next = report::next_report_time(now, at, timezone);
+            tokio::time::sleep((next - now).to_std().unwrap_or_default()).await;
+
+            let errors = self.stats.snapshot(Vec::new(), 0).errors;
+            let report = self.report_tally.lock().unwrap().take(
+                Utc::now(),
+                errors.delivery_failures - last_errors.delivery_failures,
+                errors.dropped_alerts - last_errors.dropped_alerts,
+            );
+            last_errors = errors;
+            log::info!("{}", report.summary());
+
+            if config.change_event {
+                let details = serde_json::to_value(&report).unwrap_or_default();
+                if let Err(e) = self
+                    .pd_client
+                    .send_change(&report.summary(), "near-pagerduty-monitor", Some(details))
+                    .await
+                {
+                    log::error!("Failed to send the daily report change event: {:?}", e);
+                }
+            }
+            if let Some(url) = &config.webhook_url {
+                let result = http_client
+                    .post(url)
+                    .json(&serde_json::json!({ "text": report.text() }))
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status());
+                if let Err(e) = result {
+                    log::error!("Failed to post the daily report: {:?}", e);
+                }
+            }
+        }
+    }
+
+    /// Heartbeat into the shard registry and follow which replicas are live;
+    /// while Redis is unreachable this replica matches every subscription
+    #[cfg(feature = "redis")]
+    async fn watch_shards(&self) {
+        let sharded = self
+            .config
+            .shared_state
+            .as_ref()
+            .is_some_and(|s| s.shard_subscriptions);
+        let (Some(shared), true) = (&self.shared_state, sharded) else {
+            return std::future::pending().await;
+        };
+        let mut interval = tokio::time::interval(Self::SHARD_HEARTBEAT_INTERVAL);
+        loop {
+            interval.tick().await;
+            let members = match shared
+                .shard_members(3 * Self::SHARD_HEARTBEAT_INTERVAL)
+                .await
+            {
+                Ok(members) => Some(members),
+                Err(e) => {
+                    log::warn!(
+                        "Could not reach the shard registry, matching every subscription: {:#}",
+                        e
+                    );
+                    None
+                }
+            };
+            let mut current = self.shard_members.lock().unwrap();
+            if *current != members {
+                if let Some(members) = &members {
+                    let owned = self
+                        .config
+                        .subscriptions
+                        .iter()
+                        .filter(|s| {
+                            shared::shard_owner(members, &s.name) == Some(shared.instance_id())
+                        })
+                        .count();
+                    log::info!(
+                        "{} replica(s) live; '{}' matches {} of {} subscription(s)",
+                        members.len(),
+                        shared.instance_id(),
+                        owned,
+                        self.config.subscriptions.len()
+                    );
+                }
+                *current = members;
+            }
+        }
+    }
+
+    #[cfg(not(feature = "redis"))]
+    async fn watch_shards(&self) {
+        std::future::pending().await
+    }
+
+    /// Whether this replica's shard includes a subscription
+    #[cfg(feature = "redis")]
+    fn owns_subscription(&self, subscription: &EventSubscription) -> bool {
+        let (Some(shared), Some(members)) =
+            (&self.shared_state, &*self.shard_members.lock().unwrap())
+        else {
+            return true;
+        };
+        shared::shard_owner(members, &subscription.name)
+            .is_none_or(|owner| owner == shared.instance_id())
+    }
+
+    #[cfg(not(feature = "redis"))]
+    fn owns_subscription(&self, _subscription: &EventSubscription) -> bool {
+        true
+    }
+
+    /// Hold a match while standing by, dropping those older than two leases
+    #[cfg(feature = "redis")]
+    fn stand_by(
+        &self,
+        action: &NeardataAction,
+        subscription: &EventSubscription,
+        network: &NetworkConfig,
+    ) {
+        let lease = self
+            .config
+            .shared_state
+            .as_ref()
+            .and_then(|s| s.leader_lease_secs)
+            .unwrap_or_default();
+        let retention = Duration::from_secs(2 * lease);
+        let mut held = self.standby_matches.lock().unwrap();
+        while held.front().is_some_and(|m| m.seen.elapsed() > retention) {
+            held.pop_front();
+        }
+        held.push_back(StandbyMatch {
+            seen: std::time::Instant::now(),
+            action: action.clone(),
+            subscription: subscription.clone(),
+            network: network.clone(),
+        });
+    }
+
+    /// Estimate each subscription's events per day from the last
+    /// `sample_blocks` blocks of history, and warn or refuse to start when one
+    /// exceeds `event_budget`
+    async fn check_event_budget(&self) -> Result<(), anyhow::Error> {
+        let Some(budget) = &self.config.event_budget else {
+            return Ok(());
+        };
+        let mut over_budget = Vec::new();
+        for (name, network) in &self.networks {
+            let sample = async {
+                let history = Self::history_client(name, network)?;
+                let last = history.last_final_block_height().await?;
+                let first = last.saturating_sub(budget.sample_blocks.saturating_sub(1));
+                log::info!(
+                    "Estimating event rates from {} blocks {}..={}",
+                    name,
+                    first,
+                    last
+                );
+                history.actions_in_range(first..=last).await
+            };
+            let actions = match sample.await {
+                Ok(actions) => actions,
+                Err(e) => {
+                    log::warn!("Event budget of {} not checked: {:#}", name, e);
+                    continue;
+                }
+            };
+            let subscriptions: Vec<&EventSubscription> = self
+                .config
+                .subscriptions
+                .iter()
+                .filter(|s| s.network == *name)
+                .collect();
+            for (sub, per_day) in self.estimate_events_per_day(&actions, &subscriptions) {
+                log::info!(
+                    "Subscription '{}' would match about {:.0} events/day",
+                    sub,
+                    per_day
+                );
+                if per_day > budget.max_events_per_day as f64 {
+                    over_budget.push(format!("'{}' (~{:.0}/day)", sub, per_day));
+                }
+            }
+        }
+        if over_budget.is_empty() {
+            return Ok(());
+        }
+        let message = format!(
+            "Subscriptions over the budget of {} events/day: {}",
+            budget.max_events_per_day,
+            over_budget.join(", ")
+        );
+        if budget.refuse {
+            anyhow::bail!(message);
+        }
+        log::warn!("{}", message);
+        Ok(())
+    }
+
+    /// Matches per subscription in `actions`, scaled from the span of their
+    /// block timestamps to a day
+    fn estimate_events_per_day(
+        &self,
+        actions: &[NeardataAction],
+        subscriptions: &[&EventSubscription],
+    ) -> Vec<(String, f64)> {
+        let timestamps = actions.iter().filter_map(|a| a.block_timestamp_ms);
+        let span_ms =
+            timestamps.clone().fold(f64::NAN, f64::max) - timestamps.fold(f64::NAN, f64::min);
+        // A sample from one block, or without timestamps (NaN), counts as one second
+        let span_secs = (span_ms / 1000.0).max(1.0);
+        subscriptions
+            .iter()
+            .map(|sub| {
+                let matched = actions
+                    .iter()
+                    .filter(|a| a.status == "SUCCESS" && sub.matches_account(&a.account_id))
+                    .filter(|a| self.action_matches_subscription(a, sub))
+                    .count();
+                (sub.name.clone(), matched as f64 * 86_400.0 / span_secs)
+            })
+            .collect()
+    }
+
+    /// Poll the price feed and page on `market_alerts`
+    async fn watch_markets(&self) {
+        if self.config.market_alerts.is_empty() {
+            return std::future::pending().await;
+        }
+        let feed = market::PriceFeed::new(&self.config.price_feed.url);
+        let mut watches: Vec<market::MarketWatch> = self
+            .config
+            .market_alerts
+            .iter()
+            .cloned()
+            .map(market::MarketWatch::new)
+            .collect();
+        let mut interval = tokio::time::interval(Duration::from_secs(
+            self.config.price_feed.interval_secs.max(1),
+        ));
+        loop {
+            interval.tick().await;
+            let prices = match feed.prices().await {
+                Ok(prices) => prices,
+                Err(e) => {
+                    log::warn!("Price feed unavailable: {:#}", e);
+                    continue;
+                }
+            };
+            for watch in &mut watches {
+                let Some(&price) = prices.get(&watch.alert.token_id) else {
+                    log::warn!(
+                        "Price feed has no price for {} ('{}')",
+                        watch.alert.token_id,
+                        watch.alert.name
+                    );
+                    continue;
+                };
+                let dedup_key = format!("market-{}", slug(&watch.alert.name));
+                let result = match watch.observe(price, std::time::Instant::now()) {
+                    Some(market::MarketTransition::Triggered { summary, details }) => {
+                        log::warn!("{}", summary);
+                        let details = self.with_deployment_tags(details);
+                        let source = format!("market:{}", watch.alert.token_id);
+                        self.pd_client
+                            .trigger(
+                                &summary,
+                                &source,
+                                &watch.alert.severity,
+                                Some(dedup_key),
+                                Some(details),
+                                &[],
+                            )
+                            .await
+                    }
+                    Some(market::MarketTransition::Recovered) => {
+                        log::info!("Market alert '{}' cleared at ${}", watch.alert.name, price);
+                        self.pd_client.resolve(&dedup_key).await
+                    }
+                    None => continue,
+                };
+                if let Err(e) = result {
+                    log::error!(
+                        "Failed to send market alert '{}': {:?}",
+                        watch.alert.name,
+                        e
+                    );
+                }
+            }
+        }
+    }
+
+    /// Keep track of whether the service is in a PagerDuty maintenance window
+    async fn watch_maintenance_windows(&self) {
+        let (Some(maintenance), Some(rest_client)) =
+            (&self.config.maintenance_windows, &self.rest_client)
+        else {
+            return std::future::pending().await;
+        };
+        let mut interval =
+            tokio::time::interval(Duration::from_secs(maintenance.refresh_secs.max(1)));
+        loop {
+            interval.tick().await;
+            let windows = match rest_client
+                .ongoing_maintenance_windows(&maintenance.service_id)
+                .await
+            {
+                Ok(windows) => windows,
+                Err(e) => {
+                    log::warn!(
+                        "Failed to check maintenance windows of {}: {:#}",
+                        maintenance.service_id,
+                        e
+                    );
+                    continue;
+                }
+            };
+            let active = !windows.is_empty();
+            if self.in_maintenance.swap(active, Ordering::Relaxed) != active {
+                if active {
+                    log::info!(
+                        "Service {} entered maintenance window(s) {}",
+                        maintenance.service_id,
+                        windows.join(", ")
+                    );
+                } else {
+                    log::info!(
+                        "Service {} left its maintenance window",
+                        maintenance.service_id
+                    );
+                }
+            }
+        }
+    }
+
+    /// Send or drop an event matched during a maintenance window, per `maintenance_windows.during`
+    async fn hold_for_maintenance(
+        &self,
+        event_id: &str,
+        subscription: &EventSubscription,
+        summary: &str,
+        source: &str,
+        custom_details: serde_json::Value,
+    ) -> Result<(), anyhow::Error> {
+        let during = self
+            .config
+            .maintenance_windows
+            .as_ref()
+            .map(|m| m.during)
+            .unwrap_or_default();
+        match during {
+            MaintenanceAction::Suppress => {
+                log::info!(
+                    "Suppressed '{}' during a maintenance window: {}",
+                    subscription.name,
+                    summary
+                );
+            }
+            MaintenanceAction::ChangeEvent => {
+                if let Err(e) = self
+                    .pd_client
+                    .send_change(summary, source, Some(custom_details))
+                    .await
+                {
+                    self.alerts.release_event(event_id);
+                    #[cfg(feature = "sqlite")]
+                    if let Some(store) = &self.event_store {
+                        if let Err(e) = store.set_outcome(event_id, EventOutcome::Failed) {
+                            log::warn!(
+                                "Could not record the outcome of event {}: {:#}",
+                                event_id,
+                                e
+                            );
+                        }
+                    }
+                    return Err(e);
+                }
+                log::info!(
+                    "Sent '{}' as a change event during a maintenance window",
+                    subscription.name
+                );
+            }
+        }
+        self.alerts.mark_event_sent(event_id);
+        Ok(())
+    }
+
+    /// Keep track of who `on_call_escalation_policy` pages first
+    async fn watch_on_call(&self) {
+        let (Some(policy), Some(rest_client)) =
+            (&self.config.on_call_escalation_policy, &self.rest_client)
+        else {
+            return std::future::pending().await;
+        };
+        let mut interval = tokio::time::interval(Self::ON_CALL_REFRESH_INTERVAL);
+        loop {
+            interval.tick().await;
+            match rest_client.current_on_call(policy).await {
+                Ok(on_call) => *self.on_call.lock().unwrap() = on_call,
+                Err(e) => log::warn!("Failed to look up who is on call for {}: {:#}", policy, e),
+            }
+        }
+    }
+
+    /// Page `latency_slo`'s routing key while the delivery latency p95 stays above the objective
+    async fn watch_latency_slo(&self) {
+        let Some(slo) = &self.config.latency_slo else {
+            return std::future::pending().await;
+        };
+        let mut tracker = slo::LatencySloTracker::new(slo);
+        let mut interval = tokio::time::interval(Self::LATENCY_SLO_CHECK_INTERVAL);
+        let dedup_key = "near-pagerduty-monitor-delivery-latency".to_string();
+        loop {
+            interval.tick().await;
+            let p95 = self.stats.delivery_latency_p95();
+            let result = match tracker.update(p95, std::time::Instant::now()) {
+                Some(slo::SloTransition::Breached) => {
+                    let p95 = p95.unwrap_or_default();
+                    let summary = format!(
+                        "NEAR PagerDuty Monitor alert latency p95 is {:.0}s, above the {}s objective for {}s",
+                        p95, slo.p95_secs, slo.sustained_secs
+                    );
+                    log::warn!("{}", summary);
+                    let details = serde_json::json!({
+                        "delivery_latency_p95_secs": p95,
+                        "p95_secs": slo.p95_secs,
+                        "window_secs": slo.window_secs,
+                        "sustained_secs": slo.sustained_secs,
+                    });
+                    self.slo_client
+                        .trigger(
+                            &summary,
+                            "near-pagerduty-monitor",
+                            "error",
+                            Some(dedup_key.clone()),
+                            Some(details),
+                            &[],
+                        )
+                        .await
+                }
+                Some(slo::SloTransition::Recovered) => {
+                    log::info!("Alert delivery latency is back within its objective");
+                    self.slo_client.resolve(&dedup_key).await
+                }
+                None => continue,
+            };
+            if let Err(e) = result {
+                log::error!("Failed to send delivery latency alert: {:?}", e);
+            }
+        }
+    }
+
+    /// Keep one network's neardata stream connected, reconnecting on failure
+    async fn run_network(&self, name: &str, network: &NetworkConfig) -> Result<(), MonitorExit> {
+        // In startup order, so an action alerts for dependencies first
+        let subscriptions: Vec<&EventSubscription> = self
+            .subscriptions_in_order()
+            .filter(|s| s.network == name)
+            .collect();
+        let max_attempts = subscriptions
+            .iter()
+            .filter_map(|s| s.max_reconnect_attempts)
+            .min()
+            .or(self.config.max_reconnect_attempts);
+
+        self.wait_for_upstream_networks(name).await;
+
+        // Backfill alongside the live stream so no blocks are missed meanwhile
+        let backfill = async {
+            if let Some(blocks) = subscriptions.iter().filter_map(|s| s.backfill_blocks).max() {
+                if let Err(e) = self.backfill(name, network, &subscriptions, blocks).await {
+                    log::error!("Backfill of {} failed: {:#}", name, e);
+                }
+            }
+            std::future::pending().await
+        };
+        tokio::select! {
+            result = self.run_live(name, network, &subscriptions, max_attempts) => result,
+            // Never finishes
+            _ = backfill => Ok(()),
+        }
+    }
+
+    /// Follow a network's new blocks from NEAR Lake or the neardata stream,
+    /// reconnecting until `max_attempts` consecutive connections fail
+    async fn run_live(
+        &self,
+        name: &str,
+        network: &NetworkConfig,
+        subscriptions: &[&EventSubscription],
+        max_attempts: Option<u32>,
+    ) -> Result<(), MonitorExit> {
+        if let Some(lake) = &network.lake {
+            return self
+                .run_lake(name, network, lake, subscriptions, max_attempts)
+                .await;
+        }
+
+        let mut failed_attempts = 0;
+        loop {
+            log::info!("Connecting to {} ({})", network.ws_url, name);
+            let ws_stream = match connect_async(network.ws_url.as_str()).await {
+                Ok((ws_stream, _)) => {
+                    self.record_reconnected(name, failed_attempts);
+                    failed_attempts = 0;
+                    Some(ws_stream)
+                }
+                Err(tokio_tungstenite::tungstenite::Error::Http(response))
+                    if self.config.exit_on_fatal_errors && response.status().is_client_error() =>
+                {
+                    return Err(MonitorExit::Fatal {
+                        network: name.to_string(),
+                        error: format!("HTTP {}", response.status()),
+                    });
+                }
+                Err(e) => {
+                    failed_attempts += 1;
+                    log::error!("Failed to connect to neardata ({}): {:?}", name, e);
+                    self.record_failed_reconnect(name, failed_attempts, &e.to_string());
+                    None
+                }
+            };
+
+            if let Some(ws_stream) = ws_stream {
+                let result = self
+                    .monitor_stream(ws_stream, name, network, subscriptions)
+                    .await;
+                if let Err(e) = &result {
+                    log::error!("Error in neardata stream ({}): {:?}", name, e);
+                }
+                self.stats.record_disconnected(name, result.is_err());
+            } else {
+                self.stats.record_disconnected(name, true);
+            }
+
+            if let Some(max) = max_attempts.filter(|&max| failed_attempts >= max) {
+                return Err(MonitorExit::ReconnectLimit {
+                    network: name.to_string(),
+                    attempts: max,
+                });
+            }
+            log::info!(
+                "Reconnecting to neardata ({}) in {}s...",
+                name,
+                self.config.reconnect_delay_secs
+            );
+            tokio::time::sleep(Duration::from_secs(self.config.reconnect_delay_secs)).await;
+        }
+    }
+
+    /// Hold a network back until the networks its subscriptions depend on
+    /// are connected, so dependents never alert without their upstream.
+    /// Waiting longer than `self_alerts.upstream_wait_secs` pages the meta
+    /// routing key until the upstream connects.
+    async fn wait_for_upstream_networks(&self, name: &str) {
+        let Some(upstream) = self.upstream_networks.get(name) else {
+            return;
+        };
+        let limit = Duration::from_secs(self.config.self_alerts.upstream_wait_secs);
+        let started = std::time::Instant::now();
+        let (mut waiting_logged, mut paged) = (false, false);
+        while let Some(pending) = upstream
+            .iter()
+            .find(|network| !self.stats.is_connected(network))
+        {
+            if !std::mem::replace(&mut waiting_logged, true) {
+                log::info!("Starting {} once {} is connected", name, pending);
+            }
+            if !paged && !limit.is_zero() && started.elapsed() >= limit {
+                paged = true;
+                self.record_upstream_wait(name, pending, true);
+            }
+            tokio::time::sleep(Self::UPSTREAM_POLL_INTERVAL).await;
+        }
+        if paged {
+            self.record_upstream_wait(name, "", false);
+        }
+    }
+
+    /// Subscriptions in startup order: each after everything it depends on
+    fn subscriptions_in_order(&self) -> impl Iterator<Item = &EventSubscription> {
+        self.subscription_order
+            .iter()
+            .filter_map(|name| self.config.subscriptions.iter().find(|s| s.name == *name))
+    }
+
+    /// Whether a subscription may alert; see [`Self::start_subscriptions`]
+    fn is_running(&self, sub: &EventSubscription) -> bool {
+        self.running_subscriptions
+            .lock()
+            .unwrap()
+            .contains(&sub.name)
+    }
+
+    /// Let the subscriptions of connected networks alert, in startup order,
+    /// once everything they depend on is running. A dependency on the same
+    /// network starts first; one on a network that isn't connected yet holds
+    /// its dependents back until it is.
+    fn start_subscriptions(&self) {
+        let mut running = self.running_subscriptions.lock().unwrap();
+        for sub in self.subscriptions_in_order() {
+            if running.contains(&sub.name) || !self.stats.is_connected(&sub.network) {
+                continue;
+            }
+            if let Some(pending) = sub.depends_on.iter().find(|d| !running.contains(*d)) {
+                log::info!(
+                    "Holding subscription '{}' until '{}' is running",
+                    sub.name,
+                    pending
+                );
+                continue;
+            }
+            if !sub.depends_on.is_empty() {
+                let dependencies: Vec<&str> = sub.depends_on.iter().map(String::as_str).collect();
+                log::info!(
+                    "Starting subscription '{}' after {}",
+                    sub.name,
+                    quoted_list(&dependencies)
+                );
+            }
+            running.insert(sub.name.clone());
+        }
+    }
+
+    /// Stop every subscription from alerting, dependents before what they
+    /// depend on
+    fn stop_subscriptions(&self) {
+        let mut running = self.running_subscriptions.lock().unwrap();
+        for name in self.subscription_order.iter().rev() {
+            if running.remove(name) {
+                log::debug!("Stopped subscription '{}'", name);
+            }
+        }
+    }
+
+    /// Read a network's blocks from NEAR Lake as they are written, in place
+    /// of the neardata stream. Failed reads count as failed reconnects.
+    async fn run_lake(
+        &self,
+        name: &str,
+        network: &NetworkConfig,
+        lake: &LakeConfig,
+        subscriptions: &[&EventSubscription],
+        max_attempts: Option<u32>,
+    ) -> Result<(), MonitorExit> {
+        let client = LakeClient::new(lake).map_err(|e| MonitorExit::Fatal {
+            network: name.to_string(),
+            error: format!("{:#}", e),
+        })?;
+        let index = SubscriptionIndex::new(subscriptions);
+        log::info!("Reading {} from NEAR Lake bucket {}", name, lake.bucket);
+
+        let mut next_height = lake.start_block;
+        let mut failed_attempts = 0;
+        let mut connected = false;
+        loop {
+            match self
+                .read_lake(&client, &mut next_height, name, network, &index)
+                .await
+            {
+                Ok(blocks) => {
+                    self.record_reconnected(name, failed_attempts);
+                    failed_attempts = 0;
+                    if !std::mem::replace(&mut connected, true) {
+                        self.record_connected(name);
+                    }
+                    if blocks == 0 {
+                        tokio::time::sleep(Duration::from_millis(lake.poll_interval_ms)).await;
+                    }
+                }
+                Err(e) => {
+                    failed_attempts += 1;
+                    connected = false;
+                    log::error!("Failed to read NEAR Lake ({}): {:#}", name, e);
+                    self.record_failed_reconnect(name, failed_attempts, &e.to_string());
+                    self.stats.record_disconnected(name, true);
+                    if let Some(max) = max_attempts.filter(|&max| failed_attempts >= max) {
+                        return Err(MonitorExit::ReconnectLimit {
+                            network: name.to_string(),
+                            attempts: max,
+                        });
+                    }
+                    tokio::time::sleep(Duration::from_secs(self.config.reconnect_delay_secs)).await;
+                }
+            }
+        }
+    }
+
+    /// Process the blocks written to NEAR Lake since `next_height`, starting
+    /// at the latest final block when unset, and return how many there were
+    async fn read_lake(
+        &self,
+        client: &LakeClient,
+        next_height: &mut Option<u64>,
+        name: &str,
+        network: &NetworkConfig,
+        index: &SubscriptionIndex<'_>,
+    ) -> Result<usize, anyhow::Error> {
+        let first = match *next_height {
+            Some(height) => height,
+            None => {
+                let rpc_url = network.rpc_url.as_deref().ok_or_else(|| {
+                    anyhow::anyhow!("network '{}' needs lake.start_block or an rpc_url", name)
+                })?;
+                let height = rpc::final_block_height(&reqwest::Client::new(), rpc_url).await?;
+                *next_height.insert(height)
+            }
+        };
+        let heights = client
+            .heights_from(first, Self::LAKE_BLOCKS_PER_LISTING)
+            .await?;
+        for &height in &heights {
+            let mut actions = client.block_actions(height).await?;
+            actions.retain(|action| {
+                let method_name = match &action.action {
+                    ActionType::FunctionCall(fc) => Some(fc.method_name.as_str()),
+                    _ => None,
+                };
+                action.status == "SUCCESS"
+                    && index
+                        .for_account(&action.account_id)
+                        .any(|s| s.matches_kind(action.action.kind(), method_name))
+            });
+            self.check_event_lag(name, &actions);
+            self.process_actions(&actions, index, name, network).await;
+            *next_height = Some(height + 1);
+        }
+        Ok(heights.len())
+    }
+
+    /// Alert on matching actions from each subscription's last `backfill_blocks` blocks
+    async fn backfill(
+        &self,
+        name: &str,
+        network: &NetworkConfig,
+        subscriptions: &[&EventSubscription],
+        blocks: u64,
+    ) -> Result<(), anyhow::Error> {
+        // Backfilled matches wait for their subscriptions to start, as live ones do
+        while subscriptions
+            .iter()
+            .any(|s| s.backfill_blocks.is_some() && !self.is_running(s))
+        {
+            tokio::time::sleep(Self::UPSTREAM_POLL_INTERVAL).await;
+        }
+        let history = Self::history_client(name, network)?;
+        let last = history.last_final_block_height().await?;
+        let first = last.saturating_sub(blocks.saturating_sub(1));
+        log::info!("Backfilling {} blocks {}..={}", name, first, last);
+
+        let mut blocks = std::pin::pin!(history.blocks_in_range(first..=last));
+        let mut matched = 0;
+        while let Some(actions) = blocks.next().await {
+            for action in actions?.iter().filter(|a| a.status == "SUCCESS") {
+                let in_window = subscriptions.iter().copied().filter(|sub| {
+                    sub.matches_account(&action.account_id)
+                        && last - action.block_height < sub.backfill_blocks.unwrap_or(0)
+                });
+                self.isolate_panics(name, action, async {
+                    for sub in self.matching_subscriptions(action, in_window) {
+                        matched += 1;
+                        // Already delivered events are skipped by their event ID
+                        if let Err(e) = self.process_action(action, sub, network, false).await {
+                            log::error!("Error processing backfilled action: {:?}", e);
+                        }
+                    }
+                })
+                .await;
+            }
+        }
+        log::info!("Backfill of {} matched {} action(s)", name, matched);
+        Ok(())
+    }
+
+    /// Periodically look for matching events in recent blocks that the
+    /// stream did not deliver, then page the meta routing key and deliver them
+    async fn cross_check(&self, name: &str, network: &NetworkConfig) {
+        let Some(config) = &self.config.cross_check else {
+            return std::future::pending().await;
+        };
+        let history = match Self::history_client(name, network) {
+            Ok(history) => history,
+            Err(e) => {
+                log::error!("Cross-check disabled: {:#}", e);
+                return std::future::pending().await;
+            }
+        };
+        let subscriptions: Vec<&EventSubscription> = self
+            .config
+            .subscriptions
+            .iter()
+            .filter(|s| s.network == name)
+            .collect();
+        let started_ms = self.stats.started_at().timestamp_millis() as f64;
+
+        let mut checked_up_to: Option<u64> = None;
+        let mut interval = tokio::time::interval(Duration::from_secs(config.interval_secs.max(1)));
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let last = match history.last_final_block_height().await {
+                Ok(height) => height.saturating_sub(config.settle_blocks),
+                Err(e) => {
+                    log::warn!("Cross-check of {} skipped: {:#}", name, e);
+                    continue;
+                }
+            };
+            let first = checked_up_to
+                .map_or(0, |h| h + 1)
+                .max(last.saturating_sub(config.blocks.saturating_sub(1)));
+            if first > last {
+                continue;
+            }
+            let actions = match history.actions_in_range(first..=last).await {
+                Ok(actions) => actions,
+                Err(e) => {
+                    log::warn!("Cross-check of {} skipped: {:#}", name, e);
+                    continue;
+                }
+            };
+            checked_up_to = Some(last);
+
+            // Events from before startup were never expected from the stream
+            let missed: Vec<(&NeardataAction, &EventSubscription)> = actions
+                .iter()
+                .filter(|a| a.status == "SUCCESS")
+                .filter(|a| a.block_timestamp_ms.is_some_and(|ms| ms >= started_ms))
+                .flat_map(|a| {
+                    let candidates = subscriptions
+                        .iter()
+                        .copied()
+                        .filter(|s| s.matches_account(&a.account_id));
+                    self.matching_subscriptions(a, candidates)
+                        .into_iter()
+                        .map(move |s| (a, s))
+                })
+                .filter(|(a, s)| !self.alerts.knows_event(&event_id(a, s)))
+                .collect();
+            log::debug!(
+                "Cross-checked {} blocks {}..={}: {} missed event(s)",
+                name,
+                first,
+                last,
+                missed.len()
+            );
+            if missed.is_empty() {
+                continue;
+            }
+
+            self.stats.record_missed_events(missed.len() as u64);
+            self.report_missed_events(name, &missed).await;
+            for (action, sub) in missed {
+                log::warn!(
+                    "Delivering event missed by the {} stream for '{}' (block {}, tx {:?})",
+                    name,
+                    sub.name,
+                    action.block_height,
+                    action.tx_hash
+                );
+                if let Err(e) = self.process_action(action, sub, network, false).await {
+                    log::error!("Error processing missed action: {:?}", e);
+                }
+            }
+        }
+    }
+
+    /// Page the meta routing key about events the stream did not deliver
+    async fn report_missed_events(
+        &self,
+        name: &str,
+        missed: &[(&NeardataAction, &EventSubscription)],
+    ) {
+        let summary = format!(
+            "neardata ({}) stream missed {} matching event(s) found by the cross-check",
+            name,
+            missed.len()
+        );
+        log::error!("{}", summary);
+        let events: Vec<serde_json::Value> = missed
+            .iter()
+            .take(20)
+            .map(|(action, sub)| {
+                serde_json::json!({
+                    "subscription": sub.name,
+                    "block_height": action.block_height,
+                    "tx_hash": action.tx_hash,
+                    "receipt_id": action.receipt_id,
+                })
+            })
+            .collect();
+        let details = serde_json::json!({ "network": name, "missed_events": events });
+        if let Err(e) = self
+            .meta_client
+            .trigger(
+                &summary,
+                "near-pagerduty-monitor",
+                "error",
+                Some(format!("near-pagerduty-monitor-missed-events-{}", name)),
+                Some(details),
+                &[],
+            )
+            .await
+        {
+            log::error!("Failed to send missed events alert: {:?}", e);
+        }
+    }
+
+    /// Run a past block range of one network through its subscriptions,
+    /// returning the number of matches
+    ///
+    /// Alerts are delivered while the range is read; call [`Self::drain`]
+    /// afterwards to wait for the rest.
+    pub async fn replay(
+        &self,
+        network_name: &str,
+        heights: RangeInclusive<u64>,
+    ) -> Result<usize, anyhow::Error> {
+        let network = self
+            .networks
+            .iter()
+            .find(|(name, _)| name == network_name)
+            .map(|(_, network)| network)
+            .ok_or_else(|| anyhow::anyhow!("No subscription uses network '{}'", network_name))?;
+        let subscriptions: Vec<&EventSubscription> = self
+            .config
+            .subscriptions
+            .iter()
+            .filter(|s| s.network == network_name)
+            .collect();
+
+        let dispatcher = Arc::clone(&self.dispatcher);
+        let _dispatcher = AbortOnDrop(tokio::spawn(async move { dispatcher.run().await }));
+
+        let history = Self::history_client(network_name, network)?;
+        let mut blocks = std::pin::pin!(history.blocks_in_range(heights));
+        let mut matched = 0;
+        while let Some(actions) = blocks.next().await {
+            for action in actions?.iter().filter(|a| a.status == "SUCCESS") {
+                let candidates = subscriptions
+                    .iter()
+                    .copied()
+                    .filter(|s| s.matches_account(&action.account_id));
+                for sub in self.matching_subscriptions(action, candidates) {
+                    matched += 1;
+                    self.process_action(action, sub, network, false).await?;
+                }
+            }
+        }
+        Ok(matched)
+    }
+
+    /// Run the actions of event store matches selected by `query` through
+    /// the current subscriptions again, oldest first, e.g. after fixing a
+    /// filter. Like [`Self::replay`], events already delivered are skipped by
+    /// their event ID; call [`Self::drain`] afterwards to wait for delivery.
+    #[cfg(feature = "sqlite")]
+    pub async fn reprocess(&self, query: &EventQuery) -> Result<ReprocessOutcome, anyhow::Error> {
+        let store = self.event_store.as_ref().ok_or_else(|| {
+            anyhow::anyhow!("No event_store configured; matches are not recorded")
+        })?;
+        let events = store.query(query)?;
+
+        let dispatcher = Arc::clone(&self.dispatcher);
+        let _dispatcher = AbortOnDrop(tokio::spawn(async move { dispatcher.run().await }));
+
+        let mut outcome = ReprocessOutcome::default();
+        // An action that matched several subscriptions was recorded once per match
+        let mut seen = HashSet::new();
+        for event in events.iter().rev() {
+            let Some(raw) = &event.raw else {
+                outcome.skipped += 1;
+                continue;
+            };
+            if !seen.insert((event.network.as_str(), raw.to_string())) {
+                continue;
+            }
+            let action: NeardataAction = serde_json::from_value(raw.clone())?;
+            let Some((_, network)) = self
+                .networks
+                .iter()
+                .find(|(name, _)| *name == event.network)
+            else {
+                log::warn!(
+                    "Skipping event {}: no subscription uses network '{}'",
+                    event.event_id,
+                    event.network
+                );
+                continue;
+            };
+            outcome.actions += 1;
+            let candidates =
+                self.config.subscriptions.iter().filter(|s| {
+                    s.network == event.network && s.matches_account(&action.account_id)
+                });
+            for sub in self.matching_subscriptions(&action, candidates) {
+                outcome.matched += 1;
+                self.process_action(&action, sub, network, false).await?;
+            }
+        }
+        Ok(outcome)
+    }
+
+    /// Run one subscription over a past block range of its network, e.g. to
+    /// reconstruct an incident while the monitor was down
+    ///
+    /// With `dry_run` nothing is sent or recorded: the matches are only
+    /// rendered. Otherwise they go through the usual pipeline, where events
+    /// already delivered are skipped by their event ID; call [`Self::drain`]
+    /// afterwards to wait for delivery.
+    pub async fn backfill_subscription(
+        &self,
+        subscription_name: &str,
+        heights: RangeInclusive<u64>,
+        dry_run: bool,
+    ) -> Result<Vec<BackfillMatch>, anyhow::Error> {
+        let subscription = self
+            .config
+            .subscriptions
+            .iter()
+            .find(|s| s.name == subscription_name)
+            .ok_or_else(|| anyhow::anyhow!("No subscription named '{}'", subscription_name))?;
+        let network = self
+            .networks
+            .iter()
+            .find(|(name, _)| *name == subscription.network)
+            .map(|(_, network)| network)
+            .ok_or_else(|| anyhow::anyhow!("Unknown network '{}'", subscription.network))?;
+
+        let _dispatcher = (!dry_run).then(|| {
+            let dispatcher = Arc::clone(&self.dispatcher);
+            AbortOnDrop(tokio::spawn(async move { dispatcher.run().await }))
+        });
+
+        let history = Self::history_client(&subscription.network, network)?;
+        let mut blocks = std::pin::pin!(history.blocks_in_range(heights));
+        let mut matches = Vec::new();
+        while let Some(actions) = blocks.next().await {
+            for action in actions?.iter().filter(|a| a.status == "SUCCESS") {
+                let candidates =
+                    std::iter::once(subscription).filter(|s| s.matches_account(&action.account_id));
+                for sub in self.matching_subscriptions(action, candidates) {
+                    let method_name = match &action.action {
+                        ActionType::FunctionCall(fc) => Some(fc.method_name.as_str()),
+                        _ => None,
+                    };
+                    let mut rendered = sub.for_method(method_name);
+                    self.prefetch_token_metadata(action, &rendered).await;
+                    let (summary, dedup_key) = self.render_match(action, &mut rendered);
+                    matches.push(BackfillMatch {
+                        subscription: sub.name.clone(),
+                        block_height: action.block_height,
+                        block_timestamp: action
+                            .block_timestamp_ms
+                            .and_then(|ms| chrono::DateTime::from_timestamp_millis(ms as i64)),
+                        tx_hash: action.tx_hash.clone(),
+                        severity: rendered.severity.clone(),
+                        summary,
+                        dedup_key,
+                        explorer_link: Some(
+                            self.explorer_link(action, &subscription.network, network).0,
+                        ),
+                    });
+                    if !dry_run {
+                        self.process_action(action, sub, network, false).await?;
+                    }
+                }
+            }
+        }
+        Ok(matches)
+    }
+
+    /// Write the matches of `subscription` (default: every subscription) in a
+    /// past block range to date-partitioned CSV files under `dir`, without
+    /// alerting, returning the number of rows
+    pub async fn export(
+        &self,
+        subscription: Option<&str>,
+        heights: RangeInclusive<u64>,
+        dir: &str,
+    ) -> Result<usize, anyhow::Error> {
+        let names: Vec<&str> = match subscription {
+            Some(name) => vec![name],
+            None => self
+                .config
+                .subscriptions
+                .iter()
+                .map(|s| s.name.as_str())
+                .collect(),
+        };
+        let partitions = CsvPartitions::new(dir, None);
+        let mut rows = 0;
+        for name in names {
+            for m in self
+                .backfill_subscription(name, heights.clone(), true)
+                .await?
+            {
+                partitions.append(&ExportRow {
+                    time: m.block_timestamp.unwrap_or_else(Utc::now),
+                    kind: "match".to_string(),
+                    subscription: m.subscription,
+                    severity: m.severity,
+                    summary: m.summary,
+                    dedup_key: m.dedup_key,
+                    block_height: Some(m.block_height),
+                    tx_hash: m.tx_hash,
+                    explorer_link: m.explorer_link,
+                })?;
+                rows += 1;
+            }
+        }
+        Ok(rows)
+    }
+
+    /// Run every action of a past transaction through the subscriptions of
+    /// its network and report which would have alerted, and with what, or
+    /// why not. The transaction's blocks are looked up over the network's
+    /// `rpc_url`, which finds transactions by hash and signer, and its
+    /// actions read from `history_url`. Nothing is sent.
+    pub async fn explain_transaction(
+        &self,
+        network_name: &str,
+        tx_hash: &str,
+        signer_id: &str,
+    ) -> Result<Vec<ExplainedAction>, anyhow::Error> {
+        let network = self
+            .networks
+            .iter()
+            .find(|(name, _)| name == network_name)
+            .map(|(_, network)| network)
+            .ok_or_else(|| anyhow::anyhow!("No subscription watches network '{}'", network_name))?;
+        let rpc_url = network
+            .rpc_url
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("Network '{}' has no rpc_url", network_name))?;
+        let history = Self::history_client(network_name, network)?;
+
+        let heights =
+            rpc::transaction_block_heights(&reqwest::Client::new(), rpc_url, tx_hash, signer_id)
+                .await?;
+        let mut actions = Vec::new();
+        for height in heights {
+            let block = history.block_actions(height).await?.unwrap_or_default();
+            actions.extend(
+                block
+                    .into_iter()
+                    .filter(|a| a.tx_hash.as_deref() == Some(tx_hash)),
+            );
+        }
+        if actions.is_empty() {
+            anyhow::bail!("neardata has no actions of transaction {}", tx_hash);
+        }
+
+        let subscriptions: Vec<&EventSubscription> = self
+            .config
+            .subscriptions
+            .iter()
+            .filter(|s| s.network == network_name)
+            .collect();
+        let mut explained = Vec::new();
+        for action in &actions {
+            let method_name = match &action.action {
+                ActionType::FunctionCall(fc) => Some(fc.method_name.as_str()),
+                _ => None,
+            };
+            let checked: Vec<(&EventSubscription, Option<MatchVerdict>)> = subscriptions
+                .iter()
+                .map(|sub| (*sub, self.first_failed_check(action, sub)))
+                .collect();
+            let passed = checked
+                .iter()
+                .filter(|(_, failed)| failed.is_none())
+                .map(|(sub, _)| *sub);
+            let alerting = self.matching_subscriptions(action, passed);
+
+            let mut verdicts = Vec::new();
+            for (sub, failed) in checked {
+                let verdict = match failed {
+                    Some(verdict) => verdict,
+                    None if alerting.iter().any(|a| a.name == sub.name) => {
+                        let mut rendered = sub.for_method(method_name);
+                        self.prefetch_token_metadata(action, &rendered).await;
+                        let (summary, dedup_key) = self.render_match(action, &mut rendered);
+                        MatchVerdict::Matched {
+                            severity: rendered.severity.clone(),
+                            summary,
+                            dedup_key,
+                        }
+                    }
+                    None => MatchVerdict::Deduplicated {
+                        alerted_by: alerting
+                            .first()
+                            .map_or_else(|| "another replica".to_string(), |a| a.name.clone()),
+                    },
+                };
+                verdicts.push(SubscriptionVerdict {
+                    subscription: sub.name.clone(),
+                    verdict,
+                });
+            }
+            explained.push(ExplainedAction {
+                block_height: action.block_height,
+                receipt_id: action.receipt_id.clone(),
+                account_id: action.account_id.clone(),
+                predecessor_id: action.predecessor_id.clone(),
+                action_kind: action.action.kind().to_string(),
+                method_name: method_name.map(str::to_string),
+                status: action.status.clone(),
+                verdicts,
+            });
+        }
+        Ok(explained)
+    }
+
+    /// How a match passed each of the subscription's checks and how its alert
+    /// was rendered, for `match_trace`. `subscription` has the method's
+    /// overrides applied.
+    fn match_trace(
+        action: &NeardataAction,
+        subscription: &EventSubscription,
+        summary: &str,
+        dedup_key: Option<&str>,
+    ) -> serde_json::Value {
+        let method_name = match &action.action {
+            ActionType::FunctionCall(fc) => Some(fc.method_name.as_str()),
+            _ => None,
+        };
+        let fields = action_fields(action);
+        let post_filter: Vec<serde_json::Value> = subscription
+            .post_filter
+            .iter()
+            .map(|condition| {
+                let value = match condition {
+                    PostFilter::Field(c) => filter::lookup(&fields, &c.field).cloned(),
+                    PostFilter::Cel { .. } => None,
+                };
+                serde_json::json!({
+                    "condition": condition,
+                    "value": value,
+                    "matched": condition.matches(&fields),
+                })
+            })
+            .collect();
+        let severity_rule = subscription
+            .severity_rules
+            .iter()
+            .find(|rule| rule.when.evaluate(&fields).unwrap_or(false))
+            .map(|rule| rule.when.to_string());
+        serde_json::json!({
+            "account_id": {"pattern": subscription.account_id, "value": action.account_id},
+            "action_kind": {
+                "allowed": if subscription.action_kinds.is_empty() { vec!["any".to_string()] } else { subscription.action_kinds.clone() },
+                "value": action.action.kind(),
+            },
+            "method_name": {"required": subscription.method_name, "value": method_name},
+            "post_filter": post_filter,
+            "method_override": method_name.filter(|m| subscription.method_overrides.contains_key(*m)),
+            "severity_rule": severity_rule,
+            "script": subscription.script.is_some(),
+            "severity": subscription.severity,
+            "summary_template": subscription.summary_template,
+            "summary": summary,
+            "dedup_key_template": subscription.dedup_key_template,
+            "correlation_key_template": subscription.correlation_key_template,
+            "dedup_key": dedup_key,
+        })
+    }
+
+    /// The first check of [`Self::action_matches_subscription`] (plus the
+    /// account and status checks the stream does first) an action fails
+    fn first_failed_check(
+        &self,
+        action: &NeardataAction,
+        subscription: &EventSubscription,
+    ) -> Option<MatchVerdict> {
+        if !subscription.matches_account(&action.account_id) {
+            return Some(MatchVerdict::OtherAccount);
+        }
+        if action.status != "SUCCESS" {
+            return Some(MatchVerdict::Unsuccessful);
+        }
+        let method_name = match &action.action {
+            ActionType::FunctionCall(fc) => Some(fc.method_name.as_str()),
+            _ => None,
+        };
+        if !subscription.matches_kind(action.action.kind(), method_name) {
+            return Some(MatchVerdict::KindOrMethod);
+        }
+        let fields = action_fields(action);
+        if let Some(condition) = subscription
+            .post_filter
+            .iter()
+            .find(|c| !c.matches(&fields))
+        {
+            return Some(MatchVerdict::PostFilter {
+                condition: serde_json::to_string(condition).unwrap_or_default(),
+            });
+        }
+        if !self.script_should_alert(&fields, subscription) {
+            return Some(MatchVerdict::Script);
+        }
+        None
+    }
+
+    fn history_client(
+        name: &str,
+        network: &NetworkConfig,
+    ) -> Result<NeardataHistoryClient, anyhow::Error> {
+        let url = network
+            .history_url
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("Network '{}' has no history_url", name))?;
+        Ok(NeardataHistoryClient::new(url))
+    }
+
+    /// Monitor a network's connected neardata WebSocket stream
+    async fn monitor_stream(
+        &self,
+        mut ws_stream: WsStream,
+        network_name: &str,
+        network: &NetworkConfig,
+        subscriptions: &[&EventSubscription],
+    ) -> Result<(), anyhow::Error> {
+        let index = SubscriptionIndex::new(subscriptions);
+
+        // Neardata filter format. It can't express account patterns, so with
+        // any pattern subscription every successful action is requested and
+        // matched locally.
+        let filter_entries: Vec<serde_json::Value> = if index.patterns.is_empty() {
+            index
+                .exact
+                .keys()
+                .map(|id| serde_json::json!({"accountId": id, "status": "SUCCESS"}))
+                .collect()
+        } else {
+            vec![serde_json::json!({"status": "SUCCESS"})]
+        };
+        let filter = serde_json::json!({
+            "secret": "tmp",
+            "filter": filter_entries,
+            "fetch_past_actions": 0
+        });
+
+        let filter_json = serde_json::to_string(&filter)?;
+        ws_stream.send(Message::Text(filter_json.clone())).await?;
+        log::info!("Connected and filter sent: {}", filter_json);
+        self.record_connected(network_name);
+
+        // Some streams go silent without closing the socket; a ping left
+        // unanswered for another idle period means the connection is dead
+        let idle_timeout = (self.config.stream_idle_timeout_secs > 0)
+            .then(|| Duration::from_secs(self.config.stream_idle_timeout_secs));
+        let mut pinged = false;
+        let mut received_frame = false;
+        loop {
+            let next = match idle_timeout {
+                Some(idle_timeout) => {
+                    match tokio::time::timeout(idle_timeout, ws_stream.next()).await {
+                        Ok(next) => next,
+                        Err(_) if !pinged => {
+                            log::debug!(
+                                "neardata ({}) idle for {:?}, pinging",
+                                network_name,
+                                idle_timeout
+                            );
+                            ws_stream.send(Message::Ping(Vec::new())).await?;
+                            pinged = true;
+                            continue;
+                        }
+                        Err(_) => {
+                            self.record_idle_stream(network_name);
+                            anyhow::bail!(
+                                "no message for {:?} after a ping; the stream is stale",
+                                idle_timeout * 2
+                            );
+                        }
+                    }
+                }
+                None => ws_stream.next().await,
+            };
+            let Some(msg) = next else {
+                break;
+            };
+            pinged = false;
+            let msg = msg?;
+            if matches!(msg, Message::Text(_) | Message::Binary(_))
+                && !std::mem::replace(&mut received_frame, true)
+            {
+                self.record_active_stream(network_name);
+            }
+            match msg {
+                Message::Text(text) => {
+                    self.handle_frame(&text, &index, network_name, network)
+                        .await;
+                }
+                Message::Binary(data) => match std::str::from_utf8(&data) {
+                    Ok(text) => self.handle_frame(text, &index, network_name, network).await,
+                    Err(e) => {
+                        self.stats.record_parse_error();
+                        log::warn!(
+                            "Ignoring non-UTF-8 binary frame ({} bytes): {}",
+                            data.len(),
+                            e
+                        );
+                    }
+                },
+                Message::Ping(data) => {
+                    ws_stream.send(Message::Pong(data)).await?;
+                }
+                Message::Close(_) => {
+                    log::warn!("WebSocket closed");
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Mark a network's source as connected, starting its subscriptions and
+    /// telling systemd once all are
+    fn record_connected(&self, network_name: &str) {
+        self.stats.record_connected(network_name);
+        self.start_subscriptions();
+        if self.stats.all_connected() && !self.ready_notified.swap(true, Ordering::Relaxed) {
+            systemd::notify("READY=1\nSTATUS=All neardata streams connected");
+        }
+    }
+
+    /// Process every action in a frame; frames that fail to parse are counted
+    /// and skipped rather than dropping the connection
+    async fn handle_frame(
+        &self,
+        text: &str,
+        index: &SubscriptionIndex<'_>,
+        network_name: &str,
+        network: &NetworkConfig,
+    ) {
+        // Drop non-matching actions while parsing so huge frames stay cheap
+        let matches_any = |header: &ActionHeader| {
+            index
+                .for_account(&header.account_id)
+                .any(|s| s.matches_kind(&header.kind, header.method_name.as_deref()))
+        };
+        let frame = match NeardataFrame::parse(text, matches_any) {
+            Ok(frame) => frame,
+            Err(e) => {
+                self.stats.record_parse_error();
+                log::warn!("Failed to parse neardata message: {:?}", e);
+                log::debug!("Raw message: {}", text);
+                return;
+            }
+        };
+        if frame.decoded_actions == 0 {
+            log::debug!("neardata keep-alive: {:?}", frame.notes);
+        }
+        self.check_event_lag(network_name, &frame.actions);
+        self.process_actions(&frame.actions, index, network_name, network)
+            .await;
+    }
+
+    /// Alert on actions from a network's stream that match its subscriptions,
+    /// skipping any already processed
+    async fn process_actions(
+        &self,
+        actions: &[NeardataAction],
+        index: &SubscriptionIndex<'_>,
+        network_name: &str,
+        network: &NetworkConfig,
+    ) {
+        for action in actions {
+            if self.seen_actions.contains(action) {
+                log::debug!(
+                    "Skipping re-delivered action {} of receipt {} in block {}",
+                    action.action_index,
+                    action.receipt_id.as_deref().unwrap_or("unknown"),
+                    action.block_height
+                );
+                continue;
+            }
+            let mut failed = false;
+            let completed = self
+                .isolate_panics(network_name, action, async {
+                    let candidates = index
+                        .for_account(&action.account_id)
+                        .filter(|s| self.is_running(s));
+                    for sub in self.matching_subscriptions(action, candidates) {
+                        if let Err(e) = self.process_action(action, sub, network, true).await {
+                            log::error!("Error processing action: {:?}", e);
+                            failed = true;
+                        }
+                    }
+                })
+                .await;
+            // Failed actions stay unseen so a replay can retry them
+            if completed && !failed {
+                self.seen_actions.insert(action);
+            }
+        }
+    }
+
+    /// Run the processing of one action, catching a panic (say, a template or
+    /// script choking on a pathological payload) so the event is logged and
+    /// skipped instead of restarting its network's stream. Returns whether it
+    /// completed.
+    async fn isolate_panics(
+        &self,
+        network_name: &str,
+        action: &NeardataAction,
+        process: impl std::future::Future<Output = ()>,
+    ) -> bool {
+        let Err(panic) = std::panic::AssertUnwindSafe(process).catch_unwind().await else {
+            return true;
+        };
+        self.stats.record_event_panic();
+        log::error!(
+            "Processing {} action on {} in block {} (receipt {}) panicked, skipping it: {}",
+            network_name,
+            action.account_id,
+            action.block_height,
+            action.receipt_id.as_deref().unwrap_or("unknown"),
+            supervisor::panic_message(panic.as_ref())
+        );
+        log::error!(
+            "Offending event: {}",
+            serde_json::to_string(action).unwrap_or_else(|e| format!("(unserializable: {})", e))
+        );
+        false
+    }
+
+    /// A block timestamp to measure ages from: clamped to now when it is at
+    /// most `max_clock_skew_secs` ahead of the local clock, and `None` when
+    /// it is further ahead, since one of the clocks is then wrong
+    fn checked_block_timestamp(&self, block_ms: f64) -> Option<f64> {
+        let now_ms = Utc::now().timestamp_millis() as f64;
+        let ahead_secs = (block_ms - now_ms) / 1000.0;
+        let skewed = ahead_secs > self.config.max_clock_skew_secs as f64;
+        if self.clock_skewed.swap(skewed, Ordering::Relaxed) != skewed {
+            if skewed {
+                log::warn!(
+                    "Block timestamps are {:.0}s ahead of the local clock (limit {}s); is NTP working? Ignoring them for lag and latency",
+                    ahead_secs,
+                    self.config.max_clock_skew_secs
+                );
+            } else {
+                log::info!("Block timestamps agree with the local clock again");
+            }
+        }
+        (!skewed).then_some(block_ms.min(now_ms))
+    }
+
+    /// Record how far a network's events trail the chain, paging the meta
+    /// routing key while that exceeds `max_event_lag_secs`
+    fn check_event_lag(&self, network_name: &str, actions: &[NeardataAction]) {
+        let Some(newest_ms) = actions
+            .iter()
+            .filter_map(|a| a.block_timestamp_ms)
+            .reduce(f64::max)
+            .and_then(|ms| self.checked_block_timestamp(ms))
+        else {
+            return;
+        };
+        let lag_secs = ((Utc::now().timestamp_millis() as f64 - newest_ms) / 1000.0).max(0.0);
+        self.stats.record_event_lag(network_name, lag_secs);
+
+        let Some(max_lag_secs) = self.config.max_event_lag_secs else {
+            return;
+        };
+        let lagging = lag_secs > max_lag_secs as f64;
+        let changed = {
+            let mut networks = self.lagging_networks.lock().unwrap();
+            if lagging {
+                networks.insert(network_name.to_string())
+            } else {
+                networks.remove(network_name)
+            }
+        };
+        if !changed {
+            return;
+        }
+
+        let dedup_key = format!("near-pagerduty-monitor-event-lag-{}", network_name);
+        let summary = format!(
+            "neardata ({}) events are {:.0}s behind the chain (limit {}s)",
+            network_name, lag_secs, max_lag_secs
+        );
+        let details = serde_json::json!({
+            "network": network_name,
+            "event_lag_secs": lag_secs,
+            "max_event_lag_secs": max_lag_secs,
+        });
+        if lagging {
+            log::warn!("{}", summary);
+        } else {
+            log::info!("neardata ({}) caught up with the chain", network_name);
+        }
+        let meta_client = Arc::clone(&self.meta_client);
+        tokio::spawn(async move {
+            let result = if lagging {
+                meta_client
+                    .trigger(
+                        &summary,
+                        "near-pagerduty-monitor",
+                        "error",
+                        Some(dedup_key),
+                        Some(details),
+                        &[],
+                    )
+                    .await
+            } else {
+                meta_client.resolve(&dedup_key).await
+            };
+            if let Err(e) = result {
+                log::error!("Failed to send event lag alert: {:?}", e);
+            }
+        });
+    }
+
+    /// Page the meta routing key once a network has failed to reconnect
+    /// `self_alerts.failed_reconnects` times in a row
+    fn record_failed_reconnect(&self, network_name: &str, failed_in_a_row: u32, error: &str) {
+        if failed_in_a_row != self.config.self_alerts.failed_reconnects {
+            return;
+        }
+        let summary = format!(
+            "NEAR PagerDuty Monitor can't reach neardata ({}): {} failed reconnects in a row",
+            network_name, failed_in_a_row
+        );
+        log::error!("{}", summary);
+        let details = serde_json::json!({
+            "network": network_name,
+            "failed_reconnects": failed_in_a_row,
+            "last_error": error,
+        });
+        let dedup_key = format!("near-pagerduty-monitor-reconnect-failures-{}", network_name);
+        let meta_client = Arc::clone(&self.meta_client);
+        tokio::spawn(async move {
+            if let Err(e) = meta_client
+                .trigger(
+                    &summary,
+                    "near-pagerduty-monitor",
+                    "critical",
+                    Some(dedup_key),
+                    Some(details),
+                    &[],
+                )
+                .await
+            {
+                log::error!("Failed to send reconnect failure alert: {:?}", e);
+            }
+        });
+    }
+
+    /// Page the meta routing key while a network is held back by an
+    /// `upstream` network that hasn't connected, or resolve the page once it has
+    fn record_upstream_wait(&self, network_name: &str, upstream: &str, waiting: bool) {
+        let dedup_key = format!("near-pagerduty-monitor-upstream-wait-{}", network_name);
+        let summary = format!(
+            "NEAR PagerDuty Monitor is holding back {} subscriptions: {} has not connected in {}s",
+            network_name, upstream, self.config.self_alerts.upstream_wait_secs
+        );
+        if waiting {
+            log::error!("{}", summary);
+        } else {
+            log::info!("Upstream networks of {} connected", network_name);
+        }
+        let details = serde_json::json!({
+            "network": network_name,
+            "upstream_network": upstream,
+        });
+        let meta_client = Arc::clone(&self.meta_client);
+        tokio::spawn(async move {
+            let result = if waiting {
+                meta_client
+                    .trigger(
+                        &summary,
+                        "near-pagerduty-monitor",
+                        "critical",
+                        Some(dedup_key),
+                        Some(details),
+                        &[],
+                    )
+                    .await
+            } else {
+                meta_client.resolve(&dedup_key).await
+            };
+            if let Err(e) = result {
+                log::error!("Failed to send upstream wait alert: {:?}", e);
+            }
+        });
+    }
+
+    /// Resolve the reconnect failure page once a network that paged connects again
+    fn record_reconnected(&self, network_name: &str, failed_in_a_row: u32) {
+        let threshold = self.config.self_alerts.failed_reconnects;
+        if threshold == 0 || failed_in_a_row < threshold {
+            return;
+        }
+        log::info!(
+            "Reconnected to neardata ({}) after {} failed attempts",
+            network_name,
+            failed_in_a_row
+        );
+        let dedup_key = format!("near-pagerduty-monitor-reconnect-failures-{}", network_name);
+        let meta_client = Arc::clone(&self.meta_client);
+        tokio::spawn(async move {
+            if let Err(e) = meta_client.resolve(&dedup_key).await {
+                log::error!("Failed to resolve reconnect failure alert: {:?}", e);
+            }
+        });
+    }
+
+    /// Count a connection that went idle, paging the meta routing key once
+    /// `max_idle_reconnects` in a row did
+    fn record_idle_stream(&self, network_name: &str) {
+        let idle_in_a_row = {
+            let mut idle_streams = self.idle_streams.lock().unwrap();
+            let count = idle_streams.entry(network_name.to_string()).or_default();
+            *count += 1;
+            *count
+        };
+        log::warn!(
+            "neardata ({}) went idle, {} connection(s) in a row",
+            network_name,
+            idle_in_a_row
+        );
+        if idle_in_a_row != self.config.max_idle_reconnects.max(1) {
+            return;
+        }
+        let summary = format!(
+            "neardata ({}) stream went silent {} times in a row without closing",
+            network_name, idle_in_a_row
+        );
+        let details = serde_json::json!({
+            "network": network_name,
+            "idle_reconnects": idle_in_a_row,
+            "stream_idle_timeout_secs": self.config.stream_idle_timeout_secs,
+        });
+        let dedup_key = format!("near-pagerduty-monitor-stream-idle-{}", network_name);
+        let meta_client = Arc::clone(&self.meta_client);
+        tokio::spawn(async move {
+            if let Err(e) = meta_client
+                .trigger(
+                    &summary,
+                    "near-pagerduty-monitor",
+                    "error",
+                    Some(dedup_key),
+                    Some(details),
+                    &[],
+                )
+                .await
+            {
+                log::error!("Failed to send stream idle alert: {:?}", e);
+            }
+        });
+    }
+
+    /// Reset the idle count once a connection delivers a frame, resolving
+    /// the idle alert if one was sent
+    fn record_active_stream(&self, network_name: &str) {
+        let Some(idle_in_a_row) = self.idle_streams.lock().unwrap().remove(network_name) else {
+            return;
+        };
+        if idle_in_a_row < self.config.max_idle_reconnects.max(1) {
+            return;
+        }
+        log::info!("neardata ({}) is delivering frames again", network_name);
+        let dedup_key = format!("near-pagerduty-monitor-stream-idle-{}", network_name);
+        let meta_client = Arc::clone(&self.meta_client);
+        tokio::spawn(async move {
+            if let Err(e) = meta_client.resolve(&dedup_key).await {
+                log::error!("Failed to resolve stream idle alert: {:?}", e);
+            }
+        });
+    }
+
+    /// The `candidates` that match `action`; with `dedup_across_subscriptions`
+    /// only the most severe of them, the first listed on a tie
+    fn matching_subscriptions<'s>(
+        &self,
+        action: &NeardataAction,
+        candidates: impl IntoIterator<Item = &'s EventSubscription>,
+    ) -> Vec<&'s EventSubscription> {
+        // Deduplicating across subscriptions needs every match, owned or not
+        let dedup = self.config.dedup_across_subscriptions;
+        let matched: Vec<&EventSubscription> = candidates
+            .into_iter()
+            .filter(|sub| dedup || self.owns_subscription(sub))
+            .filter(|sub| self.action_matches_subscription(action, sub))
+            .collect();
+        if !dedup {
+            return matched;
+        }
+        if matched.len() < 2 {
+            return matched
+                .into_iter()
+                .filter(|sub| self.owns_subscription(sub))
+                .collect();
+        }
+        let method_name = match &action.action {
+            ActionType::FunctionCall(fc) => Some(fc.method_name.as_str()),
+            _ => None,
+        };
+        let Some(winner) = matched.iter().copied().min_by_key(|sub| {
+            std::cmp::Reverse(dispatch::severity_rank(
+                &sub.for_method(method_name).severity,
+            ))
+        }) else {
+            return matched;
+        };
+        log::debug!(
+            "Receipt {:?} matched {} subscriptions; alerting only for '{}'",
+            action.receipt_id,
+            matched.len(),
+            winner.name
+        );
+        if !self.owns_subscription(winner) {
+            return Vec::new();
+        }
+        vec![winner]
+    }
+
+    /// Check if an action matches a subscription's filters
+    fn action_matches_subscription(
+        &self,
+        action: &NeardataAction,
+        subscription: &EventSubscription,
+    ) -> bool {
+        let method_name = match &action.action {
+            ActionType::FunctionCall(fc) => Some(fc.method_name.as_str()),
+            _ => None,
+        };
+        if !subscription.matches_kind(action.action.kind(), method_name) {
+            return false;
+        }
+        if subscription.post_filter.is_empty() && subscription.script.is_none() {
+            return true;
+        }
+        let fields = action_fields(action);
+        subscription.post_filter.iter().all(|c| c.matches(&fields))
+            && self.script_should_alert(&fields, subscription)
+    }
+
+    /// Run the subscription's `should_alert` script; a failing script lets the alert through
+    #[cfg(feature = "scripting")]
+    fn script_should_alert(
+        &self,
+        fields: &serde_json::Value,
+        subscription: &EventSubscription,
+    ) -> bool {
+        let Some(script) = self.scripts.get(&subscription.name) else {
+            return true;
+        };
+        script.should_alert(fields).unwrap_or_else(|e| {
+            log::error!(
+                "Script for '{}' failed, alerting anyway: {:#}",
+                subscription.name,
+                e
+            );
+            true
+        })
+    }
+
+    #[cfg(not(feature = "scripting"))]
+    fn script_should_alert(
+        &self,
+        _fields: &serde_json::Value,
+        _subscription: &EventSubscription,
+    ) -> bool {
+        true
+    }
+
+    /// Run the subscription's `render` script; a failing script leaves the templates in charge
+    #[cfg(feature = "scripting")]
+    fn script_render(
+        &self,
+        action: &NeardataAction,
+        subscription: &EventSubscription,
+    ) -> ScriptOutput {
+        let Some(script) = self.scripts.get(&subscription.name) else {
+            return ScriptOutput::default();
+        };
+        script.render(&action_fields(action)).unwrap_or_else(|e| {
+            log::error!(
+                "Script for '{}' failed, using templates: {:#}",
+                subscription.name,
+                e
+            );
+            ScriptOutput::default()
+        })
+    }
+
+    #[cfg(not(feature = "scripting"))]
+    fn script_render(
+        &self,
+        _action: &NeardataAction,
+        _subscription: &EventSubscription,
+    ) -> ScriptOutput {
+        ScriptOutput::default()
+    }
+
+    /// Render an alert for a matched action and hand it to the dispatcher
+    ///
+    /// Delivery latency is only measured for actions `from_stream`, since
+    /// backfilled and replayed ones are old by design.
+    async fn process_action(
+        &self,
+        action: &NeardataAction,
+        subscription: &EventSubscription,
+        network: &NetworkConfig,
+        from_stream: bool,
+    ) -> Result<(), anyhow::Error> {
+        #[cfg(feature = "redis")]
+        if !self.leading.load(Ordering::Relaxed) {
+            self.stand_by(action, subscription, network);
+            return Ok(());
+        }
+
+        let method_name = match &action.action {
+            ActionType::FunctionCall(fc) => Some(fc.method_name.as_str()),
+            _ => None,
+        };
+        let mut subscription = subscription.for_method(method_name);
+
+        log::info!(
+            "Action matched for '{}': account={}, method={:?}, from={:?}",
+            subscription.name,
+            action.account_id,
+            method_name,
+            action.predecessor_id
+        );
+
+        // Replays after a reconnect must not page twice, dedup key or not
+        let event_id = event_id(action, &subscription);
+        if !self.alerts.claim_event(&event_id) {
+            log::info!(
+                "Skipping already delivered event {} for '{}'",
+                event_id,
+                subscription.name
+            );
+            return Ok(());
+        }
+        #[cfg(feature = "redis")]
+        if let Some(shared) = &self.shared_state {
+            if !shared.claim_event(&event_id).await {
+                log::info!(
+                    "Skipping event {} for '{}', claimed by another replica",
+                    event_id,
+                    subscription.name
+                );
+                self.alerts.release_event(&event_id);
+                return Ok(());
+            }
+        }
+
+        self.prefetch_token_metadata(action, &subscription).await;
+        let (summary, dedup_key) = self.render_match(action, &mut subscription);
+        let (summary, dedup_key) = match &subscription.collapse_duplicate_summaries {
+            Some(collapse) => {
+                self.summary_bursts
+                    .collapse(&subscription.name, collapse, summary, dedup_key)
+            }
+            None => (summary, dedup_key),
+        };
+        // A raised severity re-triggers rather than adding a follow-up note
+        let mut escalated = false;
+        if let (Some(escalation), Some(key)) =
+            (subscription.severity_escalation.clone(), &dedup_key)
+        {
+            let repeats =
+                self.alerts
+                    .escalate(key, escalation.window_secs, std::time::Instant::now());
+            if let Some(severity) = escalation.severity(repeats) {
+                escalated = repeats > 0 && escalation.severity(repeats - 1) != Some(severity);
+                if escalated {
+                    log::info!(
+                        "'{}' fired {} times in a row for {}, escalating to {}",
+                        subscription.name,
+                        repeats + 1,
+                        key,
+                        severity
+                    );
+                }
+                subscription.to_mut().severity = severity.to_string();
+            }
+        }
+        let subscription = subscription.as_ref();
+
+        // Get explorer link
+        let explorer_link = Some(self.explorer_link(action, &subscription.network, network));
+        let runbook_url = self.config.runbook_url(subscription, method_name);
+
+        // Create custom details
+        let custom_details = match &subscription.custom_details_template {
+            Some(template) => self.render_custom_details(template, action, subscription, &event_id),
+            None => serde_json::json!({
+                "subscription_name": subscription.name,
+                "network": subscription.network,
+                "account_id": action.account_id,
+                "method_name": method_name,
+                "predecessor_id": action.predecessor_id,
+                "signer_id": action.signer_id,
+                "block_height": action.block_height,
+                "tx_hash": action.tx_hash,
+                "receipt_id": action.receipt_id,
+                "event_id": event_id,
+                "action": action.action,
+            }),
+        };
+        let custom_details = match &runbook_url {
+            Some(url) => {
+                let mut details = custom_details;
+                details["runbook_url"] = url.clone().into();
+                details
+            }
+            None => custom_details,
+        };
+        let custom_details = self.with_deployment_tags(custom_details);
+        let mut custom_details = Self::with_ownership(subscription, custom_details);
+        if subscription.match_trace {
+            let trace = Self::match_trace(action, subscription, &summary, dedup_key.as_deref());
+            log::info!("Match trace for '{}': {}", subscription.name, trace);
+            if let Some(map) = custom_details.as_object_mut() {
+                map.insert("match_trace".to_string(), trace);
+            }
+        }
+
+        let in_maintenance = self.in_maintenance.load(Ordering::Relaxed);
+        let now = Utc::now();
+        let muted_until = self.alerts.muted_until(&subscription.name, now);
+        let shadow_started = self
+            .alerts
+            .shadow_started(&subscription.name)
+            .unwrap_or(now);
+        let shadowed = subscription.is_shadowed(shadow_started, now);
+        #[cfg(feature = "sqlite")]
+        if let Some(store) = &self.event_store {
+            let outcome = match (
+                in_maintenance,
+                self.config.maintenance_windows.as_ref().map(|m| m.during),
+            ) {
+                _ if muted_until.is_some() => EventOutcome::Suppressed,
+                _ if shadowed => EventOutcome::Shadowed,
+                (false, _) => EventOutcome::Queued,
+                (true, Some(MaintenanceAction::ChangeEvent)) => EventOutcome::ChangeEvent,
+                (true, _) => EventOutcome::Suppressed,
+            };
+            let stored = StoredEvent {
+                time: Utc::now(),
+                event_id: event_id.clone(),
+                subscription: subscription.name.clone(),
+                network: subscription.network.clone(),
+                account_id: action.account_id.clone(),
+                method_name: method_name.map(str::to_string),
+                predecessor_id: action.predecessor_id.clone(),
+                block_height: action.block_height,
+                tx_hash: action.tx_hash.clone(),
+                severity: subscription.severity.clone(),
+                summary: summary.clone(),
+                dedup_key: dedup_key.clone(),
+                action: serde_json::to_value(&action.action).unwrap_or_default(),
+                raw: serde_json::to_value(action).ok(),
+                outcome,
+            };
+            if let Err(e) = store.record(&stored) {
+                log::warn!(
+                    "Could not record event {} in the event store: {:#}",
+                    event_id,
+                    e
+                );
+            }
+        }
+
+        if let Some(until) = muted_until {
+            log::info!(
+                "Suppressed '{}', muted until {}: {}",
+                subscription.name,
+                until.to_rfc3339(),
+                summary
+            );
+            self.alerts.mark_event_sent(&event_id);
+            return Ok(());
+        }
+        if in_maintenance && !shadowed {
+            let source = self.alert_source(action, subscription);
+            return self
+                .hold_for_maintenance(&event_id, subscription, &summary, &source, custom_details)
+                .await;
+        }
+
+        // PagerDuty already has an incident for a delivered, still open alert
+        let follow_up = dedup_key.as_ref().filter(|_| !shadowed).is_some_and(|key| {
+            let state = self.alerts.record_match(
+                key,
+                &subscription.name,
+                &subscription.severity,
+                &summary,
+                subscription.alert_ttl_secs,
+            );
+            self.config.follow_up_notes
+                && !escalated
+                && matches!(state, AlertState::Triggered | AlertState::Acknowledged)
+        });
+
+        self.stats.record_match(MatchedEvent {
+            time: Utc::now(),
+            subscription: subscription.name.clone(),
+            severity: subscription.severity.clone(),
+            summary: summary.clone(),
+            dedup_key: dedup_key.clone(),
+            tx_hash: action.tx_hash.clone(),
+            block_height: action.block_height,
+            delivered: None,
+        });
+        self.stats.record_recent_event(
+            &subscription.name,
+            RecentEvent {
+                time: Utc::now(),
+                event_id: event_id.clone(),
+                severity: subscription.severity.clone(),
+                summary: summary.clone(),
+                dedup_key: dedup_key.clone(),
+                action: action.clone(),
+            },
+        );
+
+        let source = self.alert_source(action, subscription);
+        self.report_tally.lock().unwrap().record(
+            &subscription.name,
+            &subscription.severity,
+            &source,
+        );
+        let on_call = self.on_call.lock().unwrap().clone();
+        if self.alert_tx.receiver_count() > 0 {
+            // A listener disconnecting mid-send is not an error
+            let _ = self.alert_tx.send(RenderedAlert {
+                timestamp: Utc::now().to_rfc3339(),
+                subscription: subscription.name.clone(),
+                severity: subscription.severity.clone(),
+                summary: summary.clone(),
+                source: source.clone(),
+                dedup_key: dedup_key.clone(),
+                explorer_link: explorer_link.as_ref().map(|(href, _)| href.clone()),
+                custom_details: custom_details.clone(),
+                on_call: on_call.clone(),
+            });
+        }
+        if shadowed {
+            log::info!(
+                "Withheld alert for shadowed '{}': {}",
+                subscription.name,
+                summary
+            );
+            self.alerts.mark_event_sent(&event_id);
+            return Ok(());
+        }
+        if !self.sinks.is_empty() {
+            self.publish_to_sinks(SinkEvent {
+                kind: SinkEventKind::Trigger,
+                alert_key: dedup_key.clone().unwrap_or_else(|| event_id.clone()),
+                subscription: subscription.name.clone(),
+                severity: subscription.severity.clone(),
+                summary: summary.clone(),
+                source: Some(source.clone()),
+                explorer_link: explorer_link.as_ref().map(|(href, _)| href.clone()),
+                custom_details: custom_details.clone(),
+                on_call,
+                timestamp: Utc::now(),
+            });
+        }
+
+        self.dispatcher
+            .enqueue(PendingAlert {
+                event_id,
+                alert_ttl_secs: subscription.alert_ttl_secs,
+                subscription: subscription.name.clone(),
+                severity: subscription.severity.clone(),
+                summary,
+                source,
+                dedup_key,
+                custom_details,
+                explorer_link,
+                runbook_url,
+                priority: subscription.priority.clone(),
+                escalation_policy: subscription.escalation_policy.clone(),
+                response_play: subscription.response_play.clone(),
+                coalesced_event_ids: Vec::new(),
+                follow_up,
+                block_timestamp_ms: action
+                    .block_timestamp_ms
+                    .filter(|_| from_stream)
+                    .and_then(|ms| self.checked_block_timestamp(ms)),
+            })
+            .await;
+
+        Ok(())
+    }
+
+    /// PagerDuty `source`, e.g. `near:venear.near` or `near-testnet:venear.testnet@staging`
+    fn alert_source(&self, action: &NeardataAction, subscription: &EventSubscription) -> String {
+        let mut source = if subscription.network == "mainnet" {
+            format!("near:{}", action.account_id)
+        } else {
+            format!("near-{}:{}", subscription.network, action.account_id)
+        };
+        if let Some(environment) = &self.config.environment {
+            source.push('@');
+            source.push_str(environment);
+        }
+        source
+    }
+
+    /// Add the configured environment and tags to custom_details
+    fn with_deployment_tags(&self, mut custom_details: serde_json::Value) -> serde_json::Value {
+        if let Some(map) = custom_details.as_object_mut() {
+            if let Some(environment) = &self.config.environment {
+                map.insert("environment".to_string(), environment.clone().into());
+            }
+            if !self.config.tags.is_empty() {
+                map.insert("tags".to_string(), serde_json::json!(self.config.tags));
+            }
+        }
+        custom_details
+    }
+
+    /// Add the subscription's `owner`, `team` and `service` to custom_details,
+    /// where PagerDuty event rules and downstream sinks can route on them
+    fn with_ownership(
+        subscription: &EventSubscription,
+        mut custom_details: serde_json::Value,
+    ) -> serde_json::Value {
+        if let Some(map) = custom_details.as_object_mut() {
+            for (field, value) in [
+                ("owner", &subscription.owner),
+                ("team", &subscription.team),
+                ("service", &subscription.service),
+            ] {
+                if let Some(value) = value {
+                    map.insert(field.to_string(), value.clone().into());
+                }
+            }
+        }
+        custom_details
+    }
+
+    /// Fill a subscription's `custom_details_template` from the action's
+    /// fields, with summary filters such as `datetime` and `token_amount`
+    fn render_custom_details(
+        &self,
+        template: &serde_json::Value,
+        action: &NeardataAction,
+        subscription: &EventSubscription,
+        event_id: &str,
+    ) -> serde_json::Value {
+        let mut fields = action_fields(action);
+        fields["subscription_name"] = subscription.name.clone().into();
+        fields["network"] = subscription.network.clone().into();
+        fields["severity"] = subscription.severity.clone().into();
+        fields["event_id"] = event_id.into();
+        fields["block_timestamp"] = action.block_timestamp_ms.map(|ms| ms as i64).into();
+        let timezone = subscription
+            .timezone
+            .as_deref()
+            .and_then(|tz| template::parse_timezone(tz).ok())
+            .unwrap_or(chrono_tz::Tz::UTC);
+        let metadata = self
+            .rpc_url(subscription)
+            .and_then(|rpc_url| self.token_metadata.get(rpc_url, &action.account_id));
+        template::render_json(template, &fields, &|s: &str| {
+            let s = template::render_token_amounts(s, &fields, |amount| {
+                metadata.as_ref().map(|metadata| metadata.format(amount))
+            });
+            template::render_timestamps(&s, action.block_timestamp_ms, timezone)
+        })
+    }
+
+    /// The RPC URL of a subscription's network, if it has one
+    fn rpc_url(&self, subscription: &EventSubscription) -> Option<&str> {
+        self.networks
+            .iter()
+            .find(|(name, _)| *name == subscription.network)
+            .and_then(|(_, network)| network.rpc_url.as_deref())
+    }
+
+    /// Look up the metadata of the token an action was sent to, if the
+    /// summary or custom details render a `token_amount`
+    async fn prefetch_token_metadata(
+        &self,
+        action: &NeardataAction,
+        subscription: &EventSubscription,
+    ) {
+        let uses_amounts = subscription
+            .summary_template
+            .as_deref()
+            .is_some_and(template::uses_token_amounts)
+            || subscription
+                .custom_details_template
+                .as_ref()
+                .is_some_and(|t| template::uses_token_amounts(&t.to_string()));
+        if let (true, Some(rpc_url)) = (uses_amounts, self.rpc_url(subscription)) {
+            self.token_metadata
+                .prefetch(rpc_url, &action.account_id)
+                .await;
+        }
+    }
+
+    /// Apply severity rules and the subscription's script to a matched
+    /// action, returning its summary and dedup key
+    fn render_match(
+        &self,
+        action: &NeardataAction,
+        subscription: &mut Cow<'_, EventSubscription>,
+    ) -> (String, Option<String>) {
+        if !subscription.severity_rules.is_empty() {
+            let fields = action_fields(action);
+            let rule = subscription.severity_rules.iter().find(|rule| {
+                rule.when.evaluate(&fields).unwrap_or_else(|e| {
+                    log::debug!("Severity rule `{}` did not evaluate: {:#}", rule.when, e);
+                    false
+                })
+            });
+            if let Some(rule) = rule {
+                subscription.to_mut().severity = rule.severity.clone();
+            }
+        }
+
+        let rendered = self.script_render(action, subscription);
+        if let Some(severity) = rendered.severity {
+            subscription.to_mut().severity = severity;
+        }
+        let summary = rendered
+            .summary
+            .unwrap_or_else(|| self.format_summary(action, subscription));
+        let dedup_key = rendered
+            .dedup_key
+            .or_else(|| self.format_dedup_key(action, subscription));
+        (summary, dedup_key)
+    }
+
+    fn format_summary(&self, action: &NeardataAction, subscription: &EventSubscription) -> String {
+        if let Some(template) = &subscription.summary_template {
+            let method_name = match &action.action {
+                ActionType::FunctionCall(fc) => fc.method_name.clone(),
+                _ => "unknown".to_string(),
+            };
+
+            let timezone = subscription
+                .timezone
+                .as_deref()
+                .and_then(|tz| template::parse_timezone(tz).ok())
+                .unwrap_or(chrono_tz::Tz::UTC);
+
+            let template = if ["{args.", "{storage.", "{meta_tx.", "{events."]
+                .iter()
+                .any(|prefix| template.contains(prefix))
+            {
+                let fields = action_fields(action);
+                let metadata = self
+                    .rpc_url(subscription)
+                    .and_then(|rpc_url| self.token_metadata.get(rpc_url, &action.account_id));
+                let template = template::render_token_amounts(template, &fields, |amount| {
+                    metadata.as_ref().map(|metadata| metadata.format(amount))
+                });
+                template::render_summary_fields(&template, &fields)
+            } else {
+                template.clone()
+            };
+
+            template::render_timestamps(&template, action.block_timestamp_ms, timezone)
+                .replace("{account_id}", &action.account_id)
+                .replace("{method_name}", &method_name)
+                .replace(
+                    "{predecessor_id}",
+                    action.predecessor_id.as_deref().unwrap_or("unknown"),
+                )
+                .replace(
+                    "{signer_id}",
+                    action.signer_id.as_deref().unwrap_or("unknown"),
+                )
+                .replace("{block_height}", &action.block_height.to_string())
+                .replace("{tx_hash}", action.tx_hash.as_deref().unwrap_or("unknown"))
+        } else {
+            let method_name = match &action.action {
+                ActionType::FunctionCall(fc) => format!(" calling {}", fc.method_name),
+                _ => String::new(),
+            };
+            format!(
+                "{}: Action on {}{}",
+                subscription.name, action.account_id, method_name
+            )
+        }
+    }
+
+    fn format_dedup_key(
+        &self,
+        action: &NeardataAction,
+        subscription: &EventSubscription,
+    ) -> Option<String> {
+        let dedup_key = self.render_dedup_key(action, subscription)?;
+        if self.namespaced_subscriptions.contains(&subscription.name) {
+            return Some(format!("{}-{}", slug(&subscription.name), dedup_key));
+        }
+        Some(dedup_key)
+    }
+
+    fn render_dedup_key(
+        &self,
+        action: &NeardataAction,
+        subscription: &EventSubscription,
+    ) -> Option<String> {
+        let correlation_key = subscription
+            .correlation_key_template
+            .as_ref()
+            .and_then(|template| {
+                let rendered = template::render_fields(template, &action_fields(action));
+                if rendered.is_none() {
+                    log::debug!(
+                        "Correlation key for '{}' has a missing field; using the dedup key",
+                        subscription.name
+                    );
+                }
+                rendered
+            });
+        let template = correlation_key
+            .as_ref()
+            .or(subscription.dedup_key_template.as_ref());
+        if let Some(template) = template {
+            let method_name = match &action.action {
+                ActionType::FunctionCall(fc) => fc.method_name.clone(),
+                _ => "unknown".to_string(),
+            };
+
+            Some(
+                template
+                    .replace("{account_id}", &action.account_id)
+                    .replace("{method_name}", &method_name)
+                    .replace(
+                        "{predecessor_id}",
+                        action.predecessor_id.as_deref().unwrap_or("unknown"),
+                    )
+                    .replace(
+                        "{signer_id}",
+                        action.signer_id.as_deref().unwrap_or("unknown"),
+                    )
+                    .replace("{block_height}", &action.block_height.to_string())
+                    .replace("{tx_hash}", action.tx_hash.as_deref().unwrap_or("unknown"))
+                    .replace(
+                        "{receipt_id}",
+                        action.receipt_id.as_deref().unwrap_or("unknown"),
+                    ),
+            )
+        } else {
+            // Default to tx_hash or receipt_id
+            action.tx_hash.clone().or_else(|| action.receipt_id.clone())
+        }
+    }
+
+    /// Link and label for an action, from the provider registered for the
+    /// network, else the one its config describes
+    fn explorer_link(
+        &self,
+        action: &NeardataAction,
+        name: &str,
+        network: &NetworkConfig,
+    ) -> (String, String) {
+        match self.explorers.get(name) {
+            Some(explorer) => explorer.action_link(action),
+            None => network.explorer().action_link(action),
+        }
+    }
+}
+
+#[cfg(feature = "monitor")]
+type WsStream =
+    tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+/// A network's subscriptions, looked up by the account an action is on
+#[cfg(feature = "monitor")]
+struct SubscriptionIndex<'a> {
+    exact: HashMap<&'a str, Vec<&'a EventSubscription>>,
+    /// Subscriptions whose `account_id` contains `*`
+    patterns: Vec<&'a EventSubscription>,
+}
+
+#[cfg(feature = "monitor")]
+impl<'a> SubscriptionIndex<'a> {
+    fn new(subscriptions: &[&'a EventSubscription]) -> Self {
+        let mut index = Self {
+            exact: HashMap::new(),
+            patterns: Vec::new(),
+        };
+        for sub in subscriptions {
+            if sub.is_account_pattern() {
+                index.patterns.push(sub);
+            } else {
+                index
+                    .exact
+                    .entry(sub.account_id.as_str())
+                    .or_default()
+                    .push(sub);
+            }
+        }
+        index
+    }
+
+    fn for_account<'s>(
+        &'s self,
+        account_id: &'s str,
+    ) -> impl Iterator<Item = &'a EventSubscription> + 's {
+        let exact = self.exact.get(account_id).into_iter().flatten().copied();
+        let patterns = self
+            .patterns
+            .iter()
+            .copied()
+            .filter(move |s| s.matches_account(account_id));
+        exact.chain(patterns)
+    }
+}
+
+#[cfg(feature = "monitor")]
+/// Aborts a background task when the owning future is dropped
+struct AbortOnDrop(tokio::task::JoinHandle<()>);
+
+#[cfg(feature = "monitor")]
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+#[cfg(feature = "monitor")]
+/// Why the monitor gave up, carrying the process exit code for supervisors
+#[derive(Debug)]
+pub enum MonitorExit {
+    /// A network failed to reconnect `attempts` times in a row
+    ReconnectLimit { network: String, attempts: u32 },
+    /// neardata refused the connection in a way retrying won't fix
+    Fatal { network: String, error: String },
+}
+
+#[cfg(feature = "monitor")]
+impl MonitorExit {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            MonitorExit::ReconnectLimit { .. } => 3,
+            MonitorExit::Fatal { .. } => 4,
+        }
+    }
+}
+
+#[cfg(feature = "monitor")]
+impl std::fmt::Display for MonitorExit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MonitorExit::ReconnectLimit { network, attempts } => write!(
+                f,
+                "gave up on neardata ({}) after {} failed reconnect attempts",
+                network, attempts
+            ),
+            MonitorExit::Fatal { network, error } => {
+                write!(
+                    f,
+                    "neardata ({}) rejected the connection: {}",
+                    network, error
+                )
+            }
+        }
+    }
+}
+
+#[cfg(feature = "monitor")]
+impl std::error::Error for MonitorExit {}
+
+// =============================================================================
+// Example Configurations
+// =============================================================================
+
+/// Create config for monitoring veNEAR pause calls
+pub fn venear_pause_config(routing_key: &str, venear_contract: &str) -> PagerDutyAlertConfig {
+    example_config(
+        routing_key,
+        vec![EventSubscription {
+            summary_template: Some(
+                "CRITICAL: veNEAR contract paused by {predecessor_id}".to_string(),
+            ),
+            dedup_key_template: Some("venear-pause-{tx_hash}".to_string()),
+            ..example_subscription(
+                "veNEAR: Contract Paused",
+                venear_contract,
+                Some("pause"),
+                "critical",
+            )
+        }],
+    )
 }
 
-#[derive(Debug, Serialize)]
-struct PagerDutyPayload {
-    summary: String,
-    source: String,
-    severity: String,
-    timestamp: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    custom_details: Option<serde_json::Value>,
+/// Create config for monitoring any contract method calls
+pub fn method_call_config(
+    routing_key: &str,
+    contract_id: &str,
+    method_name: Option<&str>,
+) -> PagerDutyAlertConfig {
+    let name = format!(
+        "Contract Call: {}{}",
+        contract_id,
+        method_name.map(|m| format!("::{}", m)).unwrap_or_default()
+    );
+    example_config(
+        routing_key,
+        vec![EventSubscription {
+            summary_template: Some(format!(
+                "Call to {} - {{method_name}} from {{predecessor_id}}",
+                contract_id
+            )),
+            dedup_key_template: Some(format!("{}-{{tx_hash}}", contract_id)),
+            ..example_subscription(&name, contract_id, method_name, "warning")
+        }],
+    )
 }
 
-#[derive(Debug, Serialize)]
-struct PagerDutyLink {
-    href: String,
-    text: String,
+/// Create config for a project funded through Potlock: direct donations via
+/// `donate.potlock.near`, and donations, application status changes and
+/// matching-round payouts in each of `pot_ids`
+pub fn potlock_config(
+    routing_key: &str,
+    project_id: &str,
+    pot_ids: &[&str],
+) -> PagerDutyAlertConfig {
+    let for_project = |field: &str| {
+        PostFilter::Field(FieldCondition {
+            field: field.to_string(),
+            op: ConditionOp::Equals(project_id.into()),
+        })
+    };
+    let mut subscriptions = vec![EventSubscription {
+        summary_template: Some(format!(
+            "{{predecessor_id}} donated to {} via Potlock",
+            project_id
+        )),
+        dedup_key_template: Some("potlock-donation-{tx_hash}".to_string()),
+        post_filter: vec![for_project("args.recipient_id")],
+        ..example_subscription(
+            &format!("Potlock: Donation to {}", project_id),
+            "donate.potlock.near",
+            Some("donate"),
+            "info",
+        )
+    }];
+    for pot_id in pot_ids {
+        subscriptions.push(EventSubscription {
+            summary_template: Some(format!(
+                "{{predecessor_id}} donated to {} in pot {}",
+                project_id, pot_id
+            )),
+            dedup_key_template: Some("potlock-pot-donation-{account_id}-{tx_hash}".to_string()),
+            post_filter: vec![for_project("args.project_id")],
+            ..example_subscription(
+                &format!("Potlock: Pot Donation ({})", pot_id),
+                pot_id,
+                Some("donate"),
+                "info",
+            )
+        });
+        subscriptions.push(EventSubscription {
+            summary_template: Some(format!(
+                "Potlock pot {}: {{method_name}} by {{predecessor_id}}",
+                pot_id
+            )),
+            dedup_key_template: Some("potlock-round-{account_id}-{tx_hash}".to_string()),
+            post_filter: vec![PostFilter::Field(FieldCondition {
+                field: "method_name".to_string(),
+                op: ConditionOp::In(
+                    [
+                        "chef_set_application_status",
+                        "chef_set_payouts",
+                        "challenge_payouts",
+                        "admin_process_payouts",
+                    ]
+                    .map(serde_json::Value::from)
+                    .to_vec(),
+                ),
+            })],
+            method_overrides: BTreeMap::from([
+                (
+                    "chef_set_application_status".to_string(),
+                    MethodOverride {
+                        summary_template: Some(format!(
+                            "Potlock pot {}: application status changed by {{predecessor_id}}",
+                            pot_id
+                        )),
+                        ..MethodOverride::default()
+                    },
+                ),
+                (
+                    "challenge_payouts".to_string(),
+                    MethodOverride {
+                        severity: Some("error".to_string()),
+                        ..MethodOverride::default()
+                    },
+                ),
+            ]),
+            ..example_subscription(
+                &format!("Potlock: Matching Round ({})", pot_id),
+                pot_id,
+                None,
+                "warning",
+            )
+        });
+    }
+    example_config(routing_key, subscriptions)
 }
 
-#[derive(Debug, Deserialize)]
-pub struct PagerDutyResponse {
-    pub status: String,
-    pub message: String,
-    pub dedup_key: Option<String>,
+/// Create config that pages when one of `accounts` is liquidated or
+/// force-closed on Burrow. Liquidations reach `contract.main.burrow.near` as
+/// `oracle_on_call` from the price oracle, with the `Liquidate` or
+/// `ForceClose` action in the `msg` string. Health-factor changes are only
+/// logged, and neardata carries no receipt logs, so they can't be watched.
+pub fn burrow_liquidation_config(routing_key: &str, accounts: &[&str]) -> PagerDutyAlertConfig {
+    let accounts = accounts
+        .iter()
+        .map(|a| regex::escape(a))
+        .collect::<Vec<_>>()
+        .join("|");
+    let pattern = format!(
+        r#""(Liquidate|ForceClose)"\s*:\s*\{{\s*"account_id"\s*:\s*"({})""#,
+        accounts
+    );
+    example_config(
+        routing_key,
+        vec![EventSubscription {
+            summary_template: Some(
+                "CRITICAL: Burrow position liquidated by {signer_id}".to_string(),
+            ),
+            dedup_key_template: Some("burrow-liquidation-{tx_hash}".to_string()),
+            post_filter: vec![PostFilter::Field(FieldCondition {
+                field: "args.msg".to_string(),
+                op: ConditionOp::Regex(regex::Regex::new(&pattern).expect("escaped account IDs")),
+            })],
+            ..example_subscription(
+                "Burrow: Position Liquidated",
+                "contract.main.burrow.near",
+                Some("oracle_on_call"),
+                "critical",
+            )
+        }],
+    )
 }
 
-impl PagerDutyClient {
-    const EVENTS_URL: &'static str = "https://events.pagerduty.com/v2/enqueue";
+/// Create config for a meta-transaction relayer such as Meteor's or HOT's:
+/// changes to the relayer's access keys, whose count bounds how many
+/// transactions it can send before nonces collide, and deletion of the
+/// relayer account. neardata streams only successful actions and the monitor
+/// has no balance checks, so failed meta-transactions and balance drops are
+/// not covered.
+pub fn relayer_config(routing_key: &str, relayer_id: &str) -> PagerDutyAlertConfig {
+    example_config(
+        routing_key,
+        vec![
+            EventSubscription {
+                action_kinds: vec!["AddKey".to_string(), "DeleteKey".to_string()],
+                summary_template: Some(format!(
+                    "Relayer {}: access key change signed by {{signer_id}}",
+                    relayer_id
+                )),
+                dedup_key_template: Some("relayer-keys-{account_id}-{tx_hash}".to_string()),
+                ..example_subscription("Relayer: Access Keys Changed", relayer_id, None, "warning")
+            },
+            EventSubscription {
+                action_kinds: vec!["DeleteAccount".to_string()],
+                summary_template: Some(format!(
+                    "CRITICAL: Relayer account {} deleted by {{signer_id}}",
+                    relayer_id
+                )),
+                dedup_key_template: Some("relayer-deleted-{account_id}-{tx_hash}".to_string()),
+                ..example_subscription("Relayer: Account Deleted", relayer_id, None, "critical")
+            },
+        ],
+    )
+}
 
-    pub fn new(routing_key: String) -> Self {
-        Self {
-            client: reqwest::Client::new(),
-            routing_key,
+/// Create config for a chain-signatures MPC contract such as `v1.signer`:
+/// votes to kick a node (error), votes on participants joining or on
+/// contract updates (warning), and code deployments to the contract
+/// (critical). The sign request backlog is contract state rather than an
+/// action, so it is not covered.
+pub fn mpc_config(routing_key: &str, mpc_contract: &str) -> PagerDutyAlertConfig {
+    let method_in = |methods: &[&str]| {
+        PostFilter::Field(FieldCondition {
+            field: "method_name".to_string(),
+            op: ConditionOp::In(
+                methods
+                    .iter()
+                    .map(|m| serde_json::Value::from(*m))
+                    .collect(),
+            ),
+        })
+    };
+    example_config(
+        routing_key,
+        vec![
+            EventSubscription {
+                summary_template: Some(format!(
+                    "MPC {}: {{signer_id}} voted to kick a node",
+                    mpc_contract
+                )),
+                dedup_key_template: Some("mpc-kick-{account_id}-{tx_hash}".to_string()),
+                ..example_subscription(
+                    "MPC: Node Kick Vote",
+                    mpc_contract,
+                    Some("vote_kick"),
+                    "error",
+                )
+            },
+            EventSubscription {
+                summary_template: Some(format!(
+                    "MPC {}: {{method_name}} by {{signer_id}}",
+                    mpc_contract
+                )),
+                dedup_key_template: Some("mpc-governance-{account_id}-{tx_hash}".to_string()),
+                post_filter: vec![method_in(&[
+                    "vote_join",
+                    "vote_leave",
+                    "propose_update",
+                    "vote_update",
+                ])],
+                ..example_subscription(
+                    "MPC: Participants and Updates",
+                    mpc_contract,
+                    None,
+                    "warning",
+                )
+            },
+            EventSubscription {
+                action_kinds: vec!["DeployContract".to_string()],
+                summary_template: Some(format!(
+                    "CRITICAL: new code deployed to MPC contract {}",
+                    mpc_contract
+                )),
+                dedup_key_template: Some("mpc-deploy-{account_id}-{tx_hash}".to_string()),
+                ..example_subscription("MPC: Contract Deployed", mpc_contract, None, "critical")
+            },
+        ],
+    )
+}
+
+/// Create config that pages on `ft_transfer` and `ft_transfer_call` of at
+/// least `min_amount` (in the token's base units, so 500,000 USDC with 6
+/// decimals is `500_000_000_000`) on a NEP-141 token contract. Summaries
+/// read like "500,000 USDC moved from alice.near to bob.near", with the
+/// token's symbol and decimals looked up over the network's RPC. NFT
+/// transfers carry no amount, so only fungible tokens are covered.
+pub fn large_transfer_config(
+    routing_key: &str,
+    token_contract: &str,
+    min_amount: u128,
+) -> PagerDutyAlertConfig {
+    example_config(
+        routing_key,
+        vec![EventSubscription {
+            summary_template: Some(
+                "{args.amount | token_amount} moved from {predecessor_id} to {args.receiver_id}"
+                    .to_string(),
+            ),
+            dedup_key_template: Some("large-transfer-{account_id}-{tx_hash}".to_string()),
+            post_filter: vec![
+                PostFilter::Field(FieldCondition {
+                    field: "method_name".to_string(),
+                    op: ConditionOp::In(vec!["ft_transfer".into(), "ft_transfer_call".into()]),
+                }),
+                PostFilter::Field(FieldCondition {
+                    field: "args.amount".to_string(),
+                    op: ConditionOp::Gte(min_amount),
+                }),
+            ],
+            ..example_subscription(
+                &format!("Large Transfer: {}", token_contract),
+                token_contract,
+                None,
+                "warning",
+            )
+        }],
+    )
+}
+
+/// Create config for NEP-145 storage calls on NEP-141 token contracts that
+/// concern one of `accounts`, such as treasuries: `storage_unregister`
+/// (critical), after which the account can't receive the token and, with
+/// `force`, loses its balance, and `storage_deposit` registering or topping
+/// up the account (info). One pair of subscriptions is added per contract.
+pub fn storage_events_config(
+    routing_key: &str,
+    token_contracts: &[&str],
+    accounts: &[&str],
+) -> PagerDutyAlertConfig {
+    let for_accounts = || {
+        PostFilter::Field(FieldCondition {
+            field: "storage.account_id".to_string(),
+            op: ConditionOp::In(
+                accounts
+                    .iter()
+                    .map(|a| serde_json::Value::from(*a))
+                    .collect(),
+            ),
+        })
+    };
+    let subscriptions = token_contracts
+        .iter()
+        .flat_map(|contract| {
+            [
+                EventSubscription {
+                    summary_template: Some(
+                        "CRITICAL: {storage.account_id} unregistered from token {account_id} (force: {storage.force})"
+                            .to_string(),
+                    ),
+                    dedup_key_template: Some("storage-unregister-{account_id}-{tx_hash}".to_string()),
+                    post_filter: vec![for_accounts()],
+                    ..example_subscription(
+                        &format!("Storage Unregistered: {}", contract),
+                        contract,
+                        Some("storage_unregister"),
+                        "critical",
+                    )
+                },
+                EventSubscription {
+                    summary_template: Some(
+                        "Storage deposit for {storage.account_id} on token {account_id} by {predecessor_id}".to_string(),
+                    ),
+                    dedup_key_template: Some("storage-deposit-{account_id}-{tx_hash}".to_string()),
+                    post_filter: vec![for_accounts()],
+                    ..example_subscription(
+                        &format!("Storage Deposit: {}", contract),
+                        contract,
+                        Some("storage_deposit"),
+                        "info",
+                    )
+                },
+            ]
+        })
+        .collect();
+    example_config(routing_key, subscriptions)
+}
+
+/// Create config for meta transactions relayed to any of `receivers`, such
+/// as a contract users reach through a gasless relayer: one subscription per
+/// contract (info) naming the relayer and the user who signed. It matches the
+/// `Delegate` action on the user's account, whatever account that is.
+pub fn meta_transaction_config(routing_key: &str, receivers: &[&str]) -> PagerDutyAlertConfig {
+    let subscriptions = receivers
+        .iter()
+        .map(|receiver| EventSubscription {
+            action_kinds: vec!["Delegate".to_string()],
+            summary_template: Some(
+                "{meta_tx.sender_id} called {meta_tx.method_name} on {meta_tx.receiver_id} via relayer {meta_tx.relayer_id}"
+                    .to_string(),
+            ),
+            dedup_key_template: Some("meta-tx-{account_id}-{tx_hash}".to_string()),
+            post_filter: vec![PostFilter::Field(FieldCondition {
+                field: "meta_tx.receiver_id".to_string(),
+                op: ConditionOp::Equals(serde_json::Value::from(*receiver)),
+            })],
+            ..example_subscription(&format!("Meta Transactions: {}", receiver), "*", None, "info")
+        })
+        .collect();
+    // The `*` account is deliberate: the post-filter narrows it to `receivers`
+    PagerDutyAlertConfig {
+        allow_broad_filters: true,
+        ..example_config(routing_key, subscriptions)
+    }
+}
+
+/// Global settings shared by the example configurations
+fn example_config(
+    routing_key: &str,
+    subscriptions: Vec<EventSubscription>,
+) -> PagerDutyAlertConfig {
+    PagerDutyAlertConfig {
+        routing_key: routing_key.to_string(),
+        reconnect_delay_secs: 5,
+        summary_max_length: default_summary_max_length(),
+        oversized_details_dir: None,
+        pagerduty_webhook_secret: None,
+        pagerduty_api_token: None,
+        pagerduty_from_email: None,
+        region: PagerDutyRegion::default(),
+        events_api_url: None,
+        state_file: None,
+        event_store: None,
+        retention: RetentionConfig::default(),
+        daily_report: None,
+        dashboard: false,
+        alert_stream: false,
+        admin_api: false,
+        admin_token: None,
+        chat_actions: None,
+        networks: HashMap::new(),
+        environment: None,
+        tags: BTreeMap::new(),
+        alert_queue: AlertQueueConfig::default(),
+        task_restarts: TaskRestartConfig::default(),
+        self_alerts: SelfAlertConfig::default(),
+        meta_routing_key: None,
+        max_concurrent_dispatches: default_max_concurrent_dispatches(),
+        client_url: None,
+        max_reconnect_attempts: None,
+        exit_on_fatal_errors: false,
+        drain_timeout_secs: default_drain_timeout(),
+        preflight: default_preflight(),
+        preflight_change_event: false,
+        max_event_lag_secs: None,
+        max_clock_skew_secs: default_max_clock_skew(),
+        stream_idle_timeout_secs: default_stream_idle_timeout(),
+        max_idle_reconnects: default_max_idle_reconnects(),
+        cross_check: None,
+        dedup_key_collisions: DedupCollisionPolicy::default(),
+        templates: BTreeMap::new(),
+        latency_slo: None,
+        market_alerts: Vec::new(),
+        price_feed: PriceFeedConfig::default(),
+        runbook_base_url: None,
+        recent_events_per_subscription: default_recent_events_per_subscription(),
+        allowed_accounts: Vec::new(),
+        allow_broad_filters: false,
+        event_budget: None,
+        dedup_across_subscriptions: false,
+        follow_up_notes: false,
+        resolve_expired_alerts: false,
+        on_call_escalation_policy: None,
+        maintenance_windows: None,
+        sinks: Vec::new(),
+        shared_state: None,
+        subscriptions,
+    }
+}
+
+/// A mainnet subscription with every optional setting left unset
+fn example_subscription(
+    name: &str,
+    account_id: &str,
+    method_name: Option<&str>,
+    severity: &str,
+) -> EventSubscription {
+    EventSubscription {
+        name: name.to_string(),
+        account_id: account_id.to_string(),
+        method_name: method_name.map(String::from),
+        action_kinds: Vec::new(),
+        severity: severity.to_string(),
+        summary_template: None,
+        dedup_key_template: None,
+        correlation_key_template: None,
+        custom_details_template: None,
+        priority: None,
+        escalation_policy: None,
+        response_play: None,
+        owner: None,
+        team: None,
+        service: None,
+        runbook_url: None,
+        alert_ttl_secs: None,
+        network: default_network(),
+        max_reconnect_attempts: None,
+        backfill_blocks: None,
+        method_overrides: BTreeMap::new(),
+        post_filter: Vec::new(),
+        severity_rules: Vec::new(),
+        timezone: None,
+        script: None,
+        sinks: None,
+        match_trace: false,
+        collapse_duplicate_summaries: None,
+        severity_escalation: None,
+        depends_on: Vec::new(),
+        shadow: false,
+        shadow_burn_in_secs: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A successful `pause` call on venear.near, which `venear_pause_config` alerts on
+    #[cfg(feature = "monitor")]
+    fn pause_action() -> NeardataAction {
+        serde_json::from_value(serde_json::json!({
+            "blockHeight": 1,
+            "txHash": "abc",
+            "accountId": "venear.near",
+            "status": "SUCCESS",
+            "action": {"FunctionCall": {"method_name": "pause"}},
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_venear_pause_config() {
+        let config = venear_pause_config("test-key", "venear.near");
+        assert_eq!(config.subscriptions.len(), 1);
+        assert_eq!(
+            config.subscriptions[0].method_name,
+            Some("pause".to_string())
+        );
+    }
+
+    #[test]
+    fn test_method_call_config() {
+        let config = method_call_config("test-key", "test.near", Some("transfer"));
+        assert_eq!(config.subscriptions.len(), 1);
+        assert_eq!(
+            config.subscriptions[0].method_name,
+            Some("transfer".to_string())
+        );
+    }
+
+    #[test]
+    fn test_truncate_summary_keeps_graphemes_intact() {
+        let (short, overflow) = truncate_summary("short", 10);
+        assert_eq!(short, "short");
+        assert!(overflow.is_none());
+
+        // "e" + combining acute accent is one grapheme made of two chars
+        let summary = "abcde\u{301}fgh";
+        let (truncated, overflow) = truncate_summary(summary, 6);
+        assert_eq!(truncated, "abcd…");
+        assert_eq!(overflow.as_deref(), Some("e\u{301}fgh"));
+        assert!(truncated.chars().count() <= 6);
+    }
+
+    #[test]
+    fn test_subscription_priority_from_yaml() {
+        let config: PagerDutyAlertConfig = serde_yaml::from_str(
+            r#"
+subscriptions:
+  - name: "veNEAR: Contract Paused"
+    account_id: "venear.near"
+    method_name: "pause"
+    severity: critical
+    priority: P1
+  - name: "HoS: Vote Cast"
+    account_id: "vote.dao"
+  - name: "Bridge"
+    account_id: "bridge.near"
+    method_overrides:
+      pause:
+        escalation_policy: PBRIDGE1
+        response_play: PLAY123
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.subscriptions[0].priority.as_deref(), Some("P1"));
+        assert_eq!(config.subscriptions[1].priority, None);
+        assert!(config.pagerduty_api_token.is_none());
+
+        let bridge = &config.subscriptions[2];
+        assert_eq!(bridge.escalation_policy, None);
+        let paused = bridge.for_method(Some("pause"));
+        assert_eq!(paused.escalation_policy.as_deref(), Some("PBRIDGE1"));
+        assert_eq!(paused.response_play.as_deref(), Some("PLAY123"));
+    }
+
+    #[test]
+    fn test_network_selection() {
+        #[cfg_attr(not(feature = "monitor"), allow(unused_mut))]
+        let mut config: PagerDutyAlertConfig = serde_yaml::from_str(
+            r#"
+networks:
+  localnet:
+    ws_url: "ws://localhost:8080/ws"
+    explorer_url: "http://localhost:3000"
+subscriptions:
+  - name: "Mainnet pause"
+    account_id: "venear.near"
+  - name: "Testnet pause"
+    account_id: "venear.testnet"
+    network: testnet
+  - name: "Local pause"
+    account_id: "venear.test.near"
+    network: localnet
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.subscriptions[0].network, "mainnet");
+        assert_eq!(
+            config.network("testnet").unwrap().explorer_url,
+            "https://testnet.nearblocks.io"
+        );
+        assert_eq!(
+            config.network("localnet").unwrap().ws_url,
+            "ws://localhost:8080/ws"
+        );
+
+        #[cfg(feature = "monitor")]
+        {
+            let monitor = NearPagerDutyMonitor::new(config.clone()).unwrap();
+            assert_eq!(monitor.networks.len(), 3);
+
+            config.subscriptions[2].network = "betanet".to_string();
+            assert!(NearPagerDutyMonitor::new(config).is_err());
         }
     }
 
-    /// Trigger a PagerDuty alert
-    pub async fn trigger(
-        &self,
-        summary: &str,
-        source: &str,
-        severity: &str,
-        dedup_key: Option<String>,
-        custom_details: Option<serde_json::Value>,
-        explorer_link: Option<(&str, &str)>,
-    ) -> Result<PagerDutyResponse, anyhow::Error> {
-        let links = explorer_link.map(|(href, text)| {
-            vec![PagerDutyLink {
-                href: href.to_string(),
-                text: text.to_string(),
-            }]
+    #[cfg(feature = "monitor")]
+    #[tokio::test]
+    async fn test_admin_api_requires_token() {
+        let mut config = venear_pause_config("test-key", "venear.near");
+        config.admin_api = true;
+        assert!(NearPagerDutyMonitor::new(config.clone()).is_err());
+        config.admin_token = Some("s3cret".to_string());
+        assert!(NearPagerDutyMonitor::new(config).is_ok());
+    }
+
+    #[cfg(feature = "monitor")]
+    #[tokio::test]
+    async fn test_subscription_dependencies() {
+        let mut config: PagerDutyAlertConfig = serde_yaml::from_str(
+            r#"
+subscriptions:
+  - name: "DAO proposals"
+    account_id: "dao.testnet"
+    network: testnet
+    depends_on: ["Indexer lag"]
+  - name: "Indexer lag"
+    account_id: "indexer.near"
+  - name: "Treasury"
+    account_id: "treasury.near"
+    depends_on: ["Indexer lag"]
+  - name: "DAO votes"
+    account_id: "dao.testnet"
+    network: testnet
+    depends_on: ["DAO proposals"]
+"#,
+        )
+        .unwrap();
+        let order: Vec<&str> = config
+            .startup_order()
+            .unwrap()
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(
+            order,
+            ["Indexer lag", "DAO proposals", "Treasury", "DAO votes"]
+        );
+
+        let monitor = NearPagerDutyMonitor::new(config.clone()).unwrap();
+        assert_eq!(monitor.networks[0].0, "mainnet");
+        let running = |name: &str| {
+            monitor.is_running(
+                monitor
+                    .config
+                    .subscriptions
+                    .iter()
+                    .find(|s| s.name == name)
+                    .unwrap(),
+            )
+        };
+        let waiting = monitor.wait_for_upstream_networks("testnet");
+        tokio::pin!(waiting);
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), &mut waiting)
+                .await
+                .is_err()
+        );
+        // A connected network's subscriptions still wait for their
+        // dependencies, including those on the same network
+        monitor.record_connected("testnet");
+        assert!(!running("DAO proposals") && !running("DAO votes"));
+        monitor.record_connected("mainnet");
+        tokio::time::timeout(Duration::from_secs(5), waiting)
+            .await
+            .unwrap();
+        assert!(order.iter().all(|name| running(name)));
+        monitor.stop_subscriptions();
+        assert!(!order.iter().any(|name| running(name)));
+
+        config.subscriptions[1].depends_on = vec!["Treasury".to_string()];
+        let error = config.startup_order().unwrap_err().to_string();
+        assert!(error.contains("'Indexer lag', 'Treasury'"), "{}", error);
+        // Acyclic between subscriptions, but mainnet and testnet would wait on each other
+        config.subscriptions[1].depends_on = Vec::new();
+        config.subscriptions[2].depends_on = vec!["DAO proposals".to_string()];
+        assert!(config
+            .startup_order()
+            .unwrap_err()
+            .to_string()
+            .contains("must all point one way"));
+        config.subscriptions[1].depends_on = vec!["Lag".to_string()];
+        assert!(NearPagerDutyMonitor::new(config).is_err());
+    }
+
+    #[cfg(feature = "monitor")]
+    #[test]
+    fn test_environment_and_tags() {
+        let mut config = venear_pause_config("key", "venear.near");
+        config.environment = Some("staging".to_string());
+        config
+            .tags
+            .insert("region".to_string(), "eu-west".to_string());
+        let monitor = NearPagerDutyMonitor::new(config).unwrap();
+        let action: NeardataAction = serde_json::from_value(serde_json::json!({
+            "blockHeight": 1,
+            "accountId": "venear.near",
+            "status": "SUCCESS",
+            "action": {"FunctionCall": {"method_name": "pause"}},
+        }))
+        .unwrap();
+
+        let source = monitor.alert_source(&action, &monitor.config.subscriptions[0]);
+        assert_eq!(source, "near:venear.near@staging");
+        let details =
+            monitor.with_deployment_tags(serde_json::json!({"account_id": "venear.near"}));
+        assert_eq!(details["environment"], "staging");
+        assert_eq!(details["tags"]["region"], "eu-west");
+    }
+
+    #[test]
+    fn test_trim_long_strings() {
+        let mut details = serde_json::json!({
+            "method_name": "vote",
+            "action": {"FunctionCall": {"args": "x".repeat(5000)}},
         });
+        trim_long_strings(&mut details, 100);
+        assert_eq!(details["method_name"], "vote");
+        let args = details["action"]["FunctionCall"]["args"].as_str().unwrap();
+        assert!(args.starts_with(&"x".repeat(100)));
+        assert!(args.ends_with("[4900 bytes trimmed]"));
+    }
 
-        let event = PagerDutyEvent {
-            routing_key: self.routing_key.clone(),
-            event_action: "trigger".to_string(),
-            dedup_key,
-            payload: PagerDutyPayload {
-                summary: summary.chars().take(1024).collect(), // PD limit
-                source: source.to_string(),
-                severity: severity.to_string(),
-                timestamp: Utc::now().to_rfc3339(),
-                custom_details,
-            },
-            links,
-            client: "NEAR Blockchain Monitor".to_string(),
-            client_url: "https://nearblocks.io".to_string(),
+    #[test]
+    fn test_pagerduty_response_status() {
+        let accepted: PagerDutyResponse = serde_json::from_str(
+            r#"{"status":"success","message":"Event processed","dedup_key":"srv01/HTTP"}"#,
+        )
+        .unwrap();
+        assert!(accepted.is_success());
+        assert_eq!(accepted.dedup_key.as_deref(), Some("srv01/HTTP"));
+
+        let rejected: PagerDutyResponse = serde_json::from_str(
+            r#"{"status":"invalid event","message":"Event object is invalid","errors":["'routing_key' is missing"]}"#,
+        )
+        .unwrap();
+        assert_eq!(rejected.status, EventStatus::InvalidEvent);
+        assert_eq!(rejected.errors.len(), 1);
+    }
+
+    #[cfg(feature = "monitor")]
+    #[tokio::test]
+    async fn test_reconnect_limit_exits() {
+        let mut config: PagerDutyAlertConfig = serde_yaml::from_str(
+            r#"
+reconnect_delay_secs: 0
+max_reconnect_attempts: 5
+networks:
+  unreachable:
+    ws_url: "ws://127.0.0.1:1/ws"
+    explorer_url: "http://localhost:3000"
+subscriptions:
+  - name: "Pause"
+    account_id: "venear.near"
+    network: unreachable
+    max_reconnect_attempts: 2
+"#,
+        )
+        .unwrap();
+        config.routing_key = "key".to_string();
+        let monitor = NearPagerDutyMonitor::new(config).unwrap();
+
+        let exit = monitor.start().await.unwrap_err();
+        let exit = exit.downcast_ref::<MonitorExit>().unwrap();
+        assert!(matches!(
+            exit,
+            MonitorExit::ReconnectLimit { attempts: 2, .. }
+        ));
+        assert_eq!(exit.exit_code(), 3);
+    }
+
+    #[cfg(feature = "monitor")]
+    #[tokio::test]
+    async fn test_dedup_across_subscriptions() {
+        let mut config = method_call_config("key", "venear.near", None);
+        config.subscriptions.push(
+            venear_pause_config("key", "venear.near")
+                .subscriptions
+                .remove(0),
+        );
+        let pause: NeardataAction = serde_json::from_value(serde_json::json!({
+            "blockHeight": 1,
+            "accountId": "venear.near",
+            "status": "SUCCESS",
+            "receiptId": "r1",
+            "action": {"FunctionCall": {"method_name": "pause"}},
+        }))
+        .unwrap();
+        let names = |monitor: &NearPagerDutyMonitor| {
+            monitor
+                .matching_subscriptions(&pause, &monitor.config.subscriptions)
+                .iter()
+                .map(|s| s.name.clone())
+                .collect::<Vec<_>>()
         };
 
-        let response = self
-            .client
-            .post(Self::EVENTS_URL)
-            .json(&event)
-            .send()
-            .await?;
+        let monitor = NearPagerDutyMonitor::new(config.clone()).unwrap();
+        assert_eq!(names(&monitor).len(), 2);
+        config.dedup_across_subscriptions = true;
+        let monitor = NearPagerDutyMonitor::new(config).unwrap();
+        assert_eq!(names(&monitor), ["veNEAR: Contract Paused"]);
+    }
 
-        let result: PagerDutyResponse = response.json().await?;
-        log::info!(
-            "PagerDuty alert triggered: status={}, message={}, dedup_key={:?}",
-            result.status,
-            result.message,
-            result.dedup_key
+    #[cfg(feature = "monitor")]
+    #[tokio::test]
+    async fn test_correlation_key() {
+        let mut config = method_call_config("key", "dao.near", None);
+        config.subscriptions[0].correlation_key_template =
+            Some("dao-proposal-{args.id}".to_string());
+        let monitor = NearPagerDutyMonitor::new(config).unwrap();
+        let call = |method_name: &str, args: serde_json::Value| -> NeardataAction {
+            serde_json::from_value(serde_json::json!({
+                "blockHeight": 1,
+                "txHash": format!("tx-{}", method_name),
+                "accountId": "dao.near",
+                "status": "SUCCESS",
+                "action": {"FunctionCall": {
+                    "method_name": method_name,
+                    "args": base64::Engine::encode(&base64::engine::general_purpose::STANDARD, args.to_string()),
+                }},
+            }))
+            .unwrap()
+        };
+        let sub = &monitor.config.subscriptions[0];
+        let key = |action| monitor.format_dedup_key(&action, sub);
+
+        let created = key(call("add_proposal", serde_json::json!({"id": 7})));
+        assert_eq!(created.as_deref(), Some("dao-proposal-7"));
+        assert_eq!(
+            key(call(
+                "act_proposal",
+                serde_json::json!({"id": 7, "action": "VoteApprove"})
+            )),
+            created
+        );
+        // Without the field the dedup key template applies
+        assert_eq!(
+            key(call("pause", serde_json::json!({}))).as_deref(),
+            Some("dao.near-tx-pause")
         );
-        Ok(result)
     }
 
-    /// Acknowledge an existing alert
-    pub async fn acknowledge(&self, dedup_key: &str) -> Result<PagerDutyResponse, anyhow::Error> {
-        let event = serde_json::json!({
-            "routing_key": self.routing_key,
-            "event_action": "acknowledge",
-            "dedup_key": dedup_key,
+    #[cfg(feature = "monitor")]
+    #[tokio::test]
+    async fn test_event_budget_estimate() {
+        let config = venear_pause_config("key", "venear.near");
+        let monitor = NearPagerDutyMonitor::new(config.clone()).unwrap();
+        let action = |account_id: &str, method_name: &str, timestamp_ms: f64| NeardataAction {
+            block_height: 1,
+            block_hash: None,
+            block_timestamp_ms: Some(timestamp_ms),
+            tx_hash: None,
+            receipt_id: None,
+            signer_id: None,
+            account_id: account_id.to_string(),
+            predecessor_id: None,
+            status: "SUCCESS".to_string(),
+            action: ActionType::FunctionCall(FunctionCallAction {
+                method_name: method_name.to_string(),
+                args: None,
+                deposit: None,
+                gas: None,
+            }),
+            action_index: 0,
+            events: Vec::new(),
+        };
+        // Two pauses in an hour of blocks
+        let actions = [
+            action("venear.near", "pause", 0.0),
+            action("venear.near", "unpause", 1_800_000.0),
+            action("other.near", "pause", 2_000_000.0),
+            action("venear.near", "pause", 3_600_000.0),
+        ];
+        let subscriptions: Vec<&EventSubscription> = config.subscriptions.iter().collect();
+        let estimate = monitor.estimate_events_per_day(&actions, &subscriptions);
+        assert_eq!(estimate, [("veNEAR: Contract Paused".to_string(), 48.0)]);
+        assert_eq!(
+            monitor.estimate_events_per_day(&actions[..1], &subscriptions)[0].1,
+            86_400.0
+        );
+    }
+
+    #[cfg(feature = "monitor")]
+    #[tokio::test]
+    async fn test_event_lag_tracking() {
+        let mut config = venear_pause_config("key", "venear.near");
+        config.max_event_lag_secs = Some(60);
+        let monitor = NearPagerDutyMonitor::new(config).unwrap();
+        let action = |age_ms: i64| NeardataAction {
+            block_height: 1,
+            block_hash: None,
+            block_timestamp_ms: Some((Utc::now().timestamp_millis() - age_ms) as f64),
+            tx_hash: None,
+            receipt_id: None,
+            signer_id: None,
+            account_id: "venear.near".to_string(),
+            predecessor_id: None,
+            status: "SUCCESS".to_string(),
+            action: ActionType::Other,
+            action_index: 0,
+            events: Vec::new(),
+        };
+
+        monitor.check_event_lag("mainnet", &[action(3_600_000), action(120_000)]);
+        let lag = monitor.status().networks[0].event_lag_secs.unwrap();
+        assert!((119.0..130.0).contains(&lag));
+        assert!(monitor.lagging_networks.lock().unwrap().contains("mainnet"));
+
+        monitor.check_event_lag("mainnet", &[action(1_000)]);
+        assert!(monitor.lagging_networks.lock().unwrap().is_empty());
+        // Frames without events leave the last measurement alone
+        monitor.check_event_lag("mainnet", &[]);
+        assert!(monitor.status().networks[0].event_lag_secs.unwrap() < 60.0);
+
+        // A block slightly ahead of the local clock was just produced; one
+        // far ahead means a wrong clock and is left out
+        monitor.check_event_lag("mainnet", &[action(-10_000)]);
+        assert_eq!(monitor.status().networks[0].event_lag_secs, Some(0.0));
+        monitor.check_event_lag("mainnet", &[action(120_000)]);
+        monitor.check_event_lag("mainnet", &[action(-600_000)]);
+        assert!(monitor.status().networks[0].event_lag_secs.unwrap() >= 119.0);
+        assert!(monitor.clock_skewed.load(Ordering::Relaxed));
+    }
+
+    #[cfg(feature = "monitor")]
+    #[tokio::test]
+    async fn test_idle_stream_is_dropped() {
+        // A server that takes the filter, then neither sends nor answers pings
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+            ws.next().await;
+            tokio::time::sleep(Duration::from_secs(30)).await;
         });
 
-        let response = self
-            .client
-            .post(Self::EVENTS_URL)
-            .json(&event)
-            .send()
-            .await?;
+        let mut config = venear_pause_config("key", "venear.near");
+        config.stream_idle_timeout_secs = 1;
+        config.max_idle_reconnects = 2;
+        let monitor = NearPagerDutyMonitor::new(config).unwrap();
+        let network = monitor.config.network("mainnet").unwrap();
+        let subscriptions: Vec<_> = monitor.config.subscriptions.iter().collect();
+        let (ws_stream, _) = connect_async(url.as_str()).await.unwrap();
+        let started = std::time::Instant::now();
+        let error = monitor
+            .monitor_stream(ws_stream, "mainnet", &network, &subscriptions)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("stale"), "{}", error);
+        assert!(started.elapsed() >= Duration::from_secs(2));
+        assert_eq!(
+            monitor.idle_streams.lock().unwrap().get("mainnet"),
+            Some(&1)
+        );
 
-        let result: PagerDutyResponse = response.json().await?;
-        log::info!(
-            "PagerDuty alert acknowledged: status={}, message={}",
-            result.status,
-            result.message
+        monitor.record_active_stream("mainnet");
+        assert!(monitor.idle_streams.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_dedup_key_collisions() {
+        let mut config = venear_pause_config("key", "venear.near");
+        let mut unpause = config.subscriptions[0].clone();
+        unpause.name = "veNEAR: Contract Unpaused".to_string();
+        unpause.method_name = Some("unpause".to_string());
+        config.subscriptions.push(unpause);
+        // Same literal template on two methods
+        assert_eq!(config.dedup_key_collisions().len(), 1);
+
+        config.subscriptions[1].dedup_key_template =
+            Some("venear-{method_name}-{tx_hash}".to_string());
+        config.subscriptions[0].dedup_key_template =
+            Some("venear-{method_name}-{tx_hash}".to_string());
+        assert!(config.dedup_key_collisions().is_empty());
+
+        config.subscriptions[0].dedup_key_template = None;
+        config.subscriptions[1].dedup_key_template = None;
+        assert_eq!(
+            config.dedup_key_collisions(),
+            [(
+                "veNEAR: Contract Paused".to_string(),
+                "veNEAR: Contract Unpaused".to_string()
+            )]
         );
-        Ok(result)
+        assert_eq!(slug("veNEAR: Contract Paused"), "venear-contract-paused");
     }
 
-    /// Resolve an existing alert
-    pub async fn resolve(&self, dedup_key: &str) -> Result<PagerDutyResponse, anyhow::Error> {
-        let event = serde_json::json!({
-            "routing_key": self.routing_key,
-            "event_action": "resolve",
-            "dedup_key": dedup_key,
-        });
+    #[test]
+    fn test_method_overrides() {
+        let config: PagerDutyAlertConfig = serde_yaml::from_str(
+            r#"
+subscriptions:
+  - name: "DAO"
+    account_id: "dao.near"
+    severity: warning
+    summary_template: "DAO call {method_name}"
+    method_overrides:
+      pause:
+        severity: critical
+        summary_template: "DAO paused by {predecessor_id}"
+"#,
+        )
+        .unwrap();
+        let sub = &config.subscriptions[0];
 
-        let response = self
-            .client
-            .post(Self::EVENTS_URL)
-            .json(&event)
-            .send()
-            .await?;
+        let paused = sub.for_method(Some("pause"));
+        assert_eq!(paused.severity, "critical");
+        assert_eq!(
+            paused.summary_template.as_deref(),
+            Some("DAO paused by {predecessor_id}")
+        );
+        assert_eq!(paused.name, "DAO");
 
-        let result: PagerDutyResponse = response.json().await?;
-        log::info!(
-            "PagerDuty alert resolved: status={}, message={}",
-            result.status,
-            result.message
+        assert!(matches!(sub.for_method(Some("vote")), Cow::Borrowed(_)));
+        assert!(matches!(sub.for_method(None), Cow::Borrowed(_)));
+    }
+
+    #[cfg(feature = "monitor")]
+    #[test]
+    fn test_ownership_in_custom_details() {
+        let mut config = venear_pause_config("key", "venear.near");
+        let sub = &mut config.subscriptions[0];
+        sub.team = Some("governance".to_string());
+        sub.service = Some("veNEAR".to_string());
+
+        let details =
+            NearPagerDutyMonitor::with_ownership(sub, serde_json::json!({"network": "mainnet"}));
+        assert_eq!(
+            details,
+            serde_json::json!({"network": "mainnet", "team": "governance", "service": "veNEAR"})
         );
-        Ok(result)
     }
-}
 
-// =============================================================================
-// Event Monitor
-// =============================================================================
+    #[cfg(feature = "monitor")]
+    #[tokio::test]
+    async fn test_maintenance_suppresses_alerts() {
+        let mut config = venear_pause_config("key", "venear.near");
+        config.maintenance_windows = Some(MaintenanceWindowsConfig {
+            service_id: "PSERVICE1".to_string(),
+            during: MaintenanceAction::Suppress,
+            refresh_secs: 60,
+        });
+        let monitor = NearPagerDutyMonitor::new(config).unwrap();
+        monitor.in_maintenance.store(true, Ordering::Relaxed);
+        let pause = pause_action();
+        let sub = &monitor.config.subscriptions[0];
+        let network = monitor.config.network("mainnet").unwrap();
+
+        monitor
+            .process_action(&pause, sub, &network, true)
+            .await
+            .unwrap();
+        assert!(monitor.alerts().open_alerts().is_empty());
+        assert!(monitor.alerts().knows_event(&event_id(&pause, sub)));
+    }
+
+    #[cfg(feature = "monitor")]
+    #[tokio::test]
+    async fn test_shadow_subscription_withholds_alerts() {
+        let mut config = venear_pause_config("key", "venear.near");
+        config.subscriptions[0].shadow = true;
+        let monitor = NearPagerDutyMonitor::new(config).unwrap();
+        let pause = pause_action();
+        let sub = &monitor.config.subscriptions[0];
+        let network = monitor.config.network("mainnet").unwrap();
+
+        monitor
+            .process_action(&pause, sub, &network, true)
+            .await
+            .unwrap();
+        assert!(monitor.alerts().open_alerts().is_empty());
+        assert!(monitor.alerts().knows_event(&event_id(&pause, sub)));
+        assert_eq!(monitor.status().subscriptions[0].matches, 1);
+
+        let started_at = monitor.alerts().shadow_started(&sub.name).unwrap();
+        let mut sub = sub.clone();
+        sub.shadow_burn_in_secs = Some(3600);
+        assert!(sub.is_shadowed(started_at, started_at + chrono::Duration::minutes(59)));
+        assert!(!sub.is_shadowed(started_at, started_at + chrono::Duration::hours(1)));
+        sub.shadow = false;
+        assert!(!sub.is_shadowed(started_at, started_at));
+    }
+
+    #[cfg(feature = "monitor")]
+    #[tokio::test]
+    async fn test_shadow_burn_in_survives_restarts() {
+        let path = std::env::temp_dir().join(format!("shadow-burn-in-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut config = venear_pause_config("key", "venear.near");
+        config.state_file = Some(path.to_string_lossy().into_owned());
+        config.subscriptions[0].shadow = true;
+        config.subscriptions[0].shadow_burn_in_secs = Some(3600);
+        let name = config.subscriptions[0].name.clone();
 
-/// Main event monitoring service
-pub struct NearPagerDutyMonitor {
-    config: PagerDutyAlertConfig,
-    pd_client: Arc<PagerDutyClient>,
-}
+        // A first run two hours ago
+        let first_run = Utc::now() - chrono::Duration::hours(2);
+        let store = AlertStore::open(&path).unwrap();
+        store.track_shadow_subscriptions(&[&name], first_run);
+        drop(store);
 
-impl NearPagerDutyMonitor {
-    const NEARDATA_WS_URL: &'static str = "wss://actions.near.stream/ws";
+        // A restart keeps counting from the first run, so the burn-in is over
+        let monitor = NearPagerDutyMonitor::new(config.clone()).unwrap();
+        assert_eq!(monitor.alerts().shadow_started(&name), Some(first_run));
+        let pause = pause_action();
+        let sub = &monitor.config.subscriptions[0];
+        let network = monitor.config.network("mainnet").unwrap();
+        monitor
+            .process_action(&pause, sub, &network, true)
+            .await
+            .unwrap();
+        assert_eq!(monitor.alerts().open_alerts().len(), 1);
+        drop(monitor);
 
-    pub fn new(config: PagerDutyAlertConfig) -> Self {
-        let pd_client = Arc::new(PagerDutyClient::new(config.routing_key.clone()));
-        Self { config, pd_client }
+        // Leaving shadow forgets the first run, so the next burn-in starts over
+        config.subscriptions[0].shadow = false;
+        drop(NearPagerDutyMonitor::new(config.clone()).unwrap());
+        config.subscriptions[0].shadow = true;
+        let monitor = NearPagerDutyMonitor::new(config).unwrap();
+        assert!(monitor.alerts().shadow_started(&name).unwrap() > first_run);
+        drop(monitor);
+        std::fs::remove_file(&path).unwrap();
     }
 
-    /// Start monitoring - connects to neardata and processes actions
-    pub async fn start(&self) -> Result<(), anyhow::Error> {
-        loop {
-            if let Err(e) = self.monitor_stream().await {
-                log::error!("Error in neardata stream: {:?}", e);
-            }
-            log::info!(
-                "Reconnecting to neardata in {}s...",
-                self.config.reconnect_delay_secs
-            );
-            tokio::time::sleep(Duration::from_secs(self.config.reconnect_delay_secs)).await;
-        }
+    #[cfg(feature = "monitor")]
+    #[tokio::test]
+    async fn test_event_panic_is_isolated() {
+        let monitor = NearPagerDutyMonitor::new(venear_pause_config("key", "venear.near")).unwrap();
+        let action: NeardataAction = serde_json::from_value(serde_json::json!({
+            "blockHeight": 7,
+            "receiptId": "r1",
+            "accountId": "venear.near",
+            "status": "SUCCESS",
+            "action": {"FunctionCall": {"method_name": "pause"}},
+        }))
+        .unwrap();
+
+        assert!(
+            !monitor
+                .isolate_panics("mainnet", &action, async { panic!("bad payload") })
+                .await
+        );
+        assert!(monitor.isolate_panics("mainnet", &action, async {}).await);
+        assert_eq!(monitor.status().errors.event_panics, 1);
     }
 
-    /// Monitor the neardata WebSocket stream
-    async fn monitor_stream(&self) -> Result<(), anyhow::Error> {
-        log::info!("Connecting to {}", Self::NEARDATA_WS_URL);
+    #[cfg(feature = "monitor")]
+    #[tokio::test]
+    async fn test_first_failed_check() {
+        let mut config = large_transfer_config("key", "usdc.near", 1_000);
+        config.subscriptions.push(example_subscription(
+            "Pause",
+            "venear.near",
+            Some("pause"),
+            "critical",
+        ));
+        let monitor = NearPagerDutyMonitor::new(config).unwrap();
+        let transfer = |amount: &str, status: &str| -> NeardataAction {
+            let args = base64::Engine::encode(
+                &base64::engine::general_purpose::STANDARD,
+                serde_json::json!({"receiver_id": "bob.near", "amount": amount}).to_string(),
+            );
+            serde_json::from_value(serde_json::json!({
+                "blockHeight": 7,
+                "accountId": "usdc.near",
+                "status": status,
+                "action": {"FunctionCall": {"method_name": "ft_transfer", "args": args}},
+            }))
+            .unwrap()
+        };
+        let (large, pause) = (
+            &monitor.config.subscriptions[0],
+            &monitor.config.subscriptions[1],
+        );
 
-        let (mut ws_stream, _) = connect_async(Self::NEARDATA_WS_URL).await?;
+        assert_eq!(
+            monitor.first_failed_check(&transfer("5000", "SUCCESS"), large),
+            None
+        );
+        assert_eq!(
+            monitor.first_failed_check(&transfer("5000", "SUCCESS"), pause),
+            Some(MatchVerdict::OtherAccount)
+        );
+        assert_eq!(
+            monitor.first_failed_check(&transfer("5000", "FAILURE"), large),
+            Some(MatchVerdict::Unsuccessful)
+        );
+        assert_eq!(
+            monitor.first_failed_check(&transfer("10", "SUCCESS"), large),
+            Some(MatchVerdict::PostFilter {
+                condition: r#"{"field":"args.amount","gte":"1000"}"#.to_string()
+            })
+        );
+    }
 
-        // Build filter for all monitored accounts
-        let account_ids: Vec<&str> = self
-            .config
-            .subscriptions
-            .iter()
-            .map(|s| s.account_id.as_str())
-            .collect();
+    #[cfg(feature = "monitor")]
+    #[test]
+    fn test_match_trace() {
+        let config = large_transfer_config("key", "usdc.near", 1_000);
+        let sub = &config.subscriptions[0];
+        let args = base64::Engine::encode(
+            &base64::engine::general_purpose::STANDARD,
+            r#"{"receiver_id": "bob.near", "amount": "5000"}"#,
+        );
+        let action: NeardataAction = serde_json::from_value(serde_json::json!({
+            "blockHeight": 7,
+            "accountId": "usdc.near",
+            "status": "SUCCESS",
+            "action": {"FunctionCall": {"method_name": "ft_transfer", "args": args}},
+        }))
+        .unwrap();
 
-        // Build subscription lookup by account_id for fast matching
-        let subscriptions_by_account: HashMap<&str, Vec<&EventSubscription>> = {
-            let mut map: HashMap<&str, Vec<&EventSubscription>> = HashMap::new();
-            for sub in &self.config.subscriptions {
-                map.entry(sub.account_id.as_str())
-                    .or_default()
-                    .push(sub);
-            }
-            map
-        };
+        let trace = NearPagerDutyMonitor::match_trace(&action, sub, "5000 moved", None);
+        assert_eq!(
+            trace["account_id"],
+            serde_json::json!({"pattern": "usdc.near", "value": "usdc.near"})
+        );
+        assert_eq!(trace["method_name"]["value"], "ft_transfer");
+        assert_eq!(
+            trace["post_filter"][1],
+            serde_json::json!({"condition": {"field": "args.amount", "gte": "1000"}, "value": "5000", "matched": true})
+        );
+        assert_eq!(trace["method_override"], serde_json::Value::Null);
+        assert_eq!(trace["summary"], "5000 moved");
+    }
 
-        // Neardata filter format
-        let filter = serde_json::json!({
-            "secret": "tmp",
-            "filter": account_ids.iter().map(|id| {
-                serde_json::json!({"accountId": id, "status": "SUCCESS"})
-            }).collect::<Vec<_>>(),
-            "fetch_past_actions": 0
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn test_journal_and_reprocess() {
+        let mut config = venear_pause_config("key", "venear.near");
+        config.event_store = Some(EventStoreConfig {
+            path: ":memory:".to_string(),
+            retention_days: 90,
+            max_size_mb: None,
+        });
+        config.maintenance_windows = Some(MaintenanceWindowsConfig {
+            service_id: "PSERVICE1".to_string(),
+            during: MaintenanceAction::Suppress,
+            refresh_secs: 60,
         });
+        let monitor = NearPagerDutyMonitor::new(config).unwrap();
+        monitor.in_maintenance.store(true, Ordering::Relaxed);
+        let pause = NeardataAction {
+            receipt_id: Some("r1".to_string()),
+            action_index: 2,
+            ..pause_action()
+        };
+        let sub = &monitor.config.subscriptions[0];
+        let network = monitor.config.network("mainnet").unwrap();
+        monitor
+            .process_action(&pause, sub, &network, true)
+            .await
+            .unwrap();
 
-        let filter_json = serde_json::to_string(&filter)?;
-        ws_stream.send(Message::Text(filter_json.clone())).await?;
-        log::info!("Connected and filter sent: {}", filter_json);
+        let store = monitor.event_store.as_ref().unwrap();
+        let recorded = store.query(&EventQuery::default()).unwrap();
+        assert_eq!(recorded[0].outcome, EventOutcome::Suppressed);
+        let raw: NeardataAction = serde_json::from_value(recorded[0].raw.clone().unwrap()).unwrap();
+        assert_eq!(event_id(&raw, sub), event_id(&pause, sub));
 
-        while let Some(msg) = ws_stream.next().await {
-            match msg? {
-                Message::Text(text) => {
-                    match serde_json::from_str::<NeardataMessage>(&text) {
-                        Ok(neardata_msg) => {
-                            for action in neardata_msg.actions {
-                                // Find matching subscriptions for this account
-                                if let Some(subs) = subscriptions_by_account.get(action.account_id.as_str()) {
-                                    for sub in subs {
-                                        if Self::action_matches_subscription(&action, sub) {
-                                            if let Err(e) = self.process_action(&action, sub).await {
-                                                log::error!("Error processing action: {:?}", e);
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            log::warn!("Failed to parse neardata message: {:?}", e);
-                            log::debug!("Raw message: {}", text);
-                        }
-                    }
-                }
-                Message::Ping(data) => {
-                    ws_stream.send(Message::Pong(data)).await?;
-                }
-                Message::Close(_) => {
-                    log::warn!("WebSocket closed");
-                    break;
-                }
-                _ => {}
+        // Events from before the raw action was kept can't be reprocessed
+        store
+            .record(&StoredEvent {
+                raw: None,
+                event_id: "old".to_string(),
+                ..recorded[0].clone()
+            })
+            .unwrap();
+        let outcome = monitor.reprocess(&EventQuery::default()).await.unwrap();
+        assert_eq!(
+            outcome,
+            ReprocessOutcome {
+                actions: 1,
+                matched: 1,
+                skipped: 1
             }
-        }
-
-        Ok(())
+        );
     }
 
-    /// Check if an action matches a subscription's filters
-    fn action_matches_subscription(action: &NeardataAction, subscription: &EventSubscription) -> bool {
-        // If method_name filter is set, only match FunctionCall with that method
-        if let Some(ref required_method) = subscription.method_name {
-            match &action.action {
-                ActionType::FunctionCall(fc) => {
-                    if fc.method_name != *required_method {
-                        return false;
-                    }
-                }
-                _ => return false, // Not a function call, doesn't match
-            }
-        }
-        true
+    #[cfg(feature = "redis")]
+    #[tokio::test]
+    async fn test_standby_holds_matches() {
+        let mut config = venear_pause_config("key", "venear.near");
+        config.shared_state = Some(SharedStateConfig {
+            redis_url: "redis://127.0.0.1:1".to_string(),
+            key_prefix: "test".to_string(),
+            claim_ttl_secs: 60,
+            instance_id: Some("replica-b".to_string()),
+            leader_lease_secs: Some(15),
+            shard_subscriptions: false,
+        });
+        let monitor = NearPagerDutyMonitor::new(config).unwrap();
+        let pause = pause_action();
+        let sub = &monitor.config.subscriptions[0];
+        let network = monitor.config.network("mainnet").unwrap();
+
+        monitor
+            .process_action(&pause, sub, &network, true)
+            .await
+            .unwrap();
+        assert!(!monitor.alerts().knows_event(&event_id(&pause, sub)));
+        assert_eq!(monitor.standby_matches.lock().unwrap().len(), 1);
     }
 
-    /// Process an action and send PagerDuty alert
-    async fn process_action(
-        &self,
-        action: &NeardataAction,
-        subscription: &EventSubscription,
-    ) -> Result<(), anyhow::Error> {
-        let method_name = match &action.action {
-            ActionType::FunctionCall(fc) => Some(fc.method_name.as_str()),
-            _ => None,
+    #[cfg(feature = "monitor")]
+    #[test]
+    fn test_sink_config() {
+        let mut config: PagerDutyAlertConfig = serde_yaml::from_str(
+            r#"
+sinks:
+  - name: oncall
+    type: victorops
+    api_key: "vo-key"
+    routing_key: "near"
+    min_severity: error
+subscriptions:
+  - name: "veNEAR pause"
+    account_id: "venear.near"
+    sinks: [oncall]
+"#,
+        )
+        .unwrap();
+        let SinkKind::VictorOps(victorops) = &config.sinks[0].kind else {
+            panic!("expected a victorops sink");
         };
+        assert_eq!(victorops.routing_key, "near");
+        assert!(victorops.url.starts_with("https://alert.victorops.com/"));
+        assert!(NearPagerDutyMonitor::new(config.clone()).is_ok());
 
-        log::info!(
-            "Action matched for '{}': account={}, method={:?}, from={:?}",
-            subscription.name,
-            action.account_id,
-            method_name,
-            action.predecessor_id
-        );
+        config.subscriptions[0].sinks = Some(vec!["slack".to_string()]);
+        let err = NearPagerDutyMonitor::new(config).err().unwrap();
+        assert!(err.to_string().contains("unknown sink 'slack'"));
+    }
 
-        // Format summary
-        let summary = self.format_summary(action, subscription);
+    #[test]
+    fn test_account_patterns_and_action_kinds() {
+        let config: PagerDutyAlertConfig = serde_yaml::from_str(
+            r#"
+subscriptions:
+  - name: "Bridge sub-accounts"
+    account_id: "*.bridge.near"
+    action_kinds: [CreateAccount, DeployContract]
+"#,
+        )
+        .unwrap();
+        let sub = &config.subscriptions[0];
+        assert!(sub.is_account_pattern());
+        assert!(sub.matches_account("squatter.bridge.near"));
+        assert!(sub.matches_account("a.b.bridge.near"));
+        assert!(!sub.matches_account("bridge.near"));
+        assert!(!sub.matches_account("evilbridge.near"));
+        assert!(sub.matches_kind("CreateAccount", None));
+        assert!(!sub.matches_kind("FunctionCall", Some("ft_transfer")));
 
-        // Generate dedup key
-        let dedup_key = self.format_dedup_key(action, subscription);
+        assert!(filter::glob_matches("venear.near", "venear.near"));
+        assert!(!filter::glob_matches("venear.near", "venear.near.x"));
+        assert!(filter::glob_matches("*", "anything.near"));
+        assert!(filter::glob_matches("a*b*c", "a-b-b-c"));
+        assert!(!filter::glob_matches("ab*ba", "aba"));
+    }
 
-        // Get explorer link
-        let explorer_link = Self::get_explorer_link(action);
+    #[test]
+    fn test_account_scope_guardrails() {
+        let mut config = method_call_config("key", "*", None);
+        assert!(config.check_account_scope().is_err());
+        config.allow_broad_filters = true;
+        assert!(config.check_account_scope().is_ok());
 
-        // Create custom details
-        let custom_details = serde_json::json!({
-            "subscription_name": subscription.name,
-            "account_id": action.account_id,
-            "method_name": method_name,
-            "predecessor_id": action.predecessor_id,
-            "signer_id": action.signer_id,
-            "block_height": action.block_height,
-            "tx_hash": action.tx_hash,
-            "receipt_id": action.receipt_id,
-            "action": action.action,
-        });
+        let mut config = venear_pause_config("key", "venear.near");
+        config.allowed_accounts = vec!["*.sputnik-dao.near".to_string()];
+        assert!(config.check_account_scope().is_err());
+        config.allowed_accounts.push("venear.near".to_string());
+        assert!(config.check_account_scope().is_ok());
+        config.subscriptions[0].account_id = "hos.sputnik-dao.near".to_string();
+        assert!(config.check_account_scope().is_ok());
+    }
 
-        self.pd_client
-            .trigger(
-                &summary,
-                &format!("near:{}", action.account_id),
-                &subscription.severity,
-                dedup_key,
-                Some(custom_details),
-                explorer_link
-                    .as_ref()
-                    .map(|(h, t)| (h.as_str(), t.as_str())),
-            )
-            .await?;
+    #[test]
+    fn test_potlock_config() {
+        let config = potlock_config(
+            "key",
+            "myproject.near",
+            &["round1.v1.potfactory.potlock.near"],
+        );
+        assert_eq!(config.subscriptions.len(), 3);
+        assert!(config.dedup_key_collisions().is_empty());
 
-        Ok(())
+        let donation = |receiver: &str, args: serde_json::Value| {
+            serde_json::json!({
+                "account_id": receiver,
+                "method_name": "donate",
+                "args": args,
+            })
+        };
+        let direct = &config.subscriptions[0];
+        let fields = donation(
+            "donate.potlock.near",
+            serde_json::json!({"recipient_id": "myproject.near"}),
+        );
+        assert!(direct.post_filter.iter().all(|f| f.matches(&fields)));
+        let fields = donation(
+            "donate.potlock.near",
+            serde_json::json!({"recipient_id": "other.near"}),
+        );
+        assert!(!direct.post_filter.iter().all(|f| f.matches(&fields)));
+
+        let round = &config.subscriptions[2];
+        let challenge = serde_json::json!({"method_name": "challenge_payouts"});
+        assert!(round.post_filter.iter().all(|f| f.matches(&challenge)));
+        assert_eq!(
+            round.for_method(Some("challenge_payouts")).severity,
+            "error"
+        );
+        assert!(!round
+            .post_filter
+            .iter()
+            .all(|f| f.matches(&serde_json::json!({"method_name": "donate"}))));
     }
 
-    fn format_summary(&self, action: &NeardataAction, subscription: &EventSubscription) -> String {
-        if let Some(template) = &subscription.summary_template {
-            let method_name = match &action.action {
-                ActionType::FunctionCall(fc) => fc.method_name.clone(),
-                _ => "unknown".to_string(),
-            };
+    #[test]
+    fn test_burrow_liquidation_config() {
+        let config = burrow_liquidation_config("key", &["vault.near", "treasury.sputnik-dao.near"]);
+        let sub = &config.subscriptions[0];
+        let call = |msg: &str| {
+            serde_json::json!({
+                "method_name": "oracle_on_call",
+                "args": {"sender_id": "liquidator.near", "msg": msg},
+            })
+        };
+        let liquidate =
+            r#"{"Execute":{"actions":[{"Liquidate":{"account_id":"vault.near","in_assets":[]}}]}}"#;
+        assert!(sub.post_filter.iter().all(|f| f.matches(&call(liquidate))));
+        let force_close = r#"{"Execute":{"actions":[{"ForceClose": {"account_id": "treasury.sputnik-dao.near"}}]}}"#;
+        assert!(sub
+            .post_filter
+            .iter()
+            .all(|f| f.matches(&call(force_close))));
+        let other = r#"{"Execute":{"actions":[{"Liquidate":{"account_id":"vaultxnear"}}]}}"#;
+        assert!(!sub.post_filter.iter().all(|f| f.matches(&call(other))));
+        let borrow = r#"{"Execute":{"actions":[{"Borrow":{"token_id":"wrap.near"}}]}}"#;
+        assert!(!sub.post_filter.iter().all(|f| f.matches(&call(borrow))));
+    }
 
-            template
-                .replace("{account_id}", &action.account_id)
-                .replace("{method_name}", &method_name)
-                .replace("{predecessor_id}", action.predecessor_id.as_deref().unwrap_or("unknown"))
-                .replace("{signer_id}", action.signer_id.as_deref().unwrap_or("unknown"))
-                .replace("{block_height}", &action.block_height.to_string())
-                .replace("{tx_hash}", action.tx_hash.as_deref().unwrap_or("unknown"))
-        } else {
-            let method_name = match &action.action {
-                ActionType::FunctionCall(fc) => format!(" calling {}", fc.method_name),
-                _ => String::new(),
-            };
-            format!(
-                "{}: Action on {}{}",
-                subscription.name, action.account_id, method_name
-            )
-        }
+    #[test]
+    fn test_relayer_config() {
+        let config = relayer_config("key", "relayer.hot.tg");
+        assert!(config.dedup_key_collisions().is_empty());
+        let (keys, deleted) = (&config.subscriptions[0], &config.subscriptions[1]);
+        assert!(keys.matches_kind("DeleteKey", None));
+        assert!(!keys.matches_kind("FunctionCall", Some("ft_transfer")));
+        assert!(deleted.matches_kind("DeleteAccount", None));
+        assert_eq!(deleted.severity, "critical");
     }
 
-    fn format_dedup_key(
-        &self,
-        action: &NeardataAction,
-        subscription: &EventSubscription,
-    ) -> Option<String> {
-        if let Some(template) = &subscription.dedup_key_template {
-            let method_name = match &action.action {
-                ActionType::FunctionCall(fc) => fc.method_name.clone(),
-                _ => "unknown".to_string(),
-            };
+    #[test]
+    fn test_mpc_config() {
+        let config = mpc_config("key", "v1.signer");
+        assert_eq!(config.subscriptions.len(), 3);
+        assert!(config.dedup_key_collisions().is_empty());
+        let governance = &config.subscriptions[1];
+        let call = |method: &str| serde_json::json!({"method_name": method});
+        assert!(governance
+            .post_filter
+            .iter()
+            .all(|f| f.matches(&call("vote_update"))));
+        assert!(!governance
+            .post_filter
+            .iter()
+            .all(|f| f.matches(&call("sign"))));
+        assert!(config.subscriptions[2].matches_kind("DeployContract", None));
+    }
 
-            Some(
-                template
-                    .replace("{account_id}", &action.account_id)
-                    .replace("{method_name}", &method_name)
-                    .replace("{predecessor_id}", action.predecessor_id.as_deref().unwrap_or("unknown"))
-                    .replace("{signer_id}", action.signer_id.as_deref().unwrap_or("unknown"))
-                    .replace("{block_height}", &action.block_height.to_string())
-                    .replace("{tx_hash}", action.tx_hash.as_deref().unwrap_or("unknown"))
-                    .replace("{receipt_id}", action.receipt_id.as_deref().unwrap_or("unknown")),
-            )
-        } else {
-            // Default to tx_hash or receipt_id
-            action
-                .tx_hash
-                .clone()
-                .or_else(|| action.receipt_id.clone())
-        }
+    #[test]
+    fn test_large_transfer_config() {
+        let config = large_transfer_config("key", "usdc.near", 500_000_000_000);
+        let sub = &config.subscriptions[0];
+        let transfer = |method: &str, amount: &str| {
+            serde_json::json!({
+                "method_name": method,
+                "args": {"receiver_id": "bob.near", "amount": amount},
+            })
+        };
+        assert!(sub
+            .post_filter
+            .iter()
+            .all(|f| f.matches(&transfer("ft_transfer", "500000000000"))));
+        assert!(sub
+            .post_filter
+            .iter()
+            .all(|f| f.matches(&transfer("ft_transfer_call", "900000000000"))));
+        assert!(!sub
+            .post_filter
+            .iter()
+            .all(|f| f.matches(&transfer("ft_transfer", "499999999999"))));
+        assert!(!sub
+            .post_filter
+            .iter()
+            .all(|f| f.matches(&transfer("storage_deposit", "500000000000"))));
+        assert!(template::validate(sub.summary_template.as_deref().unwrap()).is_ok());
     }
 
-    fn get_explorer_link(action: &NeardataAction) -> Option<(String, String)> {
-        if let Some(ref tx_hash) = action.tx_hash {
-            return Some((
-                format!("https://nearblocks.io/txns/{}", tx_hash),
-                "View Transaction".to_string(),
-            ));
-        }
+    #[test]
+    fn test_events_api_url() {
+        let config = |yaml: &str| -> PagerDutyAlertConfig {
+            serde_yaml::from_str(&format!("subscriptions: []\n{}", yaml)).unwrap()
+        };
+        assert_eq!(config("").events_api_url(), "https://events.pagerduty.com");
+        let eu = config("region: eu");
+        assert_eq!(eu.events_api_url(), "https://events.eu.pagerduty.com");
+        assert_eq!(eu.region.rest_api_url(), "https://api.eu.pagerduty.com");
+        let proxied = config("region: eu\nevents_api_url: https://pd-proxy.internal");
+        assert_eq!(proxied.events_api_url(), "https://pd-proxy.internal");
+        assert!(
+            serde_yaml::from_str::<PagerDutyAlertConfig>("subscriptions: []\nregion: apac")
+                .is_err()
+        );
 
-        Some((
-            format!("https://nearblocks.io/address/{}", action.account_id),
-            "View Contract".to_string(),
-        ))
+        #[cfg(feature = "client")]
+        assert_eq!(
+            PagerDutyClient::new("key".to_string())
+                .with_events_api_url("https://pd-proxy.internal/")
+                .events_url(),
+            "https://pd-proxy.internal/v2/enqueue"
+        );
     }
-}
 
-// =============================================================================
-// Example Configurations
-// =============================================================================
+    #[cfg(feature = "http-api")]
+    #[tokio::test]
+    async fn test_resolve_many_reports_partial_failures() {
+        // An Events API that rejects keys starting with "bad"
+        async fn enqueue(
+            axum::Json(event): axum::Json<serde_json::Value>,
+        ) -> (axum::http::StatusCode, axum::Json<serde_json::Value>) {
+            let key = event["dedup_key"].as_str().unwrap_or_default();
+            if key.starts_with("bad") {
+                let body = serde_json::json!({"status": "invalid event", "message": "Event object is invalid", "errors": ["bad key"]});
+                return (axum::http::StatusCode::BAD_REQUEST, axum::Json(body));
+            }
+            let body = serde_json::json!({"status": "success", "message": "Event processed", "dedup_key": key});
+            (axum::http::StatusCode::ACCEPTED, axum::Json(body))
+        }
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let app = axum::Router::new().route("/v2/enqueue", axum::routing::post(enqueue));
+        tokio::spawn(async move { axum::serve(listener, app).await });
 
-/// Create config for monitoring veNEAR pause calls
-pub fn venear_pause_config(routing_key: &str, venear_contract: &str) -> PagerDutyAlertConfig {
-    PagerDutyAlertConfig {
-        routing_key: routing_key.to_string(),
-        reconnect_delay_secs: 5,
-        subscriptions: vec![EventSubscription {
-            name: "veNEAR: Contract Paused".to_string(),
-            account_id: venear_contract.to_string(),
-            method_name: Some("pause".to_string()),
-            severity: "critical".to_string(),
-            summary_template: Some(
-                "CRITICAL: veNEAR contract paused by {predecessor_id}".to_string(),
-            ),
-            dedup_key_template: Some("venear-pause-{tx_hash}".to_string()),
-        }],
+        let client = PagerDutyClient::new("key".to_string()).with_events_api_url(url);
+        let keys: Vec<String> = (0..20)
+            .map(|i| {
+                if i % 7 == 3 {
+                    format!("bad-{}", i)
+                } else {
+                    format!("ok-{}", i)
+                }
+            })
+            .collect();
+        let outcome = client.resolve_many(&keys).await;
+        assert_eq!(outcome.succeeded.len(), 17);
+        assert_eq!(outcome.succeeded[..3], ["ok-0", "ok-1", "ok-2"]);
+        let failed: Vec<&str> = outcome.failed.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(failed, ["bad-3", "bad-10", "bad-17"]);
+        assert!(outcome.failed[0].1.contains("bad key"));
+        assert_eq!(client.acknowledge_many(&[]).await, BatchOutcome::default());
     }
-}
 
-/// Create config for monitoring any contract method calls
-pub fn method_call_config(
-    routing_key: &str,
-    contract_id: &str,
-    method_name: Option<&str>,
-) -> PagerDutyAlertConfig {
-    PagerDutyAlertConfig {
-        routing_key: routing_key.to_string(),
-        reconnect_delay_secs: 5,
-        subscriptions: vec![EventSubscription {
-            name: format!(
-                "Contract Call: {}{}",
-                contract_id,
-                method_name.map(|m| format!("::{}", m)).unwrap_or_default()
+    #[test]
+    fn test_storage_events_config() {
+        let config = storage_events_config(
+            "key",
+            &["usdc.near", "usdt.tether-token.near"],
+            &["treasury.near"],
+        );
+        assert_eq!(config.subscriptions.len(), 4);
+        let unregister = &config.subscriptions[0];
+        assert_eq!(
+            unregister.method_name.as_deref(),
+            Some("storage_unregister")
+        );
+        let call = |method_name: &str,
+                    args: serde_json::Value,
+                    predecessor_id: &str|
+         -> NeardataAction {
+            serde_json::from_value(serde_json::json!({
+                "blockHeight": 1,
+                "accountId": "usdc.near",
+                "predecessorId": predecessor_id,
+                "status": "SUCCESS",
+                "action": {"FunctionCall": {
+                    "method_name": method_name,
+                    "args": base64::Engine::encode(&base64::engine::general_purpose::STANDARD, args.to_string()),
+                }},
+            }))
+            .unwrap()
+        };
+        let matches = |sub: &EventSubscription, action: &NeardataAction| {
+            let fields = action_fields(action);
+            sub.post_filter.iter().all(|f| f.matches(&fields))
+        };
+        let forced = call(
+            "storage_unregister",
+            serde_json::json!({"force": true}),
+            "treasury.near",
+        );
+        assert!(matches(unregister, &forced));
+        assert!(!matches(
+            unregister,
+            &call("storage_unregister", serde_json::json!({}), "alice.near")
+        ));
+        assert_eq!(
+            template::render_summary_fields(
+                unregister.summary_template.as_deref().unwrap(),
+                &action_fields(&forced)
             ),
-            account_id: contract_id.to_string(),
-            method_name: method_name.map(String::from),
-            severity: "warning".to_string(),
-            summary_template: Some(format!(
-                "Call to {} - {{method_name}} from {{predecessor_id}}",
-                contract_id
-            )),
-            dedup_key_template: Some(format!("{}-{{tx_hash}}", contract_id)),
-        }],
+            "CRITICAL: treasury.near unregistered from token {account_id} (force: true)"
+        );
+        // Anyone can register the treasury
+        let deposit = &config.subscriptions[1];
+        assert!(matches(
+            deposit,
+            &call(
+                "storage_deposit",
+                serde_json::json!({"account_id": "treasury.near"}),
+                "bob.near"
+            )
+        ));
+        assert!(!matches(
+            deposit,
+            &call("storage_deposit", serde_json::json!({}), "bob.near")
+        ));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_meta_transaction_config() {
+        let config = meta_transaction_config("key", &["venear.near"]);
+        assert!(config.check_account_scope().is_ok());
+        let sub = &config.subscriptions[0];
+        let delegate = |receiver_id: &str| -> NeardataAction {
+            serde_json::from_value(serde_json::json!({
+                "blockHeight": 1,
+                "accountId": "alice.near",
+                "signerId": "relayer.near",
+                "status": "SUCCESS",
+                "action": {"Delegate": {"delegate_action": {
+                    "sender_id": "alice.near",
+                    "receiver_id": receiver_id,
+                    "actions": [{"FunctionCall": {"method_name": "delegate_all", "args": "e30=", "gas": 1, "deposit": "0"}}],
+                }}},
+            }))
+            .unwrap()
+        };
+        let relayed = delegate("venear.near");
+        assert!(sub.matches_account(&relayed.account_id));
+        assert!(sub.matches_kind(relayed.action.kind(), None));
+        let fields = action_fields(&relayed);
+        assert!(sub.post_filter.iter().all(|f| f.matches(&fields)));
+        assert!(!sub
+            .post_filter
+            .iter()
+            .all(|f| f.matches(&action_fields(&delegate("other.near")))));
+        assert_eq!(
+            template::render_summary_fields(sub.summary_template.as_deref().unwrap(), &fields),
+            "alice.near called delegate_all on venear.near via relayer relayer.near"
+        );
+    }
 
     #[test]
-    fn test_venear_pause_config() {
-        let config = venear_pause_config("test-key", "venear.near");
-        assert_eq!(config.subscriptions.len(), 1);
-        assert_eq!(config.subscriptions[0].method_name, Some("pause".to_string()));
+    fn test_runbook_urls() {
+        let config: PagerDutyAlertConfig = serde_yaml::from_str(
+            r#"
+runbook_base_url: "https://runbooks.example.com/near/"
+subscriptions:
+  - name: "DAO Calls"
+    account_id: "dao.near"
+    runbook_url: "/dao/{method_name}"
+    method_overrides:
+      pause:
+        runbook_url: "https://wiki.example.com/dao-paused"
+  - name: "veNEAR Paused"
+    account_id: "venear.near"
+"#,
+        )
+        .unwrap();
+        let dao = &config.subscriptions[0];
+        assert_eq!(
+            config.runbook_url(dao, Some("vote")).as_deref(),
+            Some("https://runbooks.example.com/near/dao/vote")
+        );
+        assert_eq!(
+            config
+                .runbook_url(&dao.for_method(Some("pause")), Some("pause"))
+                .as_deref(),
+            Some("https://wiki.example.com/dao-paused")
+        );
+        assert_eq!(
+            config
+                .runbook_url(&config.subscriptions[1], None)
+                .as_deref(),
+            Some("https://runbooks.example.com/near/venear-paused")
+        );
+
+        let mut without_base = config.clone();
+        without_base.runbook_base_url = None;
+        assert!(without_base
+            .runbook_url(&without_base.subscriptions[1], None)
+            .is_none());
+        #[cfg(feature = "monitor")]
+        assert!(NearPagerDutyMonitor::new(without_base).is_err());
     }
 
     #[test]
-    fn test_method_call_config() {
-        let config = method_call_config("test-key", "test.near", Some("transfer"));
-        assert_eq!(config.subscriptions.len(), 1);
+    fn test_named_templates() {
+        let mut config: PagerDutyAlertConfig = serde_yaml::from_str(
+            r#"
+templates:
+  dao_summary: "DAO {method_name} by {predecessor_id}"
+  per_tx: "dao-{tx_hash}"
+subscriptions:
+  - name: "DAO"
+    account_id: "dao.near"
+    summary_template: "@dao_summary"
+    dedup_key_template: "@per_tx"
+    method_overrides:
+      pause:
+        summary_template: "@@mention: paused"
+"#,
+        )
+        .unwrap();
+        config.resolve_templates().unwrap();
+        let sub = &config.subscriptions[0];
         assert_eq!(
-            config.subscriptions[0].method_name,
-            Some("transfer".to_string())
+            sub.summary_template.as_deref(),
+            Some("DAO {method_name} by {predecessor_id}")
+        );
+        assert_eq!(sub.dedup_key_template.as_deref(), Some("dao-{tx_hash}"));
+        assert_eq!(
+            sub.method_overrides["pause"].summary_template.as_deref(),
+            Some("@mention: paused")
         );
+
+        config.subscriptions[0].summary_template = Some("@missing".to_string());
+        let error = config.resolve_templates().unwrap_err().to_string();
+        assert!(error.contains("unknown template '@missing'"));
     }
 }
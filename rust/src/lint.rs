@@ -0,0 +1,312 @@
+//! Flagging risky subscription settings before they page anyone
+//!
+//! Each check is a [`LintRule`]; [`Linter::new`] runs the built-in ones and
+//! [`Linter::with_rule`] adds more. Findings are advice, not errors: a
+//! subscription that trips a rule still loads and alerts.
+
+use std::fmt;
+
+use crate::{EventSubscription, PagerDutyAlertConfig};
+
+/// Methods called often enough that one incident per call floods PagerDuty
+const HIGH_VOLUME_METHODS: &[&str] = &[
+    "ft_transfer",
+    "ft_transfer_call",
+    "ft_on_transfer",
+    "ft_resolve_transfer",
+    "storage_deposit",
+    "near_deposit",
+    "near_withdraw",
+    "nft_transfer",
+    "nft_transfer_call",
+    "nft_mint",
+];
+
+/// Placeholders that differ for every event
+const PER_EVENT_PLACEHOLDERS: &[&str] = &["{tx_hash}", "{receipt_id}", "{block_height}"];
+
+/// A check of one subscription
+pub trait LintRule: Send + Sync {
+    /// Identifier printed with each finding, e.g. `missing-dedup-key`
+    fn name(&self) -> &'static str;
+    /// Why the pattern is risky and what to do instead
+    fn explanation(&self) -> &'static str;
+    /// What is wrong with the subscription, one entry per problem
+    fn check(&self, subscription: &EventSubscription) -> Vec<String>;
+}
+
+/// One rule tripped by one subscription
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintFinding {
+    pub rule: &'static str,
+    pub subscription: String,
+    pub message: String,
+    pub explanation: &'static str,
+}
+
+/// Everything the rules found in a configuration
+#[derive(Debug, Clone, Default)]
+pub struct LintReport {
+    pub findings: Vec<LintFinding>,
+}
+
+impl LintReport {
+    pub fn is_empty(&self) -> bool {
+        self.findings.is_empty()
+    }
+}
+
+impl fmt::Display for LintReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.findings.is_empty() {
+            return writeln!(f, "No lint findings");
+        }
+        writeln!(f, "{} lint finding(s)", self.findings.len())?;
+        for finding in &self.findings {
+            writeln!(
+                f,
+                "  [{}] '{}': {}",
+                finding.rule, finding.subscription, finding.message
+            )?;
+            writeln!(f, "      {}", finding.explanation)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs lint rules over every subscription
+pub struct Linter {
+    rules: Vec<Box<dyn LintRule>>,
+}
+
+impl Default for Linter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Linter {
+    /// A linter with the built-in rules
+    pub fn new() -> Self {
+        Self {
+            rules: vec![
+                Box::new(CriticalPerEventDedup),
+                Box::new(MissingDedupKey),
+                Box::new(CollapsedCritical),
+            ],
+        }
+    }
+
+    /// Also run `rule`
+    pub fn with_rule(mut self, rule: impl LintRule + 'static) -> Self {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    pub fn lint(&self, config: &PagerDutyAlertConfig) -> LintReport {
+        let mut report = LintReport::default();
+        for subscription in &config.subscriptions {
+            for rule in &self.rules {
+                report
+                    .findings
+                    .extend(
+                        rule.check(subscription)
+                            .into_iter()
+                            .map(|message| LintFinding {
+                                rule: rule.name(),
+                                subscription: subscription.name.clone(),
+                                message,
+                                explanation: rule.explanation(),
+                            }),
+                    );
+            }
+        }
+        report
+    }
+}
+
+/// The method, severity and dedup key template of the subscription and of
+/// each of its `method_overrides`
+fn variants(subscription: &EventSubscription) -> Vec<(Option<&str>, &str, Option<&str>)> {
+    let base = (
+        subscription.method_name.as_deref(),
+        subscription.severity.as_str(),
+        subscription.dedup_key_template.as_deref(),
+    );
+    let overrides = subscription.method_overrides.iter().map(|(method, o)| {
+        (
+            Some(method.as_str()),
+            o.severity.as_deref().unwrap_or(base.1),
+            o.dedup_key_template.as_deref().or(base.2),
+        )
+    });
+    std::iter::once(base).chain(overrides).collect()
+}
+
+/// Whether `method` on the subscription's accounts is likely to match often
+fn high_volume(subscription: &EventSubscription, method: Option<&str>) -> Option<String> {
+    match method {
+        Some(method) if HIGH_VOLUME_METHODS.contains(&method) => Some(method.to_string()),
+        Some(_) => None,
+        None if subscription.action_kinds.is_empty() => {
+            Some(format!("every action on {}", subscription.account_id))
+        }
+        None => None,
+    }
+}
+
+/// Critical alerts keyed per event on something that happens all the time
+pub struct CriticalPerEventDedup;
+
+impl LintRule for CriticalPerEventDedup {
+    fn name(&self) -> &'static str {
+        "critical-per-event-dedup"
+    }
+
+    fn explanation(&self) -> &'static str {
+        "Every matching event opens its own critical incident, so a busy hour pages the on-call hundreds of times. \
+         Key the alert on what it is about (e.g. {account_id}), lower the severity, or set collapse_duplicate_summaries."
+    }
+
+    fn check(&self, subscription: &EventSubscription) -> Vec<String> {
+        if subscription.correlation_key_template.is_some() {
+            return Vec::new();
+        }
+        variants(subscription)
+            .into_iter()
+            .filter(|(_, severity, _)| *severity == "critical")
+            .filter_map(|(method, _, template)| {
+                let template = template?;
+                let placeholder = PER_EVENT_PLACEHOLDERS
+                    .iter()
+                    .find(|p| template.contains(*p))?;
+                let volume = high_volume(subscription, method)?;
+                Some(format!(
+                    "critical alerts on {} keyed per event by {} in '{}'",
+                    volume, placeholder, template
+                ))
+            })
+            .collect()
+    }
+}
+
+/// Alerts without any dedup key
+pub struct MissingDedupKey;
+
+impl LintRule for MissingDedupKey {
+    fn name(&self) -> &'static str {
+        "missing-dedup-key"
+    }
+
+    fn explanation(&self) -> &'static str {
+        "PagerDuty opens a new incident for each event, and the monitor can't track, follow up on \
+         or resolve them. Set dedup_key_template or correlation_key_template."
+    }
+
+    fn check(&self, subscription: &EventSubscription) -> Vec<String> {
+        if subscription.correlation_key_template.is_some() {
+            return Vec::new();
+        }
+        variants(subscription)
+            .into_iter()
+            .filter(|(_, _, template)| template.is_none())
+            .map(|(method, _, _)| match method {
+                Some(method) if subscription.method_overrides.contains_key(method) => {
+                    format!("no dedup key for {}", method)
+                }
+                _ => "no dedup key".to_string(),
+            })
+            .collect()
+    }
+}
+
+/// Critical alerts folded into earlier ones by summary
+pub struct CollapsedCritical;
+
+impl LintRule for CollapsedCritical {
+    fn name(&self) -> &'static str {
+        "collapsed-critical"
+    }
+
+    fn explanation(&self) -> &'static str {
+        "collapse_duplicate_summaries folds a critical event into an incident that may already be acknowledged, \
+         so a second occurrence only bumps a count. Collapse lower severities, or give critical events their own keys."
+    }
+
+    fn check(&self, subscription: &EventSubscription) -> Vec<String> {
+        if subscription.collapse_duplicate_summaries.is_none() {
+            return Vec::new();
+        }
+        let critical = variants(subscription)
+            .into_iter()
+            .any(|(_, severity, _)| severity == "critical");
+        if critical {
+            vec!["critical alerts with collapse_duplicate_summaries".to_string()]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lint_rules() {
+        let mut config = crate::venear_pause_config("routing-key", "venear.near");
+        let mut subscription = config.subscriptions[0].clone();
+        subscription.name = "Transfers".to_string();
+        subscription.method_name = Some("ft_transfer".to_string());
+        subscription.severity = "critical".to_string();
+        subscription.dedup_key_template = Some("transfer-{tx_hash}".to_string());
+        subscription.correlation_key_template = None;
+        subscription.collapse_duplicate_summaries = None;
+        subscription.method_overrides.clear();
+        config.subscriptions = vec![subscription.clone()];
+
+        let rules = |config: &PagerDutyAlertConfig| -> Vec<&'static str> {
+            Linter::new()
+                .lint(config)
+                .findings
+                .iter()
+                .map(|f| f.rule)
+                .collect()
+        };
+        assert_eq!(rules(&config), vec!["critical-per-event-dedup"]);
+
+        // A rare method isn't high volume
+        config.subscriptions[0].method_name = Some("pause".to_string());
+        assert!(Linter::new().lint(&config).is_empty());
+
+        config.subscriptions[0].dedup_key_template = None;
+        config.subscriptions[0].collapse_duplicate_summaries =
+            Some(crate::DuplicateSummaryConfig { window_secs: 300 });
+        assert_eq!(
+            rules(&config),
+            vec!["missing-dedup-key", "collapsed-critical"]
+        );
+
+        struct NoOwner;
+        impl LintRule for NoOwner {
+            fn name(&self) -> &'static str {
+                "no-owner"
+            }
+            fn explanation(&self) -> &'static str {
+                "Nobody knows who to ask"
+            }
+            fn check(&self, subscription: &EventSubscription) -> Vec<String> {
+                match subscription.owner {
+                    Some(_) => Vec::new(),
+                    None => vec!["no owner".to_string()],
+                }
+            }
+        }
+        config.subscriptions = vec![subscription];
+        let report = Linter::new().with_rule(NoOwner).lint(&config);
+        assert_eq!(report.findings.last().unwrap().rule, "no-owner");
+        assert!(report
+            .to_string()
+            .starts_with("2 lint finding(s)\n  [critical-per-event-dedup] 'Transfers'"));
+    }
+}
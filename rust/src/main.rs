@@ -1,15 +1,135 @@
 //! Main entry point for the NEAR PagerDuty Monitor binary
 
-use axum::{routing::get, Router};
+use clap::{Args, Parser, Subcommand};
 use near_pagerduty_alerts::venear_pause_config;
-use near_pagerduty_alerts::PagerDutyAlertConfig;
+use near_pagerduty_alerts::{
+    AlertEvent, AlertStore, ConfigDiff, Linter, MonitorExit, PagerDutyAlertConfig, PagerDutyClient,
+};
 use std::future::IntoFuture;
 use std::net::SocketAddr;
 use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Parser)]
+#[command(version, about = "NEAR Blockchain Event to PagerDuty Alert Bridge")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the monitor (default when no command is given)
+    Run,
+    /// Resolve a PagerDuty alert by dedup key
+    Resolve { dedup_key: String },
+    /// Acknowledge a PagerDuty alert by dedup key
+    Ack { dedup_key: String },
+    /// Resolve every open alert in the configured state file
+    ResolveAll {
+        /// Only resolve alerts of this subscription
+        #[arg(long)]
+        subscription: Option<String>,
+    },
+    /// Run the startup preflight checks and exit
+    Check,
+    /// Fetch a past block range from neardata and alert on matching actions
+    Replay {
+        /// First block height
+        from_block: u64,
+        /// Last block height (inclusive)
+        to_block: u64,
+        /// Network to replay
+        #[arg(long, default_value = "mainnet")]
+        network: String,
+    },
+    /// Run one subscription over a past block range from its network's
+    /// history_url, e.g. after the monitor was down
+    Backfill {
+        /// First block height
+        #[arg(long)]
+        from_block: u64,
+        /// Last block height (inclusive)
+        #[arg(long)]
+        to_block: u64,
+        /// Subscription to run
+        #[arg(long)]
+        subscription: String,
+        /// List what would be sent without paging or touching the state file
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Write the matches in a past block range to CSV files partitioned by date, without alerting
+    Export {
+        /// First block height
+        #[arg(long)]
+        from_block: u64,
+        /// Last block height (inclusive)
+        #[arg(long)]
+        to_block: u64,
+        /// Only export this subscription (default: all)
+        #[arg(long)]
+        subscription: Option<String>,
+        /// Directory the `date=YYYY-MM-DD` partitions are written under
+        #[arg(long)]
+        out: String,
+    },
+    /// List matches recorded in the configured event_store, newest first
+    Query {
+        #[command(flatten)]
+        filter: EventFilter,
+        /// Most matches listed
+        #[arg(long, default_value_t = 100)]
+        limit: usize,
+        /// Print one JSON object per match, with the full action
+        #[arg(long)]
+        json: bool,
+    },
+    /// Run the actions of matches recorded in the event_store through the
+    /// current subscriptions again, oldest first, alerting on new matches
+    Reprocess {
+        #[command(flatten)]
+        filter: EventFilter,
+    },
+    /// Show which subscriptions would have alerted on a past transaction, and why the others didn't
+    ExplainTx {
+        tx_hash: String,
+        /// Account that signed the transaction, which the RPC needs to find it
+        #[arg(long)]
+        signer: String,
+        /// Network the transaction is on
+        #[arg(long, default_value = "mainnet")]
+        network: String,
+        /// Print one JSON object per action, with every subscription's verdict
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show what changed between two config files
+    Diff {
+        old: String,
+        new: String,
+        /// Also record the change as a PagerDuty change event (uses the new config's routing key)
+        #[arg(long)]
+        notify: bool,
+    },
+    /// Flag risky subscription settings, such as critical alerts keyed per
+    /// event on busy methods; exits non-zero when anything is found
+    Lint {
+        /// Config file to lint (default: the usual lookup)
+        config: Option<String>,
+    },
+    /// List alerts the monitor considers open, from the configured state file
+    ListAlerts {
+        /// Include resolved, expired and failed alerts
+        #[arg(long)]
+        all: bool,
+    },
+}
 
 fn load_config_from_file(path: &str) -> Result<PagerDutyAlertConfig, anyhow::Error> {
     let content = std::fs::read_to_string(path)?;
-    let mut config: PagerDutyAlertConfig = serde_yaml::from_str(&content)?;
+    let mut config = PagerDutyAlertConfig::from_yaml(&content)?;
 
     // If routing key is not in config file, get it from environment variable
     if config.routing_key.is_empty() {
@@ -21,19 +141,481 @@ fn load_config_from_file(path: &str) -> Result<PagerDutyAlertConfig, anyhow::Err
         log::info!("Using PAGERDUTY_ROUTING_KEY from environment variable");
     }
 
+    if config.pagerduty_api_token.is_none() {
+        config.pagerduty_api_token = std::env::var("PAGERDUTY_API_TOKEN").ok();
+    }
+    if config.pagerduty_from_email.is_none() {
+        config.pagerduty_from_email = std::env::var("PAGERDUTY_FROM_EMAIL").ok();
+    }
+
     Ok(config)
 }
 
-/// Health check endpoint
-async fn health() -> &'static str {
-    "OK"
+/// Which event_store matches `query` and `reprocess` read
+#[derive(Args)]
+struct EventFilter {
+    /// Only this subscription
+    #[arg(long)]
+    subscription: Option<String>,
+    /// Only actions on this account
+    #[arg(long)]
+    account: Option<String>,
+    /// Matched at or after this time (RFC 3339, or a UTC date such as 2026-10-06)
+    #[arg(long)]
+    since: Option<String>,
+    /// Matched before this time (same formats as --since)
+    #[arg(long)]
+    until: Option<String>,
+    /// Only matches whose alert ended up queued, delivered, failed, dropped,
+    /// suppressed or change_event
+    #[arg(long)]
+    outcome: Option<String>,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
     env_logger::init();
+    let cli = Cli::parse();
+
+    match cli.command.unwrap_or(Command::Run) {
+        Command::Run => run(load_config()?).await,
+        Command::Resolve { dedup_key } => {
+            let config = load_config()?;
+            let client = PagerDutyClient::new(config.routing_key.clone())
+                .with_events_api_url(config.events_api_url());
+            let response = client.resolve(&dedup_key).await?;
+            println!("{}: {}", response.status, response.message);
+            record_in_state_file(&config, &dedup_key, AlertEvent::Resolve)
+        }
+        Command::Ack { dedup_key } => {
+            let config = load_config()?;
+            let client = PagerDutyClient::new(config.routing_key.clone())
+                .with_events_api_url(config.events_api_url());
+            let response = client.acknowledge(&dedup_key).await?;
+            println!("{}: {}", response.status, response.message);
+            record_in_state_file(&config, &dedup_key, AlertEvent::Acknowledge)
+        }
+        Command::ResolveAll { subscription } => {
+            resolve_all(&load_config()?, subscription.as_deref()).await
+        }
+        Command::Check => {
+            near_pagerduty_alerts::preflight::run(&load_config()?).await?;
+            println!("All preflight checks passed");
+            Ok(())
+        }
+        Command::Replay {
+            from_block,
+            to_block,
+            network,
+        } => {
+            let config = load_config()?;
+            let drain_timeout = Duration::from_secs(config.drain_timeout_secs);
+            let monitor = near_pagerduty_alerts::NearPagerDutyMonitor::new(config)?;
+            let matched = monitor.replay(&network, from_block..=to_block).await?;
+            let undelivered = monitor.drain(drain_timeout).await;
+            println!(
+                "Matched {} action(s) in blocks {}..={}",
+                matched, from_block, to_block
+            );
+            if undelivered > 0 {
+                anyhow::bail!("{} alert(s) were not delivered", undelivered);
+            }
+            Ok(())
+        }
+        Command::Backfill {
+            from_block,
+            to_block,
+            subscription,
+            dry_run,
+        } => {
+            let mut config = load_config()?;
+            if dry_run {
+                // Leave the state file, possibly a running monitor's, alone
+                config.state_file = None;
+            }
+            let drain_timeout = Duration::from_secs(config.drain_timeout_secs);
+            let monitor = near_pagerduty_alerts::NearPagerDutyMonitor::new(config)?;
+            let matches = monitor
+                .backfill_subscription(&subscription, from_block..=to_block, dry_run)
+                .await?;
+            for m in &matches {
+                println!(
+                    "{:>10}  {:<8}  {}  [{}]",
+                    m.block_height,
+                    m.severity,
+                    m.summary,
+                    m.dedup_key
+                        .as_deref()
+                        .or(m.tx_hash.as_deref())
+                        .unwrap_or("-")
+                );
+            }
+            let undelivered = if dry_run {
+                0
+            } else {
+                monitor.drain(drain_timeout).await
+            };
+            println!(
+                "{} {} action(s) in blocks {}..={}",
+                if dry_run { "Would alert on" } else { "Matched" },
+                matches.len(),
+                from_block,
+                to_block
+            );
+            if undelivered > 0 {
+                anyhow::bail!("{} alert(s) were not delivered", undelivered);
+            }
+            Ok(())
+        }
+        Command::Export {
+            from_block,
+            to_block,
+            subscription,
+            out,
+        } => {
+            let mut config = load_config()?;
+            config.state_file = None;
+            let monitor = near_pagerduty_alerts::NearPagerDutyMonitor::new(config)?;
+            let rows = monitor
+                .export(subscription.as_deref(), from_block..=to_block, &out)
+                .await?;
+            println!(
+                "Exported {} match(es) in blocks {}..={} to {}",
+                rows, from_block, to_block, out
+            );
+            Ok(())
+        }
+        Command::Query {
+            filter,
+            limit,
+            json,
+        } => query_events(&load_config()?, filter, limit, json),
+        Command::Reprocess { filter } => reprocess(load_config()?, filter).await,
+        Command::ExplainTx {
+            tx_hash,
+            signer,
+            network,
+            json,
+        } => explain_tx(load_config()?, &network, &tx_hash, &signer, json).await,
+        Command::Diff { old, new, notify } => diff_configs(&old, &new, notify).await,
+        Command::ListAlerts { all } => list_alerts(&load_config()?, all),
+        Command::Lint { config } => {
+            let config = match config {
+                Some(path) => load_config_from_file(&path)?,
+                None => load_config()?,
+            };
+            let report = Linter::new().lint(&config);
+            print!("{}", report);
+            if !report.is_empty() {
+                anyhow::bail!("{} lint finding(s)", report.findings.len());
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Mirror a CLI acknowledge/resolve into the state file, if one is configured
+fn record_in_state_file(
+    config: &PagerDutyAlertConfig,
+    dedup_key: &str,
+    event: AlertEvent,
+) -> Result<(), anyhow::Error> {
+    let Some(path) = &config.state_file else {
+        return Ok(());
+    };
+    let store = AlertStore::open(path)?;
+    if let Err(e) = store.apply(dedup_key, event) {
+        log::warn!("Local alert state not updated: {}", e);
+    }
+    Ok(())
+}
+
+async fn resolve_all(
+    config: &PagerDutyAlertConfig,
+    subscription: Option<&str>,
+) -> Result<(), anyhow::Error> {
+    let path = config
+        .state_file
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("No state_file configured; open alerts are not known"))?;
+    let store = AlertStore::open(path)?;
+    let client = PagerDutyClient::new(config.routing_key.clone())
+        .with_events_api_url(config.events_api_url());
+    let outcome = near_pagerduty_alerts::resolve_open_alerts(&client, &store, subscription).await;
+    for dedup_key in &outcome.resolved {
+        println!("resolved {}", dedup_key);
+    }
+    for (dedup_key, error) in &outcome.failed {
+        println!("failed   {}: {}", dedup_key, error);
+    }
+    if !outcome.failed.is_empty() {
+        anyhow::bail!("{} alert(s) could not be resolved", outcome.failed.len());
+    }
+    println!("Resolved {} alert(s)", outcome.resolved.len());
+    Ok(())
+}
+
+async fn diff_configs(old: &str, new: &str, notify: bool) -> Result<(), anyhow::Error> {
+    let old_config = load_config_from_file(old)?;
+    let new_config = load_config_from_file(new)?;
+    let diff = ConfigDiff::between(&old_config, &new_config);
+    print!("{}", diff);
+    if notify && !diff.is_empty() {
+        let client = PagerDutyClient::new(new_config.routing_key.clone())
+            .with_events_api_url(new_config.events_api_url());
+        client
+            .send_change(
+                &diff.summary(),
+                "near-pagerduty-monitor",
+                Some(serde_json::to_value(&diff)?),
+            )
+            .await?;
+        log::info!("Configuration change recorded in PagerDuty");
+    }
+    Ok(())
+}
+
+fn list_alerts(config: &PagerDutyAlertConfig, all: bool) -> Result<(), anyhow::Error> {
+    let path = config
+        .state_file
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("No state_file configured; alert state is not persisted"))?;
+    let mut records = AlertStore::read_records(Path::new(path))?;
+    records.retain(|r| all || r.state.is_open());
+
+    if records.is_empty() {
+        println!("No open alerts");
+        return Ok(());
+    }
+
+    println!(
+        "{:<40} {:<32} {:<9} {:<20} {:<20} {:<12}",
+        "DEDUP KEY", "SUBSCRIPTION", "SEVERITY", "FIRST SEEN", "LAST SEEN", "STATE"
+    );
+    for r in records {
+        println!(
+            "{:<40} {:<32} {:<9} {:<20} {:<20} {:<12}",
+            r.dedup_key,
+            r.subscription,
+            r.severity,
+            r.first_seen.format("%Y-%m-%d %H:%M:%S"),
+            r.last_seen.format("%Y-%m-%d %H:%M:%S"),
+            format!("{:?}", r.state).to_lowercase(),
+        );
+    }
+    Ok(())
+}
+
+#[cfg(feature = "sqlite")]
+impl EventFilter {
+    fn query(
+        self,
+        limit: Option<usize>,
+    ) -> Result<near_pagerduty_alerts::EventQuery, anyhow::Error> {
+        Ok(near_pagerduty_alerts::EventQuery {
+            subscription: self.subscription,
+            account_id: self.account,
+            since: self.since.as_deref().map(parse_time).transpose()?,
+            until: self.until.as_deref().map(parse_time).transpose()?,
+            outcome: self.outcome.as_deref().map(str::parse).transpose()?,
+            limit,
+        })
+    }
+}
 
-    // Try to load config from config.yaml, fallback to environment variable + hardcoded config
+#[cfg(feature = "sqlite")]
+fn query_events(
+    config: &PagerDutyAlertConfig,
+    filter: EventFilter,
+    limit: usize,
+    json: bool,
+) -> Result<(), anyhow::Error> {
+    let store_config = config
+        .event_store
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("No event_store configured; matches are not recorded"))?;
+    let store = near_pagerduty_alerts::EventStore::open(
+        &store_config.path,
+        store_config.retention_days,
+        store_config.max_size_bytes(),
+    )?;
+    let events = store.query(&filter.query(Some(limit))?)?;
+
+    if json {
+        for event in &events {
+            println!("{}", serde_json::to_string(event)?);
+        }
+        return Ok(());
+    }
+    if events.is_empty() {
+        println!("No matching events");
+        return Ok(());
+    }
+    println!(
+        "{:<20} {:<32} {:<9} {:<12} {:<12} SUMMARY",
+        "TIME", "SUBSCRIPTION", "SEVERITY", "BLOCK", "OUTCOME"
+    );
+    for event in events {
+        println!(
+            "{:<20} {:<32} {:<9} {:<12} {:<12} {}",
+            event.time.format("%Y-%m-%d %H:%M:%S"),
+            event.subscription,
+            event.severity,
+            event.block_height,
+            event.outcome,
+            event.summary,
+        );
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn query_events(
+    _config: &PagerDutyAlertConfig,
+    _filter: EventFilter,
+    _limit: usize,
+    _json: bool,
+) -> Result<(), anyhow::Error> {
+    anyhow::bail!("query needs the event store, but this build lacks the `sqlite` feature")
+}
+
+#[cfg(feature = "sqlite")]
+async fn reprocess(config: PagerDutyAlertConfig, filter: EventFilter) -> Result<(), anyhow::Error> {
+    if config.event_store.is_none() {
+        anyhow::bail!("No event_store configured; matches are not recorded");
+    }
+    let drain_timeout = Duration::from_secs(config.drain_timeout_secs);
+    let monitor = near_pagerduty_alerts::NearPagerDutyMonitor::new(config)?;
+    let outcome = monitor.reprocess(&filter.query(None)?).await?;
+    let undelivered = monitor.drain(drain_timeout).await;
+    println!(
+        "Reprocessed {} action(s), {} match(es); {} recorded without the action were skipped",
+        outcome.actions, outcome.matched, outcome.skipped
+    );
+    if undelivered > 0 {
+        anyhow::bail!("{} alert(s) were not delivered", undelivered);
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "sqlite"))]
+async fn reprocess(
+    _config: PagerDutyAlertConfig,
+    _filter: EventFilter,
+) -> Result<(), anyhow::Error> {
+    anyhow::bail!("reprocess needs the event store, but this build lacks the `sqlite` feature")
+}
+
+async fn explain_tx(
+    mut config: PagerDutyAlertConfig,
+    network: &str,
+    tx_hash: &str,
+    signer: &str,
+    json: bool,
+) -> Result<(), anyhow::Error> {
+    use near_pagerduty_alerts::MatchVerdict;
+
+    // Leave the state file, possibly a running monitor's, alone
+    config.state_file = None;
+    let monitor = near_pagerduty_alerts::NearPagerDutyMonitor::new(config)?;
+    let actions = monitor
+        .explain_transaction(network, tx_hash, signer)
+        .await?;
+    if json {
+        for action in &actions {
+            println!("{}", serde_json::to_string(action)?);
+        }
+        return Ok(());
+    }
+
+    let mut alerts = 0;
+    for action in &actions {
+        println!(
+            "Block {} receipt {}: {}{} on {} from {} ({})",
+            action.block_height,
+            action.receipt_id.as_deref().unwrap_or("-"),
+            action.action_kind,
+            action
+                .method_name
+                .as_deref()
+                .map(|m| format!(" {}", m))
+                .unwrap_or_default(),
+            action.account_id,
+            action.predecessor_id.as_deref().unwrap_or("unknown"),
+            action.status,
+        );
+        let mut other_accounts = 0;
+        for v in &action.verdicts {
+            let reason = match &v.verdict {
+                MatchVerdict::Matched {
+                    severity,
+                    summary,
+                    dedup_key,
+                } => {
+                    alerts += 1;
+                    println!(
+                        "  ALERT  {}: [{}] {} ({})",
+                        v.subscription,
+                        severity,
+                        summary,
+                        dedup_key.as_deref().unwrap_or("no dedup key")
+                    );
+                    continue;
+                }
+                MatchVerdict::OtherAccount => {
+                    other_accounts += 1;
+                    continue;
+                }
+                MatchVerdict::Unsuccessful => {
+                    "the action failed; only successful actions alert".to_string()
+                }
+                MatchVerdict::KindOrMethod => "action_kinds or method_name exclude it".to_string(),
+                MatchVerdict::PostFilter { condition } => {
+                    format!("post_filter {} does not hold", condition)
+                }
+                MatchVerdict::Script => "should_alert returned false".to_string(),
+                MatchVerdict::Deduplicated { alerted_by } => {
+                    format!(
+                        "dedup_across_subscriptions alerts for '{}' instead",
+                        alerted_by
+                    )
+                }
+            };
+            println!("  skip   {}: {}", v.subscription, reason);
+        }
+        if other_accounts > 0 {
+            println!(
+                "  ({} subscription(s) watch other accounts)",
+                other_accounts
+            );
+        }
+    }
+    println!(
+        "{} action(s), {} alert(s) would have been sent",
+        actions.len(),
+        alerts
+    );
+    Ok(())
+}
+
+/// An RFC 3339 time, or the start of a UTC date
+#[cfg(feature = "sqlite")]
+fn parse_time(value: &str) -> Result<chrono::DateTime<chrono::Utc>, anyhow::Error> {
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(time.to_utc());
+    }
+    let date = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| {
+        anyhow::anyhow!(
+            "'{}' is neither an RFC 3339 time nor a YYYY-MM-DD date",
+            value
+        )
+    })?;
+    Ok(date.and_time(chrono::NaiveTime::MIN).and_utc())
+}
+
+/// Load config.yaml if present, otherwise fall back to the environment and the
+/// hardcoded veNEAR pause monitor configuration
+fn load_config() -> Result<PagerDutyAlertConfig, anyhow::Error> {
     let config = if Path::new("config.yaml").exists() {
         log::info!("Loading configuration from config.yaml");
         load_config_from_file("config.yaml")?
@@ -51,23 +633,21 @@ async fn main() -> Result<(), anyhow::Error> {
 
         venear_pause_config(&routing_key, &venear_contract)
     };
+    Ok(config)
+}
 
+async fn run(config: PagerDutyAlertConfig) -> Result<(), anyhow::Error> {
     log::info!(
         "Starting NEAR action monitor with {} subscription(s)",
         config.subscriptions.len()
     );
-
-    for sub in &config.subscriptions {
-        log::info!(
-            "  - {}: account={}, method={:?}",
-            sub.name,
-            sub.account_id,
-            sub.method_name
-        );
+    for line in config.effective_summary() {
+        log::info!("{}", line);
     }
 
-    // Start HTTP server for health checks
-    let app = Router::new().route("/health", get(health));
+    if config.preflight {
+        near_pagerduty_alerts::preflight::run(&config).await?;
+    }
 
     let port: u16 = std::env::var("PORT")
         .unwrap_or_else(|_| "8080".to_string())
@@ -78,16 +658,51 @@ async fn main() -> Result<(), anyhow::Error> {
     log::info!("Starting HTTP server on {}", addr);
 
     // Run HTTP server and monitor concurrently
-    let monitor = near_pagerduty_alerts::NearPagerDutyMonitor::new(config);
+    let drain_timeout = Duration::from_secs(config.drain_timeout_secs);
+    let monitor = Arc::new(near_pagerduty_alerts::NearPagerDutyMonitor::new(config)?);
+    let app = near_pagerduty_alerts::http::router(Arc::clone(&monitor));
+    // The server keeps running while draining so /api/status shows progress
+    let mut server =
+        tokio::spawn(axum::serve(tokio::net::TcpListener::bind(addr).await?, app).into_future());
 
     tokio::select! {
-        result = axum::serve(tokio::net::TcpListener::bind(addr).await?, app).into_future() => {
+        result = &mut server => {
             log::error!("HTTP server exited: {:?}", result);
         }
+        _ = shutdown_signal() => {
+            // Dropping the monitor future stops reading events; queued alerts remain
+            log::info!("Shutdown requested, no longer reading events");
+        }
         result = monitor.start() => {
             log::error!("Monitor exited: {:?}", result);
+            // Let a supervisor such as systemd decide what happens next
+            if let Some(exit) = result.as_ref().err().and_then(|e| e.downcast_ref::<MonitorExit>()) {
+                log::error!("{}", exit);
+                monitor.drain(drain_timeout).await;
+                std::process::exit(exit.exit_code());
+            }
         }
     }
 
+    monitor.drain(drain_timeout).await;
     Ok(())
 }
+
+/// Resolve on SIGTERM (sent by Kubernetes, systemd and Docker on stop) or Ctrl-C
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                tokio::select! {
+                    _ = sigterm.recv() => {}
+                    _ = tokio::signal::ctrl_c() => {}
+                }
+                return;
+            }
+            Err(e) => log::warn!("Cannot listen for SIGTERM: {:?}", e),
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
+}
@@ -0,0 +1,192 @@
+//! Token price alerts
+//!
+//! neardata only carries on-chain actions, so prices are polled from an HTTP
+//! feed (Intear's price API by default). Each [`MarketAlert`] triggers when
+//! its token crosses a threshold or moves sharply within a window, and
+//! resolves once the condition no longer holds.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+use crate::MarketAlert;
+
+/// Client for a token price feed
+pub(crate) struct PriceFeed {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl PriceFeed {
+    pub fn new(url: impl Into<String>) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .expect("Failed to build HTTP client");
+        Self {
+            client,
+            url: url.into(),
+        }
+    }
+
+    /// Current USD price by token ID
+    pub async fn prices(&self) -> Result<HashMap<String, f64>, anyhow::Error> {
+        let response = self.client.get(&self.url).send().await?;
+        if !response.status().is_success() {
+            anyhow::bail!("price feed {} returned {}", self.url, response.status());
+        }
+        Ok(parse_prices(&response.json().await?))
+    }
+}
+
+/// Read a `{token_id: price}` object, where a price is a number, a numeric
+/// string, or an object with a `price` field
+fn parse_prices(body: &Value) -> HashMap<String, f64> {
+    let Some(tokens) = body.as_object() else {
+        return HashMap::new();
+    };
+    tokens
+        .iter()
+        .filter_map(|(token_id, value)| {
+            let price = match value {
+                Value::Object(fields) => fields.get("price")?,
+                value => value,
+            };
+            let price = match price {
+                Value::Number(n) => n.as_f64()?,
+                Value::String(s) => s.parse().ok()?,
+                _ => return None,
+            };
+            Some((token_id.clone(), price))
+        })
+        .collect()
+}
+
+/// A change in whether a market alert's conditions hold
+#[derive(Debug, PartialEq)]
+pub(crate) enum MarketTransition {
+    Triggered { summary: String, details: Value },
+    Recovered,
+}
+
+/// Price history and alert state for one [`MarketAlert`]
+pub(crate) struct MarketWatch {
+    pub alert: MarketAlert,
+    /// Prices seen within `window_secs`, oldest first
+    history: VecDeque<(Instant, f64)>,
+    firing: bool,
+}
+
+impl MarketWatch {
+    pub fn new(alert: MarketAlert) -> Self {
+        Self {
+            alert,
+            history: VecDeque::new(),
+            firing: false,
+        }
+    }
+
+    pub fn observe(&mut self, price: f64, now: Instant) -> Option<MarketTransition> {
+        let window = Duration::from_secs(self.alert.window_secs);
+        while self
+            .history
+            .front()
+            .is_some_and(|(at, _)| now.saturating_duration_since(*at) > window)
+        {
+            self.history.pop_front();
+        }
+        self.history.push_back((now, price));
+
+        let (_, window_start) = self.history[0];
+        let change_pct = if window_start > 0.0 {
+            (price - window_start) / window_start * 100.0
+        } else {
+            0.0
+        };
+        let reason = if self.alert.above.is_some_and(|above| price > above) {
+            format!("above {}", self.alert.above.unwrap_or_default())
+        } else if self.alert.below.is_some_and(|below| price < below) {
+            format!("below {}", self.alert.below.unwrap_or_default())
+        } else if self
+            .alert
+            .change_pct
+            .is_some_and(|limit| change_pct.abs() >= limit)
+        {
+            format!("{:+.1}% in {}s", change_pct, self.alert.window_secs)
+        } else {
+            return std::mem::take(&mut self.firing).then_some(MarketTransition::Recovered);
+        };
+        if std::mem::replace(&mut self.firing, true) {
+            return None;
+        }
+
+        let summary = match &self.alert.summary_template {
+            Some(template) => template
+                .replace("{name}", &self.alert.name)
+                .replace("{token_id}", &self.alert.token_id)
+                .replace("{price}", &price.to_string())
+                .replace("{change_pct}", &format!("{:.1}", change_pct))
+                .replace("{reason}", &reason),
+            None => format!(
+                "{}: {} at ${} ({})",
+                self.alert.name, self.alert.token_id, price, reason
+            ),
+        };
+        let details = serde_json::json!({
+            "market_alert": self.alert.name,
+            "token_id": self.alert.token_id,
+            "price": price,
+            "reason": reason,
+            "change_pct": change_pct,
+            "window_secs": self.alert.window_secs,
+        });
+        Some(MarketTransition::Triggered { summary, details })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_market_watch() {
+        let prices = parse_prices(&serde_json::json!({
+            "wrap.near": {"price": "3.25", "symbol": "wNEAR"},
+            "usdt.tether-token.near": 1.0,
+            "broken.near": {"symbol": "X"},
+        }));
+        assert_eq!(prices["wrap.near"], 3.25);
+        assert_eq!(prices["usdt.tether-token.near"], 1.0);
+        assert!(!prices.contains_key("broken.near"));
+
+        let mut watch = MarketWatch::new(MarketAlert {
+            name: "NEAR crash".to_string(),
+            token_id: "wrap.near".to_string(),
+            above: None,
+            below: Some(2.0),
+            change_pct: Some(20.0),
+            window_secs: 3600,
+            severity: "critical".to_string(),
+            summary_template: None,
+        });
+        let start = Instant::now();
+        let at = |mins: u64| start + Duration::from_secs(mins * 60);
+
+        assert_eq!(watch.observe(3.0, at(0)), None);
+        assert_eq!(watch.observe(2.8, at(10)), None);
+        match watch.observe(2.3, at(20)) {
+            Some(MarketTransition::Triggered { summary, .. }) => {
+                assert_eq!(summary, "NEAR crash: wrap.near at $2.3 (-23.3% in 3600s)")
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        // Still firing: no repeat page
+        assert_eq!(watch.observe(1.9, at(30)), None);
+        // The 3.0 sample has left the window and the price is back above 2
+        assert_eq!(
+            watch.observe(2.5, at(95)),
+            Some(MarketTransition::Recovered)
+        );
+    }
+}
@@ -0,0 +1,112 @@
+//! Meta transactions (NEP-366 delegate actions)
+//!
+//! A relayer pays gas for actions a user signed off-chain: its transaction
+//! carries a `Delegate` action on the user's account, whose inner actions
+//! then run on `receiver_id` as if the user had sent them. On the `Delegate`
+//! action the signer is the relayer and the user is only named inside it,
+//! so [`MetaTransaction`] reads both, and
+//! [`action_fields`](crate::action_fields) exposes it as `meta_tx` for
+//! filters and templates.
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{ActionType, DelegateAction};
+
+/// A relayed meta transaction
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MetaTransaction {
+    /// Account that submitted and paid for the transaction
+    pub relayer_id: Option<String>,
+    /// Account that signed the delegated actions
+    pub sender_id: String,
+    /// Account the delegated actions run on
+    pub receiver_id: String,
+    /// Kinds of the delegated actions, e.g. `["FunctionCall"]`
+    pub action_kinds: Vec<String>,
+    /// Method of the first delegated function call
+    pub method_name: Option<String>,
+    /// Decoded JSON arguments of the first delegated function call
+    pub args: Value,
+}
+
+impl MetaTransaction {
+    /// Read a `Delegate` action signed by `signer_id`, the relayer
+    pub fn from_action(action: &DelegateAction, signer_id: Option<&str>) -> Self {
+        let delegated = &action.delegate_action;
+        let first_call = delegated.actions.iter().find_map(|action| match action {
+            ActionType::FunctionCall(fc) => Some(fc),
+            _ => None,
+        });
+        Self {
+            relayer_id: signer_id.map(str::to_string),
+            sender_id: delegated.sender_id.clone(),
+            receiver_id: delegated.receiver_id.clone(),
+            action_kinds: delegated
+                .actions
+                .iter()
+                .map(|a| a.kind().to_string())
+                .collect(),
+            method_name: first_call.map(|fc| fc.method_name.clone()),
+            args: first_call
+                .and_then(|fc| fc.args.as_deref())
+                .and_then(|args| base64::engine::general_purpose::STANDARD.decode(args).ok())
+                .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+                .unwrap_or(Value::Null),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NeardataAction;
+
+    #[test]
+    fn test_meta_transaction_from_delegate_action() {
+        // As neardata and RPC render a delegate action, with an inner action
+        // kind this crate doesn't know
+        let action: NeardataAction = serde_json::from_value(serde_json::json!({
+            "blockHeight": 1,
+            "accountId": "alice.near",
+            "signerId": "relayer.near",
+            "predecessorId": "relayer.near",
+            "status": "SUCCESS",
+            "action": {"Delegate": {
+                "delegate_action": {
+                    "sender_id": "alice.near",
+                    "receiver_id": "venear.near",
+                    "actions": [
+                        {"FunctionCall": {"method_name": "delegate_all", "args": "eyJyZWNlaXZlcl9pZCI6ImJvYi5uZWFyIn0=", "gas": 1, "deposit": "0"}},
+                        {"DeployGlobalContract": {"code": ""}}
+                    ],
+                    "nonce": 7,
+                    "max_block_height": 100,
+                    "public_key": "ed25519:abc"
+                },
+                "signature": "ed25519:sig"
+            }}
+        }))
+        .unwrap();
+        assert_eq!(action.action.kind(), "Delegate");
+        let ActionType::Delegate(delegate) = &action.action else {
+            panic!("expected a delegate action");
+        };
+        let meta_tx = MetaTransaction::from_action(delegate, action.signer_id.as_deref());
+        assert_eq!(
+            meta_tx,
+            MetaTransaction {
+                relayer_id: Some("relayer.near".to_string()),
+                sender_id: "alice.near".to_string(),
+                receiver_id: "venear.near".to_string(),
+                action_kinds: vec!["FunctionCall".to_string(), "Other".to_string()],
+                method_name: Some("delegate_all".to_string()),
+                args: serde_json::json!({"receiver_id": "bob.near"}),
+            }
+        );
+        let fields = crate::action_fields(&action);
+        assert_eq!(fields["meta_tx"]["sender_id"], "alice.near");
+        assert_eq!(fields["meta_tx"]["args"]["receiver_id"], "bob.near");
+    }
+}
@@ -0,0 +1,112 @@
+//! PagerDuty webhooks (V3)
+//!
+//! When a responder acknowledges or resolves an incident in PagerDuty
+//! itself, a webhook subscription on the service posts it to the bridge's
+//! `/webhooks/pagerduty`, which applies it to the alert's lifecycle so the
+//! state file and mirrored sinks follow PagerDuty. Incidents are matched to
+//! alerts by their incident key, which is the dedup key the alert was
+//! triggered with. Every delivery is signed with the subscription's secret.
+
+use serde::Deserialize;
+
+use crate::{chat_actions::hmac_sha256, AlertEvent};
+
+/// A change to an alert reported by PagerDuty
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct WebhookChange {
+    pub dedup_key: String,
+    pub event: AlertEvent,
+}
+
+#[derive(Deserialize)]
+struct Payload {
+    event: PayloadEvent,
+}
+
+#[derive(Deserialize)]
+struct PayloadEvent {
+    event_type: String,
+    data: IncidentData,
+}
+
+#[derive(Deserialize)]
+struct IncidentData {
+    #[serde(default)]
+    incident_key: Option<String>,
+}
+
+/// Whether `header`, the `X-PagerDuty-Signature` of `body`, holds a
+/// signature under `secret`. While a secret is rotated PagerDuty sends one
+/// per secret, as `v1=<hex>,v1=<hex>`.
+pub(crate) fn verify_signature(secret: &str, body: &[u8], header: &str) -> bool {
+    let expected = format!("{:x}", hmac_sha256(secret.as_bytes(), body));
+    header
+        .split(',')
+        .filter_map(|signature| signature.trim().strip_prefix("v1="))
+        .any(|signature| {
+            // Compare in constant time so the signature can't be guessed byte by byte
+            signature.len() == expected.len()
+                && signature
+                    .bytes()
+                    .zip(expected.bytes())
+                    .fold(0, |diff, (a, b)| diff | (a ^ b))
+                    == 0
+        })
+}
+
+/// The lifecycle change in a webhook body, or `None` for events that don't
+/// move an alert, such as `incident.annotated` or pings
+pub(crate) fn parse(body: &[u8]) -> Result<Option<WebhookChange>, serde_json::Error> {
+    let payload: Payload = serde_json::from_slice(body)?;
+    let event = match payload.event.event_type.as_str() {
+        "incident.acknowledged" => AlertEvent::Acknowledge,
+        "incident.resolved" => AlertEvent::Resolve,
+        _ => return Ok(None),
+    };
+    Ok(payload
+        .event
+        .data
+        .incident_key
+        .map(|dedup_key| WebhookChange { dedup_key, event }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pagerduty_webhook() {
+        let body = br#"{"event": {
+            "id": "01DEN2HNNHSMYN6Q1ER2OAJTE4",
+            "event_type": "incident.acknowledged",
+            "resource_type": "incident",
+            "occurred_at": "2026-10-16T12:00:00Z",
+            "data": {"id": "PGR0VU2", "type": "incident", "status": "acknowledged", "incident_key": "venear-paused-abc"}
+        }}"#;
+        let signature = format!("{:x}", hmac_sha256(b"s3cret", body));
+        let header = format!("v1={}", signature);
+        assert!(verify_signature("s3cret", body, &header));
+        assert!(verify_signature(
+            "s3cret",
+            body,
+            &format!("v1=00, {}", header)
+        ));
+        assert!(!verify_signature("other", body, &header));
+        assert!(!verify_signature("s3cret", b"{}", &header));
+        assert!(!verify_signature("s3cret", body, &signature));
+
+        assert_eq!(
+            parse(body).unwrap(),
+            Some(WebhookChange {
+                dedup_key: "venear-paused-abc".to_string(),
+                event: AlertEvent::Acknowledge,
+            })
+        );
+        let annotated =
+            br#"{"event": {"event_type": "incident.annotated", "data": {"incident_key": "k"}}}"#;
+        assert_eq!(parse(annotated).unwrap(), None);
+        let ping = br#"{"event": {"event_type": "pagey.ping", "data": {}}}"#;
+        assert_eq!(parse(ping).unwrap(), None);
+        assert!(parse(b"not json").is_err());
+    }
+}
@@ -0,0 +1,147 @@
+//! Startup checks run before the monitor connects
+//!
+//! Misconfiguration should stop the bridge at startup with a clear message,
+//! not surface as a failed delivery when the first real alert fires.
+
+use std::time::Duration;
+
+use crate::{PagerDutyAlertConfig, PagerDutyClient, PagerDutyRestClient};
+
+/// How long each connectivity check may take
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// PagerDuty integration keys are 32 characters long
+const ROUTING_KEY_LENGTH: usize = 32;
+
+/// Run every check, returning one error that lists all failures
+pub async fn run(config: &PagerDutyAlertConfig) -> Result<(), anyhow::Error> {
+    let mut failures = Vec::new();
+
+    if config.routing_key.is_empty() {
+        failures.push("PagerDuty routing key is not set".to_string());
+    } else if config.routing_key.len() != ROUTING_KEY_LENGTH {
+        log::warn!(
+            "PagerDuty routing key is {} characters long, expected {}",
+            config.routing_key.len(),
+            ROUTING_KEY_LENGTH
+        );
+    }
+
+    let mut networks: Vec<&str> = config
+        .subscriptions
+        .iter()
+        .map(|s| s.network.as_str())
+        .collect();
+    networks.sort_unstable();
+    networks.dedup();
+    for name in networks {
+        let Some(network) = config.network(name) else {
+            failures.push(format!("Unknown network '{}'", name));
+            continue;
+        };
+        if let Some(lake) = &network.lake {
+            if let Err(e) = crate::lake::LakeClient::new(lake) {
+                failures.push(format!("NEAR Lake ({}): {:#}", name, e));
+            }
+            match check_reachable(&lake.bucket_url()).await {
+                Ok(()) => log::info!("Preflight: NEAR Lake ({}) is reachable", name),
+                Err(e) => failures.push(format!(
+                    "NEAR Lake ({}) at {}: {:#}",
+                    name,
+                    lake.bucket_url(),
+                    e
+                )),
+            }
+            continue;
+        }
+        match check_reachable(&network.ws_url).await {
+            Ok(()) => log::info!("Preflight: neardata ({}) is reachable", name),
+            Err(e) => failures.push(format!(
+                "neardata ({}) at {}: {:#}",
+                name, network.ws_url, e
+            )),
+        }
+    }
+
+    if !config.routing_key.is_empty() && config.preflight_change_event {
+        let client = PagerDutyClient::new(config.routing_key.clone())
+            .with_events_api_url(config.events_api_url());
+        let sent = tokio::time::timeout(
+            CHECK_TIMEOUT,
+            client.send_change(
+                "NEAR PagerDuty Monitor started",
+                "near-pagerduty-monitor",
+                None,
+            ),
+        )
+        .await;
+        match sent {
+            Ok(Ok(_)) => log::info!("Preflight: PagerDuty accepted the routing key"),
+            Ok(Err(e)) => failures.push(format!("PagerDuty routing key: {:#}", e)),
+            Err(_) => failures.push("PagerDuty Events API timed out".to_string()),
+        }
+    }
+
+    if let Some(token) = &config.pagerduty_api_token {
+        let client = PagerDutyRestClient::new(token.clone(), config.pagerduty_from_email.clone())
+            .with_api_url(config.region.rest_api_url());
+        match tokio::time::timeout(CHECK_TIMEOUT, client.check_token()).await {
+            Ok(Ok(())) => log::info!("Preflight: PagerDuty API token is valid"),
+            Ok(Err(e)) => failures.push(format!("PagerDuty API token: {:#}", e)),
+            Err(_) => failures.push("PagerDuty REST API timed out".to_string()),
+        }
+    }
+
+    if failures.is_empty() {
+        return Ok(());
+    }
+    anyhow::bail!("Preflight checks failed:\n  - {}", failures.join("\n  - "))
+}
+
+/// Resolve a WebSocket URL's host and open a TCP connection to it
+async fn check_reachable(ws_url: &str) -> Result<(), anyhow::Error> {
+    let url = reqwest::Url::parse(ws_url)?;
+    let host = url
+        .host_str()
+        .ok_or_else(|| anyhow::anyhow!("URL has no host"))?;
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| anyhow::anyhow!("URL has no port"))?;
+
+    let connect = async {
+        let mut addrs = tokio::net::lookup_host((host, port)).await?;
+        let addr = addrs
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("{} did not resolve", host))?;
+        tokio::net::TcpStream::connect(addr).await?;
+        Ok::<_, anyhow::Error>(())
+    };
+    tokio::time::timeout(CHECK_TIMEOUT, connect)
+        .await
+        .map_err(|_| anyhow::anyhow!("timed out"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reports_every_failure() {
+        let mut config = crate::venear_pause_config("", "venear.near");
+        config.networks.insert(
+            "mainnet".to_string(),
+            crate::NetworkConfig {
+                ws_url: "ws://127.0.0.1:1/ws".to_string(),
+                explorer_url: "http://localhost:3000".to_string(),
+                history_url: None,
+                rpc_url: None,
+                explorer_links: None,
+                lake: None,
+            },
+        );
+
+        let error = run(&config).await.unwrap_err().to_string();
+        assert!(error.contains("routing key is not set"));
+        assert!(error.contains("neardata (mainnet)"));
+    }
+}
@@ -0,0 +1,124 @@
+//! Skipping actions re-delivered after a reconnect
+//!
+//! On reconnect the stream may resend actions from the block it was in,
+//! which would run them through the subscriptions a second time. Actions
+//! are recognized by transaction, receipt and index within the receipt; the
+//! most recently seen [`MAX_SEEN_ACTIONS`] are remembered.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+};
+
+use crate::NeardataAction;
+
+/// Number of processed actions remembered
+const MAX_SEEN_ACTIONS: usize = 10_000;
+
+type ActionKey = (Option<String>, String, u32);
+
+#[derive(Default)]
+struct Seen {
+    /// Last use of each key
+    used: HashMap<ActionKey, u64>,
+    /// Keys by last use, least recent first
+    order: BTreeMap<u64, ActionKey>,
+    clock: u64,
+}
+
+/// A bounded LRU of processed actions
+#[derive(Default)]
+pub(crate) struct SeenActions {
+    seen: Mutex<Seen>,
+}
+
+impl SeenActions {
+    /// Whether the action was already processed, marking it recently used.
+    /// Actions without a receipt ID can't be recognized and never are.
+    pub fn contains(&self, action: &NeardataAction) -> bool {
+        let Some(key) = key(action) else {
+            return false;
+        };
+        let mut seen = self.seen.lock().unwrap();
+        seen.clock += 1;
+        let now = seen.clock;
+        let Some(used) = seen.used.get_mut(&key) else {
+            return false;
+        };
+        let last = std::mem::replace(used, now);
+        seen.order.remove(&last);
+        seen.order.insert(now, key);
+        true
+    }
+
+    /// Remember a processed action, forgetting the least recently used
+    /// one beyond the limit
+    pub fn insert(&self, action: &NeardataAction) {
+        let Some(key) = key(action) else {
+            return;
+        };
+        let mut seen = self.seen.lock().unwrap();
+        seen.clock += 1;
+        let now = seen.clock;
+        if let Some(last) = seen.used.insert(key.clone(), now) {
+            seen.order.remove(&last);
+        }
+        seen.order.insert(now, key);
+        while seen.used.len() > MAX_SEEN_ACTIONS {
+            let Some((_, oldest)) = seen.order.pop_first() else {
+                break;
+            };
+            seen.used.remove(&oldest);
+        }
+    }
+}
+
+fn key(action: &NeardataAction) -> Option<ActionKey> {
+    Some((
+        action.tx_hash.clone(),
+        action.receipt_id.clone()?,
+        action.action_index,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seen_actions_lru() {
+        let action = |receipt_id: &str, action_index: u32| -> NeardataAction {
+            let mut action: NeardataAction = serde_json::from_value(serde_json::json!({
+                "blockHeight": 1,
+                "txHash": "tx",
+                "receiptId": receipt_id,
+                "accountId": "venear.near",
+                "status": "SUCCESS",
+                "action": "Other",
+            }))
+            .unwrap();
+            action.action_index = action_index;
+            action
+        };
+        let seen = SeenActions::default();
+        assert!(!seen.contains(&action("r0", 0)));
+        seen.insert(&action("r0", 0));
+        assert!(seen.contains(&action("r0", 0)));
+        // The next action of the same receipt is a different event
+        assert!(!seen.contains(&action("r0", 1)));
+
+        // A hit keeps r0 while older actions are evicted
+        for i in 1..MAX_SEEN_ACTIONS {
+            seen.insert(&action(&format!("r{}", i), 0));
+        }
+        assert!(seen.contains(&action("r0", 0)));
+        seen.insert(&action("new", 0));
+        assert!(seen.contains(&action("r0", 0)));
+        assert!(!seen.contains(&action("r1", 0)));
+
+        let mut unknown = action("r0", 0);
+        unknown.receipt_id = None;
+        seen.insert(&unknown);
+        assert!(!seen.contains(&unknown));
+    }
+}
@@ -0,0 +1,224 @@
+//! Daily roll-up of what the monitor alerted on
+//!
+//! Matches are tallied as they happen; once a day the tally becomes a report
+//! of alerts by subscription and severity, the noisiest sources and the
+//! delivery problems of the period, sent as a PagerDuty change event and/or
+//! posted to a chat webhook, so teams can see which subscriptions page too much.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+
+/// Sources listed in a report
+const TOP_SOURCES: usize = 5;
+
+/// Matches since the last report
+pub(crate) struct ReportTally {
+    since: DateTime<Utc>,
+    /// (subscription, severity) -> matches
+    matches: BTreeMap<(String, String), u64>,
+    /// PagerDuty `source` -> matches
+    sources: HashMap<String, u64>,
+}
+
+impl ReportTally {
+    pub fn new(since: DateTime<Utc>) -> Self {
+        Self {
+            since,
+            matches: BTreeMap::new(),
+            sources: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, subscription: &str, severity: &str, source: &str) {
+        *self
+            .matches
+            .entry((subscription.to_string(), severity.to_string()))
+            .or_default() += 1;
+        *self.sources.entry(source.to_string()).or_default() += 1;
+    }
+
+    /// Close the period at `until`, starting a new one
+    pub fn take(
+        &mut self,
+        until: DateTime<Utc>,
+        delivery_failures: u64,
+        dropped_alerts: u64,
+    ) -> DailyReport {
+        let tally = std::mem::replace(self, Self::new(until));
+        let mut top_sources: Vec<SourceCount> = tally
+            .sources
+            .into_iter()
+            .map(|(source, matches)| SourceCount { source, matches })
+            .collect();
+        top_sources.sort_by(|a, b| {
+            b.matches
+                .cmp(&a.matches)
+                .then_with(|| a.source.cmp(&b.source))
+        });
+        top_sources.truncate(TOP_SOURCES);
+        DailyReport {
+            since: tally.since,
+            until,
+            total: tally.matches.values().sum(),
+            by_subscription: tally
+                .matches
+                .into_iter()
+                .map(|((subscription, severity), matches)| SubscriptionCount {
+                    subscription,
+                    severity,
+                    matches,
+                })
+                .collect(),
+            top_sources,
+            delivery_failures,
+            dropped_alerts,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct SubscriptionCount {
+    pub subscription: String,
+    pub severity: String,
+    pub matches: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct SourceCount {
+    pub source: String,
+    pub matches: u64,
+}
+
+/// One period's roll-up
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct DailyReport {
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    pub total: u64,
+    /// Sorted by subscription, then severity
+    pub by_subscription: Vec<SubscriptionCount>,
+    /// Most frequent first
+    pub top_sources: Vec<SourceCount>,
+    pub delivery_failures: u64,
+    pub dropped_alerts: u64,
+}
+
+impl DailyReport {
+    /// One line for the change event summary
+    pub fn summary(&self) -> String {
+        let mut severities: BTreeMap<&str, u64> = BTreeMap::new();
+        for count in &self.by_subscription {
+            *severities.entry(count.severity.as_str()).or_default() += count.matches;
+        }
+        let mut by_rank: Vec<(&str, u64)> = severities.into_iter().collect();
+        by_rank.sort_by_key(|(severity, _)| {
+            std::cmp::Reverse(crate::dispatch::severity_rank(severity))
+        });
+        let breakdown = by_rank
+            .iter()
+            .map(|(severity, matches)| format!("{} {}", matches, severity))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut summary = format!("Daily alert report: {} alert(s)", self.total);
+        if !breakdown.is_empty() {
+            summary.push_str(&format!(" ({})", breakdown));
+        }
+        if self.delivery_failures > 0 || self.dropped_alerts > 0 {
+            summary.push_str(&format!(
+                ", {} delivery failure(s), {} dropped",
+                self.delivery_failures, self.dropped_alerts
+            ));
+        }
+        summary
+    }
+
+    /// Plain text for chat
+    pub fn text(&self) -> String {
+        let mut text = format!(
+            "{}\n{} to {}\n",
+            self.summary(),
+            self.since.format("%Y-%m-%d %H:%M UTC"),
+            self.until.format("%Y-%m-%d %H:%M UTC")
+        );
+        if !self.by_subscription.is_empty() {
+            text.push_str("\nBy subscription:\n");
+            for count in &self.by_subscription {
+                text.push_str(&format!(
+                    "  {} [{}]: {}\n",
+                    count.subscription, count.severity, count.matches
+                ));
+            }
+        }
+        if !self.top_sources.is_empty() {
+            text.push_str("\nNoisiest sources:\n");
+            for count in &self.top_sources {
+                text.push_str(&format!("  {}: {}\n", count.source, count.matches));
+            }
+        }
+        text
+    }
+}
+
+/// The first `at` in `timezone` after `now`
+pub(crate) fn next_report_time(now: DateTime<Utc>, at: NaiveTime, timezone: Tz) -> DateTime<Utc> {
+    let local = now.with_timezone(&timezone).date_naive();
+    (0..3)
+        .filter_map(|days| {
+            let date = local + chrono::Duration::days(days);
+            // A time skipped by a DST change falls back to an hour later
+            timezone
+                .from_local_datetime(&date.and_time(at))
+                .earliest()
+                .or_else(|| {
+                    timezone
+                        .from_local_datetime(&(date.and_time(at) + chrono::Duration::hours(1)))
+                        .earliest()
+                })
+        })
+        .map(|time| time.with_timezone(&Utc))
+        .find(|time| *time > now)
+        .expect("a report time within three days")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_roll_up() {
+        let start: DateTime<Utc> = "2026-10-05T09:00:00Z".parse().unwrap();
+        let mut tally = ReportTally::new(start);
+        for _ in 0..3 {
+            tally.record("DAO proposals", "warning", "near:dao.near");
+        }
+        tally.record("Pause", "critical", "near:venear.near");
+        tally.record("DAO proposals", "critical", "near:dao.near");
+
+        let end: DateTime<Utc> = "2026-10-06T09:00:00Z".parse().unwrap();
+        let report = tally.take(end, 2, 0);
+        assert_eq!(report.total, 5);
+        assert_eq!(
+            report.summary(),
+            "Daily alert report: 5 alert(s) (2 critical, 3 warning), 2 delivery failure(s), 0 dropped"
+        );
+        assert_eq!(report.by_subscription[0].subscription, "DAO proposals");
+        assert_eq!(report.by_subscription[0].severity, "critical");
+        assert_eq!(report.top_sources[0].source, "near:dao.near");
+        assert_eq!(report.top_sources[0].matches, 4);
+        assert!(report.text().contains("  Pause [critical]: 1\n"));
+
+        // The next period starts empty
+        assert_eq!(tally.take(end, 0, 0).total, 0);
+
+        let at = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+        let berlin: Tz = "Europe/Berlin".parse().unwrap();
+        assert_eq!(
+            next_report_time(start, at, berlin),
+            "2026-10-06T07:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_eq!(next_report_time(start, at, Tz::UTC), end);
+    }
+}
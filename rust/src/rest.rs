@@ -0,0 +1,424 @@
+//! PagerDuty REST API client
+//!
+//! The Events API only creates alerts; anything that touches the resulting
+//! incident (priority, notes, escalation) goes through the REST API, which
+//! authenticates with an API token instead of a routing key.
+
+use std::{collections::HashMap, time::Duration};
+
+use serde::Deserialize;
+use tokio::sync::OnceCell;
+
+/// PagerDuty REST API v2 client
+pub struct PagerDutyRestClient {
+    client: reqwest::Client,
+    api_token: String,
+    from_email: Option<String>,
+    api_url: String,
+    /// Priority name (lowercased) -> priority ID, fetched once on first use
+    priorities: OnceCell<HashMap<String, String>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IncidentList {
+    incidents: Vec<IncidentReference>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IncidentReference {
+    pub id: String,
+    #[serde(default)]
+    pub incident_key: Option<String>,
+    #[serde(default)]
+    pub status: Option<String>,
+}
+
+/// Changes made through the REST API to the incident an alert creates
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IncidentUpdate {
+    /// Priority name such as `P1`
+    pub priority: Option<String>,
+    /// ID of an escalation policy to reassign the incident to
+    pub escalation_policy: Option<String>,
+    /// ID of a response play to run on the incident
+    pub response_play: Option<String>,
+}
+
+impl IncidentUpdate {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct MaintenanceWindowList {
+    maintenance_windows: Vec<IncidentReference>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OnCallList {
+    oncalls: Vec<OnCall>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OnCall {
+    escalation_level: u32,
+    user: UserReference,
+}
+
+#[derive(Debug, Deserialize)]
+struct UserReference {
+    summary: String,
+}
+
+impl OnCallList {
+    /// The user on the first escalation level, who is paged first
+    fn first_responder(self) -> Option<String> {
+        self.oncalls
+            .into_iter()
+            .min_by_key(|on_call| on_call.escalation_level)
+            .map(|on_call| on_call.user.summary)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PriorityList {
+    priorities: Vec<Priority>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Priority {
+    id: String,
+    name: String,
+}
+
+impl PagerDutyRestClient {
+    /// Incidents are created asynchronously after an Events API trigger, so
+    /// lookups by dedup key are retried this many times
+    const INCIDENT_LOOKUP_ATTEMPTS: u32 = 5;
+    const INCIDENT_LOOKUP_DELAY: Duration = Duration::from_secs(3);
+
+    /// `from_email` is required by PagerDuty for any request that modifies an incident
+    pub fn new(api_token: String, from_email: Option<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_token,
+            from_email,
+            api_url: crate::PagerDutyRegion::Us.rest_api_url().to_string(),
+            priorities: OnceCell::new(),
+        }
+    }
+
+    /// Call another REST API, such as the EU region's
+    pub fn with_api_url(mut self, api_url: impl Into<String>) -> Self {
+        self.api_url = api_url.into();
+        self
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let mut request = self
+            .client
+            .request(method, format!("{}{}", self.api_url, path))
+            .header("Authorization", format!("Token token={}", self.api_token))
+            .header("Accept", "application/vnd.pagerduty+json;version=2");
+        if let Some(from) = &self.from_email {
+            request = request.header("From", from);
+        }
+        request
+    }
+
+    /// Verify the API token with a read-only request
+    pub async fn check_token(&self) -> Result<(), anyhow::Error> {
+        self.request(reqwest::Method::GET, "/abilities")
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Find the open incident created for a dedup key (the REST API calls it `incident_key`)
+    pub async fn find_incident(
+        &self,
+        dedup_key: &str,
+    ) -> Result<Option<IncidentReference>, anyhow::Error> {
+        let response = self
+            .request(reqwest::Method::GET, "/incidents")
+            .query(&[("incident_key", dedup_key)])
+            .send()
+            .await?
+            .error_for_status()?;
+        let list: IncidentList = response.json().await?;
+        Ok(list.incidents.into_iter().next())
+    }
+
+    /// Like [`find_incident`](Self::find_incident), but waits for PagerDuty to
+    /// finish creating the incident after a trigger
+    pub async fn wait_for_incident(
+        &self,
+        dedup_key: &str,
+    ) -> Result<IncidentReference, anyhow::Error> {
+        for attempt in 1..=Self::INCIDENT_LOOKUP_ATTEMPTS {
+            if let Some(incident) = self.find_incident(dedup_key).await? {
+                return Ok(incident);
+            }
+            if attempt < Self::INCIDENT_LOOKUP_ATTEMPTS {
+                tokio::time::sleep(Self::INCIDENT_LOOKUP_DELAY).await;
+            }
+        }
+        anyhow::bail!("No incident found for dedup key {}", dedup_key)
+    }
+
+    /// Resolve a priority name such as `P1` to its account-specific ID
+    pub async fn priority_id(&self, name: &str) -> Result<Option<String>, anyhow::Error> {
+        let priorities = self
+            .priorities
+            .get_or_try_init(|| async {
+                let response = self
+                    .request(reqwest::Method::GET, "/priorities")
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                let list: PriorityList = response.json().await?;
+                Ok::<_, anyhow::Error>(
+                    list.priorities
+                        .into_iter()
+                        .map(|p| (p.name.to_lowercase(), p.id))
+                        .collect(),
+                )
+            })
+            .await?;
+        Ok(priorities.get(&name.to_lowercase()).cloned())
+    }
+
+    /// Set the priority of an incident
+    pub async fn set_incident_priority(
+        &self,
+        incident_id: &str,
+        priority_id: &str,
+    ) -> Result<(), anyhow::Error> {
+        let body = serde_json::json!({
+            "incident": {
+                "type": "incident_reference",
+                "priority": {"id": priority_id, "type": "priority_reference"},
+            }
+        });
+        self.request(reqwest::Method::PUT, &format!("/incidents/{}", incident_id))
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Reassign an incident to another escalation policy, which notifies its first level
+    pub async fn set_incident_escalation_policy(
+        &self,
+        incident_id: &str,
+        escalation_policy_id: &str,
+    ) -> Result<(), anyhow::Error> {
+        let body = serde_json::json!({
+            "incident": {
+                "type": "incident_reference",
+                "escalation_policy": {"id": escalation_policy_id, "type": "escalation_policy_reference"},
+            }
+        });
+        self.request(reqwest::Method::PUT, &format!("/incidents/{}", incident_id))
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Run a response play on an incident, e.g. to add responders or start a conference bridge
+    pub async fn run_response_play(
+        &self,
+        incident_id: &str,
+        response_play_id: &str,
+    ) -> Result<(), anyhow::Error> {
+        let body = serde_json::json!({
+            "incident": {"id": incident_id, "type": "incident_reference"},
+        });
+        self.request(
+            reqwest::Method::POST,
+            &format!("/response_plays/{}/run", response_play_id),
+        )
+        .json(&body)
+        .send()
+        .await?
+        .error_for_status()?;
+        Ok(())
+    }
+
+    /// Name of whoever an escalation policy pages first right now
+    pub async fn current_on_call(
+        &self,
+        escalation_policy_id: &str,
+    ) -> Result<Option<String>, anyhow::Error> {
+        let response = self
+            .request(reqwest::Method::GET, "/oncalls")
+            .query(&[
+                ("escalation_policy_ids[]", escalation_policy_id),
+                ("earliest", "true"),
+            ])
+            .send()
+            .await?
+            .error_for_status()?;
+        let list: OnCallList = response.json().await?;
+        Ok(list.first_responder())
+    }
+
+    /// IDs of the maintenance windows a service is in right now
+    pub async fn ongoing_maintenance_windows(
+        &self,
+        service_id: &str,
+    ) -> Result<Vec<String>, anyhow::Error> {
+        let response = self
+            .request(reqwest::Method::GET, "/maintenance_windows")
+            .query(&[("service_ids[]", service_id), ("filter", "ongoing")])
+            .send()
+            .await?
+            .error_for_status()?;
+        let list: MaintenanceWindowList = response.json().await?;
+        Ok(list.maintenance_windows.into_iter().map(|w| w.id).collect())
+    }
+
+    /// Add a note to an incident's timeline
+    pub async fn add_note(&self, incident_id: &str, content: &str) -> Result<(), anyhow::Error> {
+        let body = serde_json::json!({"note": {"content": content}});
+        self.request(
+            reqwest::Method::POST,
+            &format!("/incidents/{}/notes", incident_id),
+        )
+        .json(&body)
+        .send()
+        .await?
+        .error_for_status()?;
+        Ok(())
+    }
+
+    /// Add a note to the unresolved incident behind a dedup key, returning
+    /// `false` if there is none
+    pub async fn add_note_for(
+        &self,
+        dedup_key: &str,
+        content: &str,
+    ) -> Result<bool, anyhow::Error> {
+        let incident = self
+            .find_incident(dedup_key)
+            .await?
+            .filter(|incident| incident.status.as_deref() != Some("resolved"));
+        let Some(incident) = incident else {
+            return Ok(false);
+        };
+        self.add_note(&incident.id, content).await?;
+        log::info!(
+            "Added a note to PagerDuty incident {} for {}",
+            incident.id,
+            dedup_key
+        );
+        Ok(true)
+    }
+
+    /// Apply everything in `update` to the incident behind a dedup key,
+    /// attempting each change even if an earlier one fails
+    pub async fn apply_update(
+        &self,
+        dedup_key: &str,
+        update: &IncidentUpdate,
+    ) -> Result<(), anyhow::Error> {
+        let incident = self.wait_for_incident(dedup_key).await?;
+        let mut failures = Vec::new();
+        if let Some(priority) = &update.priority {
+            let result = match self.priority_id(priority).await {
+                Ok(Some(priority_id)) => {
+                    self.set_incident_priority(&incident.id, &priority_id).await
+                }
+                Ok(None) => Err(anyhow::anyhow!(
+                    "Priority '{}' is not defined in PagerDuty",
+                    priority
+                )),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(()) => log::info!(
+                    "PagerDuty incident {} set to priority {}",
+                    incident.id,
+                    priority
+                ),
+                Err(e) => failures.push(format!("priority {}: {:#}", priority, e)),
+            }
+        }
+        if let Some(policy) = &update.escalation_policy {
+            match self
+                .set_incident_escalation_policy(&incident.id, policy)
+                .await
+            {
+                Ok(()) => log::info!(
+                    "PagerDuty incident {} reassigned to escalation policy {}",
+                    incident.id,
+                    policy
+                ),
+                Err(e) => failures.push(format!("escalation policy {}: {:#}", policy, e)),
+            }
+        }
+        if let Some(play) = &update.response_play {
+            match self.run_response_play(&incident.id, play).await {
+                Ok(()) => log::info!(
+                    "Ran response play {} on PagerDuty incident {}",
+                    play,
+                    incident.id
+                ),
+                Err(e) => failures.push(format!("response play {}: {:#}", play, e)),
+            }
+        }
+        if failures.is_empty() {
+            return Ok(());
+        }
+        anyhow::bail!("Incident {}: {}", incident.id, failures.join("; "))
+    }
+
+    /// Apply a named priority to the incident behind a dedup key
+    pub async fn apply_priority(
+        &self,
+        dedup_key: &str,
+        priority: &str,
+    ) -> Result<(), anyhow::Error> {
+        let priority_id = self.priority_id(priority).await?.ok_or_else(|| {
+            anyhow::anyhow!("Priority '{}' is not defined in PagerDuty", priority)
+        })?;
+        let incident = self.wait_for_incident(dedup_key).await?;
+        self.set_incident_priority(&incident.id, &priority_id)
+            .await?;
+        log::info!(
+            "PagerDuty incident {} set to priority {}",
+            incident.id,
+            priority
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_responder() {
+        let list: OnCallList = serde_json::from_value(serde_json::json!({
+            "oncalls": [
+                {"escalation_level": 2, "user": {"id": "PB", "summary": "Bo Backup"}},
+                {"escalation_level": 1, "user": {"id": "PA", "summary": "Ada Primary"}},
+            ]
+        }))
+        .unwrap();
+        assert_eq!(list.first_responder().as_deref(), Some("Ada Primary"));
+        assert_eq!(
+            OnCallList {
+                oncalls: Vec::new()
+            }
+            .first_responder(),
+            None
+        );
+    }
+}
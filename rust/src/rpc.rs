@@ -0,0 +1,168 @@
+//! Calls to a NEAR JSON-RPC endpoint
+//!
+//! neardata carries actions but not contract state or transaction lookups,
+//! so the few things only a node can answer go through the network's
+//! `rpc_url`: view calls such as `ft_metadata`, the blocks a transaction's
+//! receipts executed in, and where to start reading NEAR Lake.
+
+use std::collections::BTreeSet;
+
+use serde_json::Value;
+
+/// `{}` base64-encoded, the arguments of argument-less view calls
+const EMPTY_ARGS_BASE64: &str = "e30=";
+
+/// Send one JSON-RPC request, returning its `result`
+async fn request(
+    client: &reqwest::Client,
+    rpc_url: &str,
+    method: &str,
+    params: Value,
+) -> Result<Value, anyhow::Error> {
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": "near-pagerduty-monitor",
+        "method": method,
+        "params": params,
+    });
+    let response = client.post(rpc_url).json(&request).send().await?;
+    if !response.status().is_success() {
+        anyhow::bail!("RPC {} returned {}", rpc_url, response.status());
+    }
+    let mut body: Value = response.json().await?;
+    if let Some(error) = body.get("error") {
+        anyhow::bail!("RPC error: {}", error);
+    }
+    Ok(body["result"].take())
+}
+
+/// Call an argument-less view method, returning its JSON result
+pub(crate) async fn call_function(
+    client: &reqwest::Client,
+    rpc_url: &str,
+    account_id: &str,
+    method_name: &str,
+) -> Result<Value, anyhow::Error> {
+    let result = request(
+        client,
+        rpc_url,
+        "query",
+        serde_json::json!({
+            "request_type": "call_function",
+            "finality": "final",
+            "account_id": account_id,
+            "method_name": method_name,
+            "args_base64": EMPTY_ARGS_BASE64,
+        }),
+    )
+    .await?;
+    parse_call_result(&result)
+}
+
+/// Heights of the blocks a transaction and its receipts executed in,
+/// ascending. The RPC finds transactions by hash and signer.
+pub(crate) async fn transaction_block_heights(
+    client: &reqwest::Client,
+    rpc_url: &str,
+    tx_hash: &str,
+    signer_id: &str,
+) -> Result<Vec<u64>, anyhow::Error> {
+    let status = request(
+        client,
+        rpc_url,
+        "EXPERIMENTAL_tx_status",
+        serde_json::json!({
+            "tx_hash": tx_hash,
+            "sender_account_id": signer_id,
+            "wait_until": "NONE",
+        }),
+    )
+    .await?;
+    let mut heights = BTreeSet::new();
+    for block_hash in outcome_block_hashes(&status)? {
+        let block = request(
+            client,
+            rpc_url,
+            "block",
+            serde_json::json!({ "block_id": block_hash }),
+        )
+        .await?;
+        let height = block["header"]["height"]
+            .as_u64()
+            .ok_or_else(|| anyhow::anyhow!("block {} has no height", block_hash))?;
+        heights.insert(height);
+    }
+    Ok(heights.into_iter().collect())
+}
+
+/// Height of the latest final block
+pub(crate) async fn final_block_height(
+    client: &reqwest::Client,
+    rpc_url: &str,
+) -> Result<u64, anyhow::Error> {
+    let block = request(
+        client,
+        rpc_url,
+        "block",
+        serde_json::json!({ "finality": "final" }),
+    )
+    .await?;
+    block["header"]["height"]
+        .as_u64()
+        .ok_or_else(|| anyhow::anyhow!("final block has no height"))
+}
+
+/// A `call_function` result holds the returned JSON as a byte array
+fn parse_call_result(result: &Value) -> Result<Value, anyhow::Error> {
+    if let Some(error) = result.get("error") {
+        anyhow::bail!("view call failed: {}", error);
+    }
+    let bytes: Vec<u8> = serde_json::from_value(result["result"].clone())
+        .map_err(|e| anyhow::anyhow!("unexpected RPC response: {}", e))?;
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+/// Distinct blocks of a transaction's own outcome and its receipts' outcomes
+fn outcome_block_hashes(status: &Value) -> Result<BTreeSet<String>, anyhow::Error> {
+    let outcomes = std::iter::once(&status["transaction_outcome"])
+        .chain(status["receipts_outcome"].as_array().into_iter().flatten());
+    let hashes: BTreeSet<String> = outcomes
+        .filter_map(|outcome| outcome["block_hash"].as_str().map(str::to_string))
+        .collect();
+    if hashes.is_empty() {
+        anyhow::bail!("transaction status has no outcomes");
+    }
+    Ok(hashes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rpc_results() {
+        let bytes: Vec<u8> = br#"{"symbol":"USDC","decimals":6}"#.to_vec();
+        let result = serde_json::json!({"result": bytes, "logs": [], "block_height": 1});
+        assert_eq!(parse_call_result(&result).unwrap()["symbol"], "USDC");
+        assert!(parse_call_result(
+            &serde_json::json!({"error": "wasm execution failed: MethodNotFound"})
+        )
+        .is_err());
+
+        let status = serde_json::json!({
+            "transaction_outcome": {"block_hash": "A", "id": "tx"},
+            "receipts_outcome": [
+                {"block_hash": "A", "id": "r1"},
+                {"block_hash": "B", "id": "r2"},
+            ],
+        });
+        assert_eq!(
+            outcome_block_hashes(&status)
+                .unwrap()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            ["A", "B"]
+        );
+        assert!(outcome_block_hashes(&serde_json::json!({})).is_err());
+    }
+}
@@ -0,0 +1,124 @@
+//! Rhai scripts attached to subscriptions
+//!
+//! A subscription's `script` may define either or both of:
+//!
+//! - `fn should_alert(event)`, returning `false` to ignore a match
+//! - `fn render(event)`, returning a summary string or a map with any of
+//!   `summary`, `severity` and `dedup_key`
+//!
+//! `event` is the map described by [`action_fields`](crate::action_fields).
+
+use rhai::{Dynamic, Engine, Scope, AST};
+use serde_json::Value;
+
+use crate::ScriptOutput;
+
+/// Upper bound on the work one call may do, so a runaway loop can't stall the stream
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// A compiled subscription script
+pub(crate) struct SubscriptionScript {
+    engine: Engine,
+    ast: AST,
+    has_should_alert: bool,
+    has_render: bool,
+}
+
+impl SubscriptionScript {
+    pub fn compile(source: &str) -> Result<Self, anyhow::Error> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        let ast = engine
+            .compile(source)
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let defines = |name: &str| {
+            ast.iter_functions()
+                .any(|f| f.name == name && f.params.len() == 1)
+        };
+        let has_should_alert = defines("should_alert");
+        let has_render = defines("render");
+        if !has_should_alert && !has_render {
+            anyhow::bail!("script defines neither should_alert(event) nor render(event)");
+        }
+        Ok(Self {
+            engine,
+            ast,
+            has_should_alert,
+            has_render,
+        })
+    }
+
+    pub fn should_alert(&self, fields: &Value) -> Result<bool, anyhow::Error> {
+        if !self.has_should_alert {
+            return Ok(true);
+        }
+        self.call::<bool>("should_alert", fields)
+    }
+
+    pub fn render(&self, fields: &Value) -> Result<ScriptOutput, anyhow::Error> {
+        if !self.has_render {
+            return Ok(ScriptOutput::default());
+        }
+        let output = self.call::<Dynamic>("render", fields)?;
+        if output.is_string() {
+            return Ok(ScriptOutput {
+                summary: Some(
+                    output
+                        .into_string()
+                        .map_err(|t| anyhow::anyhow!("expected a string, got {}", t))?,
+                ),
+                ..ScriptOutput::default()
+            });
+        }
+        rhai::serde::from_dynamic(&output)
+            .map_err(|e| anyhow::anyhow!("render must return a string or a map: {}", e))
+    }
+
+    fn call<T: Clone + Send + Sync + 'static>(
+        &self,
+        name: &str,
+        fields: &Value,
+    ) -> Result<T, anyhow::Error> {
+        let event = rhai::serde::to_dynamic(fields).map_err(|e| anyhow::anyhow!("{}", e))?;
+        self.engine
+            .call_fn::<T>(&mut Scope::new(), &self.ast, name, (event,))
+            .map_err(|e| anyhow::anyhow!("{}: {}", name, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_alert_and_render() {
+        let script = SubscriptionScript::compile(
+            r#"
+            fn should_alert(event) { event.args.amount.parse_int() > 100 }
+            fn render(event) {
+                if event.method_name == "pause" { return "Paused by " + event.predecessor_id; }
+                #{ summary: "Big transfer", severity: "critical" }
+            }
+            "#,
+        )
+        .unwrap();
+
+        let fields = serde_json::json!({"method_name": "ft_transfer", "predecessor_id": "a.near", "args": {"amount": "500"}});
+        assert!(script.should_alert(&fields).unwrap());
+        let output = script.render(&fields).unwrap();
+        assert_eq!(output.summary.as_deref(), Some("Big transfer"));
+        assert_eq!(output.severity.as_deref(), Some("critical"));
+        assert!(output.dedup_key.is_none());
+
+        let fields = serde_json::json!({"method_name": "pause", "predecessor_id": "a.near", "args": {"amount": "1"}});
+        assert!(!script.should_alert(&fields).unwrap());
+        assert_eq!(
+            script.render(&fields).unwrap().summary.as_deref(),
+            Some("Paused by a.near")
+        );
+
+        let runaway = SubscriptionScript::compile("fn should_alert(event) { loop {} }").unwrap();
+        assert!(runaway.should_alert(&fields).is_err());
+        assert!(SubscriptionScript::compile("let x = 1;").is_err());
+    }
+}
@@ -0,0 +1,265 @@
+//! Event claims shared between replicas through Redis
+//!
+//! Replicas watching the same subscriptions all receive every event. Before
+//! alerting, each claims the event's ID in Redis, and only the replica whose
+//! claim succeeds sends it, so running several for redundancy doesn't page
+//! several times. A replica keeps its own claims, so it can retry an event
+//! whose delivery failed. If Redis is unreachable, claims succeed: paging
+//! twice beats not paging. Alert lifecycle records stay local to each replica.
+//!
+//! With leader election, replicas also compete for a lease on one key. Only
+//! the holder alerts; the others keep their connections open and hold on to
+//! recent matches, which they replay through the claims when they take over.
+//!
+//! With sharding, replicas instead register in a sorted set scored by their
+//! last heartbeat, and each subscription belongs to one live replica, chosen
+//! by rendezvous hashing so a replica joining or leaving only moves the
+//! subscriptions it gains or owned.
+
+use std::time::Duration;
+
+use redis::aio::MultiplexedConnection;
+use sha2::{Digest, Sha256};
+
+use crate::SharedStateConfig;
+
+/// A connection opened on first use and reopened after an error
+pub(crate) struct RedisConnection {
+    client: redis::Client,
+    connection: tokio::sync::Mutex<Option<MultiplexedConnection>>,
+}
+
+impl RedisConnection {
+    pub fn open(url: &str) -> Result<Self, anyhow::Error> {
+        Ok(Self {
+            client: redis::Client::open(url)?,
+            connection: tokio::sync::Mutex::new(None),
+        })
+    }
+
+    pub async fn get(&self) -> Result<MultiplexedConnection, redis::RedisError> {
+        let mut connection = self.connection.lock().await;
+        if let Some(connection) = connection.as_ref() {
+            return Ok(connection.clone());
+        }
+        let fresh = self.client.get_multiplexed_async_connection().await?;
+        *connection = Some(fresh.clone());
+        Ok(fresh)
+    }
+
+    /// Drop the connection so the next call reconnects
+    pub async fn reset(&self) {
+        *self.connection.lock().await = None;
+    }
+}
+
+pub(crate) struct SharedState {
+    redis: RedisConnection,
+    key_prefix: String,
+    claim_ttl_secs: u64,
+    instance_id: String,
+}
+
+impl SharedState {
+    pub fn new(config: &SharedStateConfig) -> Result<Self, anyhow::Error> {
+        Ok(Self {
+            redis: RedisConnection::open(&config.redis_url)?,
+            key_prefix: config.key_prefix.clone(),
+            claim_ttl_secs: config.claim_ttl_secs,
+            instance_id: config
+                .instance_id
+                .clone()
+                .unwrap_or_else(default_instance_id),
+        })
+    }
+
+    pub fn instance_id(&self) -> &str {
+        &self.instance_id
+    }
+
+    /// Claim an event for this replica, returning `false` if another one has it
+    pub async fn claim_event(&self, event_id: &str) -> bool {
+        match self.try_claim_event(event_id).await {
+            Ok(claimed) => claimed,
+            Err(e) => {
+                log::warn!(
+                    "Could not claim event {} in Redis, alerting anyway: {}",
+                    event_id,
+                    e
+                );
+                self.redis.reset().await;
+                true
+            }
+        }
+    }
+
+    async fn try_claim_event(&self, event_id: &str) -> Result<bool, redis::RedisError> {
+        let mut connection = self.redis.get().await?;
+        let key = format!("{}:event:{}", self.key_prefix, event_id);
+        let set: Option<String> = redis::cmd("SET")
+            .arg(&key)
+            .arg(&self.instance_id)
+            .arg("NX")
+            .arg("EX")
+            .arg(self.claim_ttl_secs)
+            .query_async(&mut connection)
+            .await?;
+        if set.is_some() {
+            return Ok(true);
+        }
+        let owner: Option<String> = redis::cmd("GET")
+            .arg(&key)
+            .query_async(&mut connection)
+            .await?;
+        // An expired claim is anyone's
+        Ok(owner.is_none_or(|owner| owner == self.instance_id))
+    }
+}
+
+impl SharedState {
+    /// Renew this replica's leader lease, or take it if no one holds it,
+    /// returning whether this replica leads
+    ///
+    /// If Redis is unreachable every replica leads, as with claims.
+    pub async fn hold_leadership(&self, lease: Duration) -> bool {
+        match self.try_hold_leadership(lease).await {
+            Ok(leading) => leading,
+            Err(e) => {
+                log::warn!(
+                    "Could not reach Redis for leader election, acting as leader: {}",
+                    e
+                );
+                self.redis.reset().await;
+                true
+            }
+        }
+    }
+
+    async fn try_hold_leadership(&self, lease: Duration) -> Result<bool, redis::RedisError> {
+        let renew = redis::Script::new(
+            r"if redis.call('GET', KEYS[1]) == ARGV[1] then return redis.call('PEXPIRE', KEYS[1], ARGV[2]) else return 0 end",
+        );
+        let mut connection = self.redis.get().await?;
+        let key = format!("{}:leader", self.key_prefix);
+        let lease_ms = lease.as_millis() as u64;
+        let renewed: i64 = renew
+            .key(&key)
+            .arg(&self.instance_id)
+            .arg(lease_ms)
+            .invoke_async(&mut connection)
+            .await?;
+        if renewed == 1 {
+            return Ok(true);
+        }
+        let taken: Option<String> = redis::cmd("SET")
+            .arg(&key)
+            .arg(&self.instance_id)
+            .arg("NX")
+            .arg("PX")
+            .arg(lease_ms)
+            .query_async(&mut connection)
+            .await?;
+        Ok(taken.is_some())
+    }
+}
+
+impl SharedState {
+    /// Record this replica's heartbeat and return the replicas with one
+    /// within `expiry`, this one included
+    pub async fn shard_members(&self, expiry: Duration) -> Result<Vec<String>, anyhow::Error> {
+        let result = self.try_shard_members(expiry).await;
+        if result.is_err() {
+            self.redis.reset().await;
+        }
+        Ok(result?)
+    }
+
+    async fn try_shard_members(&self, expiry: Duration) -> Result<Vec<String>, redis::RedisError> {
+        let mut connection = self.redis.get().await?;
+        let key = format!("{}:replicas", self.key_prefix);
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        let oldest_ms = now_ms - expiry.as_millis() as i64;
+        let (_, _, members): (i64, i64, Vec<String>) = redis::pipe()
+            .cmd("ZADD")
+            .arg(&key)
+            .arg(now_ms)
+            .arg(&self.instance_id)
+            .cmd("ZREMRANGEBYSCORE")
+            .arg(&key)
+            .arg("-inf")
+            .arg(oldest_ms)
+            .cmd("ZRANGE")
+            .arg(&key)
+            .arg(0)
+            .arg(-1)
+            .query_async(&mut connection)
+            .await?;
+        Ok(members)
+    }
+}
+
+/// The replica a subscription belongs to: the member with the highest hash
+/// of its name and the subscription's
+pub(crate) fn shard_owner<'m>(members: &'m [String], subscription: &str) -> Option<&'m str> {
+    members
+        .iter()
+        .max_by_key(|member| {
+            let mut hasher = Sha256::new();
+            hasher.update(member.as_bytes());
+            hasher.update([0]);
+            hasher.update(subscription.as_bytes());
+            hasher.finalize()
+        })
+        .map(String::as_str)
+}
+
+/// `$HOSTNAME` (the pod name on Kubernetes) and the process ID
+fn default_instance_id() -> String {
+    let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "near-pagerduty-monitor".to_string());
+    format!("{}-{}", host, std::process::id())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_claims_fail_open() {
+        let state = SharedState::new(&SharedStateConfig {
+            redis_url: "redis://127.0.0.1:1".to_string(),
+            key_prefix: "test".to_string(),
+            claim_ttl_secs: 60,
+            instance_id: Some("replica-a".to_string()),
+            leader_lease_secs: Some(15),
+            shard_subscriptions: true,
+        })
+        .unwrap();
+        assert_eq!(state.instance_id(), "replica-a");
+        assert!(state.claim_event("event-1").await);
+        assert!(state.hold_leadership(Duration::from_secs(15)).await);
+        assert!(state.shard_members(Duration::from_secs(30)).await.is_err());
+    }
+
+    #[test]
+    fn test_shard_owner() {
+        let members: Vec<String> = (0..3).map(|i| format!("replica-{}", i)).collect();
+        let subscriptions: Vec<String> = (0..60).map(|i| format!("subscription {}", i)).collect();
+        let owners: Vec<&str> = subscriptions
+            .iter()
+            .map(|sub| shard_owner(&members, sub).unwrap())
+            .collect();
+        for member in &members {
+            let owned = owners.iter().filter(|owner| *owner == member).count();
+            assert!((10..=30).contains(&owned), "{} owns {}", member, owned);
+        }
+
+        // Removing a replica only moves the subscriptions it owned
+        let remaining = &members[..2];
+        for (sub, owner) in subscriptions.iter().zip(&owners) {
+            if *owner != members[2] {
+                assert_eq!(shard_owner(remaining, sub), Some(*owner));
+            }
+        }
+        assert_eq!(shard_owner(&[], "subscription 0"), None);
+    }
+}
@@ -0,0 +1,428 @@
+//! Alert destinations besides PagerDuty
+//!
+//! PagerDuty stays the system of record: sinks receive a copy of every alert,
+//! and of its acknowledgement and resolution, for teams that are paged or
+//! coordinate elsewhere. Delivery to a sink is best effort; failures are
+//! logged and never hold up PagerDuty.
+
+#[cfg(feature = "monitor")]
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+#[cfg(feature = "monitor")]
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "monitor")]
+use tokio::sync::oneshot;
+
+#[cfg(feature = "monitor")]
+use crate::{dispatch::severity_rank, AlertRecord, ChatActionsConfig, EventSubscription};
+
+mod csv_export;
+mod discord;
+mod google_chat;
+mod incident_io;
+mod matrix;
+mod mqtt;
+mod pubsub;
+mod redis_stream;
+mod squadcast;
+mod twilio;
+mod victorops;
+mod webhook;
+mod zenduty;
+
+pub use csv_export::CsvExportConfig;
+#[cfg(feature = "monitor")]
+pub(crate) use csv_export::{CsvPartitions, ExportRow};
+pub use discord::DiscordConfig;
+pub use google_chat::GoogleChatConfig;
+pub use incident_io::IncidentIoConfig;
+pub use matrix::MatrixConfig;
+pub use mqtt::MqttConfig;
+pub use pubsub::PubSubConfig;
+pub use redis_stream::RedisStreamConfig;
+pub use squadcast::SquadcastConfig;
+pub use twilio::TwilioConfig;
+pub use victorops::VictorOpsConfig;
+pub use webhook::{WebhookAuth, WebhookConfig};
+pub use zenduty::ZendutyConfig;
+
+/// A named destination that subscriptions can send their alerts to
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SinkConfig {
+    /// Name subscriptions refer to in their `sinks`
+    pub name: String,
+    /// Only forward alerts of this severity or higher (default: all)
+    #[serde(default)]
+    pub min_severity: Option<String>,
+    #[serde(flatten)]
+    pub kind: SinkKind,
+}
+
+/// Where a sink delivers to, selected by `type`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SinkKind {
+    /// Splunk On-Call (formerly VictorOps) REST endpoint
+    #[serde(rename = "victorops")]
+    VictorOps(VictorOpsConfig),
+    /// incident.io HTTP alert source
+    #[serde(rename = "incident_io")]
+    IncidentIo(IncidentIoConfig),
+    /// Squadcast incident webhook
+    Squadcast(SquadcastConfig),
+    /// Zenduty events integration
+    Zenduty(ZendutyConfig),
+    /// Google Chat incoming webhook
+    GoogleChat(GoogleChatConfig),
+    /// Discord channel webhook
+    Discord(DiscordConfig),
+    /// Matrix room, posted to as a bot account
+    Matrix(MatrixConfig),
+    /// Twilio SMS and voice calls, for critical alerts only
+    Twilio(TwilioConfig),
+    /// Google Cloud Pub/Sub topic (requires the `pubsub` feature)
+    #[serde(rename = "pubsub")]
+    PubSub(PubSubConfig),
+    /// MQTT broker (requires the `mqtt` feature)
+    Mqtt(MqttConfig),
+    /// Redis stream (requires the `redis` feature)
+    Redis(RedisStreamConfig),
+    /// CSV files partitioned by date, for loading into a data warehouse
+    Csv(CsvExportConfig),
+    /// Any HTTP endpoint, with a templated JSON body
+    Webhook(WebhookConfig),
+}
+
+/// The step of an alert's lifecycle a sink is told about
+#[cfg(feature = "monitor")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum SinkEventKind {
+    Trigger,
+    Acknowledge,
+    Resolve,
+}
+
+/// An alert, or a change to one, as sent to sinks
+#[cfg(feature = "monitor")]
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct SinkEvent {
+    pub kind: SinkEventKind,
+    /// Identifies the alert across its lifecycle: the dedup key, or the
+    /// event ID for alerts without one
+    pub alert_key: String,
+    pub subscription: String,
+    pub severity: String,
+    pub summary: String,
+    /// PagerDuty `source`; only known for triggers
+    pub source: Option<String>,
+    pub explorer_link: Option<String>,
+    /// `custom_details` of the PagerDuty alert; null for acknowledgements and resolutions
+    pub custom_details: serde_json::Value,
+    /// Who `on_call_escalation_policy` pages first; only known for triggers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_call: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[cfg(feature = "monitor")]
+impl SinkEvent {
+    /// An acknowledgement or resolution of a tracked alert
+    pub fn lifecycle(kind: SinkEventKind, record: &AlertRecord) -> Self {
+        Self {
+            kind,
+            alert_key: record.dedup_key.clone(),
+            subscription: record.subscription.clone(),
+            severity: record.severity.clone(),
+            summary: record.summary.clone(),
+            source: None,
+            explorer_link: None,
+            custom_details: serde_json::Value::Null,
+            on_call: None,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+/// A critical trigger for sink tests to override the fields they exercise
+#[cfg(all(test, feature = "monitor"))]
+pub(crate) fn test_event() -> SinkEvent {
+    SinkEvent {
+        kind: SinkEventKind::Trigger,
+        alert_key: "venear-pause-venear.near".to_string(),
+        subscription: "veNEAR pause".to_string(),
+        severity: "critical".to_string(),
+        summary: "veNEAR paused".to_string(),
+        source: Some("near:venear.near".to_string()),
+        explorer_link: Some("https://nearblocks.io/txns/abc".to_string()),
+        custom_details: serde_json::Value::Null,
+        on_call: None,
+        timestamp: Utc::now(),
+    }
+}
+
+/// Color sinks show a severity in
+#[cfg(feature = "monitor")]
+pub(crate) fn severity_color(severity: &str) -> &'static str {
+    match severity {
+        "critical" => "#d32f2f",
+        "error" => "#f57c00",
+        "warning" => "#fbc02d",
+        _ => "#1976d2",
+    }
+}
+
+/// A destination alerts are mirrored to
+#[cfg(feature = "monitor")]
+#[async_trait::async_trait]
+pub(crate) trait AlertSink: Send + Sync {
+    async fn send(&self, event: &SinkEvent) -> Result<(), anyhow::Error>;
+}
+
+#[cfg(feature = "monitor")]
+struct NamedSink {
+    name: String,
+    min_severity: Option<String>,
+    sink: Arc<dyn AlertSink>,
+    /// Alert key -> signal that fires when the sink is done with the latest
+    /// event for that alert
+    last_by_key: Mutex<HashMap<String, oneshot::Receiver<()>>>,
+}
+
+/// Every configured sink
+#[cfg(feature = "monitor")]
+pub(crate) struct Sinks {
+    sinks: Vec<NamedSink>,
+}
+
+#[cfg(feature = "monitor")]
+impl Sinks {
+    /// Build the configured sinks, checking that names are unique and that
+    /// subscriptions only refer to sinks that exist
+    pub fn new(
+        configs: &[SinkConfig],
+        subscriptions: &[EventSubscription],
+        chat_actions: Option<&ChatActionsConfig>,
+        client: &reqwest::Client,
+    ) -> Result<Self, anyhow::Error> {
+        let mut names = HashSet::new();
+        let mut sinks = Vec::new();
+        for config in configs {
+            if !names.insert(config.name.as_str()) {
+                anyhow::bail!("Sink '{}' is defined more than once", config.name);
+            }
+            let sink: Arc<dyn AlertSink> = match &config.kind {
+                SinkKind::VictorOps(c) => {
+                    Arc::new(victorops::VictorOpsSink::new(c.clone(), client.clone()))
+                }
+                SinkKind::IncidentIo(c) => {
+                    Arc::new(incident_io::IncidentIoSink::new(c.clone(), client.clone()))
+                }
+                SinkKind::Squadcast(c) => {
+                    Arc::new(squadcast::SquadcastSink::new(c.clone(), client.clone()))
+                }
+                SinkKind::Zenduty(c) => {
+                    Arc::new(zenduty::ZendutySink::new(c.clone(), client.clone()))
+                }
+                SinkKind::GoogleChat(c) => Arc::new(google_chat::GoogleChatSink::new(
+                    c.clone(),
+                    chat_actions.cloned(),
+                    client.clone(),
+                )),
+                SinkKind::Discord(c) => {
+                    Arc::new(discord::DiscordSink::new(c.clone(), client.clone()))
+                }
+                SinkKind::Matrix(c) => Arc::new(matrix::MatrixSink::new(
+                    c.clone(),
+                    chat_actions.cloned(),
+                    client.clone(),
+                )),
+                SinkKind::Twilio(c) => Arc::new(twilio::TwilioSink::new(c.clone(), client.clone())),
+                #[cfg(feature = "pubsub")]
+                SinkKind::PubSub(c) => Arc::new(
+                    pubsub::PubSubSink::new(c.clone(), client.clone())
+                        .map_err(|e| anyhow::anyhow!("Sink '{}': {}", config.name, e))?,
+                ),
+                #[cfg(not(feature = "pubsub"))]
+                SinkKind::PubSub(_) => {
+                    anyhow::bail!(
+                        "Sink '{}' publishes to Pub/Sub, but this build lacks the `pubsub` feature",
+                        config.name
+                    )
+                }
+                #[cfg(feature = "mqtt")]
+                SinkKind::Mqtt(c) => Arc::new(
+                    mqtt::MqttSink::new(c.clone())
+                        .map_err(|e| anyhow::anyhow!("Sink '{}': {}", config.name, e))?,
+                ),
+                #[cfg(not(feature = "mqtt"))]
+                SinkKind::Mqtt(_) => {
+                    anyhow::bail!(
+                        "Sink '{}' publishes to MQTT, but this build lacks the `mqtt` feature",
+                        config.name
+                    )
+                }
+                #[cfg(feature = "redis")]
+                SinkKind::Redis(c) => Arc::new(
+                    redis_stream::RedisStreamSink::new(c.clone())
+                        .map_err(|e| anyhow::anyhow!("Sink '{}': {}", config.name, e))?,
+                ),
+                #[cfg(not(feature = "redis"))]
+                SinkKind::Redis(_) => {
+                    anyhow::bail!(
+                        "Sink '{}' writes to Redis, but this build lacks the `redis` feature",
+                        config.name
+                    )
+                }
+                SinkKind::Csv(c) => Arc::new(csv_export::CsvExportSink::new(c.clone())),
+                SinkKind::Webhook(c) => {
+                    Arc::new(webhook::WebhookSink::new(c.clone(), client.clone()))
+                }
+            };
+            sinks.push(NamedSink {
+                name: config.name.clone(),
+                min_severity: config.min_severity.clone(),
+                sink,
+                last_by_key: Mutex::new(HashMap::new()),
+            });
+        }
+        for sub in subscriptions {
+            for name in sub.sinks.iter().flatten() {
+                if !names.contains(name.as_str()) {
+                    anyhow::bail!(
+                        "Subscription '{}' sends to unknown sink '{}'",
+                        sub.name,
+                        name
+                    );
+                }
+            }
+        }
+        Ok(Self { sinks })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+
+    /// Send an event in the background to every sink in `only` (default:
+    /// all) whose `min_severity` it meets. Each sink receives the events of
+    /// one alert in the order they were published, so a resolution can never
+    /// overtake its trigger.
+    pub fn publish(&self, event: SinkEvent, only: Option<&[String]>) {
+        let event = Arc::new(event);
+        let targets = self.sinks.iter().filter(|s| {
+            only.is_none_or(|names| names.contains(&s.name))
+                && s.min_severity
+                    .as_deref()
+                    .is_none_or(|min| severity_rank(&event.severity) >= severity_rank(min))
+        });
+        for target in targets {
+            let name = target.name.clone();
+            let sink = Arc::clone(&target.sink);
+            let event = Arc::clone(&event);
+            let (done_tx, done_rx) = oneshot::channel();
+            let previous = {
+                let mut last_by_key = target.last_by_key.lock().unwrap();
+                // Forget alerts whose last event has been sent
+                last_by_key.retain(|_, rx| {
+                    matches!(rx.try_recv(), Err(oneshot::error::TryRecvError::Empty))
+                });
+                last_by_key.insert(event.alert_key.clone(), done_rx)
+            };
+            tokio::spawn(async move {
+                if let Some(previous) = previous {
+                    // Resolves when the earlier event is done, whether or not it was accepted
+                    let _ = previous.await;
+                }
+                let _done = done_tx;
+                if let Err(e) = sink.send(&event).await {
+                    log::warn!(
+                        "Sink '{}' did not accept {:?} for {}: {:#}",
+                        name,
+                        event.kind,
+                        event.alert_key,
+                        e
+                    );
+                }
+            });
+        }
+    }
+}
+
+#[cfg(all(test, feature = "monitor"))]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    /// Records the kinds it receives, taking longer the earlier in an
+    /// alert's lifecycle the event is
+    #[derive(Default)]
+    struct SlowSink {
+        received: Mutex<Vec<SinkEventKind>>,
+    }
+
+    #[async_trait::async_trait]
+    impl AlertSink for SlowSink {
+        async fn send(&self, event: &SinkEvent) -> Result<(), anyhow::Error> {
+            let delay = match event.kind {
+                SinkEventKind::Trigger => 60,
+                SinkEventKind::Acknowledge => 30,
+                SinkEventKind::Resolve => 0,
+            };
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            self.received.lock().unwrap().push(event.kind);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_events_for_one_alert_arrive_in_order() {
+        let sink = Arc::new(SlowSink::default());
+        let sinks = Sinks {
+            sinks: vec![NamedSink {
+                name: "slow".to_string(),
+                min_severity: None,
+                sink: Arc::clone(&sink) as Arc<dyn AlertSink>,
+                last_by_key: Mutex::new(HashMap::new()),
+            }],
+        };
+        for kind in [
+            SinkEventKind::Trigger,
+            SinkEventKind::Acknowledge,
+            SinkEventKind::Resolve,
+        ] {
+            sinks.publish(
+                SinkEvent {
+                    kind,
+                    ..test_event()
+                },
+                None,
+            );
+        }
+        // Another alert's events don't wait behind this one's
+        sinks.publish(
+            SinkEvent {
+                kind: SinkEventKind::Resolve,
+                alert_key: "other".to_string(),
+                ..test_event()
+            },
+            None,
+        );
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert_eq!(
+            *sink.received.lock().unwrap(),
+            [
+                SinkEventKind::Resolve,
+                SinkEventKind::Trigger,
+                SinkEventKind::Acknowledge,
+                SinkEventKind::Resolve,
+            ]
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//! CSV files partitioned by date
+//!
+//! Rows go to `<dir>/date=YYYY-MM-DD/events.csv`, the layout warehouse
+//! loaders (BigQuery, Athena, Snowflake stages) read as a date partition.
+//! The `export` command writes past matches in the same format.
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "monitor")]
+use std::{
+    fs::{File, OpenOptions},
+    path::PathBuf,
+    sync::Mutex,
+};
+
+#[cfg(feature = "monitor")]
+use chrono::{DateTime, NaiveDate, Utc};
+
+#[cfg(feature = "monitor")]
+use crate::sink::{AlertSink, SinkEvent};
+
+/// Settings of the directory rows are appended under
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CsvExportConfig {
+    /// Root directory of the date partitions
+    pub dir: String,
+    /// Days partitions are kept, deleting older ones as a new day starts (default: forever)
+    #[serde(default)]
+    pub retention_days: Option<u64>,
+}
+
+/// One exported row: a match, or a step in an alert's lifecycle
+#[cfg(feature = "monitor")]
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ExportRow {
+    pub time: DateTime<Utc>,
+    /// `trigger`, `acknowledge` or `resolve` from the sink; `match` from `export`
+    pub kind: String,
+    pub subscription: String,
+    pub severity: String,
+    pub summary: String,
+    pub dedup_key: Option<String>,
+    pub block_height: Option<u64>,
+    pub tx_hash: Option<String>,
+    pub explorer_link: Option<String>,
+}
+
+#[cfg(feature = "monitor")]
+impl ExportRow {
+    fn from_event(event: &SinkEvent) -> Result<Self, anyhow::Error> {
+        let kind = serde_json::to_value(event.kind)?;
+        Ok(Self {
+            time: event.timestamp,
+            kind: kind.as_str().unwrap_or_default().to_string(),
+            subscription: event.subscription.clone(),
+            severity: event.severity.clone(),
+            summary: event.summary.clone(),
+            dedup_key: Some(event.alert_key.clone()),
+            block_height: event.custom_details["block_height"].as_u64(),
+            tx_hash: event.custom_details["tx_hash"].as_str().map(str::to_string),
+            explorer_link: event.explorer_link.clone(),
+        })
+    }
+}
+
+/// Appends rows to the partition of their date, keeping the current one open
+#[cfg(feature = "monitor")]
+pub(crate) struct CsvPartitions {
+    dir: PathBuf,
+    retention_days: Option<u64>,
+    current: Mutex<Option<(NaiveDate, csv::Writer<File>)>>,
+}
+
+#[cfg(feature = "monitor")]
+impl CsvPartitions {
+    pub fn new(dir: impl Into<PathBuf>, retention_days: Option<u64>) -> Self {
+        Self {
+            dir: dir.into(),
+            retention_days,
+            current: Mutex::new(None),
+        }
+    }
+
+    /// Delete partitions more than `retention_days` before `today`
+    fn prune(&self, today: NaiveDate) -> Result<(), anyhow::Error> {
+        let Some(days) = self.retention_days else {
+            return Ok(());
+        };
+        let oldest = today - chrono::Duration::days(days as i64);
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let date = path
+                .file_name()
+                .and_then(|name| name.to_str()?.strip_prefix("date="))
+                .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok());
+            if date.is_some_and(|date| date < oldest) {
+                std::fs::remove_dir_all(&path)?;
+                log::info!("Deleted CSV partition {} past retention", path.display());
+            }
+        }
+        Ok(())
+    }
+
+    fn partition(&self, date: NaiveDate) -> PathBuf {
+        self.dir
+            .join(format!("date={}", date.format("%Y-%m-%d")))
+            .join("events.csv")
+    }
+
+    pub fn append(&self, row: &ExportRow) -> Result<(), anyhow::Error> {
+        let date = row.time.date_naive();
+        let mut current = self.current.lock().unwrap();
+        if current.as_ref().is_none_or(|(open, _)| *open != date) {
+            let path = self.partition(date);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            // Files written before keep their header
+            let has_header = path.metadata().is_ok_and(|m| m.len() > 0);
+            let file = OpenOptions::new().create(true).append(true).open(&path)?;
+            let writer = csv::WriterBuilder::new()
+                .has_headers(!has_header)
+                .from_writer(file);
+            *current = Some((date, writer));
+            if let Err(e) = self.prune(date) {
+                log::warn!("Could not delete expired CSV partitions: {:#}", e);
+            }
+        }
+        let (_, writer) = current.as_mut().expect("partition opened above");
+        writer.serialize(row)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "monitor")]
+pub(crate) struct CsvExportSink {
+    partitions: CsvPartitions,
+}
+
+#[cfg(feature = "monitor")]
+impl CsvExportSink {
+    pub fn new(config: CsvExportConfig) -> Self {
+        Self {
+            partitions: CsvPartitions::new(config.dir, config.retention_days),
+        }
+    }
+}
+
+#[cfg(feature = "monitor")]
+#[async_trait::async_trait]
+impl AlertSink for CsvExportSink {
+    async fn send(&self, event: &SinkEvent) -> Result<(), anyhow::Error> {
+        self.partitions.append(&ExportRow::from_event(event)?)
+    }
+}
+
+#[cfg(all(test, feature = "monitor"))]
+mod tests {
+    use super::*;
+    use crate::sink::SinkEventKind;
+
+    #[test]
+    fn test_rows_partitioned_by_date() {
+        let dir = std::env::temp_dir().join(format!("near-pagerduty-csv-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let partitions = CsvPartitions::new(&dir, Some(30));
+        let event = |timestamp: &str, kind| SinkEvent {
+            kind,
+            alert_key: "dao-proposal-7".to_string(),
+            subscription: "DAO".to_string(),
+            severity: "warning".to_string(),
+            summary: "Proposal 7, \"treasury\" transfer".to_string(),
+            source: None,
+            explorer_link: None,
+            custom_details: serde_json::json!({"block_height": 120, "tx_hash": "abc"}),
+            on_call: None,
+            timestamp: timestamp.parse().unwrap(),
+        };
+        for (timestamp, kind) in [
+            ("2026-03-01T23:59:00Z", SinkEventKind::Trigger),
+            ("2026-03-02T00:10:00Z", SinkEventKind::Acknowledge),
+        ] {
+            partitions
+                .append(&ExportRow::from_event(&event(timestamp, kind)).unwrap())
+                .unwrap();
+        }
+        // Reopening a partition appends without a second header
+        CsvPartitions::new(&dir, Some(30))
+            .append(
+                &ExportRow::from_event(&event("2026-03-01T23:59:30Z", SinkEventKind::Resolve))
+                    .unwrap(),
+            )
+            .unwrap();
+
+        let first = std::fs::read_to_string(dir.join("date=2026-03-01/events.csv")).unwrap();
+        let lines: Vec<&str> = first.lines().collect();
+        assert_eq!(
+            lines[0],
+            "time,kind,subscription,severity,summary,dedup_key,block_height,tx_hash,explorer_link"
+        );
+        assert_eq!(
+            lines[1],
+            "2026-03-01T23:59:00Z,trigger,DAO,warning,\"Proposal 7, \"\"treasury\"\" transfer\",dao-proposal-7,120,abc,"
+        );
+        assert!(lines[2].contains(",resolve,"));
+        assert_eq!(lines.len(), 3);
+        let second = std::fs::read_to_string(dir.join("date=2026-03-02/events.csv")).unwrap();
+        assert_eq!(second.lines().count(), 2);
+
+        // A new day deletes partitions past retention
+        partitions
+            .append(
+                &ExportRow::from_event(&event("2026-04-01T00:00:00Z", SinkEventKind::Trigger))
+                    .unwrap(),
+            )
+            .unwrap();
+        assert!(!dir.join("date=2026-03-01").exists());
+        assert!(dir.join("date=2026-03-02").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,160 @@
+//! Discord webhook
+//!
+//! Alerts are posted as embeds colored by severity, with link buttons to the
+//! transaction and runbook. Discord channels are often public, so only
+//! triggers are posted: acknowledgements and resolutions stay internal.
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "monitor")]
+use crate::sink::{severity_color, AlertSink, SinkEvent, SinkEventKind};
+
+/// Settings of a channel's webhook
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DiscordConfig {
+    /// Webhook URL from the channel's Integrations settings, including its token
+    pub webhook_url: String,
+    /// Name to post as instead of the webhook's own
+    #[serde(default)]
+    pub username: Option<String>,
+}
+
+#[cfg(feature = "monitor")]
+pub(crate) struct DiscordSink {
+    config: DiscordConfig,
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "monitor")]
+impl DiscordSink {
+    pub fn new(config: DiscordConfig, client: reqwest::Client) -> Self {
+        Self { config, client }
+    }
+}
+
+#[cfg(feature = "monitor")]
+#[async_trait::async_trait]
+impl AlertSink for DiscordSink {
+    async fn send(&self, event: &SinkEvent) -> Result<(), anyhow::Error> {
+        if event.kind != SinkEventKind::Trigger {
+            return Ok(());
+        }
+        self.client
+            .post(&self.config.webhook_url)
+            // Webhooks not owned by an application only send link buttons with this
+            .query(&[("with_components", "true")])
+            .json(&message(event, self.config.username.as_deref()))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Longest embed title Discord accepts
+#[cfg(feature = "monitor")]
+const MAX_TITLE_CHARS: usize = 256;
+
+/// An embed for a trigger, with its links as buttons
+#[cfg(feature = "monitor")]
+fn message(event: &SinkEvent, username: Option<&str>) -> serde_json::Value {
+    let title: String = if event.summary.chars().count() > MAX_TITLE_CHARS {
+        event
+            .summary
+            .chars()
+            .take(MAX_TITLE_CHARS - 1)
+            .chain(['…'])
+            .collect()
+    } else {
+        event.summary.clone()
+    };
+    let color = u32::from_str_radix(severity_color(&event.severity).trim_start_matches('#'), 16)
+        .unwrap_or_default();
+    let mut fields = vec![
+        serde_json::json!({"name": "Severity", "value": event.severity.to_uppercase(), "inline": true}),
+    ];
+    if let Some(source) = &event.source {
+        fields.push(serde_json::json!({"name": "Source", "value": source, "inline": true}));
+    }
+    if let Some(on_call) = &event.on_call {
+        fields.push(serde_json::json!({"name": "On call", "value": on_call, "inline": true}));
+    }
+    let buttons: Vec<serde_json::Value> = [
+        ("View Transaction", event.explorer_link.as_deref()),
+        ("Runbook", event.custom_details["runbook_url"].as_str()),
+    ]
+    .into_iter()
+    // Style 5 is a link button
+    .filter_map(|(label, url)| {
+        Some(serde_json::json!({"type": 2, "style": 5, "label": label, "url": url?}))
+    })
+    .collect();
+    let mut body = serde_json::json!({
+        "embeds": [{
+            "title": title,
+            "color": color,
+            "fields": fields,
+            "footer": {"text": event.subscription},
+            "timestamp": event.timestamp.to_rfc3339(),
+        }],
+        // Summaries come from on-chain data; never let them ping anyone
+        "allowed_mentions": {"parse": []},
+    });
+    if let Some(url) = &event.explorer_link {
+        body["embeds"][0]["url"] = url.as_str().into();
+    }
+    if !buttons.is_empty() {
+        body["components"] = serde_json::json!([{"type": 1, "components": buttons}]);
+    }
+    if let Some(username) = username {
+        body["username"] = username.into();
+    }
+    body
+}
+
+#[cfg(all(test, feature = "monitor"))]
+mod tests {
+    use super::*;
+    use crate::sink::test_event;
+
+    #[test]
+    fn test_discord_message() {
+        let mut event = SinkEvent {
+            alert_key: "proposal-42".to_string(),
+            subscription: "New proposals".to_string(),
+            severity: "warning".to_string(),
+            summary: "New proposal #42 @everyone".to_string(),
+            source: Some("near:vote.venear.near".to_string()),
+            custom_details: serde_json::json!({}),
+            ..test_event()
+        };
+        let body = message(&event, Some("House of Stake"));
+        let embed = &body["embeds"][0];
+        assert_eq!(embed["title"], "New proposal #42 @everyone");
+        assert_eq!(embed["color"], 0xfbc02d);
+        assert_eq!(embed["url"], "https://nearblocks.io/txns/abc");
+        assert_eq!(embed["fields"][1]["value"], "near:vote.venear.near");
+        assert_eq!(embed["footer"]["text"], "New proposals");
+        assert_eq!(body["allowed_mentions"]["parse"], serde_json::json!([]));
+        assert_eq!(body["username"], "House of Stake");
+        let buttons = body["components"][0]["components"].as_array().unwrap();
+        assert_eq!(buttons.len(), 1);
+        assert_eq!(buttons[0]["label"], "View Transaction");
+        assert_eq!(embed["fields"].as_array().unwrap().len(), 2);
+
+        event.on_call = Some("Alice Example".to_string());
+        let fields = &message(&event, None)["embeds"][0]["fields"];
+        assert_eq!(fields[2]["name"], "On call");
+        assert_eq!(fields[2]["value"], "Alice Example");
+
+        event.summary = "x".repeat(300);
+        event.explorer_link = None;
+        let body = message(&event, None);
+        assert_eq!(
+            body["embeds"][0]["title"].as_str().unwrap().chars().count(),
+            MAX_TITLE_CHARS
+        );
+        assert!(body.get("components").is_none());
+        assert!(body.get("username").is_none());
+    }
+}
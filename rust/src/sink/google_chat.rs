@@ -0,0 +1,175 @@
+//! Google Chat incoming webhook
+//!
+//! Each alert is a card in its own thread, keyed by our dedup key, and its
+//! acknowledgement and resolution are posted as replies in that thread.
+//! With `chat_actions`, cards also get Acknowledge and Resolve buttons.
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "monitor")]
+use crate::sink::{severity_color, AlertSink, SinkEvent, SinkEventKind};
+#[cfg(feature = "monitor")]
+use crate::{ChatAction, ChatActionsConfig};
+
+/// Settings of a space's incoming webhook
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct GoogleChatConfig {
+    /// Webhook URL from the space's Apps & integrations settings, including its key and token
+    pub webhook_url: String,
+}
+
+#[cfg(feature = "monitor")]
+pub(crate) struct GoogleChatSink {
+    config: GoogleChatConfig,
+    chat_actions: Option<ChatActionsConfig>,
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "monitor")]
+impl GoogleChatSink {
+    pub fn new(
+        config: GoogleChatConfig,
+        chat_actions: Option<ChatActionsConfig>,
+        client: reqwest::Client,
+    ) -> Self {
+        Self {
+            config,
+            chat_actions,
+            client,
+        }
+    }
+}
+
+#[cfg(feature = "monitor")]
+#[async_trait::async_trait]
+impl AlertSink for GoogleChatSink {
+    async fn send(&self, event: &SinkEvent) -> Result<(), anyhow::Error> {
+        self.client
+            .post(&self.config.webhook_url)
+            .query(&[
+                ("threadKey", event.alert_key.as_str()),
+                ("messageReplyOption", "REPLY_MESSAGE_FALLBACK_TO_NEW_THREAD"),
+            ])
+            .json(&message(event, self.chat_actions.as_ref()))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// A card for a trigger, or a text reply for an acknowledgement or resolution
+#[cfg(feature = "monitor")]
+fn message(event: &SinkEvent, chat_actions: Option<&ChatActionsConfig>) -> serde_json::Value {
+    match event.kind {
+        SinkEventKind::Acknowledge => {
+            return serde_json::json!({"text": format!("Acknowledged: {}", event.summary)});
+        }
+        SinkEventKind::Resolve => {
+            return serde_json::json!({"text": format!("Resolved: {}", event.summary)});
+        }
+        SinkEventKind::Trigger => {}
+    }
+
+    let severity = format!(
+        "<font color=\"{}\"><b>{}</b></font>",
+        severity_color(&event.severity),
+        event.severity.to_uppercase()
+    );
+    let mut widgets = vec![serde_json::json!({
+        "decoratedText": {"topLabel": "Severity", "text": severity},
+    })];
+    if let Some(source) = &event.source {
+        widgets.push(serde_json::json!({"decoratedText": {"topLabel": "Source", "text": source}}));
+    }
+    if let Some(on_call) = &event.on_call {
+        widgets
+            .push(serde_json::json!({"decoratedText": {"topLabel": "On call", "text": on_call}}));
+    }
+    let links = [
+        ("View Transaction", event.explorer_link.as_deref()),
+        ("Runbook", event.custom_details["runbook_url"].as_str()),
+    ];
+    let actions = chat_actions.into_iter().flat_map(|config| {
+        [
+            ("Acknowledge", ChatAction::Acknowledge),
+            ("Resolve", ChatAction::Resolve),
+        ]
+        .map(|(text, action)| (text, Some(config.link(action, &event.alert_key))))
+    });
+    let buttons: Vec<serde_json::Value> = links
+        .into_iter()
+        .map(|(text, url)| (text, url.map(str::to_string)))
+        .chain(actions)
+        .filter_map(|(text, url)| {
+            Some(serde_json::json!({"text": text, "onClick": {"openLink": {"url": url?}}}))
+        })
+        .collect();
+    if !buttons.is_empty() {
+        widgets.push(serde_json::json!({"buttonList": {"buttons": buttons}}));
+    }
+    serde_json::json!({
+        // Shown in notifications, which don't render cards
+        "text": format!("{}: {}", event.severity.to_uppercase(), event.summary),
+        "cardsV2": [{
+            "cardId": "alert",
+            "card": {
+                "header": {"title": event.summary, "subtitle": event.subscription},
+                "sections": [{"widgets": widgets}],
+            },
+        }],
+    })
+}
+
+#[cfg(all(test, feature = "monitor"))]
+mod tests {
+    use super::*;
+    use crate::sink::test_event;
+
+    #[test]
+    fn test_google_chat_message() {
+        let mut event = SinkEvent {
+            custom_details: serde_json::json!({"runbook_url": "https://runbooks.example/venear"}),
+            ..test_event()
+        };
+        let body = message(&event, None);
+        let card = &body["cardsV2"][0]["card"];
+        assert_eq!(card["header"]["title"], "veNEAR paused");
+        let widgets = &card["sections"][0]["widgets"];
+        assert!(widgets[0]["decoratedText"]["text"]
+            .as_str()
+            .unwrap()
+            .contains("#d32f2f"));
+        let buttons = &widgets[2]["buttonList"]["buttons"];
+        assert_eq!(
+            buttons[0]["onClick"]["openLink"]["url"],
+            "https://nearblocks.io/txns/abc"
+        );
+        assert_eq!(buttons[1]["text"], "Runbook");
+        assert_eq!(buttons.as_array().unwrap().len(), 2);
+
+        let chat_actions = ChatActionsConfig {
+            public_url: "https://alerts.example.com".to_string(),
+            secret: "s3cret".to_string(),
+        };
+        let body = message(&event, Some(&chat_actions));
+        let buttons =
+            &body["cardsV2"][0]["card"]["sections"][0]["widgets"][2]["buttonList"]["buttons"];
+        assert_eq!(buttons[3]["text"], "Resolve");
+        assert!(buttons[3]["onClick"]["openLink"]["url"]
+            .as_str()
+            .unwrap()
+            .starts_with(
+                "https://alerts.example.com/api/alerts/resolve?key=venear-pause-venear.near&sig="
+            ));
+
+        event.on_call = Some("Alice Example".to_string());
+        let widgets = &message(&event, None)["cardsV2"][0]["card"]["sections"][0]["widgets"];
+        assert_eq!(widgets[2]["decoratedText"]["topLabel"], "On call");
+        assert_eq!(widgets[2]["decoratedText"]["text"], "Alice Example");
+        assert_eq!(widgets[3]["buttonList"]["buttons"][1]["text"], "Runbook");
+
+        event.kind = SinkEventKind::Resolve;
+        assert_eq!(message(&event, None)["text"], "Resolved: veNEAR paused");
+    }
+}
@@ -0,0 +1,112 @@
+//! incident.io HTTP alert source
+//!
+//! Events are deduplicated by `deduplication_key`, our dedup key, so a
+//! resolution resolves the alert its trigger created. incident.io alerts have
+//! no acknowledged state; acknowledgements are not sent.
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "monitor")]
+use crate::sink::{AlertSink, SinkEvent, SinkEventKind};
+
+/// Settings of an HTTP alert source
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct IncidentIoConfig {
+    /// ID of the alert source config, the last segment of its URL
+    pub alert_source_config_id: String,
+    /// Secret token shown when the alert source was created
+    pub token: String,
+    /// Alert events endpoint (default: `https://api.incident.io/v2/alert_events/http`)
+    #[serde(default = "default_url")]
+    pub url: String,
+}
+
+fn default_url() -> String {
+    "https://api.incident.io/v2/alert_events/http".to_string()
+}
+
+#[cfg(feature = "monitor")]
+pub(crate) struct IncidentIoSink {
+    config: IncidentIoConfig,
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "monitor")]
+impl IncidentIoSink {
+    pub fn new(config: IncidentIoConfig, client: reqwest::Client) -> Self {
+        Self { config, client }
+    }
+}
+
+#[cfg(feature = "monitor")]
+#[async_trait::async_trait]
+impl AlertSink for IncidentIoSink {
+    async fn send(&self, event: &SinkEvent) -> Result<(), anyhow::Error> {
+        let Some(body) = payload(event) else {
+            return Ok(());
+        };
+        let url = format!(
+            "{}/{}",
+            self.config.url.trim_end_matches('/'),
+            self.config.alert_source_config_id
+        );
+        self.client
+            .post(url)
+            .bearer_auth(&self.config.token)
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Alert event for a trigger or resolution; `None` for acknowledgements
+#[cfg(feature = "monitor")]
+fn payload(event: &SinkEvent) -> Option<serde_json::Value> {
+    let status = match event.kind {
+        SinkEventKind::Trigger => "firing",
+        SinkEventKind::Resolve => "resolved",
+        SinkEventKind::Acknowledge => return None,
+    };
+    let mut body = serde_json::json!({
+        "title": event.summary,
+        "status": status,
+        "deduplication_key": event.alert_key,
+        "metadata": {
+            "subscription": event.subscription,
+            "severity": event.severity,
+            "source": event.source,
+            "details": event.custom_details,
+        },
+    });
+    if let Some(link) = &event.explorer_link {
+        body["source_url"] = link.clone().into();
+        body["description"] = format!("{}\n\n{}", event.summary, link).into();
+    }
+    Some(body)
+}
+
+#[cfg(all(test, feature = "monitor"))]
+mod tests {
+    use super::*;
+    use crate::sink::test_event;
+
+    #[test]
+    fn test_incident_io_payload() {
+        let mut event = SinkEvent {
+            custom_details: serde_json::json!({"method_name": "pause"}),
+            ..test_event()
+        };
+        let body = payload(&event).unwrap();
+        assert_eq!(body["status"], "firing");
+        assert_eq!(body["deduplication_key"], "venear-pause-venear.near");
+        assert_eq!(body["source_url"], "https://nearblocks.io/txns/abc");
+        assert_eq!(body["metadata"]["severity"], "critical");
+
+        event.kind = SinkEventKind::Acknowledge;
+        assert!(payload(&event).is_none());
+        event.kind = SinkEventKind::Resolve;
+        assert_eq!(payload(&event).unwrap()["status"], "resolved");
+    }
+}
@@ -0,0 +1,216 @@
+//! Matrix room
+//!
+//! Alerts, acknowledgements and resolutions are posted to one room as HTML
+//! formatted messages, sent as a bot user through the client-server API.
+//! With `chat_actions`, alerts also link to acknowledging and resolving them.
+
+#[cfg(feature = "monitor")]
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "monitor")]
+use crate::sink::{severity_color, AlertSink, SinkEvent, SinkEventKind};
+#[cfg(feature = "monitor")]
+use crate::{ChatAction, ChatActionsConfig};
+
+/// Settings of the room and the account that posts to it
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MatrixConfig {
+    /// Base URL of the bot account's homeserver, e.g. `https://matrix.org`
+    pub homeserver_url: String,
+    /// Access token of the bot account, which must have joined the room
+    pub access_token: String,
+    /// Room ID such as `!abcdef:matrix.org` (not an alias)
+    pub room_id: String,
+}
+
+#[cfg(feature = "monitor")]
+pub(crate) struct MatrixSink {
+    config: MatrixConfig,
+    chat_actions: Option<ChatActionsConfig>,
+    client: reqwest::Client,
+    /// Transaction IDs only have to be unique per access token, so a start
+    /// time and a counter suffice
+    started_ms: i64,
+    sent: AtomicU64,
+}
+
+#[cfg(feature = "monitor")]
+impl MatrixSink {
+    pub fn new(
+        config: MatrixConfig,
+        chat_actions: Option<ChatActionsConfig>,
+        client: reqwest::Client,
+    ) -> Self {
+        Self {
+            config,
+            chat_actions,
+            client,
+            started_ms: chrono::Utc::now().timestamp_millis(),
+            sent: AtomicU64::new(0),
+        }
+    }
+}
+
+#[cfg(feature = "monitor")]
+#[async_trait::async_trait]
+impl AlertSink for MatrixSink {
+    async fn send(&self, event: &SinkEvent) -> Result<(), anyhow::Error> {
+        let txn_id = format!(
+            "near-{}-{}",
+            self.started_ms,
+            self.sent.fetch_add(1, Ordering::Relaxed)
+        );
+        let mut url = reqwest::Url::parse(&self.config.homeserver_url)?;
+        url.path_segments_mut()
+            .map_err(|_| {
+                anyhow::anyhow!(
+                    "homeserver_url '{}' can't have a path",
+                    self.config.homeserver_url
+                )
+            })?
+            .pop_if_empty()
+            .extend([
+                "_matrix",
+                "client",
+                "v3",
+                "rooms",
+                &self.config.room_id,
+                "send",
+                "m.room.message",
+                &txn_id,
+            ]);
+        self.client
+            .put(url)
+            .bearer_auth(&self.config.access_token)
+            .json(&message(event, self.chat_actions.as_ref()))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "monitor")]
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// An `m.room.message` with a plain-text body and its HTML rendering
+#[cfg(feature = "monitor")]
+fn message(event: &SinkEvent, chat_actions: Option<&ChatActionsConfig>) -> serde_json::Value {
+    let (label, color) = match event.kind {
+        SinkEventKind::Trigger => (
+            event.severity.to_uppercase(),
+            severity_color(&event.severity),
+        ),
+        SinkEventKind::Acknowledge => ("ACKNOWLEDGED".to_string(), severity_color("info")),
+        SinkEventKind::Resolve => ("RESOLVED".to_string(), "#388e3c"),
+    };
+    let mut body = format!("[{}] {}", label, event.summary);
+    let mut html = format!(
+        "<p><font color=\"{color}\" data-mx-color=\"{color}\"><b>[{}]</b></font> {}</p>",
+        label,
+        escape_html(&event.summary)
+    );
+    let mut facts = vec![format!("Subscription: {}", event.subscription)];
+    if let Some(source) = &event.source {
+        facts.push(format!("Source: {}", source));
+    }
+    if let Some(on_call) = &event.on_call {
+        facts.push(format!("On call: {}", on_call));
+    }
+    facts.push(format!("Dedup key: {}", event.alert_key));
+    body.push_str(&format!("\n{}", facts.join("\n")));
+    let facts: Vec<String> = facts.iter().map(|fact| escape_html(fact)).collect();
+    html.push_str(&format!("<p>{}</p>", facts.join("<br>")));
+    let mut links = Vec::new();
+    if let Some(link) = &event.explorer_link {
+        links.push(("View Transaction", link.clone()));
+    }
+    if let (SinkEventKind::Trigger, Some(config)) = (event.kind, chat_actions) {
+        links.push((
+            "Acknowledge",
+            config.link(ChatAction::Acknowledge, &event.alert_key),
+        ));
+        links.push((
+            "Resolve",
+            config.link(ChatAction::Resolve, &event.alert_key),
+        ));
+    }
+    if !links.is_empty() {
+        for (text, link) in &links {
+            body.push_str(&format!("\n{}: {}", text, link));
+        }
+        let anchors: Vec<String> = links
+            .iter()
+            .map(|(text, link)| format!("<a href=\"{}\">{}</a>", escape_html(link), text))
+            .collect();
+        html.push_str(&format!("<p>{}</p>", anchors.join(" · ")));
+    }
+    serde_json::json!({
+        // Notices don't trigger other bots in the room
+        "msgtype": "m.notice",
+        "body": body,
+        "format": "org.matrix.custom.html",
+        "formatted_body": html,
+    })
+}
+
+#[cfg(all(test, feature = "monitor"))]
+mod tests {
+    use super::*;
+    use crate::sink::test_event;
+
+    #[test]
+    fn test_matrix_message() {
+        let mut event = SinkEvent {
+            summary: "veNEAR <paused>".to_string(),
+            ..test_event()
+        };
+        let body = message(&event, None);
+        assert!(body["body"]
+            .as_str()
+            .unwrap()
+            .starts_with("[CRITICAL] veNEAR <paused>\nSubscription: veNEAR pause"));
+        let html = body["formatted_body"].as_str().unwrap();
+        assert!(html.contains("data-mx-color=\"#d32f2f\""));
+        assert!(html.contains("veNEAR &lt;paused&gt;"));
+        assert!(html.contains("<a href=\"https://nearblocks.io/txns/abc\">"));
+
+        let chat_actions = ChatActionsConfig {
+            public_url: "https://alerts.example.com".to_string(),
+            secret: "s3cret".to_string(),
+        };
+        let html = message(&event, Some(&chat_actions))["formatted_body"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        assert!(html.contains("<a href=\"https://alerts.example.com/api/alerts/acknowledge?key=venear-pause-venear.near&amp;sig="));
+
+        event.on_call = Some("Alice <SRE>".to_string());
+        let body = message(&event, None);
+        assert!(body["body"]
+            .as_str()
+            .unwrap()
+            .contains("\nSource: near:venear.near\nOn call: Alice <SRE>\n"));
+        assert!(body["formatted_body"]
+            .as_str()
+            .unwrap()
+            .contains("<br>On call: Alice &lt;SRE&gt;<br>"));
+
+        event.kind = SinkEventKind::Resolve;
+        assert!(!message(&event, Some(&chat_actions))["formatted_body"]
+            .as_str()
+            .unwrap()
+            .contains("/api/alerts/"));
+        assert!(message(&event, None)["body"]
+            .as_str()
+            .unwrap()
+            .starts_with("[RESOLVED]"));
+    }
+}
@@ -0,0 +1,165 @@
+//! MQTT broker
+//!
+//! Every event is published as JSON to a topic rendered per alert, so local
+//! dashboards and status displays can subscribe to e.g. `near/alerts/+/critical`.
+//! The connection is opened on the first alert and re-established by the
+//! client library when it drops.
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "mqtt")]
+use crate::sink::{AlertSink, SinkEvent, SinkEventKind};
+
+/// Settings of the broker and what is published to it
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MqttConfig {
+    /// Broker URL such as `mqtt://broker.local:1883` or `mqtts://...`
+    pub broker_url: String,
+    /// Topic with `{subscription}` (slugged), `{severity}` and `{kind}`
+    /// placeholders (default: `near/alerts/{subscription}/{severity}`)
+    #[serde(default = "default_topic")]
+    pub topic: String,
+    /// 0 (at most once), 1 (at least once) or 2 (exactly once) (default: 1)
+    #[serde(default = "default_qos")]
+    pub qos: u8,
+    /// Keep the last message on each topic for new subscribers (default: false)
+    #[serde(default)]
+    pub retain: bool,
+    /// Client ID, unless the URL sets `client_id` (default: `near-pagerduty-monitor`)
+    #[serde(default = "default_client_id")]
+    pub client_id: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+fn default_topic() -> String {
+    "near/alerts/{subscription}/{severity}".to_string()
+}
+
+fn default_qos() -> u8 {
+    1
+}
+
+fn default_client_id() -> String {
+    "near-pagerduty-monitor".to_string()
+}
+
+#[cfg(feature = "mqtt")]
+pub(crate) struct MqttSink {
+    config: MqttConfig,
+    options: rumqttc::MqttOptions,
+    qos: rumqttc::QoS,
+    client: tokio::sync::OnceCell<rumqttc::AsyncClient>,
+}
+
+#[cfg(feature = "mqtt")]
+impl MqttSink {
+    /// Publishes buffered while the connection is down
+    const QUEUE_CAPACITY: usize = 64;
+    const RECONNECT_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+
+    pub fn new(config: MqttConfig) -> Result<Self, anyhow::Error> {
+        let qos = rumqttc::qos(config.qos)
+            .map_err(|_| anyhow::anyhow!("qos must be 0, 1 or 2, not {}", config.qos))?;
+        let mut url = reqwest::Url::parse(&config.broker_url)?;
+        if !url.query_pairs().any(|(name, _)| name == "client_id") {
+            url.query_pairs_mut()
+                .append_pair("client_id", &config.client_id);
+        }
+        let mut options = rumqttc::MqttOptions::parse_url(url.as_str())
+            .map_err(|e| anyhow::anyhow!("Invalid broker_url '{}': {}", config.broker_url, e))?;
+        if let Some(username) = &config.username {
+            options.set_credentials(username, config.password.as_deref().unwrap_or_default());
+        }
+        Ok(Self {
+            config,
+            options,
+            qos,
+            client: tokio::sync::OnceCell::new(),
+        })
+    }
+
+    /// The client, connecting and starting its event loop on first use
+    async fn client(&self) -> &rumqttc::AsyncClient {
+        self.client
+            .get_or_init(|| async {
+                let (client, mut event_loop) =
+                    rumqttc::AsyncClient::new(self.options.clone(), Self::QUEUE_CAPACITY);
+                let broker = self.config.broker_url.clone();
+                tokio::spawn(async move {
+                    loop {
+                        if let Err(e) = event_loop.poll().await {
+                            log::warn!("MQTT connection to {} failed: {}", broker, e);
+                            tokio::time::sleep(Self::RECONNECT_DELAY).await;
+                        }
+                    }
+                });
+                client
+            })
+            .await
+    }
+}
+
+#[cfg(feature = "mqtt")]
+#[async_trait::async_trait]
+impl AlertSink for MqttSink {
+    async fn send(&self, event: &SinkEvent) -> Result<(), anyhow::Error> {
+        let topic = topic(&self.config.topic, event);
+        self.client()
+            .await
+            .publish(
+                topic,
+                self.qos,
+                self.config.retain,
+                serde_json::to_vec(event)?,
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+/// The topic template with the event's values, which can't contain the
+/// `/`, `+` or `#` that MQTT gives meaning to
+#[cfg(feature = "mqtt")]
+fn topic(template: &str, event: &SinkEvent) -> String {
+    let kind = match event.kind {
+        SinkEventKind::Trigger => "trigger",
+        SinkEventKind::Acknowledge => "acknowledge",
+        SinkEventKind::Resolve => "resolve",
+    };
+    template
+        .replace("{subscription}", &crate::slug(&event.subscription))
+        .replace("{severity}", &crate::slug(&event.severity))
+        .replace("{kind}", kind)
+}
+
+#[cfg(all(test, feature = "mqtt"))]
+mod tests {
+    use super::*;
+    use crate::sink::test_event;
+
+    #[test]
+    fn test_mqtt_topic() {
+        let event = SinkEvent {
+            kind: SinkEventKind::Resolve,
+            subscription: "veNEAR pause/unpause".to_string(),
+            source: None,
+            explorer_link: None,
+            ..test_event()
+        };
+        assert_eq!(
+            topic(&default_topic(), &event),
+            "near/alerts/venear-pause-unpause/critical"
+        );
+        assert_eq!(topic("alerts/{kind}", &event), "alerts/resolve");
+
+        let config: MqttConfig = serde_json::from_value(serde_json::json!({
+            "broker_url": "mqtt://broker.local",
+            "qos": 3,
+        }))
+        .unwrap();
+        assert!(MqttSink::new(config).is_err());
+    }
+}
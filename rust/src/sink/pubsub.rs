@@ -0,0 +1,231 @@
+//! Google Cloud Pub/Sub topic
+//!
+//! Every event is published as JSON with our dedup key as its ordering key,
+//! so subscribers with message ordering enabled see an alert's trigger,
+//! acknowledgement and resolution in order. Requests are authorized with a
+//! service account key file, or with the metadata server's default service
+//! account when running on GCP.
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "pubsub")]
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "pubsub")]
+use base64::Engine;
+
+#[cfg(feature = "pubsub")]
+use crate::sink::{AlertSink, SinkEvent};
+
+/// Settings of the topic alerts are published to
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PubSubConfig {
+    pub project_id: String,
+    pub topic: String,
+    /// Service account key file (default: the metadata server's service account)
+    #[serde(default)]
+    pub credentials_file: Option<String>,
+    /// API endpoint; ordering keys work best with a regional one such as
+    /// `https://europe-west1-pubsub.googleapis.com` (default: `https://pubsub.googleapis.com`)
+    #[serde(default = "default_endpoint")]
+    pub endpoint: String,
+}
+
+fn default_endpoint() -> String {
+    "https://pubsub.googleapis.com".to_string()
+}
+
+/// The fields of a service account key file used to request tokens
+#[cfg(feature = "pubsub")]
+#[derive(Deserialize)]
+struct ServiceAccountKey {
+    client_email: String,
+    private_key: String,
+    token_uri: String,
+}
+
+#[cfg(feature = "pubsub")]
+#[derive(Deserialize)]
+struct AccessToken {
+    access_token: String,
+    expires_in: u64,
+}
+
+#[cfg(feature = "pubsub")]
+pub(crate) struct PubSubSink {
+    config: PubSubConfig,
+    key: Option<ServiceAccountKey>,
+    client: reqwest::Client,
+    /// Current access token and when to stop using it
+    token: tokio::sync::Mutex<Option<(String, Instant)>>,
+}
+
+#[cfg(feature = "pubsub")]
+impl PubSubSink {
+    const SCOPE: &'static str = "https://www.googleapis.com/auth/pubsub";
+    const METADATA_TOKEN_URL: &'static str =
+        "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+    /// Tokens are refreshed this long before they expire
+    const TOKEN_MARGIN: Duration = Duration::from_secs(60);
+
+    pub fn new(config: PubSubConfig, client: reqwest::Client) -> Result<Self, anyhow::Error> {
+        let key = match &config.credentials_file {
+            Some(path) => {
+                let content = std::fs::read_to_string(path).map_err(|e| {
+                    anyhow::anyhow!("Cannot read credentials_file '{}': {}", path, e)
+                })?;
+                Some(serde_json::from_str(&content)?)
+            }
+            None => None,
+        };
+        Ok(Self {
+            config,
+            key,
+            client,
+            token: tokio::sync::Mutex::new(None),
+        })
+    }
+
+    async fn access_token(&self) -> Result<String, anyhow::Error> {
+        let mut token = self.token.lock().await;
+        if let Some((value, valid_until)) = token.as_ref() {
+            if Instant::now() < *valid_until {
+                return Ok(value.clone());
+            }
+        }
+        let fresh: AccessToken = match &self.key {
+            Some(key) => {
+                let assertion = signed_jwt(key, chrono::Utc::now().timestamp())?;
+                self.client
+                    .post(&key.token_uri)
+                    .form(&[
+                        ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                        ("assertion", &assertion),
+                    ])
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?
+            }
+            None => {
+                self.client
+                    .get(Self::METADATA_TOKEN_URL)
+                    .header("Metadata-Flavor", "Google")
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?
+            }
+        };
+        let lifetime = Duration::from_secs(fresh.expires_in).saturating_sub(Self::TOKEN_MARGIN);
+        *token = Some((fresh.access_token.clone(), Instant::now() + lifetime));
+        Ok(fresh.access_token)
+    }
+}
+
+#[cfg(feature = "pubsub")]
+#[async_trait::async_trait]
+impl AlertSink for PubSubSink {
+    async fn send(&self, event: &SinkEvent) -> Result<(), anyhow::Error> {
+        let url = format!(
+            "{}/v1/projects/{}/topics/{}:publish",
+            self.config.endpoint.trim_end_matches('/'),
+            self.config.project_id,
+            self.config.topic
+        );
+        self.client
+            .post(url)
+            .bearer_auth(self.access_token().await?)
+            .json(&publish_request(event)?)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// A JWT asserting the service account's identity, signed with its key
+#[cfg(feature = "pubsub")]
+fn signed_jwt(key: &ServiceAccountKey, now: i64) -> Result<String, anyhow::Error> {
+    let encode = |value: serde_json::Value| {
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(value.to_string())
+    };
+    let header = encode(serde_json::json!({"alg": "RS256", "typ": "JWT"}));
+    let claims = encode(serde_json::json!({
+        "iss": key.client_email,
+        "scope": PubSubSink::SCOPE,
+        "aud": key.token_uri,
+        "iat": now,
+        "exp": now + 3600,
+    }));
+    let unsigned = format!("{}.{}", header, claims);
+    let private_key = openssl::pkey::PKey::private_key_from_pem(key.private_key.as_bytes())?;
+    let mut signer =
+        openssl::sign::Signer::new(openssl::hash::MessageDigest::sha256(), &private_key)?;
+    signer.update(unsigned.as_bytes())?;
+    let signature = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(signer.sign_to_vec()?);
+    Ok(format!("{}.{}", unsigned, signature))
+}
+
+#[cfg(feature = "pubsub")]
+fn publish_request(event: &SinkEvent) -> Result<serde_json::Value, anyhow::Error> {
+    let data = base64::engine::general_purpose::STANDARD.encode(serde_json::to_vec(event)?);
+    Ok(serde_json::json!({
+        "messages": [{
+            "data": data,
+            "orderingKey": event.alert_key,
+            "attributes": {
+                "kind": event.kind,
+                "subscription": event.subscription,
+                "severity": event.severity,
+            },
+        }],
+    }))
+}
+
+#[cfg(all(test, feature = "pubsub"))]
+mod tests {
+    use super::*;
+    use crate::sink::{test_event, SinkEventKind};
+
+    #[test]
+    fn test_pubsub_publish_request() {
+        let event = SinkEvent {
+            kind: SinkEventKind::Acknowledge,
+            source: None,
+            explorer_link: None,
+            ..test_event()
+        };
+        let request = publish_request(&event).unwrap();
+        let message = &request["messages"][0];
+        assert_eq!(message["orderingKey"], "venear-pause-venear.near");
+        assert_eq!(message["attributes"]["kind"], "acknowledge");
+        let data = base64::engine::general_purpose::STANDARD
+            .decode(message["data"].as_str().unwrap())
+            .unwrap();
+        let data: serde_json::Value = serde_json::from_slice(&data).unwrap();
+        assert_eq!(data["summary"], "veNEAR paused");
+        assert_eq!(data["alert_key"], "venear-pause-venear.near");
+
+        let rsa = openssl::rsa::Rsa::generate(2048).unwrap();
+        let private_key = openssl::pkey::PKey::from_rsa(rsa).unwrap();
+        let key = ServiceAccountKey {
+            client_email: "alerts@project.iam.gserviceaccount.com".to_string(),
+            private_key: String::from_utf8(private_key.private_key_to_pem_pkcs8().unwrap())
+                .unwrap(),
+            token_uri: "https://oauth2.googleapis.com/token".to_string(),
+        };
+        let jwt = signed_jwt(&key, 1_700_000_000).unwrap();
+        let (unsigned, signature) = jwt.rsplit_once('.').unwrap();
+        let signature = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(signature)
+            .unwrap();
+        let mut verifier =
+            openssl::sign::Verifier::new(openssl::hash::MessageDigest::sha256(), &private_key)
+                .unwrap();
+        verifier.update(unsigned.as_bytes()).unwrap();
+        assert!(verifier.verify(&signature).unwrap());
+    }
+}
@@ -0,0 +1,103 @@
+//! Redis stream
+//!
+//! Every event is appended to a stream with XADD, so any number of consumers
+//! (or consumer groups) can read alerts at their own pace.
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "redis")]
+use crate::shared::RedisConnection;
+#[cfg(feature = "redis")]
+use crate::sink::{AlertSink, SinkEvent};
+
+/// Settings of the stream alerts are appended to
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RedisStreamConfig {
+    /// e.g. `redis://:password@redis.local:6379/0`
+    pub redis_url: String,
+    /// Stream key (default: `near-alerts`)
+    #[serde(default = "default_stream")]
+    pub stream: String,
+    /// Approximate number of entries kept (default: 10000)
+    #[serde(default = "default_max_len")]
+    pub max_len: u64,
+}
+
+fn default_stream() -> String {
+    "near-alerts".to_string()
+}
+
+fn default_max_len() -> u64 {
+    10_000
+}
+
+#[cfg(feature = "redis")]
+pub(crate) struct RedisStreamSink {
+    config: RedisStreamConfig,
+    redis: RedisConnection,
+}
+
+#[cfg(feature = "redis")]
+impl RedisStreamSink {
+    pub fn new(config: RedisStreamConfig) -> Result<Self, anyhow::Error> {
+        let redis = RedisConnection::open(&config.redis_url)?;
+        Ok(Self { config, redis })
+    }
+}
+
+#[cfg(feature = "redis")]
+#[async_trait::async_trait]
+impl AlertSink for RedisStreamSink {
+    async fn send(&self, event: &SinkEvent) -> Result<(), anyhow::Error> {
+        let mut command = redis::cmd("XADD");
+        command
+            .arg(&self.config.stream)
+            .arg("MAXLEN")
+            .arg("~")
+            .arg(self.config.max_len)
+            .arg("*");
+        for (field, value) in fields(event)? {
+            command.arg(field).arg(value);
+        }
+        let mut connection = self.redis.get().await?;
+        let result: Result<String, redis::RedisError> = command.query_async(&mut connection).await;
+        if let Err(e) = result {
+            self.redis.reset().await;
+            return Err(e.into());
+        }
+        Ok(())
+    }
+}
+
+/// Stream entry fields: the values consumers commonly filter on, and the whole event as JSON
+#[cfg(feature = "redis")]
+fn fields(event: &SinkEvent) -> Result<Vec<(&'static str, String)>, anyhow::Error> {
+    let kind = serde_json::to_value(event.kind)?;
+    Ok(vec![
+        ("kind", kind.as_str().unwrap_or_default().to_string()),
+        ("alert_key", event.alert_key.clone()),
+        ("subscription", event.subscription.clone()),
+        ("severity", event.severity.clone()),
+        ("summary", event.summary.clone()),
+        ("event", serde_json::to_string(event)?),
+    ])
+}
+
+#[cfg(all(test, feature = "redis"))]
+mod tests {
+    use super::*;
+    use crate::sink::test_event;
+
+    #[test]
+    fn test_redis_stream_fields() {
+        let event = SinkEvent {
+            explorer_link: None,
+            ..test_event()
+        };
+        let fields = fields(&event).unwrap();
+        assert_eq!(fields[0], ("kind", "trigger".to_string()));
+        assert_eq!(fields[1].1, "venear-pause-venear.near");
+        let json: serde_json::Value = serde_json::from_str(&fields[5].1).unwrap();
+        assert_eq!(json["source"], "near:venear.near");
+    }
+}
@@ -0,0 +1,146 @@
+//! Squadcast incident webhook (API v2)
+//!
+//! Events with the same `event_id`, our dedup key, belong to one incident,
+//! so a resolution resolves it. The webhook can't acknowledge; acknowledgements
+//! are not sent.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "monitor")]
+use crate::sink::{severity_color, AlertSink, SinkEvent, SinkEventKind};
+
+/// Settings of an Incident Webhook alert source
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SquadcastConfig {
+    /// Webhook URL of the alert source, which includes its API key
+    pub webhook_url: String,
+    /// Squadcast priority (P1-P5) by severity (default: critical P1, error P2,
+    /// warning P3, info P5)
+    #[serde(default)]
+    pub priorities: BTreeMap<String, String>,
+    /// Tags added to every incident, e.g. `team: protocol`
+    #[serde(default)]
+    pub tags: BTreeMap<String, String>,
+}
+
+#[cfg(feature = "monitor")]
+impl SquadcastConfig {
+    fn priority(&self, severity: &str) -> &str {
+        if let Some(priority) = self.priorities.get(severity) {
+            return priority;
+        }
+        match severity {
+            "critical" => "P1",
+            "error" => "P2",
+            "warning" => "P3",
+            _ => "P5",
+        }
+    }
+}
+
+#[cfg(feature = "monitor")]
+pub(crate) struct SquadcastSink {
+    config: SquadcastConfig,
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "monitor")]
+impl SquadcastSink {
+    pub fn new(config: SquadcastConfig, client: reqwest::Client) -> Self {
+        Self { config, client }
+    }
+}
+
+#[cfg(feature = "monitor")]
+#[async_trait::async_trait]
+impl AlertSink for SquadcastSink {
+    async fn send(&self, event: &SinkEvent) -> Result<(), anyhow::Error> {
+        let Some(body) = payload(&self.config, event) else {
+            return Ok(());
+        };
+        self.client
+            .post(&self.config.webhook_url)
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Webhook body for a trigger or resolution; `None` for acknowledgements
+#[cfg(feature = "monitor")]
+fn payload(config: &SquadcastConfig, event: &SinkEvent) -> Option<serde_json::Value> {
+    let status = match event.kind {
+        SinkEventKind::Trigger => "trigger",
+        SinkEventKind::Resolve => "resolve",
+        SinkEventKind::Acknowledge => return None,
+    };
+    let mut tags: serde_json::Map<String, serde_json::Value> = config
+        .tags
+        .iter()
+        .map(|(key, value)| (key.clone(), value.clone().into()))
+        .collect();
+    tags.insert(
+        "severity".to_string(),
+        serde_json::json!({"value": event.severity, "color": severity_color(&event.severity)}),
+    );
+    tags.insert(
+        "subscription".to_string(),
+        event.subscription.clone().into(),
+    );
+    if let Some(source) = &event.source {
+        tags.insert("source".to_string(), source.clone().into());
+    }
+    let description = match &event.explorer_link {
+        Some(link) => format!("{}\n\n[View transaction]({})", event.summary, link),
+        None => event.summary.clone(),
+    };
+    Some(serde_json::json!({
+        "message": event.summary,
+        "description": description,
+        "status": status,
+        "event_id": event.alert_key,
+        "priority": config.priority(&event.severity),
+        "tags": tags,
+        "details": event.custom_details,
+    }))
+}
+
+#[cfg(all(test, feature = "monitor"))]
+mod tests {
+    use super::*;
+    use crate::sink::test_event;
+
+    #[test]
+    fn test_squadcast_payload() {
+        let mut config = SquadcastConfig {
+            webhook_url: "https://api.squadcast.com/v2/incidents/api/key".to_string(),
+            priorities: BTreeMap::from([("warning".to_string(), "P4".to_string())]),
+            tags: BTreeMap::from([("team".to_string(), "protocol".to_string())]),
+        };
+        let mut event = SinkEvent {
+            explorer_link: None,
+            ..test_event()
+        };
+        let body = payload(&config, &event).unwrap();
+        assert_eq!(body["status"], "trigger");
+        assert_eq!(body["event_id"], "venear-pause-venear.near");
+        assert_eq!(body["priority"], "P1");
+        assert_eq!(body["tags"]["team"], "protocol");
+        assert_eq!(body["tags"]["severity"]["value"], "critical");
+        assert_eq!(body["tags"]["source"], "near:venear.near");
+
+        event.severity = "warning".to_string();
+        assert_eq!(payload(&config, &event).unwrap()["priority"], "P4");
+        config.priorities.clear();
+        assert_eq!(payload(&config, &event).unwrap()["priority"], "P3");
+
+        event.kind = SinkEventKind::Acknowledge;
+        assert!(payload(&config, &event).is_none());
+        event.kind = SinkEventKind::Resolve;
+        assert_eq!(payload(&config, &event).unwrap()["status"], "resolve");
+    }
+}
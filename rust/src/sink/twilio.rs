@@ -0,0 +1,153 @@
+//! Twilio SMS and voice calls
+//!
+//! A last-resort channel for when PagerDuty itself may be unreachable: only
+//! critical alerts are sent, and acknowledgements and resolutions are not,
+//! whatever the sink's `min_severity`.
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "monitor")]
+use crate::sink::{AlertSink, SinkEvent, SinkEventKind};
+
+/// Settings of the Twilio account and who it notifies
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TwilioConfig {
+    pub account_sid: String,
+    pub auth_token: String,
+    /// Twilio number messages and calls come from, in E.164 format
+    pub from: String,
+    /// Numbers to notify, in E.164 format
+    pub to: Vec<String>,
+    /// Also call each number and read the alert out (default: false)
+    #[serde(default)]
+    pub call: bool,
+    /// API base URL (default: `https://api.twilio.com/2010-04-01`)
+    #[serde(default = "default_url")]
+    pub url: String,
+}
+
+fn default_url() -> String {
+    "https://api.twilio.com/2010-04-01".to_string()
+}
+
+#[cfg(feature = "monitor")]
+pub(crate) struct TwilioSink {
+    config: TwilioConfig,
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "monitor")]
+impl TwilioSink {
+    /// Longest summary put in a text, so most alerts fit in two SMS segments
+    const SMS_SUMMARY_MAX_CHARS: usize = 240;
+
+    pub fn new(config: TwilioConfig, client: reqwest::Client) -> Self {
+        Self { config, client }
+    }
+
+    async fn post(&self, resource: &str, form: &[(&str, &str)]) -> Result<(), anyhow::Error> {
+        let url = format!(
+            "{}/Accounts/{}/{}.json",
+            self.config.url.trim_end_matches('/'),
+            self.config.account_sid,
+            resource
+        );
+        self.client
+            .post(url)
+            .basic_auth(&self.config.account_sid, Some(&self.config.auth_token))
+            .form(form)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "monitor")]
+#[async_trait::async_trait]
+impl AlertSink for TwilioSink {
+    async fn send(&self, event: &SinkEvent) -> Result<(), anyhow::Error> {
+        if event.kind != SinkEventKind::Trigger || event.severity != "critical" {
+            return Ok(());
+        }
+        let text = sms_body(event, Self::SMS_SUMMARY_MAX_CHARS);
+        let twiml = voice_twiml(event);
+        // One unreachable number must not keep the others from being notified
+        let mut failures = Vec::new();
+        for to in &self.config.to {
+            if let Err(e) = self
+                .post(
+                    "Messages",
+                    &[("To", to), ("From", &self.config.from), ("Body", &text)],
+                )
+                .await
+            {
+                failures.push(format!("SMS to {}: {:#}", to, e));
+            }
+            if self.config.call {
+                if let Err(e) = self
+                    .post(
+                        "Calls",
+                        &[("To", to), ("From", &self.config.from), ("Twiml", &twiml)],
+                    )
+                    .await
+                {
+                    failures.push(format!("call to {}: {:#}", to, e));
+                }
+            }
+        }
+        if failures.is_empty() {
+            return Ok(());
+        }
+        anyhow::bail!("{}", failures.join("; "))
+    }
+}
+
+#[cfg(feature = "monitor")]
+fn sms_body(event: &SinkEvent, summary_max_chars: usize) -> String {
+    let (summary, _) = crate::truncate_summary(&event.summary, summary_max_chars);
+    match &event.explorer_link {
+        Some(link) => format!("CRITICAL: {}\n{}", summary, link),
+        None => format!("CRITICAL: {}", summary),
+    }
+}
+
+/// TwiML that reads the summary out twice
+#[cfg(feature = "monitor")]
+fn voice_twiml(event: &SinkEvent) -> String {
+    let summary = event
+        .summary
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    format!(
+        "<Response><Say loop=\"2\">Critical NEAR alert. {}</Say></Response>",
+        summary
+    )
+}
+
+#[cfg(all(test, feature = "monitor"))]
+mod tests {
+    use super::*;
+    use crate::sink::test_event;
+
+    #[test]
+    fn test_twilio_messages() {
+        let event = SinkEvent {
+            summary: "veNEAR paused by <owner>".to_string(),
+            ..test_event()
+        };
+        assert_eq!(
+            sms_body(&event, 240),
+            "CRITICAL: veNEAR paused by <owner>\nhttps://nearblocks.io/txns/abc"
+        );
+        assert_eq!(
+            sms_body(&event, 7),
+            "CRITICAL: veNEAR…\nhttps://nearblocks.io/txns/abc"
+        );
+        assert_eq!(
+            voice_twiml(&event),
+            "<Response><Say loop=\"2\">Critical NEAR alert. veNEAR paused by &lt;owner&gt;</Say></Response>"
+        );
+    }
+}
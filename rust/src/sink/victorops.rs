@@ -0,0 +1,124 @@
+//! Splunk On-Call (formerly VictorOps) REST endpoint
+//!
+//! Alerts become incidents keyed by `entity_id`, which is our dedup key, so
+//! repeated triggers update one incident and acknowledgements and
+//! resolutions close the one PagerDuty's counterpart belongs to.
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "monitor")]
+use crate::sink::{AlertSink, SinkEvent, SinkEventKind};
+
+/// Settings of the REST endpoint integration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct VictorOpsConfig {
+    /// REST endpoint API key, from the integration's URL
+    pub api_key: String,
+    /// Routing key that selects the escalation policy
+    pub routing_key: String,
+    /// REST endpoint base URL (default: `https://alert.victorops.com/integrations/generic/20131114/alert`)
+    #[serde(default = "default_url")]
+    pub url: String,
+}
+
+fn default_url() -> String {
+    "https://alert.victorops.com/integrations/generic/20131114/alert".to_string()
+}
+
+#[cfg(feature = "monitor")]
+pub(crate) struct VictorOpsSink {
+    config: VictorOpsConfig,
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "monitor")]
+impl VictorOpsSink {
+    pub fn new(config: VictorOpsConfig, client: reqwest::Client) -> Self {
+        Self { config, client }
+    }
+}
+
+#[cfg(feature = "monitor")]
+#[async_trait::async_trait]
+impl AlertSink for VictorOpsSink {
+    async fn send(&self, event: &SinkEvent) -> Result<(), anyhow::Error> {
+        let url = format!(
+            "{}/{}/{}",
+            self.config.url.trim_end_matches('/'),
+            self.config.api_key,
+            self.config.routing_key
+        );
+        self.client
+            .post(url)
+            .json(&payload(event))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// `message_type` for an event: triggers by severity, then the lifecycle steps
+#[cfg(feature = "monitor")]
+fn message_type(event: &SinkEvent) -> &'static str {
+    match event.kind {
+        SinkEventKind::Trigger => match event.severity.as_str() {
+            "critical" | "error" => "CRITICAL",
+            "warning" => "WARNING",
+            _ => "INFO",
+        },
+        SinkEventKind::Acknowledge => "ACKNOWLEDGEMENT",
+        SinkEventKind::Resolve => "RECOVERY",
+    }
+}
+
+#[cfg(feature = "monitor")]
+fn payload(event: &SinkEvent) -> serde_json::Value {
+    let state_message = match &event.explorer_link {
+        Some(link) => format!("{}\n{}", event.summary, link),
+        None => event.summary.clone(),
+    };
+    serde_json::json!({
+        "message_type": message_type(event),
+        "entity_id": event.alert_key,
+        "entity_display_name": event.summary,
+        "state_message": state_message,
+        "state_start_time": event.timestamp.timestamp(),
+        "monitoring_tool": "near-pagerduty-monitor",
+        "host_name": event.source,
+        "subscription": event.subscription,
+        "severity": event.severity,
+        "explorer_link": event.explorer_link,
+        "details": event.custom_details,
+    })
+}
+
+#[cfg(all(test, feature = "monitor"))]
+mod tests {
+    use super::*;
+    use crate::sink::test_event;
+
+    #[test]
+    fn test_victorops_payload() {
+        let mut event = SinkEvent {
+            severity: "error".to_string(),
+            custom_details: serde_json::json!({"method_name": "pause"}),
+            timestamp: chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+            ..test_event()
+        };
+        let body = payload(&event);
+        assert_eq!(body["message_type"], "CRITICAL");
+        assert_eq!(body["entity_id"], "venear-pause-venear.near");
+        assert_eq!(
+            body["state_message"],
+            "veNEAR paused\nhttps://nearblocks.io/txns/abc"
+        );
+        assert_eq!(body["state_start_time"], 1_700_000_000);
+        assert_eq!(body["details"]["method_name"], "pause");
+
+        event.severity = "info".to_string();
+        assert_eq!(payload(&event)["message_type"], "INFO");
+        event.kind = SinkEventKind::Resolve;
+        assert_eq!(payload(&event)["message_type"], "RECOVERY");
+    }
+}
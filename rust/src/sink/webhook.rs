@@ -0,0 +1,127 @@
+//! Generic HTTP webhook
+//!
+//! For internal incident tooling, n8n or Zapier: every trigger,
+//! acknowledgement and resolution is POSTed as JSON, by default the event
+//! itself, or `body_template` filled in from it. A string in the template
+//! that is just one placeholder such as `{custom_details}` keeps the field's
+//! JSON value.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "monitor")]
+use crate::sink::{AlertSink, SinkEvent};
+
+/// Settings of the endpoint and the request sent to it
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WebhookConfig {
+    pub webhook_url: String,
+    /// Extra request headers, e.g. `X-Api-Key`
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub auth: Option<WebhookAuth>,
+    /// JSON body with `{kind}`, `{alert_key}`, `{subscription}`, `{severity}`,
+    /// `{summary}`, `{source}`, `{explorer_link}`, `{custom_details}` and
+    /// `{timestamp}` placeholders (default: all of them as one object)
+    #[serde(default)]
+    pub body_template: Option<serde_json::Value>,
+}
+
+/// How requests authenticate, selected by `type`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WebhookAuth {
+    Bearer { token: String },
+    Basic { username: String, password: String },
+}
+
+#[cfg(feature = "monitor")]
+pub(crate) struct WebhookSink {
+    config: WebhookConfig,
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "monitor")]
+impl WebhookSink {
+    pub fn new(config: WebhookConfig, client: reqwest::Client) -> Self {
+        Self { config, client }
+    }
+}
+
+#[cfg(feature = "monitor")]
+#[async_trait::async_trait]
+impl AlertSink for WebhookSink {
+    async fn send(&self, event: &SinkEvent) -> Result<(), anyhow::Error> {
+        let mut request = self
+            .client
+            .post(&self.config.webhook_url)
+            .json(&body(&self.config, event)?);
+        for (name, value) in &self.config.headers {
+            request = request.header(name, value);
+        }
+        request = match &self.config.auth {
+            Some(WebhookAuth::Bearer { token }) => request.bearer_auth(token),
+            Some(WebhookAuth::Basic { username, password }) => {
+                request.basic_auth(username, Some(password))
+            }
+            None => request,
+        };
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "monitor")]
+fn body(config: &WebhookConfig, event: &SinkEvent) -> Result<serde_json::Value, anyhow::Error> {
+    let fields = serde_json::to_value(event)?;
+    Ok(match &config.body_template {
+        Some(template) => crate::template::render_json(template, &fields, &str::to_string),
+        None => fields,
+    })
+}
+
+#[cfg(all(test, feature = "monitor"))]
+mod tests {
+    use super::*;
+    use crate::sink::test_event;
+
+    #[test]
+    fn test_webhook_body() {
+        let mut config: WebhookConfig = serde_yaml::from_str(
+            r#"
+webhook_url: "https://n8n.example.com/webhook/near"
+auth: {type: bearer, token: "secret"}
+"#,
+        )
+        .unwrap();
+        assert!(matches!(config.auth, Some(WebhookAuth::Bearer { .. })));
+        let event = SinkEvent {
+            alert_key: "venear-pause-abc".to_string(),
+            source: None,
+            custom_details: serde_json::json!({"block_height": 7}),
+            timestamp: "2026-10-16T09:00:00Z".parse().unwrap(),
+            ..test_event()
+        };
+        let body_of = |config: &WebhookConfig| body(config, &event).unwrap();
+        assert_eq!(body_of(&config)["alert_key"], "venear-pause-abc");
+        assert_eq!(body_of(&config)["kind"], "trigger");
+
+        config.body_template = Some(serde_json::json!({
+            "title": "[{severity}] {summary}",
+            "details": "{custom_details}",
+            "link": "{explorer_link}",
+            "origin": "{source}",
+        }));
+        assert_eq!(
+            body_of(&config),
+            serde_json::json!({
+                "title": "[critical] veNEAR paused",
+                "details": {"block_height": 7},
+                "link": "https://nearblocks.io/txns/abc",
+                "origin": null,
+            })
+        );
+    }
+}
@@ -0,0 +1,120 @@
+//! Zenduty events integration
+//!
+//! Zenduty's alert types cover the whole lifecycle, so triggers,
+//! acknowledgements and resolutions all go to the incident keyed by
+//! `entity_id`, our dedup key.
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "monitor")]
+use crate::sink::{AlertSink, SinkEvent, SinkEventKind};
+
+/// Settings of an API integration on a Zenduty service
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ZendutyConfig {
+    /// Integration key of the service's API integration
+    pub integration_key: String,
+    /// Events endpoint (default: `https://www.zenduty.com/api/events`)
+    #[serde(default = "default_url")]
+    pub url: String,
+}
+
+fn default_url() -> String {
+    "https://www.zenduty.com/api/events".to_string()
+}
+
+#[cfg(feature = "monitor")]
+pub(crate) struct ZendutySink {
+    config: ZendutyConfig,
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "monitor")]
+impl ZendutySink {
+    pub fn new(config: ZendutyConfig, client: reqwest::Client) -> Self {
+        Self { config, client }
+    }
+}
+
+#[cfg(feature = "monitor")]
+#[async_trait::async_trait]
+impl AlertSink for ZendutySink {
+    async fn send(&self, event: &SinkEvent) -> Result<(), anyhow::Error> {
+        // The endpoint requires the trailing slash
+        let url = format!(
+            "{}/{}/",
+            self.config.url.trim_end_matches('/'),
+            self.config.integration_key
+        );
+        self.client
+            .post(url)
+            .json(&payload(event))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// `alert_type` for an event: triggers by severity, then the lifecycle steps
+#[cfg(feature = "monitor")]
+fn alert_type(event: &SinkEvent) -> &'static str {
+    match event.kind {
+        SinkEventKind::Trigger => match event.severity.as_str() {
+            "critical" => "critical",
+            "error" => "error",
+            "warning" => "warning",
+            _ => "info",
+        },
+        SinkEventKind::Acknowledge => "acknowledged",
+        SinkEventKind::Resolve => "resolved",
+    }
+}
+
+#[cfg(feature = "monitor")]
+fn payload(event: &SinkEvent) -> serde_json::Value {
+    let urls: Vec<serde_json::Value> = event
+        .explorer_link
+        .iter()
+        .map(|link| serde_json::json!({"link_url": link, "link_text": "View Transaction"}))
+        .collect();
+    serde_json::json!({
+        "alert_type": alert_type(event),
+        "message": event.summary,
+        "summary": event.summary,
+        "entity_id": event.alert_key,
+        "urls": urls,
+        "payload": {
+            "subscription": event.subscription,
+            "severity": event.severity,
+            "source": event.source,
+            "details": event.custom_details,
+        },
+    })
+}
+
+#[cfg(all(test, feature = "monitor"))]
+mod tests {
+    use super::*;
+    use crate::sink::test_event;
+
+    #[test]
+    fn test_zenduty_payload() {
+        let mut event = SinkEvent {
+            severity: "error".to_string(),
+            ..test_event()
+        };
+        let body = payload(&event);
+        assert_eq!(body["alert_type"], "error");
+        assert_eq!(body["entity_id"], "venear-pause-venear.near");
+        assert_eq!(
+            body["urls"][0]["link_url"],
+            "https://nearblocks.io/txns/abc"
+        );
+
+        event.kind = SinkEventKind::Acknowledge;
+        assert_eq!(payload(&event)["alert_type"], "acknowledged");
+        event.kind = SinkEventKind::Resolve;
+        assert_eq!(payload(&event)["alert_type"], "resolved");
+    }
+}
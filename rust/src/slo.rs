@@ -0,0 +1,82 @@
+//! Delivery latency SLO
+//!
+//! The monitor samples the p95 latency from an event's block to PagerDuty
+//! accepting its alert. A p95 above the SLO pages only once it has stayed
+//! there for `sustained_secs`, so one slow burst doesn't wake anyone, and
+//! resolves as soon as the p95 is back under the SLO.
+
+use std::time::{Duration, Instant};
+
+use crate::LatencySloConfig;
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum SloTransition {
+    Breached,
+    Recovered,
+}
+
+pub(crate) struct LatencySloTracker {
+    p95_secs: f64,
+    sustained: Duration,
+    /// When the p95 last went above the SLO
+    breach_since: Option<Instant>,
+    paging: bool,
+}
+
+impl LatencySloTracker {
+    pub fn new(config: &LatencySloConfig) -> Self {
+        Self {
+            p95_secs: config.p95_secs,
+            sustained: Duration::from_secs(config.sustained_secs),
+            breach_since: None,
+            paging: false,
+        }
+    }
+
+    /// Feed the current p95 (`None` when nothing was delivered in the window)
+    pub fn update(&mut self, p95: Option<f64>, now: Instant) -> Option<SloTransition> {
+        if !p95.is_some_and(|p95| p95 > self.p95_secs) {
+            self.breach_since = None;
+            return std::mem::take(&mut self.paging).then_some(SloTransition::Recovered);
+        }
+        let since = *self.breach_since.get_or_insert(now);
+        if !self.paging && now.saturating_duration_since(since) >= self.sustained {
+            self.paging = true;
+            return Some(SloTransition::Breached);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pages_only_on_sustained_breach() {
+        let mut tracker = LatencySloTracker::new(&LatencySloConfig {
+            p95_secs: 10.0,
+            window_secs: 300,
+            sustained_secs: 60,
+            routing_key: None,
+        });
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert_eq!(tracker.update(Some(30.0), at(0)), None);
+        // A dip below the SLO restarts the clock
+        assert_eq!(tracker.update(Some(5.0), at(30)), None);
+        assert_eq!(tracker.update(Some(30.0), at(40)), None);
+        assert_eq!(tracker.update(Some(30.0), at(90)), None);
+        assert_eq!(
+            tracker.update(Some(30.0), at(100)),
+            Some(SloTransition::Breached)
+        );
+        assert_eq!(tracker.update(Some(30.0), at(200)), None);
+        assert_eq!(
+            tracker.update(None, at(300)),
+            Some(SloTransition::Recovered)
+        );
+        assert_eq!(tracker.update(None, at(330)), None);
+    }
+}
@@ -0,0 +1,789 @@
+//! Alert lifecycle tracking
+//!
+//! Every alert the monitor sends is tracked per dedup key as an [`AlertRecord`]
+//! whose [`AlertState`] only moves along valid transitions:
+//!
+//! ```text
+//! Pending ──delivered──► Triggered ──acknowledge──► Acknowledged
+//!    │                      │                           │
+//!    ├──────────────────────┴──────── resolve ──────────┴──► Resolved
+//!    │                      └────────── expire ─────────┴──► Expired
+//!    └──── delivery timeout ────► Failed
+//! ```
+//!
+//! Events come from the monitor's own deliveries and TTL sweeps, from the
+//! `ack` and `resolve` commands and chat actions, and from PagerDuty webhooks
+//! when a responder acts on the incident in PagerDuty itself. Resolved,
+//! expired and failed alerts start a fresh lifecycle when triggered again.
+//! When a state file is configured, changes are written to it within a
+//! second so the CLI can inspect what the running monitor considers open.
+//!
+//! The store also remembers the content-hash IDs of recently delivered events,
+//! so an action replayed after a reconnect is never triggered twice, and
+//! which subscriptions are muted until when.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Where an alert is in its lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertState {
+    /// Matched locally, not yet accepted by PagerDuty
+    Pending,
+    /// Accepted by PagerDuty
+    Triggered,
+    /// Acknowledged by a responder
+    Acknowledged,
+    /// Resolved, either by a responder or by the monitor
+    Resolved,
+    /// Dropped locally after its TTL elapsed without being resolved
+    Expired,
+    /// Never accepted by PagerDuty within [`PENDING_TIMEOUT`]
+    Failed,
+}
+
+/// Inputs that drive [`AlertState`] transitions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertEvent {
+    /// A matching action occurred
+    Matched,
+    /// PagerDuty accepted the trigger
+    Delivered,
+    Acknowledge,
+    Resolve,
+    Expire,
+    /// PagerDuty did not accept the trigger within [`PENDING_TIMEOUT`]
+    DeliveryTimeout,
+}
+
+/// How long an alert may stay pending before it counts as failed, e.g.
+/// because every delivery attempt was rejected or it was dropped from a
+/// full queue
+pub const PENDING_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+impl AlertState {
+    /// Whether PagerDuty (or the monitor) still considers the alert active
+    pub fn is_open(self) -> bool {
+        matches!(
+            self,
+            AlertState::Pending | AlertState::Triggered | AlertState::Acknowledged
+        )
+    }
+
+    /// The state after `event`, or `None` if the transition is not allowed
+    pub fn transition(self, event: AlertEvent) -> Option<AlertState> {
+        use AlertEvent as E;
+        use AlertState as S;
+
+        match (self, event) {
+            // A new occurrence of a closed alert starts over; an open one keeps its state
+            (S::Resolved | S::Expired | S::Failed, E::Matched) => Some(S::Pending),
+            (state, E::Matched) => Some(state),
+            // A spilled alert may still be delivered after it timed out
+            (S::Pending | S::Failed, E::Delivered) => Some(S::Triggered),
+            (S::Triggered | S::Acknowledged, E::Delivered) => Some(self),
+            (S::Triggered | S::Acknowledged, E::Acknowledge) => Some(S::Acknowledged),
+            (S::Pending | S::Triggered | S::Acknowledged | S::Resolved, E::Resolve) => {
+                Some(S::Resolved)
+            }
+            (S::Triggered | S::Acknowledged, E::Expire) => Some(S::Expired),
+            (S::Pending, E::DeliveryTimeout) => Some(S::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// Everything the monitor knows about one alert
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRecord {
+    pub dedup_key: String,
+    pub subscription: String,
+    pub severity: String,
+    pub summary: String,
+    pub state: AlertState,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    /// Number of matching actions since the lifecycle started
+    pub occurrences: u64,
+    /// Open alerts expire after this many seconds without a new occurrence
+    #[serde(default)]
+    pub ttl_secs: Option<u64>,
+    /// Occurrences in a row within the `severity_escalation` window
+    #[serde(default)]
+    pub escalation_level: u32,
+    /// When `state` last changed, which decides whose change wins when the
+    /// CLI and a running monitor both update the state file
+    #[serde(default)]
+    pub state_changed_at: Option<DateTime<Utc>>,
+    /// `last_seen` on the monotonic clock, for occurrences seen by this process
+    #[serde(skip)]
+    last_seen_at: Option<Instant>,
+}
+
+impl AlertRecord {
+    /// Time since the last occurrence, which TTLs and escalation windows are
+    /// measured on. Occurrences seen by this process use the monotonic clock,
+    /// so a wall-clock step can't expire or escalate alerts early; ones loaded
+    /// from the state file fall back to `last_seen`, counting one in the
+    /// future as just seen.
+    fn since_last_seen(&self, now: Instant) -> Duration {
+        match self.last_seen_at {
+            Some(at) => now.saturating_duration_since(at),
+            None => (Utc::now() - self.last_seen).to_std().unwrap_or_default(),
+        }
+    }
+
+    fn set_state(&mut self, state: AlertState) {
+        if state != self.state {
+            self.state = state;
+            self.state_changed_at = Some(Utc::now());
+        }
+    }
+}
+
+/// Error returned for a transition that the state machine does not allow
+#[derive(Debug)]
+pub struct InvalidTransition {
+    pub dedup_key: String,
+    pub from: Option<AlertState>,
+    pub event: AlertEvent,
+}
+
+impl std::fmt::Display for InvalidTransition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.from {
+            Some(from) => write!(
+                f,
+                "alert {} cannot go from {:?} on {:?}",
+                self.dedup_key, from, self.event
+            ),
+            None => write!(f, "alert {} is not tracked", self.dedup_key),
+        }
+    }
+}
+
+impl std::error::Error for InvalidTransition {}
+
+/// Number of delivered event IDs remembered for duplicate suppression
+const MAX_SENT_EVENTS: usize = 10_000;
+
+/// Event IDs that were delivered or are being delivered
+#[derive(Default)]
+struct EventLog {
+    /// Delivered IDs, oldest first
+    sent: VecDeque<String>,
+    sent_set: HashSet<String>,
+    in_flight: HashSet<String>,
+}
+
+/// On-disk layout of the state file
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum StateFile {
+    Current {
+        alerts: Vec<AlertRecord>,
+        #[serde(default)]
+        sent_events: Vec<String>,
+        /// End of each muted subscription's mute
+        #[serde(default)]
+        mutes: BTreeMap<String, DateTime<Utc>>,
+        /// When each shadow subscription first ran, for its burn-in
+        #[serde(default)]
+        shadow_starts: BTreeMap<String, DateTime<Utc>>,
+    },
+    /// Files written before delivered events were tracked
+    Legacy(Vec<AlertRecord>),
+}
+
+/// Store of alert lifecycles keyed by dedup key, optionally persisted to a JSON file
+#[derive(Default)]
+pub struct AlertStore {
+    alerts: Mutex<HashMap<String, AlertRecord>>,
+    events: Mutex<EventLog>,
+    mutes: Mutex<BTreeMap<String, DateTime<Utc>>>,
+    shadow_starts: Mutex<BTreeMap<String, DateTime<Utc>>>,
+    path: Option<PathBuf>,
+    /// Changed since the last flush
+    dirty: AtomicBool,
+    /// Held while a flush writes the file
+    writing: Mutex<()>,
+}
+
+impl Drop for AlertStore {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+impl AlertStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open a store backed by `path`, loading existing records if the file exists
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, anyhow::Error> {
+        let path = path.into();
+        let (records, sent_events, mutes, shadow_starts) = match Self::read_file(&path)? {
+            StateFile::Current {
+                alerts,
+                sent_events,
+                mutes,
+                shadow_starts,
+            } => (alerts, sent_events, mutes, shadow_starts),
+            StateFile::Legacy(alerts) => (alerts, Vec::new(), BTreeMap::new(), BTreeMap::new()),
+        };
+        let alerts = records
+            .into_iter()
+            .map(|r| (r.dedup_key.clone(), r))
+            .collect();
+        let events = EventLog {
+            sent_set: sent_events.iter().cloned().collect(),
+            sent: sent_events.into(),
+            in_flight: HashSet::new(),
+        };
+        Ok(Self {
+            alerts: Mutex::new(alerts),
+            events: Mutex::new(events),
+            mutes: Mutex::new(mutes),
+            shadow_starts: Mutex::new(shadow_starts),
+            path: Some(path),
+            dirty: AtomicBool::new(false),
+            writing: Mutex::new(()),
+        })
+    }
+
+    /// Read the records in a state file without taking ownership of it
+    pub fn read_records(path: &Path) -> Result<Vec<AlertRecord>, anyhow::Error> {
+        Ok(match Self::read_file(path)? {
+            StateFile::Current { alerts, .. } | StateFile::Legacy(alerts) => alerts,
+        })
+    }
+
+    fn read_file(path: &Path) -> Result<StateFile, anyhow::Error> {
+        if !path.exists() {
+            return Ok(StateFile::Legacy(Vec::new()));
+        }
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Note a change for the next [`flush`](Self::flush)
+    fn mark_dirty(&self) {
+        if self.path.is_some() {
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Write all records to the state file if anything changed since the
+    /// last flush (a no-op for in-memory stores). A running monitor flushes
+    /// every second and the store flushes when dropped, rather than writing
+    /// on every change.
+    ///
+    /// State changes written to the file since it was read, by the `ack` and
+    /// `resolve` commands while the monitor runs, are adopted first rather
+    /// than overwritten.
+    pub fn flush(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        // One writer at a time, so an older snapshot never replaces a newer one
+        let _writing = self.writing.lock().unwrap();
+        if !self.dirty.swap(false, Ordering::Relaxed) {
+            return;
+        }
+        let on_disk = Self::read_records(path).unwrap_or_else(|e| {
+            log::warn!(
+                "Could not read {} before writing it: {:#}",
+                path.display(),
+                e
+            );
+            Vec::new()
+        });
+
+        let state = {
+            // `alerts` is always locked before `events`, `mutes` and `shadow_starts`
+            let mut alerts = self.alerts.lock().unwrap();
+            for theirs in on_disk {
+                let Some(ours) = alerts.get_mut(&theirs.dedup_key) else {
+                    continue;
+                };
+                if theirs.state_changed_at > ours.state_changed_at {
+                    log::info!(
+                        "Alert {} is {:?}, as recorded in {}",
+                        theirs.dedup_key,
+                        theirs.state,
+                        path.display()
+                    );
+                    ours.state = theirs.state;
+                    ours.state_changed_at = theirs.state_changed_at;
+                }
+            }
+            let mut records: Vec<AlertRecord> = alerts.values().cloned().collect();
+            records.sort_by_key(|r| r.first_seen);
+            StateFile::Current {
+                alerts: records,
+                sent_events: self.events.lock().unwrap().sent.iter().cloned().collect(),
+                mutes: self.mutes.lock().unwrap().clone(),
+                shadow_starts: self.shadow_starts.lock().unwrap().clone(),
+            }
+        };
+
+        // Write to a temp file and rename so readers never see a partial file
+        let tmp = path.with_extension("tmp");
+        let result = serde_json::to_vec(&state)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(std::fs::write(&tmp, json)?))
+            .and_then(|_| Ok(std::fs::rename(&tmp, path)?));
+        if let Err(e) = result {
+            log::error!("Failed to write alert state to {}: {:?}", path.display(), e);
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Record a matching action, creating the alert or starting a new lifecycle
+    pub fn record_match(
+        &self,
+        dedup_key: &str,
+        subscription: &str,
+        severity: &str,
+        summary: &str,
+        ttl_secs: Option<u64>,
+    ) -> AlertState {
+        let now = Utc::now();
+        let mut alerts = self.alerts.lock().unwrap();
+        let record = alerts
+            .entry(dedup_key.to_string())
+            .or_insert_with(|| AlertRecord {
+                dedup_key: dedup_key.to_string(),
+                subscription: subscription.to_string(),
+                severity: severity.to_string(),
+                summary: summary.to_string(),
+                state: AlertState::Resolved,
+                first_seen: now,
+                last_seen: now,
+                occurrences: 0,
+                ttl_secs,
+                escalation_level: 0,
+                state_changed_at: None,
+                last_seen_at: None,
+            });
+
+        if !record.state.is_open() {
+            record.first_seen = now;
+            record.occurrences = 0;
+        }
+        // Matched is valid from every state
+        record.set_state(
+            record
+                .state
+                .transition(AlertEvent::Matched)
+                .unwrap_or(record.state),
+        );
+        record.subscription = subscription.to_string();
+        record.severity = severity.to_string();
+        record.summary = summary.to_string();
+        record.last_seen = now;
+        record.last_seen_at = Some(Instant::now());
+        record.occurrences += 1;
+        record.ttl_secs = ttl_secs;
+        let state = record.state;
+        self.mark_dirty();
+        state
+    }
+
+    /// Count an occurrence towards `severity_escalation`, before it is
+    /// recorded: one level up if the previous occurrence was less than
+    /// `window_secs` before `now`, else back to 0
+    pub fn escalate(&self, dedup_key: &str, window_secs: u64, now: Instant) -> u32 {
+        let mut alerts = self.alerts.lock().unwrap();
+        let Some(record) = alerts.get_mut(dedup_key) else {
+            return 0;
+        };
+        record.escalation_level = if record.since_last_seen(now) < Duration::from_secs(window_secs)
+        {
+            record.escalation_level + 1
+        } else {
+            0
+        };
+        let level = record.escalation_level;
+        self.mark_dirty();
+        level
+    }
+
+    /// Apply a lifecycle event to a tracked alert
+    pub fn apply(
+        &self,
+        dedup_key: &str,
+        event: AlertEvent,
+    ) -> Result<AlertState, InvalidTransition> {
+        let mut alerts = self.alerts.lock().unwrap();
+        let Some(record) = alerts.get_mut(dedup_key) else {
+            return Err(InvalidTransition {
+                dedup_key: dedup_key.to_string(),
+                from: None,
+                event,
+            });
+        };
+        let next = record.state.transition(event).ok_or(InvalidTransition {
+            dedup_key: dedup_key.to_string(),
+            from: Some(record.state),
+            event,
+        })?;
+        record.set_state(next);
+        self.mark_dirty();
+        Ok(next)
+    }
+
+    /// Claim an event for delivery, returning `false` if it was already
+    /// delivered or is being delivered
+    pub fn claim_event(&self, event_id: &str) -> bool {
+        let mut events = self.events.lock().unwrap();
+        if events.sent_set.contains(event_id) {
+            return false;
+        }
+        events.in_flight.insert(event_id.to_string())
+    }
+
+    /// Whether an event is being delivered or was delivered
+    pub fn knows_event(&self, event_id: &str) -> bool {
+        let events = self.events.lock().unwrap();
+        events.sent_set.contains(event_id) || events.in_flight.contains(event_id)
+    }
+
+    /// Give up a claim after delivery failed, so a replay can try again
+    pub fn release_event(&self, event_id: &str) {
+        self.events.lock().unwrap().in_flight.remove(event_id);
+    }
+
+    /// Remember that an event was delivered
+    pub fn mark_event_sent(&self, event_id: &str) {
+        {
+            let mut events = self.events.lock().unwrap();
+            events.in_flight.remove(event_id);
+            if !events.sent_set.insert(event_id.to_string()) {
+                return;
+            }
+            events.sent.push_back(event_id.to_string());
+            if events.sent.len() > MAX_SENT_EVENTS {
+                if let Some(oldest) = events.sent.pop_front() {
+                    events.sent_set.remove(&oldest);
+                }
+            }
+        }
+        self.mark_dirty();
+    }
+
+    /// Mute a subscription until `until`, replacing an earlier mute
+    pub fn mute(&self, subscription: &str, until: DateTime<Utc>) {
+        self.mutes
+            .lock()
+            .unwrap()
+            .insert(subscription.to_string(), until);
+        self.mark_dirty();
+    }
+
+    /// Lift a subscription's mute, returning whether it was muted
+    pub fn unmute(&self, subscription: &str) -> bool {
+        let removed = self.mutes.lock().unwrap().remove(subscription).is_some();
+        if removed {
+            self.mark_dirty();
+        }
+        removed
+    }
+
+    /// When the subscription's mute ends, if it is muted at `now`
+    pub fn muted_until(&self, subscription: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.mutes
+            .lock()
+            .unwrap()
+            .get(subscription)
+            .copied()
+            .filter(|until| *until > now)
+    }
+
+    /// Record `now` as the first run of each shadow subscription in `names`
+    /// not seen before, and forget the others, so one put back into shadow
+    /// later starts its burn-in over
+    pub fn track_shadow_subscriptions(&self, names: &[&str], now: DateTime<Utc>) {
+        let mut shadow_starts = self.shadow_starts.lock().unwrap();
+        let before = shadow_starts.clone();
+        shadow_starts.retain(|name, _| names.contains(&name.as_str()));
+        for name in names {
+            shadow_starts.entry(name.to_string()).or_insert(now);
+        }
+        if *shadow_starts != before {
+            self.mark_dirty();
+        }
+    }
+
+    /// When a shadow subscription first ran, as recorded by
+    /// [`track_shadow_subscriptions`](Self::track_shadow_subscriptions)
+    pub fn shadow_started(&self, subscription: &str) -> Option<DateTime<Utc>> {
+        self.shadow_starts
+            .lock()
+            .unwrap()
+            .get(subscription)
+            .copied()
+    }
+
+    pub fn get(&self, dedup_key: &str) -> Option<AlertRecord> {
+        self.alerts.lock().unwrap().get(dedup_key).cloned()
+    }
+
+    /// All alerts that are still open, oldest first
+    pub fn open_alerts(&self) -> Vec<AlertRecord> {
+        let mut open: Vec<AlertRecord> = self
+            .alerts
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|r| r.state.is_open())
+            .cloned()
+            .collect();
+        open.sort_by_key(|r| r.first_seen);
+        open
+    }
+
+    /// Forget closed alerts last seen before `before`, returning how many
+    pub fn prune_closed(&self, before: DateTime<Utc>) -> usize {
+        let mut alerts = self.alerts.lock().unwrap();
+        let count = alerts.len();
+        alerts.retain(|_, record| record.state.is_open() || record.last_seen >= before);
+        let pruned = count - alerts.len();
+        if pruned > 0 {
+            self.mark_dirty();
+        }
+        pruned
+    }
+
+    /// Expire delivered alerts whose TTL has elapsed since their last occurrence,
+    /// returning the expired dedup keys
+    pub fn expire_stale(&self, now: Instant) -> Vec<String> {
+        let mut expired = Vec::new();
+        let mut alerts = self.alerts.lock().unwrap();
+        for record in alerts.values_mut() {
+            let Some(ttl) = record.ttl_secs else {
+                continue;
+            };
+            if record.since_last_seen(now) < Duration::from_secs(ttl) {
+                continue;
+            }
+            if let Some(next) = record.state.transition(AlertEvent::Expire) {
+                record.set_state(next);
+                expired.push(record.dedup_key.clone());
+            }
+        }
+        if !expired.is_empty() {
+            self.mark_dirty();
+        }
+        expired
+    }
+
+    /// Fail alerts still pending [`PENDING_TIMEOUT`] after their last
+    /// occurrence, returning the failed dedup keys
+    pub fn fail_undelivered(&self, now: Instant) -> Vec<String> {
+        let mut failed = Vec::new();
+        let mut alerts = self.alerts.lock().unwrap();
+        for record in alerts.values_mut() {
+            if record.since_last_seen(now) < PENDING_TIMEOUT {
+                continue;
+            }
+            if let Some(next) = record.state.transition(AlertEvent::DeliveryTimeout) {
+                record.set_state(next);
+                failed.push(record.dedup_key.clone());
+            }
+        }
+        if !failed.is_empty() {
+            self.mark_dirty();
+        }
+        failed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lifecycle_transitions() {
+        let store = AlertStore::new();
+        assert_eq!(
+            store.record_match("k", "sub", "critical", "paused", None),
+            AlertState::Pending
+        );
+        assert_eq!(
+            store.apply("k", AlertEvent::Delivered).unwrap(),
+            AlertState::Triggered
+        );
+        assert!(store.apply("k", AlertEvent::Expire).is_ok());
+        assert!(store.apply("k", AlertEvent::Acknowledge).is_err());
+
+        // A closed alert starts a new lifecycle on the next match
+        assert_eq!(
+            store.record_match("k", "sub", "critical", "paused", None),
+            AlertState::Pending
+        );
+        assert_eq!(store.get("k").unwrap().occurrences, 1);
+        store.apply("k", AlertEvent::Delivered).unwrap();
+        assert_eq!(
+            store.apply("k", AlertEvent::Acknowledge).unwrap(),
+            AlertState::Acknowledged
+        );
+        assert_eq!(
+            store.apply("k", AlertEvent::Resolve).unwrap(),
+            AlertState::Resolved
+        );
+        assert!(store.open_alerts().is_empty());
+        assert!(store.apply("missing", AlertEvent::Resolve).is_err());
+    }
+
+    #[test]
+    fn test_state_file_round_trip() {
+        let path = std::env::temp_dir().join(format!("alert-state-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let store = AlertStore::open(&path).unwrap();
+        store.record_match("k", "sub", "error", "guardians changed", None);
+        store.apply("k", AlertEvent::Delivered).unwrap();
+        store.flush();
+
+        let records = AlertStore::read_records(&path).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].state, AlertState::Triggered);
+
+        assert!(!store.knows_event("event"));
+        assert!(store.claim_event("event"));
+        assert!(store.knows_event("event"));
+        assert!(!store.claim_event("event"));
+        store.mark_event_sent("event");
+        store.flush();
+        assert!(!std::fs::read_to_string(&path).unwrap().contains('\n'));
+
+        let reopened = AlertStore::open(&path).unwrap();
+        assert_eq!(reopened.open_alerts().len(), 1);
+        assert!(reopened.knows_event("event"));
+        assert!(!reopened.claim_event("event"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_cli_changes_survive_monitor_writes() {
+        let path = std::env::temp_dir().join(format!("alert-cli-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let monitor = AlertStore::open(&path).unwrap();
+        monitor.record_match("k", "sub", "critical", "paused", None);
+        monitor.apply("k", AlertEvent::Delivered).unwrap();
+        monitor.flush();
+
+        // `resolve` opens the file on its own while the monitor keeps running
+        let cli = AlertStore::open(&path).unwrap();
+        cli.apply("k", AlertEvent::Resolve).unwrap();
+        drop(cli);
+
+        monitor.mark_event_sent("event");
+        monitor.flush();
+        assert_eq!(monitor.get("k").unwrap().state, AlertState::Resolved);
+        let records = AlertStore::read_records(&path).unwrap();
+        assert_eq!(records[0].state, AlertState::Resolved);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_mutes_survive_reopen() {
+        let path = std::env::temp_dir().join(format!("alert-mutes-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let now = Utc::now();
+        let store = AlertStore::open(&path).unwrap();
+        store.mute("Flapping", now + chrono::Duration::hours(2));
+        store.mute("Expired", now - chrono::Duration::minutes(1));
+        assert!(store.muted_until("Flapping", now).is_some());
+        assert_eq!(store.muted_until("Expired", now), None);
+        assert_eq!(store.muted_until("Other", now), None);
+        store.flush();
+
+        let reopened = AlertStore::open(&path).unwrap();
+        assert_eq!(
+            reopened.muted_until("Flapping", now),
+            Some(now + chrono::Duration::hours(2))
+        );
+        assert!(reopened.unmute("Flapping"));
+        assert!(!reopened.unmute("Flapping"));
+        reopened.flush();
+        assert_eq!(
+            AlertStore::open(&path)
+                .unwrap()
+                .muted_until("Flapping", now),
+            None
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_expire_stale_respects_ttl() {
+        let store = AlertStore::new();
+        store.record_match("short", "sub", "info", "a", Some(60));
+        store.record_match("forever", "sub", "info", "b", None);
+        store.apply("short", AlertEvent::Delivered).unwrap();
+        store.apply("forever", AlertEvent::Delivered).unwrap();
+
+        assert!(store.expire_stale(Instant::now()).is_empty());
+        let later = Instant::now() + Duration::from_secs(120);
+        assert_eq!(store.expire_stale(later), vec!["short".to_string()]);
+        assert_eq!(store.get("short").unwrap().state, AlertState::Expired);
+        assert_eq!(store.get("forever").unwrap().state, AlertState::Triggered);
+    }
+
+    #[test]
+    fn test_undelivered_alerts_fail() {
+        let store = AlertStore::new();
+        store.record_match("stuck", "sub", "critical", "a", None);
+        store.record_match("sent", "sub", "critical", "b", None);
+        store.apply("sent", AlertEvent::Delivered).unwrap();
+
+        assert!(store.fail_undelivered(Instant::now()).is_empty());
+        let later = Instant::now() + PENDING_TIMEOUT;
+        assert_eq!(store.fail_undelivered(later), vec!["stuck".to_string()]);
+        assert_eq!(store.get("stuck").unwrap().state, AlertState::Failed);
+        assert_eq!(store.get("sent").unwrap().state, AlertState::Triggered);
+        assert!(store.open_alerts().iter().all(|r| r.dedup_key == "sent"));
+
+        // A late delivery from the spill still counts
+        assert_eq!(
+            store.apply("stuck", AlertEvent::Delivered).unwrap(),
+            AlertState::Triggered
+        );
+    }
+
+    #[test]
+    fn test_prune_closed_keeps_open_alerts() {
+        let store = AlertStore::new();
+        store.record_match("open", "sub", "info", "a", None);
+        store.record_match("resolved", "sub", "info", "b", None);
+        store.apply("resolved", AlertEvent::Delivered).unwrap();
+        store.apply("resolved", AlertEvent::Resolve).unwrap();
+
+        assert_eq!(
+            store.prune_closed(Utc::now() - chrono::Duration::days(1)),
+            0
+        );
+        assert_eq!(
+            store.prune_closed(Utc::now() + chrono::Duration::days(1)),
+            1
+        );
+        assert!(store.get("resolved").is_none());
+        assert!(store.get("open").is_some());
+    }
+}
@@ -0,0 +1,472 @@
+//! Runtime statistics for the dashboard and status API
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::{AlertRecord, EventSubscription, NeardataAction};
+
+/// Number of recently matched events kept for display
+const RECENT_MATCHES: usize = 50;
+
+/// Default span of deliveries the latency percentile covers
+const LATENCY_WINDOW_SECS: u64 = 300;
+
+/// A matched action as shown on the dashboard
+#[derive(Debug, Clone, Serialize)]
+pub struct MatchedEvent {
+    pub time: DateTime<Utc>,
+    pub subscription: String,
+    pub severity: String,
+    pub summary: String,
+    pub dedup_key: Option<String>,
+    pub tx_hash: Option<String>,
+    pub block_height: u64,
+    /// Whether PagerDuty accepted the alert (`None` while sending)
+    pub delivered: Option<bool>,
+}
+
+/// A matched action kept in full, for `/debug/recent`
+#[derive(Debug, Clone, Serialize)]
+pub struct RecentEvent {
+    pub time: DateTime<Utc>,
+    pub event_id: String,
+    pub severity: String,
+    pub summary: String,
+    pub dedup_key: Option<String>,
+    pub action: NeardataAction,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkStatus {
+    pub name: String,
+    pub connected_since: Option<DateTime<Utc>>,
+    /// Seconds between the newest received event's block and its arrival
+    pub event_lag_secs: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SubscriptionStatus {
+    pub name: String,
+    pub network: String,
+    pub account_id: String,
+    pub method_name: Option<String>,
+    pub severity: String,
+    pub connected: bool,
+    pub matches: u64,
+    pub last_match: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ErrorCounters {
+    /// WebSocket connection failures and disconnects
+    pub stream_errors: u64,
+    /// Messages from neardata that could not be parsed
+    pub parse_errors: u64,
+    /// Alerts PagerDuty did not accept
+    pub delivery_failures: u64,
+    /// Alerts discarded because the alert queue was full
+    pub dropped_alerts: u64,
+    /// Alerts written to disk because the alert queue was full
+    pub spilled_alerts: u64,
+    /// Matching events found by the cross-check that the stream never delivered
+    pub missed_events: u64,
+    /// Panics caught in monitor tasks, which were restarted
+    pub task_panics: u64,
+    /// Events skipped because processing them panicked
+    pub event_panics: u64,
+}
+
+/// Point-in-time view of the monitor
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusSnapshot {
+    pub started_at: DateTime<Utc>,
+    /// Whether every network's stream is connected
+    pub connected: bool,
+    pub networks: Vec<NetworkStatus>,
+    pub reconnects: u64,
+    pub subscriptions: Vec<SubscriptionStatus>,
+    pub recent_matches: Vec<MatchedEvent>,
+    pub open_alerts: Vec<AlertRecord>,
+    /// Alerts waiting to be sent to PagerDuty
+    pub queued_alerts: usize,
+    /// Alerts folded into a queued alert with the same dedup key
+    pub coalesced_alerts: u64,
+    /// 95th percentile seconds from block to accepted PagerDuty event, over the latency window
+    pub delivery_latency_p95_secs: Option<f64>,
+    /// Shutting down: no new events are read while the queue is delivered
+    pub draining: bool,
+    pub errors: ErrorCounters,
+}
+
+struct StatsInner {
+    /// Network name -> time its stream connected (`None` while disconnected)
+    networks: BTreeMap<String, Option<DateTime<Utc>>>,
+    /// Network name -> lag of the last frame with events
+    event_lag: BTreeMap<String, f64>,
+    reconnects: u64,
+    coalesced_alerts: u64,
+    subscriptions: Vec<SubscriptionStatus>,
+    recent_matches: VecDeque<MatchedEvent>,
+    /// Subscription name -> its latest matches, newest first
+    recent_events: BTreeMap<String, VecDeque<RecentEvent>>,
+    /// Delivery latencies in seconds by when they were recorded, on the
+    /// monotonic clock so a wall-clock step can't empty or stretch the window
+    delivery_latencies: VecDeque<(Instant, f64)>,
+    draining: bool,
+    errors: ErrorCounters,
+}
+
+/// Counters and recent history updated by the monitor as it runs
+pub struct MonitorStats {
+    started_at: DateTime<Utc>,
+    /// Matches kept per subscription for `/debug/recent`
+    recent_events_capacity: usize,
+    latency_window: Duration,
+    inner: Mutex<StatsInner>,
+}
+
+impl MonitorStats {
+    pub fn new(subscriptions: &[EventSubscription]) -> Self {
+        let networks = subscriptions
+            .iter()
+            .map(|s| (s.network.clone(), None))
+            .collect();
+        let subscriptions = subscriptions
+            .iter()
+            .map(|s| SubscriptionStatus {
+                name: s.name.clone(),
+                network: s.network.clone(),
+                account_id: s.account_id.clone(),
+                method_name: s.method_name.clone(),
+                severity: s.severity.clone(),
+                connected: false,
+                matches: 0,
+                last_match: None,
+            })
+            .collect();
+        Self {
+            started_at: Utc::now(),
+            recent_events_capacity: 0,
+            latency_window: Duration::from_secs(LATENCY_WINDOW_SECS),
+            inner: Mutex::new(StatsInner {
+                networks,
+                event_lag: BTreeMap::new(),
+                reconnects: 0,
+                coalesced_alerts: 0,
+                subscriptions,
+                recent_matches: VecDeque::with_capacity(RECENT_MATCHES),
+                recent_events: BTreeMap::new(),
+                delivery_latencies: VecDeque::new(),
+                draining: false,
+                errors: ErrorCounters::default(),
+            }),
+        }
+    }
+
+    /// Keep the last `capacity` matches of each subscription in full
+    pub fn with_recent_events(mut self, capacity: usize) -> Self {
+        self.recent_events_capacity = capacity;
+        self
+    }
+
+    /// Compute the delivery latency percentile over this many seconds (default: 300)
+    pub fn with_latency_window(mut self, secs: u64) -> Self {
+        self.latency_window = Duration::from_secs(secs);
+        self
+    }
+
+    pub fn record_connected(&self, network: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.networks.insert(network.to_string(), Some(Utc::now()));
+        for sub in inner
+            .subscriptions
+            .iter_mut()
+            .filter(|s| s.network == network)
+        {
+            sub.connected = true;
+        }
+    }
+
+    pub fn record_disconnected(&self, network: &str, error: bool) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.networks.insert(network.to_string(), None);
+        inner.reconnects += 1;
+        if error {
+            inner.errors.stream_errors += 1;
+        }
+        for sub in inner
+            .subscriptions
+            .iter_mut()
+            .filter(|s| s.network == network)
+        {
+            sub.connected = false;
+        }
+    }
+
+    pub fn record_event_lag(&self, network: &str, lag_secs: f64) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.event_lag.insert(network.to_string(), lag_secs);
+    }
+
+    pub fn record_parse_error(&self) {
+        self.inner.lock().unwrap().errors.parse_errors += 1;
+    }
+
+    pub fn record_dropped(&self) {
+        self.inner.lock().unwrap().errors.dropped_alerts += 1;
+    }
+
+    pub fn record_spilled(&self) {
+        self.inner.lock().unwrap().errors.spilled_alerts += 1;
+    }
+
+    pub fn record_coalesced(&self) {
+        self.inner.lock().unwrap().coalesced_alerts += 1;
+    }
+
+    pub fn record_missed_events(&self, count: u64) {
+        self.inner.lock().unwrap().errors.missed_events += count;
+    }
+
+    pub fn record_task_panic(&self) {
+        self.inner.lock().unwrap().errors.task_panics += 1;
+    }
+
+    pub fn record_event_panic(&self) {
+        self.inner.lock().unwrap().errors.event_panics += 1;
+    }
+
+    pub fn record_match(&self, event: MatchedEvent) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(sub) = inner
+            .subscriptions
+            .iter_mut()
+            .find(|s| s.name == event.subscription)
+        {
+            sub.matches += 1;
+            sub.last_match = Some(event.time);
+        }
+        if inner.recent_matches.len() == RECENT_MATCHES {
+            inner.recent_matches.pop_back();
+        }
+        inner.recent_matches.push_front(event);
+    }
+
+    pub fn record_recent_event(&self, subscription: &str, event: RecentEvent) {
+        if self.recent_events_capacity == 0 {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        let events = inner
+            .recent_events
+            .entry(subscription.to_string())
+            .or_default();
+        if events.len() == self.recent_events_capacity {
+            events.pop_back();
+        }
+        events.push_front(event);
+    }
+
+    /// Latest matches by subscription, newest first, optionally for one subscription
+    pub fn recent_events(&self, subscription: Option<&str>) -> BTreeMap<String, Vec<RecentEvent>> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .recent_events
+            .iter()
+            .filter(|(name, _)| subscription.is_none_or(|s| s == name.as_str()))
+            .map(|(name, events)| (name.clone(), events.iter().cloned().collect()))
+            .collect()
+    }
+
+    /// Record the delivery outcome of the most recent match for a dedup key
+    pub fn record_delivery(&self, subscription: &str, dedup_key: Option<&str>, delivered: bool) {
+        let mut inner = self.inner.lock().unwrap();
+        if !delivered {
+            inner.errors.delivery_failures += 1;
+        }
+        if let Some(event) = inner
+            .recent_matches
+            .iter_mut()
+            .find(|e| e.subscription == subscription && e.dedup_key.as_deref() == dedup_key)
+        {
+            event.delivered = Some(delivered);
+        }
+    }
+
+    /// Record seconds from an event's block to PagerDuty accepting its alert
+    pub fn record_delivery_latency(&self, latency_secs: f64) {
+        let now = Instant::now();
+        let mut inner = self.inner.lock().unwrap();
+        inner.delivery_latencies.push_back((now, latency_secs));
+        while inner
+            .delivery_latencies
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) > self.latency_window)
+        {
+            inner.delivery_latencies.pop_front();
+        }
+    }
+
+    /// 95th percentile delivery latency within the window, if anything was delivered
+    pub fn delivery_latency_p95(&self) -> Option<f64> {
+        let inner = self.inner.lock().unwrap();
+        p95(&inner.delivery_latencies, self.latency_window)
+    }
+
+    pub fn set_draining(&self) {
+        self.inner.lock().unwrap().draining = true;
+    }
+
+    pub fn started_at(&self) -> DateTime<Utc> {
+        self.started_at
+    }
+
+    /// Whether a network's stream is connected
+    pub fn is_connected(&self, network: &str) -> bool {
+        self.inner
+            .lock()
+            .unwrap()
+            .networks
+            .get(network)
+            .is_some_and(Option::is_some)
+    }
+
+    /// Whether every network's stream is connected
+    pub fn all_connected(&self) -> bool {
+        self.inner
+            .lock()
+            .unwrap()
+            .networks
+            .values()
+            .all(Option::is_some)
+    }
+
+    pub fn snapshot(&self, open_alerts: Vec<AlertRecord>, queued_alerts: usize) -> StatusSnapshot {
+        let inner = self.inner.lock().unwrap();
+        StatusSnapshot {
+            started_at: self.started_at,
+            connected: inner.networks.values().all(Option::is_some),
+            networks: inner
+                .networks
+                .iter()
+                .map(|(name, since)| NetworkStatus {
+                    name: name.clone(),
+                    connected_since: *since,
+                    event_lag_secs: inner.event_lag.get(name).copied(),
+                })
+                .collect(),
+            reconnects: inner.reconnects,
+            subscriptions: inner.subscriptions.clone(),
+            recent_matches: inner.recent_matches.iter().cloned().collect(),
+            open_alerts,
+            queued_alerts,
+            coalesced_alerts: inner.coalesced_alerts,
+            delivery_latency_p95_secs: p95(&inner.delivery_latencies, self.latency_window),
+            draining: inner.draining,
+            errors: inner.errors.clone(),
+        }
+    }
+}
+
+/// Nearest-rank 95th percentile of the latencies recorded since `cutoff`
+fn p95(latencies: &VecDeque<(Instant, f64)>, window: Duration) -> Option<f64> {
+    let now = Instant::now();
+    let mut values: Vec<f64> = latencies
+        .iter()
+        .filter(|(at, _)| now.duration_since(*at) <= window)
+        .map(|(_, latency)| *latency)
+        .collect();
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f64::total_cmp);
+    let rank = (values.len() * 95).div_ceil(100);
+    Some(values[rank - 1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matched(subscription: &str, dedup_key: &str) -> MatchedEvent {
+        MatchedEvent {
+            time: Utc::now(),
+            subscription: subscription.to_string(),
+            severity: "info".to_string(),
+            summary: "vote".to_string(),
+            dedup_key: Some(dedup_key.to_string()),
+            tx_hash: None,
+            block_height: 1,
+            delivered: None,
+        }
+    }
+
+    #[test]
+    fn test_recent_matches_are_bounded_and_track_delivery() {
+        let config = crate::venear_pause_config("key", "venear.near");
+        let stats = MonitorStats::new(&config.subscriptions);
+        let name = config.subscriptions[0].name.as_str();
+
+        for i in 0..(RECENT_MATCHES + 10) {
+            stats.record_match(matched(name, &i.to_string()));
+        }
+        stats.record_delivery(name, Some("59"), false);
+
+        let snapshot = stats.snapshot(Vec::new(), 0);
+        assert_eq!(snapshot.recent_matches.len(), RECENT_MATCHES);
+        assert_eq!(snapshot.recent_matches[0].delivered, Some(false));
+        assert_eq!(
+            snapshot.subscriptions[0].matches,
+            (RECENT_MATCHES + 10) as u64
+        );
+        assert_eq!(snapshot.errors.delivery_failures, 1);
+        assert_eq!(snapshot.delivery_latency_p95_secs, None);
+
+        for latency in 1..=20 {
+            stats.record_delivery_latency(latency as f64);
+        }
+        assert_eq!(stats.delivery_latency_p95(), Some(19.0));
+    }
+
+    #[test]
+    fn test_recent_events_per_subscription() {
+        let config = crate::venear_pause_config("key", "venear.near");
+        let stats = MonitorStats::new(&config.subscriptions).with_recent_events(3);
+        let action: NeardataAction = serde_json::from_value(serde_json::json!({
+            "blockHeight": 1, "blockHash": "h", "blockTimestampMs": 0,
+            "accountId": "venear.near", "receiptId": "r", "predecessorId": "p",
+            "signerId": "s", "action": "Unknown", "status": "SUCCESS"
+        }))
+        .unwrap();
+
+        for (subscription, i) in [("a", 0), ("a", 1), ("b", 2), ("a", 3), ("a", 4)] {
+            stats.record_recent_event(
+                subscription,
+                RecentEvent {
+                    time: Utc::now(),
+                    event_id: i.to_string(),
+                    severity: "info".to_string(),
+                    summary: "vote".to_string(),
+                    dedup_key: None,
+                    action: action.clone(),
+                },
+            );
+        }
+
+        let recent = stats.recent_events(None);
+        let ids: Vec<&str> = recent["a"].iter().map(|e| e.event_id.as_str()).collect();
+        assert_eq!(ids, ["4", "3", "1"]);
+        assert_eq!(recent["b"].len(), 1);
+        assert_eq!(stats.recent_events(Some("b")).len(), 1);
+
+        let disabled = MonitorStats::new(&config.subscriptions);
+        disabled.record_recent_event("a", recent["a"][0].clone());
+        assert!(disabled.recent_events(None).is_empty());
+    }
+}
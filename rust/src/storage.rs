@@ -0,0 +1,114 @@
+//! NEP-145 storage management calls on token contracts
+//!
+//! An account must be registered with a NEP-141 token to hold it. A
+//! treasury that `storage_unregister`s, with `force` burning any balance it
+//! still has, can no longer receive the token, which is easy to miss among
+//! ordinary transfers. [`StorageEvent`] reads these calls, and
+//! [`action_fields`](crate::action_fields) exposes it as `storage` for
+//! filters and templates.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A storage registration change
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StorageEvent {
+    /// `storage_deposit`, registering `account_id` or topping up its storage
+    Deposit {
+        account_id: String,
+        /// Deposited yoctoNEAR
+        deposit: Option<String>,
+        /// Only register, refunding anything above the minimum
+        registration_only: bool,
+    },
+    /// `storage_unregister` by `account_id` itself
+    Unregister {
+        account_id: String,
+        /// Unregister even with a balance left, burning it
+        force: bool,
+    },
+}
+
+impl StorageEvent {
+    /// Read a call from its method, decoded JSON arguments, deposit and
+    /// caller; `None` for other methods or calls without a caller
+    pub fn from_call(
+        method_name: &str,
+        args: &Value,
+        deposit: Option<&str>,
+        predecessor_id: Option<&str>,
+    ) -> Option<Self> {
+        match method_name {
+            "storage_deposit" => Some(Self::Deposit {
+                // Registers the caller unless `account_id` is given
+                account_id: args["account_id"].as_str().or(predecessor_id)?.to_string(),
+                deposit: deposit.map(str::to_string),
+                registration_only: args["registration_only"].as_bool().unwrap_or(false),
+            }),
+            "storage_unregister" => Some(Self::Unregister {
+                account_id: predecessor_id?.to_string(),
+                force: args["force"].as_bool().unwrap_or(false),
+            }),
+            _ => None,
+        }
+    }
+
+    /// The account whose registration changed
+    pub fn account_id(&self) -> &str {
+        match self {
+            Self::Deposit { account_id, .. } | Self::Unregister { account_id, .. } => account_id,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_storage_events_from_calls() {
+        let deposit = StorageEvent::from_call(
+            "storage_deposit",
+            &serde_json::json!({"account_id": "treasury.near", "registration_only": true}),
+            Some("1250000000000000000000"),
+            Some("relayer.near"),
+        )
+        .unwrap();
+        assert_eq!(deposit.account_id(), "treasury.near");
+        assert_eq!(
+            serde_json::to_value(&deposit).unwrap(),
+            serde_json::json!({
+                "kind": "deposit",
+                "account_id": "treasury.near",
+                "deposit": "1250000000000000000000",
+                "registration_only": true,
+            })
+        );
+        // Without arguments the caller registers itself
+        let own =
+            StorageEvent::from_call("storage_deposit", &Value::Null, None, Some("alice.near"));
+        assert_eq!(own.unwrap().account_id(), "alice.near");
+
+        assert_eq!(
+            StorageEvent::from_call(
+                "storage_unregister",
+                &serde_json::json!({"force": true}),
+                Some("1"),
+                Some("treasury.near")
+            ),
+            Some(StorageEvent::Unregister {
+                account_id: "treasury.near".to_string(),
+                force: true,
+            })
+        );
+        assert_eq!(
+            StorageEvent::from_call("storage_unregister", &Value::Null, None, None),
+            None
+        );
+        assert_eq!(
+            StorageEvent::from_call("ft_transfer", &Value::Null, None, Some("alice.near")),
+            None
+        );
+    }
+}
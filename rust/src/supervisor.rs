@@ -0,0 +1,213 @@
+//! Restarting monitor tasks that panic
+//!
+//! Each network's stream, its cross-check and the background watchers run
+//! under [`Supervisor::run`], which catches a panic and starts the task again
+//! instead of letting it take the rest of the monitor down. A task that
+//! panics more than `max_restarts` times within `window_secs` is crash
+//! looping: the meta routing key is paged and the task restarts at the
+//! budget's pace until it stays up for a whole window, which resolves the page.
+
+use std::{
+    any::Any,
+    collections::{HashMap, VecDeque},
+    future::Future,
+    panic::AssertUnwindSafe,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures_util::FutureExt;
+use tokio::time::Instant;
+
+use crate::{MonitorStats, PagerDutyClient, TaskRestartConfig};
+
+#[derive(Default)]
+struct TaskHistory {
+    /// When the task panicked within the current window
+    panics: VecDeque<Instant>,
+    /// Whether the meta routing key was paged about this task
+    escalated: bool,
+}
+
+pub(crate) struct Supervisor {
+    budget: TaskRestartConfig,
+    /// Pause before restarting a task that is within its budget
+    restart_delay: Duration,
+    meta_client: Arc<PagerDutyClient>,
+    stats: Arc<MonitorStats>,
+    tasks: Mutex<HashMap<String, TaskHistory>>,
+}
+
+impl Supervisor {
+    pub fn new(
+        budget: TaskRestartConfig,
+        meta_client: Arc<PagerDutyClient>,
+        stats: Arc<MonitorStats>,
+    ) -> Self {
+        Self {
+            budget,
+            restart_delay: Duration::from_secs(1),
+            meta_client,
+            stats,
+            tasks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn window(&self) -> Duration {
+        Duration::from_secs(self.budget.window_secs)
+    }
+
+    /// Run the future `start` returns until it finishes, starting a new one
+    /// whenever it panics
+    pub async fn run<T, F, Fut>(&self, task: &str, mut start: F) -> T
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        loop {
+            let mut running = std::pin::pin!(AssertUnwindSafe(start()).catch_unwind());
+            let mut stable = std::pin::pin!(tokio::time::sleep(self.window()));
+            let mut stayed_up = false;
+            let result = loop {
+                tokio::select! {
+                    result = &mut running => break result,
+                    _ = &mut stable, if !stayed_up => {
+                        stayed_up = true;
+                        self.stayed_up(task).await;
+                    }
+                }
+            };
+            match result {
+                Ok(output) => return output,
+                Err(panic) => {
+                    let message = panic_message(panic.as_ref());
+                    log::error!("Task {} panicked: {}", task, message);
+                    self.stats.record_task_panic();
+                    let delay = self.record_panic(task, &message).await;
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+
+    /// Count a panic against the task's budget, paging once it is exceeded,
+    /// and return how long to wait before restarting it
+    async fn record_panic(&self, task: &str, message: &str) -> Duration {
+        let now = Instant::now();
+        let (panics, escalate) = {
+            let mut tasks = self.tasks.lock().unwrap();
+            let history = tasks.entry(task.to_string()).or_default();
+            history.panics.push_back(now);
+            while history
+                .panics
+                .front()
+                .is_some_and(|&t| now - t > self.window())
+            {
+                history.panics.pop_front();
+            }
+            let panics = history.panics.len();
+            if panics as u32 <= self.budget.max_restarts {
+                return self.restart_delay;
+            }
+            let escalate = !history.escalated;
+            history.escalated = true;
+            (panics, escalate)
+        };
+
+        if escalate {
+            let summary = format!(
+                "Monitor task {} is crash looping ({} panics in {}s)",
+                task, panics, self.budget.window_secs
+            );
+            log::error!("{}", summary);
+            let details = serde_json::json!({
+                "task": task,
+                "panics": panics,
+                "window_secs": self.budget.window_secs,
+                "last_panic": message,
+            });
+            if let Err(e) = self
+                .meta_client
+                .trigger(
+                    &summary,
+                    "near-pagerduty-monitor",
+                    "critical",
+                    Some(crash_loop_dedup_key(task)),
+                    Some(details),
+                    &[],
+                )
+                .await
+            {
+                log::error!("Failed to send crash loop alert: {:?}", e);
+            }
+        }
+        // Over budget, keep restarting only as fast as the budget allows
+        self.window() / self.budget.max_restarts.max(1)
+    }
+
+    /// Forget a task's panics once it has run for a whole window, resolving
+    /// its crash loop page if there was one
+    async fn stayed_up(&self, task: &str) {
+        let escalated = match self.tasks.lock().unwrap().remove(task) {
+            Some(history) => history.escalated,
+            None => return,
+        };
+        if !escalated {
+            return;
+        }
+        log::info!("Task {} recovered from its crash loop", task);
+        if let Err(e) = self.meta_client.resolve(&crash_loop_dedup_key(task)).await {
+            log::error!("Failed to resolve crash loop alert: {:?}", e);
+        }
+    }
+}
+
+fn crash_loop_dedup_key(task: &str) -> String {
+    format!("near-pagerduty-monitor-crash-loop-{}", task)
+}
+
+/// The message a panic was raised with
+pub(crate) fn panic_message(panic: &(dyn Any + Send)) -> String {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "(non-string panic payload)".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_restarts_panicking_task() {
+        let stats = Arc::new(MonitorStats::new(&[]));
+        let mut supervisor = Supervisor::new(
+            TaskRestartConfig::default(),
+            Arc::new(PagerDutyClient::new("key".to_string())),
+            Arc::clone(&stats),
+        );
+        supervisor.restart_delay = Duration::ZERO;
+
+        let mut attempts = 0;
+        let output = supervisor
+            .run("flaky", || {
+                attempts += 1;
+                let attempt = attempts;
+                async move {
+                    if attempt < 3 {
+                        panic!("attempt {} failed", attempt);
+                    }
+                    attempt
+                }
+            })
+            .await;
+        assert_eq!(output, 3);
+        assert_eq!(stats.snapshot(Vec::new(), 0).errors.task_panics, 2);
+        let tasks = supervisor.tasks.lock().unwrap();
+        assert_eq!(tasks["flaky"].panics.len(), 2);
+        assert!(!tasks["flaky"].escalated);
+    }
+}
@@ -0,0 +1,79 @@
+//! systemd service notifications
+//!
+//! When run as a `Type=notify` unit, the monitor reports `READY=1` once every
+//! network's stream is connected and, if `WatchdogSec=` is set, pets the
+//! watchdog only while all streams stay connected. Outside systemd
+//! (`NOTIFY_SOCKET` unset) these are no-ops.
+
+use std::time::Duration;
+
+/// Send a state string such as `READY=1` to systemd, returning whether it was delivered
+pub(crate) fn notify(state: &str) -> bool {
+    let Ok(socket) = std::env::var("NOTIFY_SOCKET") else {
+        return false;
+    };
+    match notify_socket(&socket, state) {
+        Ok(()) => true,
+        Err(e) => {
+            log::warn!("Failed to notify systemd ({}): {:?}", state, e);
+            false
+        }
+    }
+}
+
+/// How often to pet the watchdog: half of `WATCHDOG_USEC`, if the watchdog is meant for us
+pub(crate) fn watchdog_interval() -> Option<Duration> {
+    let usec: u64 = std::env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    if let Ok(pid) = std::env::var("WATCHDOG_PID") {
+        if pid.parse::<u32>().ok()? != std::process::id() {
+            return None;
+        }
+    }
+    Some(Duration::from_micros(usec / 2))
+}
+
+#[cfg(unix)]
+fn notify_socket(socket: &str, state: &str) -> std::io::Result<()> {
+    use std::os::unix::net::UnixDatagram;
+
+    let datagram = UnixDatagram::unbound()?;
+    match socket.strip_prefix('@') {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            datagram.send_to_addr(state.as_bytes(), &addr)?;
+        }
+        _ => {
+            datagram.send_to(state.as_bytes(), socket)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn notify_socket(_socket: &str, _state: &str) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "sd_notify requires Unix domain sockets",
+    ))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixDatagram;
+
+    #[test]
+    fn test_notify_socket_sends_state() {
+        let path = std::env::temp_dir().join(format!("notify-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let systemd = UnixDatagram::bind(&path).unwrap();
+
+        notify_socket(path.to_str().unwrap(), "READY=1").unwrap();
+        let mut buf = [0u8; 64];
+        let len = systemd.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"READY=1");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
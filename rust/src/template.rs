@@ -0,0 +1,293 @@
+//! Template helpers beyond plain `{placeholder}` substitution
+//!
+//! `{block_timestamp | datetime("%Y-%m-%d %H:%M %Z", "Europe/Berlin")}`
+//! renders the block time in a time zone. Without the second argument the
+//! subscription's `timezone` is used, and UTC without that.
+//!
+//! `{args.proposal_id}` renders a field of the call's decoded JSON arguments,
+//! and `{args.amount | token_amount}` a NEP-141 amount in the units and
+//! symbol of the token contract, such as `500,000 USDC`. On storage
+//! management calls, `{storage.account_id}` is the account whose token
+//! registration changed (see [`StorageEvent`](crate::StorageEvent)). On
+//! meta transactions, `{meta_tx.relayer_id}` is the relayer that submitted
+//! it and `{meta_tx.sender_id}` the user who signed the delegated actions
+//! (see [`MetaTransaction`](crate::MetaTransaction)). Actions read from
+//! blocks carry their receipt's NEP-297 events, e.g. `{events.0.event}`.
+//!
+//! A subscription's `custom_details_template` is JSON whose strings may use
+//! any field of the action, such as `{predecessor_id}` or `{args.deadline}`;
+//! a string that is just one placeholder keeps the field's JSON type.
+
+use std::sync::OnceLock;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use regex::{Captures, Regex};
+use serde_json::Value;
+
+fn datetime_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(
+            r#"\{block_timestamp\s*\|\s*datetime\(\s*"([^"]*)"\s*(?:,\s*"([^"]*)"\s*)?\)\s*\}"#,
+        )
+        .expect("valid datetime pattern")
+    })
+}
+
+fn field_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"\{((?:args|storage|meta_tx|events)(?:\.[A-Za-z0-9_]+)+)\}")
+            .expect("valid field pattern")
+    })
+}
+
+fn path_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"\{([a-z_][a-z0-9_]*(?:\.[A-Za-z0-9_]+)*)\}").expect("valid path pattern")
+    })
+}
+
+fn token_amount_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"\{(args(?:\.[A-Za-z0-9_]+)+)\s*\|\s*token_amount\s*\}")
+            .expect("valid token amount pattern")
+    })
+}
+
+/// Whether a template renders token amounts, so token metadata is needed
+pub fn uses_token_amounts(template: &str) -> bool {
+    token_amount_pattern().is_match(template)
+}
+
+/// Parse an IANA time zone name such as `Europe/Berlin`
+pub fn parse_timezone(name: &str) -> Result<Tz, anyhow::Error> {
+    name.parse()
+        .map_err(|_| anyhow::anyhow!("unknown time zone '{}'", name))
+}
+
+/// Check a template's `datetime` formats and time zones
+pub fn validate(template: &str) -> Result<(), anyhow::Error> {
+    for captures in datetime_pattern().captures_iter(template) {
+        if !valid_format(&captures[1]) {
+            anyhow::bail!("invalid datetime format \"{}\"", &captures[1]);
+        }
+        if let Some(name) = captures.get(2) {
+            parse_timezone(name.as_str())?;
+        }
+    }
+    Ok(())
+}
+
+/// Expand `{block_timestamp | datetime(...)}` and `{block_timestamp}` (milliseconds)
+pub fn render_timestamps(template: &str, timestamp_ms: Option<f64>, default_tz: Tz) -> String {
+    let time = timestamp_ms.and_then(|ms| DateTime::<Utc>::from_timestamp_millis(ms as i64));
+    let rendered = datetime_pattern().replace_all(template, |captures: &Captures| {
+        let Some(time) = time else {
+            return "unknown".to_string();
+        };
+        let format = &captures[1];
+        if !valid_format(format) {
+            return "invalid datetime format".to_string();
+        }
+        match captures.get(2).map(|name| parse_timezone(name.as_str())) {
+            None => time.with_timezone(&default_tz).format(format).to_string(),
+            Some(Ok(tz)) => time.with_timezone(&tz).format(format).to_string(),
+            Some(Err(_)) => "invalid time zone".to_string(),
+        }
+    });
+    let raw = timestamp_ms.map_or_else(|| "unknown".to_string(), |ms| (ms as i64).to_string());
+    rendered.replace("{block_timestamp}", &raw)
+}
+
+/// Expand `{args.<path>}` from an action's fields (see
+/// [`action_fields`](crate::action_fields)), or `None` if any of them is missing
+pub fn render_fields(template: &str, fields: &Value) -> Option<String> {
+    let (rendered, missing) = expand_fields(template, fields, "");
+    (!missing).then_some(rendered)
+}
+
+/// Expand `{args.<path>}` for a summary, where missing fields read `unknown`
+pub fn render_summary_fields(template: &str, fields: &Value) -> String {
+    expand_fields(template, fields, "unknown").0
+}
+
+fn expand_fields(template: &str, fields: &Value, missing_text: &str) -> (String, bool) {
+    let mut missing = false;
+    let rendered =
+        field_pattern().replace_all(template, |captures: &Captures| match crate::filter::lookup(
+            fields,
+            &captures[1],
+        ) {
+            Some(Value::String(s)) => s.clone(),
+            Some(value) => value.to_string(),
+            None => {
+                missing = true;
+                missing_text.to_string()
+            }
+        });
+    (rendered.into_owned(), missing)
+}
+
+/// Expand `{args.<path> | token_amount}` with `format`, which renders an
+/// amount in base units for the token, falling back to the raw amount
+pub fn render_token_amounts(
+    template: &str,
+    fields: &Value,
+    format: impl Fn(u128) -> Option<String>,
+) -> String {
+    token_amount_pattern()
+        .replace_all(template, |captures: &Captures| {
+            let amount = match crate::filter::lookup(fields, &captures[1]) {
+                Some(Value::String(s)) => s.parse::<u128>().ok(),
+                Some(Value::Number(n)) => n.as_u64().map(u128::from),
+                _ => None,
+            };
+            match amount {
+                Some(amount) => format(amount).unwrap_or_else(|| amount.to_string()),
+                None => "unknown".to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// Render a JSON template from `fields`. A string that is exactly one
+/// `{path}` becomes that field's value (`null` if missing); other strings go
+/// through `expand` for filters such as `datetime`, then have each `{path}`
+/// replaced by the field as text (`unknown` if missing).
+pub fn render_json(template: &Value, fields: &Value, expand: &impl Fn(&str) -> String) -> Value {
+    match template {
+        Value::String(s) => {
+            if let Some(captures) = path_pattern().captures(s) {
+                if captures[0].len() == s.len() {
+                    return crate::filter::lookup(fields, &captures[1])
+                        .cloned()
+                        .unwrap_or(Value::Null);
+                }
+            }
+            let expanded = expand(s);
+            let rendered = path_pattern().replace_all(&expanded, |captures: &Captures| {
+                match crate::filter::lookup(fields, &captures[1]) {
+                    Some(Value::String(s)) => s.clone(),
+                    Some(Value::Null) | None => "unknown".to_string(),
+                    Some(value) => value.to_string(),
+                }
+            });
+            Value::String(rendered.into_owned())
+        }
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| render_json(item, fields, expand))
+                .collect(),
+        ),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| (key.clone(), render_json(value, fields, expand)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Formatting an invalid strftime string would panic, so reject it up front
+fn valid_format(format: &str) -> bool {
+    !StrftimeItems::new(format).any(|item| matches!(item, Item::Error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_datetime_rendering() {
+        // 2024-01-15 12:30:00 UTC
+        let ms = Some(1_705_321_800_000.0);
+        let template = r#"At {block_timestamp | datetime("%Y-%m-%d %H:%M %Z", "Europe/Berlin")} ({block_timestamp|datetime("%H:%M")}, {block_timestamp})"#;
+        assert_eq!(
+            render_timestamps(template, ms, Tz::UTC),
+            "At 2024-01-15 13:30 CET (12:30, 1705321800000)"
+        );
+        assert_eq!(
+            render_timestamps(
+                r#"{block_timestamp | datetime("%H:%M %Z")}"#,
+                ms,
+                parse_timezone("America/New_York").unwrap()
+            ),
+            "07:30 EST"
+        );
+        assert_eq!(
+            render_timestamps(r#"{block_timestamp | datetime("%H:%M")}"#, None, Tz::UTC),
+            "unknown"
+        );
+
+        assert!(validate(template).is_ok());
+        assert!(validate(r#"{block_timestamp | datetime("%H", "Mars/Olympus")}"#).is_err());
+        assert!(validate(r#"{block_timestamp | datetime("%Q")}"#).is_err());
+    }
+
+    #[test]
+    fn test_field_rendering() {
+        let fields = serde_json::json!({"args": {"proposal_id": 42, "vote": {"kind": "Approve"}}});
+        assert_eq!(
+            render_fields(
+                "proposal-{args.proposal_id}-{args.vote.kind}-{tx_hash}",
+                &fields
+            )
+            .as_deref(),
+            Some("proposal-42-Approve-{tx_hash}")
+        );
+        assert_eq!(render_fields("proposal-{args.id}", &fields), None);
+        assert_eq!(
+            render_summary_fields("Proposal {args.id}", &fields),
+            "Proposal unknown"
+        );
+
+        let fields =
+            serde_json::json!({"args": {"amount": "500000000000", "receiver_id": "bob.near"}});
+        let template = "{args.amount | token_amount} to {args.receiver_id}";
+        assert!(uses_token_amounts(template));
+        let rendered = render_token_amounts(template, &fields, |amount| {
+            Some(format!("{} USDC", amount / 1_000_000))
+        });
+        assert_eq!(
+            render_summary_fields(&rendered, &fields),
+            "500000 USDC to bob.near"
+        );
+        assert_eq!(
+            render_token_amounts(template, &fields, |_| None),
+            "500000000000 to {args.receiver_id}"
+        );
+    }
+
+    #[test]
+    fn test_json_rendering() {
+        let fields = serde_json::json!({
+            "predecessor_id": "alice.near",
+            "block_height": 120,
+            "args": {"proposal": {"id": 7, "deadline": "1700000000"}},
+        });
+        let template = serde_json::json!({
+            "proposal_id": "{args.proposal.id}",
+            "proposer": "{predecessor_id}",
+            "details": ["Deadline {args.proposal.deadline} at {block_height}", "{args.missing}", 3],
+            "note": "{args.missing} at {block_timestamp}",
+        });
+        let rendered = render_json(&template, &fields, &|s: &str| {
+            s.replace("{block_timestamp}", "noon")
+        });
+        assert_eq!(
+            rendered,
+            serde_json::json!({
+                "proposal_id": 7,
+                "proposer": "alice.near",
+                "details": ["Deadline 1700000000 at 120", null, 3],
+                "note": "unknown at noon",
+            })
+        );
+    }
+}
@@ -0,0 +1,124 @@
+//! Fungible token amounts in human units
+//!
+//! NEP-141 amounts are integers in the token's base units, so a summary of
+//! `{args.amount}` reads `500000000000`. `{args.amount | token_amount}`
+//! renders `500,000 USDC` instead, using the `ft_metadata` of the contract
+//! the action was sent to. Metadata is fetched over the network's RPC once
+//! per contract and cached; until it is known, amounts render raw.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::Deserialize;
+
+/// The parts of NEP-148 metadata amounts are rendered with
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub(crate) struct TokenMetadata {
+    pub symbol: String,
+    pub decimals: u8,
+}
+
+impl TokenMetadata {
+    /// `amount` (in base units) as e.g. `1,234.5 USDC`
+    pub fn format(&self, amount: u128) -> String {
+        format!("{} {}", format_amount(amount, self.decimals), self.symbol)
+    }
+}
+
+/// Token metadata by RPC URL and contract, fetched on first use
+pub(crate) struct TokenMetadataCache {
+    client: reqwest::Client,
+    cache: Mutex<HashMap<(String, String), TokenMetadata>>,
+}
+
+impl TokenMetadataCache {
+    pub fn new(client: reqwest::Client) -> Self {
+        Self {
+            client,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, rpc_url: &str, contract_id: &str) -> Option<TokenMetadata> {
+        self.cache
+            .lock()
+            .unwrap()
+            .get(&(rpc_url.to_string(), contract_id.to_string()))
+            .cloned()
+    }
+
+    /// Fetch and cache `contract_id`'s metadata unless it is already known.
+    /// Failures are logged and retried on the next call.
+    pub async fn prefetch(&self, rpc_url: &str, contract_id: &str) {
+        if self.get(rpc_url, contract_id).is_some() {
+            return;
+        }
+        let metadata = crate::rpc::call_function(&self.client, rpc_url, contract_id, "ft_metadata")
+            .await
+            .and_then(|metadata| Ok(serde_json::from_value::<TokenMetadata>(metadata)?));
+        match metadata {
+            Ok(metadata) => {
+                log::debug!(
+                    "{} is {} with {} decimals",
+                    contract_id,
+                    metadata.symbol,
+                    metadata.decimals
+                );
+                self.cache
+                    .lock()
+                    .unwrap()
+                    .insert((rpc_url.to_string(), contract_id.to_string()), metadata);
+            }
+            Err(e) => log::warn!("Could not fetch token metadata of {}: {:#}", contract_id, e),
+        }
+    }
+}
+
+/// `amount` base units as a decimal with thousands separators: two decimal
+/// places at most, or six for amounts below one, without trailing zeros
+pub(crate) fn format_amount(amount: u128, decimals: u8) -> String {
+    let scale = 10u128.checked_pow(decimals as u32).unwrap_or(u128::MAX);
+    let whole = amount / scale;
+    let places = if whole == 0 { 6 } else { 2 }.min(decimals as u32);
+    let fraction = if places == 0 {
+        0
+    } else {
+        (amount % scale) / (scale / 10u128.pow(places))
+    };
+
+    let digits = whole.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    if fraction > 0 {
+        let fraction = format!("{:0width$}", fraction, width = places as usize);
+        grouped.push('.');
+        grouped.push_str(fraction.trim_end_matches('0'));
+    }
+    grouped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_amounts() {
+        assert_eq!(format_amount(500_000_000_000, 6), "500,000");
+        assert_eq!(format_amount(1_234_567_890, 6), "1,234.56");
+        assert_eq!(format_amount(1_500_000, 6), "1.5");
+        assert_eq!(format_amount(1_200, 6), "0.0012");
+        assert_eq!(format_amount(1_000_000_000_000_000_000_000_000, 24), "1");
+        assert_eq!(format_amount(42, 0), "42");
+
+        let metadata: TokenMetadata = serde_json::from_value(serde_json::json!({
+            "spec": "ft-1.0.0", "name": "USD Coin", "symbol": "USDC", "decimals": 6
+        }))
+        .unwrap();
+        assert_eq!(metadata.format(500_000_000_000), "500,000 USDC");
+    }
+}